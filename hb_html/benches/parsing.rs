@@ -0,0 +1,46 @@
+//! Compares parsing a [`HtmlDocument`], which copies every string, with parsing a
+//! [`BorrowedDocument`], which borrows them from the input.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use hb_html::borrowed::BorrowedDocument;
+use hb_html::objects::HtmlDocument;
+
+/// Builds the HTML of a page with a few thousand tags, attributes and text runs.
+fn build_html() -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html><html lang=en><head><title>Bench</title><meta charset=\"utf-8\"></head><body id=top>",
+    );
+    for section in 0..20 {
+        html.push_str(&format!(
+            r#"<div class="section s{}" data-section={}><h2 class=title>Section {}</h2><ul class=list>"#,
+            section, section, section
+        ));
+        for item in 0..50 {
+            html.push_str(&format!(
+                r#"<li class="item i{}"><span class=name>Item &amp; more</span> <a href="/{}/{}" title="Item {}" class=link>link</a><br><!-- item --></li>"#,
+                item % 5,
+                section,
+                item,
+                item
+            ));
+        }
+        html.push_str("</ul><p class=footer>End <em>of</em> section</p></div>");
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn parsing_benchmark(c: &mut Criterion) {
+    let html = build_html();
+    let mut group = c.benchmark_group("parsing");
+    group.throughput(Throughput::Bytes(html.len() as u64));
+    group.bench_function("owned", |b| {
+        b.iter(|| black_box(&html).parse::<HtmlDocument>().unwrap())
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| BorrowedDocument::parse(black_box(&html)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parsing_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+//! Compares running each selector with [`HtmlDocument::find`] against matching all of them
+//! in a single traversal with a [`CompiledSelectorSet`].
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use hb_html::objects::{CssSelector, HtmlDocument};
+use hb_html::selector_set::CompiledSelectorSet;
+
+/// Builds a document with a few thousand tags nested a few levels deep.
+fn build_document() -> HtmlDocument {
+    let mut html = String::from("<html><head><title>Bench</title></head><body id=top>");
+    for section in 0..20 {
+        html.push_str(&format!(
+            r#"<div class="section s{}"><h2 class=title>Section {}</h2><ul class=list>"#,
+            section, section
+        ));
+        for item in 0..50 {
+            html.push_str(&format!(
+                r#"<li class="item i{}"><span class=name>Item</span> <a href="/{}/{}" class=link>link</a></li>"#,
+                item % 5,
+                section,
+                item
+            ));
+        }
+        html.push_str("</ul><p class=footer>End <em>of</em> section</p></div>");
+    }
+    html.push_str("</body></html>");
+    html.parse::<HtmlDocument>().unwrap()
+}
+
+const SELECTORS: &[&str] = &[
+    "a",
+    "div.section a.link",
+    "#top > div h2.title",
+    "ul.list > li.i3 > a",
+    "li.item + li.i1",
+    "h2 ~ ul li span.name",
+    "p.footer em",
+    "div.s7 li",
+    "table td",
+    ".missing a",
+    "li:first-child",
+    "a[href^=/1]",
+    "span",
+    "body div ul li a",
+    "nav a",
+    "#nothing",
+];
+
+fn selector_benchmark(c: &mut Criterion) {
+    let doc = build_document();
+    let set = CompiledSelectorSet::from_strs(SELECTORS).unwrap();
+    let mut group = c.benchmark_group("selectors");
+    group.bench_function("find_each", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            for s in SELECTORS {
+                total += doc.find(black_box(s)).results.len();
+            }
+            total
+        })
+    });
+    group.bench_function("compiled_set", |b| {
+        b.iter(|| black_box(doc.match_selector_set(&set)).len())
+    });
+    let first = "li.i3 a".parse::<CssSelector>().unwrap();
+    group.bench_function("find_first", |b| {
+        b.iter(|| doc.find(black_box("li.i3 a")).results.into_iter().next())
+    });
+    group.bench_function("select_first", |b| {
+        b.iter(|| doc.select_first(black_box(&first)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, selector_benchmark);
+criterion_main!(benches);
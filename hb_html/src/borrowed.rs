@@ -0,0 +1,355 @@
+//! A HTML tree which borrows its strings from the parsed input instead of copying them.
+//!
+//! [`BorrowedDocument::parse`] uses the same parser as a [`HtmlDocument`], so it accepts
+//! and rejects the same HTML, but tag names, ids, classes, attributes, text and comments
+//! are [`Cow`] slices of the input, so parsing a large document doesn't allocate a
+//! `String` for each of them. Text and attribute values are kept as they are written, like
+//! in [`HtmlDocument`], so they always point into the input until they are changed. Use
+//! [`BorrowedDocument::into_owned`] to get a [`HtmlDocument`] when the tree has to
+//! outlive the input or be queried.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::borrowed::{BorrowedDocument, BorrowedNode};
+//! use hb_html::objects::HtmlDocument;
+//! use std::borrow::Cow;
+//! let html = String::from(
+//!     r#"<!DOCTYPE html><html><body><p class="intro note" data-id=7>Hello &amp; welcome</p></body></html>"#,
+//! );
+//! let doc = BorrowedDocument::parse(&html).unwrap();
+//! let body = match &doc.nodes[0] {
+//!     BorrowedNode::Tag(html) => match &html.contents[0] {
+//!         BorrowedNode::Tag(body) => body,
+//!         _ => panic!("body not found"),
+//!     },
+//!     _ => panic!("html not found"),
+//! };
+//! let p = match &body.contents[0] {
+//!     BorrowedNode::Tag(p) => p,
+//!     _ => panic!("p not found"),
+//! };
+//! assert!(matches!(p.tag, Cow::Borrowed("p")));
+//! assert_eq!(p.classes, vec!["intro", "note"]);
+//! assert_eq!(p.get_attribute("data-id"), Some("7"));
+//! assert_eq!(p.text(), "Hello &amp; welcome");
+//!
+//! // the owned tree is the same one HtmlDocument parses
+//! let owned: HtmlDocument = doc.into_owned();
+//! drop(html);
+//! assert_eq!(owned.doctype, "html");
+//! ```
+use crate::error::ParseHtmlError;
+use crate::objects::{HtmlDocument, HtmlNode, HtmlTag};
+use crate::parsing::{parse_borrowed_html_tag, ParsedBorrowedTagType};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A HTML document whose strings borrow from the input it was parsed from.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BorrowedDocument<'a> {
+    /// The doctype string from the document, usually "html".
+    pub doctype: Cow<'a, str>,
+    /// All HTML tags and comments that appear at the top level in the document.
+    pub nodes: Vec<BorrowedNode<'a>>,
+}
+
+/// A HTML tag whose strings borrow from the input, like [`HtmlTag`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BorrowedTag<'a> {
+    pub tag: Cow<'a, str>,
+    pub ids: Vec<Cow<'a, str>>,
+    pub classes: Vec<Cow<'a, str>>,
+    pub attributes: HashMap<Cow<'a, str>, Cow<'a, str>>,
+    pub contents: Vec<BorrowedNode<'a>>,
+}
+
+/// The contents of a [`BorrowedTag`], like [`HtmlNode`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedNode<'a> {
+    Tag(BorrowedTag<'a>),
+    Comment(Cow<'a, str>),
+    Text(Cow<'a, str>),
+}
+
+impl<'a> BorrowedDocument<'a> {
+    /// Parses a HTML document without copying its strings. Text outside of the tags at the
+    /// top level is skipped, as it is when parsing a [`HtmlDocument`].
+    pub fn parse(html: &'a str) -> Result<BorrowedDocument<'a>, ParseHtmlError> {
+        let mut doc = BorrowedDocument::default();
+        let mut chs = html.chars();
+        while let Some(ch) = chs.next() {
+            if ch != '<' {
+                continue;
+            }
+            match parse_borrowed_html_tag(&mut chs)? {
+                ParsedBorrowedTagType::EndTag(t) => {
+                    return Err(ParseHtmlError::new(format!(
+                        "Found end tag {} before start tag.",
+                        t
+                    )))
+                }
+                ParsedBorrowedTagType::NewTag(tag) => doc.nodes.push(BorrowedNode::Tag(tag)),
+                ParsedBorrowedTagType::Comment(c) => {
+                    doc.nodes.push(BorrowedNode::Comment(c.into()))
+                }
+                ParsedBorrowedTagType::DocType(doctype) => {
+                    if !doc.doctype.is_empty() {
+                        return Err(ParseHtmlError::new(format!(
+                            "Doctype was defined twice, first {} and second {}",
+                            doc.doctype, doctype,
+                        )));
+                    }
+                    doc.doctype = doctype.into();
+                }
+            }
+        }
+        Ok(doc)
+    }
+
+    /// Copies the strings which are still borrowed into a [`HtmlDocument`], which doesn't
+    /// depend on the input.
+    pub fn into_owned(self) -> HtmlDocument {
+        HtmlDocument {
+            doctype: self.doctype.into_owned(),
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(BorrowedNode::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl<'a> BorrowedTag<'a> {
+    /// Gets the value of an attribute. The id and class attributes are in ids and classes.
+    pub fn get_attribute(&self, attribute: &str) -> Option<&str> {
+        self.attributes.get(attribute).map(|v| v.as_ref())
+    }
+
+    /// Gets the text of the tag and all of its contents.
+    pub fn text(&self) -> String {
+        let mut output = String::new();
+        for c in &self.contents {
+            c.push_text(&mut output);
+        }
+        output
+    }
+
+    /// Copies the tag into a [`HtmlTag`].
+    pub fn into_owned(self) -> HtmlTag {
+        HtmlTag {
+            tag: self.tag.into_owned(),
+            ids: self.ids.into_iter().map(Cow::into_owned).collect(),
+            classes: self.classes.into_iter().map(Cow::into_owned).collect(),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(|(a, v)| (a.into_owned(), v.into_owned()))
+                .collect(),
+            contents: self
+                .contents
+                .into_iter()
+                .map(BorrowedNode::into_owned)
+                .collect(),
+            namespace: None,
+        }
+    }
+}
+
+impl<'a> BorrowedNode<'a> {
+    /// Gets the text of the node, which is empty for a comment.
+    pub fn text(&self) -> String {
+        let mut output = String::new();
+        self.push_text(&mut output);
+        output
+    }
+
+    fn push_text(&self, output: &mut String) {
+        match self {
+            BorrowedNode::Tag(t) => {
+                for c in &t.contents {
+                    c.push_text(output);
+                }
+            }
+            BorrowedNode::Comment(_) => (),
+            BorrowedNode::Text(t) => output.push_str(t),
+        }
+    }
+
+    /// Copies the node into a [`HtmlNode`].
+    pub fn into_owned(self) -> HtmlNode {
+        match self {
+            BorrowedNode::Tag(t) => HtmlNode::Tag(t.into_owned()),
+            BorrowedNode::Comment(c) => HtmlNode::Comment(c.into_owned()),
+            BorrowedNode::Text(t) => HtmlNode::Text(t.into_owned()),
+        }
+    }
+}
+
+impl<'a> From<BorrowedDocument<'a>> for HtmlDocument {
+    fn from(doc: BorrowedDocument<'a>) -> HtmlDocument {
+        doc.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod borrowed_tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"<!DOCTYPE html>
+<!-- A test document -->
+<html lang=en><head><title>Test</title><meta charset="utf-8"></head>
+<body id="main top" class=page>
+<h1 class="title big">A &lt;heading&gt;</h1>
+<p data-a="x y" hidden>Some <b>bold</b> text<br><img src=/a.png alt="An image"></p>
+<!-- a comment -->
+<ul><li>One</li><li class=last>Two</li></ul>
+<input type=checkbox checked />
+</body></html>"#;
+
+    fn borrowed_strings<'a>(node: &BorrowedNode<'a>, all_borrowed: &mut bool) {
+        match node {
+            BorrowedNode::Tag(t) => {
+                let strings = std::iter::once(&t.tag)
+                    .chain(t.ids.iter())
+                    .chain(t.classes.iter())
+                    .chain(t.attributes.iter().flat_map(|(a, v)| [a, v]));
+                for s in strings {
+                    *all_borrowed &= matches!(s, Cow::Borrowed(_));
+                }
+                for c in &t.contents {
+                    borrowed_strings(c, all_borrowed);
+                }
+            }
+            BorrowedNode::Comment(s) | BorrowedNode::Text(s) => {
+                *all_borrowed &= matches!(s, Cow::Borrowed(_))
+            }
+        }
+    }
+
+    #[test]
+    fn borrowed_parse_test() {
+        let doc = BorrowedDocument::parse(DOCUMENT).unwrap();
+        assert_eq!(doc.doctype, "html");
+        assert_eq!(doc.nodes.len(), 2);
+        assert_eq!(
+            doc.nodes[0],
+            BorrowedNode::Comment(" A test document ".into())
+        );
+        let mut all_borrowed = true;
+        for node in &doc.nodes {
+            borrowed_strings(node, &mut all_borrowed);
+        }
+        assert!(all_borrowed);
+
+        let html = match &doc.nodes[1] {
+            BorrowedNode::Tag(t) => t,
+            _ => panic!("html not found"),
+        };
+        assert_eq!(html.get_attribute("lang"), Some("en"));
+        let body = match &html.contents[2] {
+            BorrowedNode::Tag(t) => t,
+            n => panic!("body not found, got {:?}", n),
+        };
+        assert_eq!(body.tag, "body");
+        assert_eq!(body.ids, vec!["main", "top"]);
+        assert_eq!(body.classes, vec!["page"]);
+        assert_eq!(
+            body.text(),
+            "\nA &lt;heading&gt;\nSome bold text\n\nOneTwo\n\n"
+        );
+        let input = match &body.contents[body.contents.len() - 2] {
+            BorrowedNode::Tag(t) => t,
+            n => panic!("input not found, got {:?}", n),
+        };
+        assert_eq!(input.get_attribute("type"), Some("checkbox"));
+        assert_eq!(input.get_attribute("checked"), Some(""));
+        assert!(input.contents.is_empty());
+    }
+
+    #[test]
+    fn borrowed_into_owned_test() {
+        let doc = BorrowedDocument::parse(DOCUMENT).unwrap();
+        let owned = DOCUMENT.parse::<HtmlDocument>().unwrap();
+        assert_eq!(doc.clone().into_owned(), owned);
+        assert_eq!(HtmlDocument::from(doc), owned);
+
+        let mut doc = BorrowedDocument::parse("<p>text</p>").unwrap();
+        if let BorrowedNode::Tag(p) = &mut doc.nodes[0] {
+            p.contents[0] = BorrowedNode::Text(Cow::Owned("changed".to_owned()));
+        }
+        assert_eq!(
+            doc.into_owned(),
+            "<p>changed</p>".parse::<HtmlDocument>().unwrap()
+        );
+    }
+
+    #[test]
+    fn borrowed_errors_test() {
+        let tests = vec![
+            ("</p>", "Found end tag p before start tag."),
+            ("<div><p></div>", "Incorrect end tag found div but expected p."),
+            ("<div>text", "End of file without finding tag div."),
+            (
+                "<div",
+                "Could parse starting tag after d because end of string 'iv' encountered before any end char '[' ', '>']' was found",
+            ),
+            (
+                "<a href=\"/x>link</a>",
+                "could not get value of attribute 'href' because Closing '\"' for string '/x>link</a>' not found",
+            ),
+            (
+                "<!DOCTYPE a><!DOCTYPE b>",
+                "Doctype was defined twice, first a and second b",
+            ),
+            (
+                "<div><!DOCTYPE html></div>",
+                "'DOCTYPE html' element found in middle of content",
+            ),
+            ("<", "End of file without reading any chars in this tag."),
+        ];
+        for (html, msg) in tests {
+            assert_eq!(
+                BorrowedDocument::parse(html),
+                Err(ParseHtmlError::with_msg(msg)),
+                "{}",
+                html
+            );
+        }
+    }
+
+    #[test]
+    fn borrowed_matches_owned_test() {
+        let tests = vec![
+            DOCUMENT,
+            "<p>text</p>",
+            "<p\nclass=a>text</p>",
+            "<p id>text</p>",
+            "<p id=a class>text</p>",
+            "<input class>",
+            "<p a=>text</p>",
+            "<p a b=1 c=2 d>text</p>",
+            "<script>if (a < b) {}</script>",
+            "<!--x--><!---->",
+            "<!-- a -- b --><p>text</p>",
+            "<!DOCTYPE html><html><body><br><img src=/a.png/><hr/></body></html>",
+            "<div><p></div>",
+            "<div>text",
+            "<div",
+            "<a href=\"/x>link</a>",
+            "<div><!DOCTYPE html></div>",
+            "</p>",
+            "<",
+            "",
+        ];
+        for html in tests {
+            assert_eq!(
+                BorrowedDocument::parse(html).map(BorrowedDocument::into_owned),
+                html.parse::<HtmlDocument>(),
+                "{}",
+                html
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Building HTML trees in code with the [`html!`] macro.
+//!
+//! The macro takes HTML written like JSX and expands to the code which constructs the
+//! [`HtmlNode`], so mistakes such as a mismatched closing tag are compile errors instead of
+//! parsing errors at runtime. Rust values are used with braces:
+//!
+//! * `name={expr}` sets an attribute to anything which implements Display.
+//! * `{expr}` adds children from anything which implements [`IntoHtmlNodes`], such as
+//!   text, a [`HtmlNode`], a [`HtmlTag`], an Option or a Vec.
+//! * `{for expr}` adds children from each item of an iterator.
+//!
+//! Text and attribute values are encoded with
+//! [`encode_entities`](crate::entities::encode_entities), because the tree holds them as
+//! they would be written in HTML. A single element or text becomes a [`HtmlNode`] and a
+//! fragment, `<>...</>`, becomes a `Vec<HtmlNode>`.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::build::html;
+//! let id = "steps";
+//! let steps = vec!["Boil", "Serve"];
+//! let node = html! {
+//!     <ol class="list big" id={id} start=2 reversed>
+//!         {for steps.iter().map(|s| html! { <li>{*s}</li> })}
+//!         <li>"Fish & chips"<br></li>
+//!     </ol>
+//! };
+//! let expected = concat!(
+//!     r#"<ol class="list big" id="steps" start="2" reversed>"#,
+//!     "<li>Boil</li><li>Serve</li><li>Fish &amp; chips<br></li></ol>"
+//! )
+//! .parse::<hb_html::objects::HtmlDocument>()
+//! .unwrap();
+//! assert_eq!(node, expected.nodes[0]);
+//! ```
+//!
+//! Closing tags must match:
+//!
+//! ```compile_fail
+//! let node = hb_html::build::html! { <div><p>"text"</div> };
+//! ```
+//!
+//! Void elements can't have contents or a closing tag, and other elements can't be
+//! self-closing:
+//!
+//! ```compile_fail
+//! let node = hb_html::build::html! { <p><br></br></p> };
+//! ```
+//!
+//! ```compile_fail
+//! let node = hb_html::build::html! { <div/> };
+//! ```
+use crate::entities::encode_entities;
+use crate::objects::{HtmlNode, HtmlTag};
+pub use hb_macros::html;
+
+/// A value which can be added to the contents of a tag by the [`html!`] macro.
+pub trait IntoHtmlNodes {
+    /// Adds the value to the end of the nodes.
+    fn append_to(self, nodes: &mut Vec<HtmlNode>);
+}
+
+impl IntoHtmlNodes for HtmlNode {
+    fn append_to(self, nodes: &mut Vec<HtmlNode>) {
+        nodes.push(self);
+    }
+}
+
+impl IntoHtmlNodes for &HtmlNode {
+    fn append_to(self, nodes: &mut Vec<HtmlNode>) {
+        nodes.push(self.clone());
+    }
+}
+
+impl IntoHtmlNodes for HtmlTag {
+    fn append_to(self, nodes: &mut Vec<HtmlNode>) {
+        nodes.push(HtmlNode::Tag(self));
+    }
+}
+
+impl IntoHtmlNodes for &HtmlTag {
+    fn append_to(self, nodes: &mut Vec<HtmlNode>) {
+        nodes.push(HtmlNode::Tag(self.clone()));
+    }
+}
+
+impl<T: IntoHtmlNodes> IntoHtmlNodes for Vec<T> {
+    fn append_to(self, nodes: &mut Vec<HtmlNode>) {
+        for value in self {
+            value.append_to(nodes);
+        }
+    }
+}
+
+impl<T: IntoHtmlNodes> IntoHtmlNodes for Option<T> {
+    fn append_to(self, nodes: &mut Vec<HtmlNode>) {
+        if let Some(value) = self {
+            value.append_to(nodes);
+        }
+    }
+}
+
+/// Text is encoded, and added to the text before it if there is some.
+macro_rules! impl_text_into_html_nodes {
+    ($($t:ty),*) => {
+        $(
+            impl IntoHtmlNodes for $t {
+                fn append_to(self, nodes: &mut Vec<HtmlNode>) {
+                    let text = encode_entities(&self.to_string());
+                    match nodes.last_mut() {
+                        Some(HtmlNode::Text(last)) => last.push_str(&text),
+                        _ => nodes.push(HtmlNode::Text(text)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_text_into_html_nodes!(
+    &str, String, &String, char, bool, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128,
+    usize, f32, f64
+);
+
+#[cfg(test)]
+mod build_tests {
+    use super::*;
+    use crate::objects::HtmlDocument;
+
+    fn nodes(html: &str) -> Vec<HtmlNode> {
+        html.parse::<HtmlDocument>().unwrap().nodes
+    }
+
+    #[test]
+    fn html_macro_test() {
+        let title = "Tea & cake";
+        let extra: Option<HtmlNode> = None;
+        let count = 3;
+        let node = html! {
+            <div id="main" class={format!("card {}", "wide")} data-count={count} aria-label="x">
+                <h1 title={title}>{title}</h1>
+                "Count: " {count}
+                {extra}
+                <img src="a.png" alt=""/>
+                <input type="checkbox" checked>
+                <my-widget for="x"></my-widget>
+            </div>
+        };
+        let expected = nodes(
+            r#"<div id="main" class="card wide" data-count="3" aria-label="x"><h1 title="Tea &amp; cake">Tea &amp; cake</h1>Count: 3<img src="a.png" alt=""><input type="checkbox" checked><my-widget for="x"></my-widget></div>"#,
+        );
+        assert_eq!(node, expected[0]);
+    }
+
+    #[test]
+    fn html_macro_fragment_test() {
+        let items = ["a", "b"];
+        let list: Vec<HtmlNode> = html! {
+            <>
+                "start"
+                {for items.iter().map(|i| html! { <li>{*i}</li> })}
+                <p>{vec![html! { <b>"x"</b> }, html! { "y" }]}</p>
+            </>
+        };
+        assert_eq!(list[0], HtmlNode::Text("start".to_owned()));
+        assert_eq!(list[1..], nodes("<li>a</li><li>b</li><p><b>x</b>y</p>"));
+        let text: HtmlNode = html! { "a < b" };
+        assert_eq!(text, HtmlNode::Text("a &lt; b".to_owned()));
+    }
+}
@@ -0,0 +1,287 @@
+//! Resolves the CSS cascade for the tags in a HTML document.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::css::{CssDeclaration, CssStyleRule, CssStylesheet};
+//! use hb_html::objects::{CssSelector, HtmlDocument};
+//! let doc = r#"<html><body><p class="ad">Buy!</p><p>Hello</p></body></html>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let stylesheet = CssStylesheet::new().rules(vec![CssStyleRule::new(
+//!     "p.ad".parse::<CssSelector>().unwrap(),
+//!     vec![CssDeclaration::new("display", "none")],
+//! )]);
+//! let styles = doc.cascade(&stylesheet);
+//! let body = doc.find("body").results.remove(0);
+//! assert_eq!(styles.visible_text(&body), "Hello");
+//! ```
+use crate::css::{CssDeclaration, CssStylesheet};
+use crate::objects::{CssSpecificity, HtmlDocument, HtmlNode, HtmlTag};
+use crate::querying::HtmlQueryResult;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// The declarations that apply to a single HTML tag once the cascade has been resolved.
+#[derive(Debug, Clone)]
+pub struct CascadedStyle<'a> {
+    /// The tag that the declarations apply to.
+    pub node: HtmlQueryResult<'a>,
+    /// The winning declaration for each property, keyed by the property name.
+    pub declarations: HashMap<String, CssDeclaration>,
+}
+
+impl<'a> CascadedStyle<'a> {
+    /// Gets the value of the winning declaration for the property, if there is one.
+    pub fn get(&self, property: &str) -> Option<&str> {
+        self.declarations
+            .get(&property.to_lowercase())
+            .map(|d| d.value.as_str())
+    }
+
+    /// Checks if the tag itself has been given display:none.
+    pub fn is_display_none(&self) -> bool {
+        match self.get("display") {
+            Some(display) => display.trim().eq_ignore_ascii_case("none"),
+            None => false,
+        }
+    }
+}
+
+/// The result of running the cascade over a HTML document. It contains a [`CascadedStyle`]
+/// for every tag in the document, in document order.
+#[derive(Debug, Clone)]
+pub struct CascadedStyles<'a> {
+    pub styles: Vec<CascadedStyle<'a>>,
+    index: HashMap<*const HtmlTag, usize>,
+}
+
+/// Identifies where a declaration came from so the cascade can order them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CascadeOrder {
+    important: bool,
+    inline: bool,
+    specificity: CssSpecificity,
+    position: usize,
+}
+
+impl<'a> CascadedStyles<'a> {
+    /// Gets the cascaded style of the tag the [`HtmlQueryResult`] points to.
+    pub fn get(&self, node: &HtmlQueryResult) -> Option<&CascadedStyle<'a>> {
+        match node.get_node() {
+            Some(HtmlNode::Tag(t)) => self.get_for_tag(t),
+            _ => None,
+        }
+    }
+
+    /// Gets the cascaded style of a tag from the document the cascade was run on.
+    pub fn get_for_tag(&self, tag: &HtmlTag) -> Option<&CascadedStyle<'a>> {
+        self.index
+            .get(&(tag as *const HtmlTag))
+            .map(|i| &self.styles[*i])
+    }
+
+    /// Checks if the node is hidden because either it or one of its ancestors has
+    /// display:none or the hidden attribute.
+    pub fn is_hidden(&self, node: &HtmlQueryResult) -> bool {
+        node.get_path_iter().any(|n| match n {
+            HtmlNode::Tag(t) => self.is_tag_hidden(t),
+            _ => false,
+        })
+    }
+
+    fn is_tag_hidden(&self, tag: &HtmlTag) -> bool {
+        if let Some(style) = self.get_for_tag(tag) {
+            if style.declarations.contains_key("display") {
+                return style.is_display_none();
+            }
+        }
+        tag.attributes.contains_key("hidden")
+    }
+
+    /// Gets the text of the node skipping over any hidden tags.
+    pub fn visible_text(&self, node: &HtmlQueryResult) -> String {
+        if self.is_hidden(node) {
+            return String::new();
+        }
+        match node.get_node() {
+            Some(n) => self.visible_node_text(n),
+            None => String::new(),
+        }
+    }
+
+    fn visible_node_text(&self, node: &HtmlNode) -> String {
+        match node {
+            HtmlNode::Tag(t) => {
+                if self.is_tag_hidden(t) {
+                    return String::new();
+                }
+                let mut output = String::new();
+                for c in &t.contents {
+                    output.push_str(self.visible_node_text(c).as_str());
+                }
+                output
+            }
+            HtmlNode::Comment(_) => String::new(),
+            HtmlNode::Text(t) => t.clone(),
+        }
+    }
+}
+
+/// Runs the cascade for every tag in the nodes provided using the stylesheet as well as any
+/// inline style="" attributes. Declarations are ordered by !important, then inline styles
+/// over the stylesheet, then selector specificity, and then the order they appear in.
+pub fn cascade<'a>(nodes: &'a Vec<HtmlNode>, stylesheet: &CssStylesheet) -> CascadedStyles<'a> {
+    let mut styles = CascadedStyles {
+        styles: vec![],
+        index: HashMap::new(),
+    };
+    if nodes.is_empty() {
+        return styles;
+    }
+    let mut res = HtmlQueryResult {
+        path: vec![(nodes, 0)],
+    };
+    loop {
+        if let Some(HtmlNode::Tag(tag)) = res.get_node() {
+            let mut winners: HashMap<String, (CascadeOrder, &CssDeclaration)> = HashMap::new();
+            let mut position = 0;
+            for rule in stylesheet.style_rules() {
+                match res.matched_specificity(&rule.selector) {
+                    None => position += rule.declarations.len(),
+                    Some(specificity) => {
+                        for dec in &rule.declarations {
+                            let order = CascadeOrder {
+                                important: dec.important,
+                                inline: false,
+                                specificity,
+                                position,
+                            };
+                            apply_declaration(&mut winners, order, dec);
+                            position += 1;
+                        }
+                    }
+                }
+            }
+            let inline = tag.inline_style();
+            for dec in &inline {
+                let order = CascadeOrder {
+                    important: dec.important,
+                    inline: true,
+                    specificity: CssSpecificity::default(),
+                    position,
+                };
+                apply_declaration(&mut winners, order, dec);
+                position += 1;
+            }
+            styles
+                .index
+                .insert(tag as *const HtmlTag, styles.styles.len());
+            styles.styles.push(CascadedStyle {
+                node: res.clone(),
+                declarations: winners
+                    .into_iter()
+                    .map(|(p, (_, d))| (p, d.clone()))
+                    .collect(),
+            });
+        }
+        if res.walk_next().is_none() {
+            break;
+        }
+    }
+    styles
+}
+
+fn apply_declaration<'d>(
+    winners: &mut HashMap<String, (CascadeOrder, &'d CssDeclaration)>,
+    order: CascadeOrder,
+    dec: &'d CssDeclaration,
+) {
+    match winners.get(&dec.property) {
+        Some((existing, _)) if existing.cmp(&order) == Ordering::Greater => (),
+        _ => {
+            winners.insert(dec.property.clone(), (order, dec));
+        }
+    }
+}
+
+impl HtmlDocument {
+    /// Runs the cascade over the whole document using the stylesheet provided.
+    /// See [`cascade`] for details.
+    pub fn cascade(&self, stylesheet: &CssStylesheet) -> CascadedStyles<'_> {
+        cascade(&self.nodes, stylesheet)
+    }
+}
+
+#[cfg(test)]
+mod cascade_tests {
+    use super::*;
+    use crate::css::CssStyleRule;
+    use crate::objects::CssSelector;
+
+    fn rule(selector: &str, declarations: &str) -> CssStyleRule {
+        CssStyleRule::new(
+            selector.parse::<CssSelector>().unwrap(),
+            CssDeclaration::parse_list(declarations).unwrap(),
+        )
+    }
+
+    #[test]
+    fn cascade_order_test() {
+        let doc = r#"<html><body>
+<p id=first class="a b">One</p>
+<p class=a style="color: green">Two</p>
+<p class=b style="color: green">Three</p>
+</body></html>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let stylesheet = CssStylesheet::new().rules(vec![
+            rule("p", "color: black; margin: 0"),
+            rule("p.a", "color: red"),
+            rule(".b", "color: blue"),
+            rule("#first", "margin: 1px"),
+            rule("p.b", "color: purple !important"),
+            rule("p", "margin: 2px"),
+        ]);
+        let styles = doc.cascade(&stylesheet);
+        let ps = doc.find("p").results;
+        // p.b !important beats everything
+        assert_eq!(styles.get(&ps[0]).unwrap().get("color"), Some("purple"));
+        // id beats later rules with a lower specificity
+        assert_eq!(styles.get(&ps[0]).unwrap().get("margin"), Some("1px"));
+        // inline beats the stylesheet
+        assert_eq!(styles.get(&ps[1]).unwrap().get("color"), Some("green"));
+        // later rule wins when the specificity is equal
+        assert_eq!(styles.get(&ps[1]).unwrap().get("margin"), Some("2px"));
+        // !important beats inline
+        assert_eq!(styles.get(&ps[2]).unwrap().get("color"), Some("purple"));
+        assert_eq!(styles.styles.len(), 5);
+    }
+
+    #[test]
+    fn cascade_inline_important_test() {
+        let doc = r#"<div class=x style="display: block !important">Shown</div>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let stylesheet = CssStylesheet::new().rules(vec![rule(".x", "display: none !important")]);
+        let styles = doc.cascade(&stylesheet);
+        let div = &doc.find("div").results.remove(0);
+        assert_eq!(styles.get(div).unwrap().get("display"), Some("block"));
+        assert!(!styles.is_hidden(div));
+    }
+
+    #[test]
+    fn cascade_hidden_test() {
+        let doc = r#"<html><body><div class=ad>Ad <span>text</span></div><p>Hello <span style="DISPLAY: None">secret</span>world<b hidden>!</b></p></body></html>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let stylesheet = CssStylesheet::new().rules(vec![rule("div.ad", "display: none")]);
+        let styles = doc.cascade(&stylesheet);
+        let spans = doc.find("span").results;
+        assert!(styles.is_hidden(&spans[0]));
+        assert!(styles.is_hidden(&spans[1]));
+        assert!(!styles.is_hidden(&doc.find("p").results.remove(0)));
+        let body = &doc.find("body").results.remove(0);
+        assert_eq!(styles.visible_text(body), "Hello world");
+    }
+}
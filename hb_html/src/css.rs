@@ -0,0 +1,462 @@
+//! Objects representing CSS stylesheets and the declarations inside them.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::css::{CssDeclaration, CssRule, CssStylesheet};
+//! use hb_html::objects::HtmlDocument;
+//! let mut doc = r#"<html><head><style>
+//! /* hide the adverts */
+//! @import url("print.css") print;
+//! .ad { display: none }
+//! @media screen { p { color: red !important } }
+//! </style></head><body><p class=ad style="color: blue">Buy!</p></body></html>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let stylesheet = doc.stylesheet();
+//! assert_eq!(stylesheet.rules.len(), 3);
+//! assert_eq!(stylesheet.style_rules().len(), 2);
+//! // rewrite the first <style> tag without the @import
+//! let mut modified = stylesheet.clone();
+//! modified.rules.retain(|r| !matches!(r, CssRule::Import(_)));
+//! doc.style_tags_mut()[0].set_stylesheet(&modified);
+//! assert_eq!(doc.stylesheets(), vec![modified]);
+//! ```
+use crate::css_parsing::CssParserFunctions;
+use crate::error::ParseHtmlError;
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use hb_parse::StrParser;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents a single CSS declaration such as `display: none !important`.
+pub struct CssDeclaration {
+    /// The property name, stored in lower case.
+    pub property: String,
+    /// The value of the property with the !important flag removed.
+    pub value: String,
+    /// True if the declaration was marked with !important.
+    pub important: bool,
+}
+
+impl CssDeclaration {
+    /// Create a normal (not !important) declaration.
+    ///
+    /// # Arguments
+    ///
+    /// * `property` - The property name, this is converted to lower case.
+    /// * `value` - The value of the property.
+    pub fn new<P: Into<String>, V: Into<String>>(property: P, value: V) -> CssDeclaration {
+        CssDeclaration {
+            property: property.into().to_lowercase(),
+            value: value.into(),
+            important: false,
+        }
+    }
+
+    /// Sets the !important flag of the declaration.
+    pub fn important(mut self, important: bool) -> CssDeclaration {
+        self.important = important;
+        self
+    }
+
+    /// Parses a list of declarations seperated by ';' such as the contents of a style=""
+    /// attribute. Invalid declarations are skipped as per the CSS error handling rules.
+    pub fn parse_list(declarations: &str) -> Result<Vec<CssDeclaration>, ParseHtmlError> {
+        StrParser::new(declarations)
+            .parse_css_declarations()
+            .map_err(|e| ParseHtmlError::with_msg(format!("{}", e)))
+    }
+}
+
+impl fmt::Display for CssDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.important {
+            write!(f, "{}: {} !important", self.property, self.value)
+        } else {
+            write!(f, "{}: {}", self.property, self.value)
+        }
+    }
+}
+
+/// Writes the declarations as they would appear inside a block or style="" attribute.
+fn write_declarations(f: &mut fmt::Formatter, declarations: &[CssDeclaration]) -> fmt::Result {
+    for (i, dec) in declarations.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{};", dec)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents a CSS style rule, which is a selector followed by a block of declarations.
+pub struct CssStyleRule {
+    pub selector: CssSelector,
+    pub declarations: Vec<CssDeclaration>,
+}
+
+impl CssStyleRule {
+    pub fn new(selector: CssSelector, declarations: Vec<CssDeclaration>) -> CssStyleRule {
+        CssStyleRule {
+            selector,
+            declarations,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents a @media rule and the rules inside it.
+pub struct CssMediaRule {
+    /// The media query list, for example "screen and (max-width: 600px)".
+    pub query: String,
+    pub rules: Vec<CssRule>,
+}
+
+impl CssMediaRule {
+    /// Checks if the media query list could apply to a screen. Media features such as
+    /// (max-width: 600px) are not evaluated, only the media type is checked.
+    pub fn applies_to_screen(&self) -> bool {
+        if self.query.trim().is_empty() {
+            return true;
+        }
+        self.query.split(',').any(|q| {
+            let q = q.trim().to_lowercase();
+            let mut words = q.split_whitespace();
+            let mut first = words.next().unwrap_or("");
+            let negated = first == "not";
+            if negated || first == "only" {
+                first = words.next().unwrap_or("");
+            }
+            let matches = first.starts_with('(') || first == "all" || first == "screen";
+            matches != negated
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents an @import rule.
+pub struct CssImportRule {
+    /// The url of the imported stylesheet.
+    pub url: String,
+    /// The media query list the import applies to, empty if there is none.
+    pub media: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents any at-rule which does not have a specific representation, such as @charset
+/// or @keyframes. The block is kept as the raw text between the braces.
+pub struct CssAtRule {
+    /// The name of the rule without the @.
+    pub name: String,
+    pub prelude: String,
+    pub block: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents the different types of rules that can appear in a stylesheet.
+pub enum CssRule {
+    /// A style rule such as `p.intro { color: red }`.
+    Style(CssStyleRule),
+    /// A style rule where the selector could not be parsed into a [`CssSelector`], for example
+    /// one using :hover. The selector text is kept so the rule can be written back out.
+    UnparsedStyle {
+        selector: String,
+        declarations: Vec<CssDeclaration>,
+    },
+    /// A @media rule.
+    Media(CssMediaRule),
+    /// A @import rule.
+    Import(CssImportRule),
+    /// A @font-face rule with its declarations.
+    FontFace(Vec<CssDeclaration>),
+    /// Any other at-rule.
+    Other(CssAtRule),
+}
+
+impl fmt::Display for CssRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CssRule::Style(rule) => {
+                write!(f, "{} {{ ", rule.selector)?;
+                write_declarations(f, &rule.declarations)?;
+                write!(f, " }}")
+            }
+            CssRule::UnparsedStyle {
+                selector,
+                declarations,
+            } => {
+                write!(f, "{} {{ ", selector)?;
+                write_declarations(f, declarations)?;
+                write!(f, " }}")
+            }
+            CssRule::Media(media) => {
+                writeln!(f, "@media {} {{", media.query)?;
+                for rule in &media.rules {
+                    writeln!(f, "{}", rule)?;
+                }
+                write!(f, "}}")
+            }
+            CssRule::Import(import) => {
+                if import.media.is_empty() {
+                    write!(f, "@import url(\"{}\");", import.url)
+                } else {
+                    write!(f, "@import url(\"{}\") {};", import.url, import.media)
+                }
+            }
+            CssRule::FontFace(declarations) => {
+                write!(f, "@font-face {{ ")?;
+                write_declarations(f, declarations)?;
+                write!(f, " }}")
+            }
+            CssRule::Other(rule) => {
+                write!(f, "@{}", rule.name)?;
+                if !rule.prelude.is_empty() {
+                    write!(f, " {}", rule.prelude)?;
+                }
+                match &rule.block {
+                    Some(block) => write!(f, " {{{}}}", block),
+                    None => write!(f, ";"),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// Represents a CSS stylesheet. The rules are stored in the order they appear in the
+/// stylesheet which is used to break ties in the cascade.
+pub struct CssStylesheet {
+    pub rules: Vec<CssRule>,
+}
+
+impl CssStylesheet {
+    /// Creates an empty [`CssStylesheet`].
+    pub fn new() -> CssStylesheet {
+        CssStylesheet { rules: vec![] }
+    }
+
+    /// Sets the rules of the stylesheet to the style rules provided.
+    pub fn rules(mut self, rules: Vec<CssStyleRule>) -> CssStylesheet {
+        self.rules = rules.into_iter().map(CssRule::Style).collect();
+        self
+    }
+
+    /// Gets all of the style rules that apply to a screen in the order they appear, including
+    /// the rules inside any @media rules that apply to a screen.
+    pub fn style_rules(&self) -> Vec<&CssStyleRule> {
+        let mut style_rules = vec![];
+        collect_style_rules(&self.rules, &mut style_rules);
+        style_rules
+    }
+}
+
+fn collect_style_rules<'a>(rules: &'a [CssRule], style_rules: &mut Vec<&'a CssStyleRule>) {
+    for rule in rules {
+        match rule {
+            CssRule::Style(r) => style_rules.push(r),
+            CssRule::Media(m) if m.applies_to_screen() => {
+                collect_style_rules(&m.rules, style_rules);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl FromStr for CssStylesheet {
+    type Err = ParseHtmlError;
+    fn from_str(css: &str) -> Result<Self, <Self as std::str::FromStr>::Err> {
+        StrParser::new(css)
+            .parse_css_stylesheet()
+            .map_err(|e| ParseHtmlError::with_msg(format!("{}", e)))
+    }
+}
+
+/// Formats the stylesheet as CSS with one rule per line.
+impl fmt::Display for CssStylesheet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rule in &self.rules {
+            writeln!(f, "{}", rule)?;
+        }
+        Ok(())
+    }
+}
+
+impl HtmlTag {
+    /// Parses the declarations in the style="" attribute of the tag.
+    /// Returns an empty vector if there is no style attribute or it can't be parsed, in
+    /// the same way a browser ignores a malformed style attribute. Use
+    /// [`CssDeclaration::parse_list`] to get the error instead.
+    pub fn inline_style(&self) -> Vec<CssDeclaration> {
+        match self.attributes.get("style") {
+            None => vec![],
+            Some(style) => CssDeclaration::parse_list(style).unwrap_or_default(),
+        }
+    }
+
+    /// Replaces the style="" attribute with the declarations provided. The attribute is
+    /// removed if there are no declarations.
+    pub fn set_inline_style(&mut self, declarations: &[CssDeclaration]) {
+        if declarations.is_empty() {
+            self.attributes.remove("style");
+            return;
+        }
+        let style = declarations
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<String>>()
+            .join("; ");
+        self.attributes.insert("style".to_owned(), style);
+    }
+
+    /// Parses the contents of a \<style\> tag as a stylesheet.
+    /// Returns None if the tag is not a style tag, and an empty stylesheet if the contents
+    /// can't be parsed. Parse the text of the tag as a [`CssStylesheet`] to get the error
+    /// instead.
+    pub fn stylesheet(&self) -> Option<CssStylesheet> {
+        if self.tag != "style" {
+            return None;
+        }
+        Some(self.text().parse::<CssStylesheet>().unwrap_or_default())
+    }
+
+    /// Replaces the contents of the tag with the stylesheet.
+    pub fn set_stylesheet(&mut self, stylesheet: &CssStylesheet) {
+        self.contents = vec![HtmlNode::Text(stylesheet.to_string())];
+    }
+}
+
+fn collect_style_tags<'a>(nodes: &'a mut [HtmlNode], tags: &mut Vec<&'a mut HtmlTag>) {
+    for node in nodes.iter_mut() {
+        if let HtmlNode::Tag(t) = node {
+            if t.tag == "style" {
+                tags.push(t);
+            } else {
+                collect_style_tags(&mut t.contents, tags);
+            }
+        }
+    }
+}
+
+impl HtmlDocument {
+    /// Parses the stylesheets in every \<style\> tag in the document, in document order.
+    pub fn stylesheets(&self) -> Vec<CssStylesheet> {
+        self.find("style")
+            .nodes()
+            .iter()
+            .filter_map(|n| match n {
+                HtmlNode::Tag(t) => t.stylesheet(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Combines the stylesheets from every \<style\> tag in the document into one stylesheet
+    /// which can be used with [`HtmlDocument::cascade`].
+    pub fn stylesheet(&self) -> CssStylesheet {
+        CssStylesheet {
+            rules: self
+                .stylesheets()
+                .into_iter()
+                .flat_map(|s| s.rules)
+                .collect(),
+        }
+    }
+
+    /// Gets mutable references to every \<style\> tag in the document, in document order.
+    /// Use [`HtmlTag::set_stylesheet`] to modify them.
+    pub fn style_tags_mut(&mut self) -> Vec<&mut HtmlTag> {
+        let mut tags = vec![];
+        collect_style_tags(&mut self.nodes, &mut tags);
+        tags
+    }
+}
+
+#[cfg(test)]
+mod css_tests {
+    use super::*;
+
+    #[test]
+    fn css_media_applies_to_screen_test() {
+        let tests = vec![
+            ("", true),
+            ("screen", true),
+            ("all and (max-width: 600px)", true),
+            ("(min-width: 20em)", true),
+            ("print", false),
+            ("only print", false),
+            ("not print", true),
+            ("not screen", false),
+            ("print, screen and (color)", true),
+        ];
+        for (query, applies) in tests {
+            let media = CssMediaRule {
+                query: query.to_owned(),
+                rules: vec![],
+            };
+            assert_eq!(media.applies_to_screen(), applies, "{}", query);
+        }
+    }
+
+    #[test]
+    fn css_stylesheet_display_test() {
+        let css = r#"@charset "utf-8";
+@import url("print.css") print;
+p.a, div > span { color: red; margin: 0 !important; }
+a:hover { color: blue; }
+@media screen {
+p { color: green; }
+}
+@font-face { font-family: Test; src: url(test.woff); }
+@keyframes spin { from { top: 0 } to { top: 10px } }
+"#;
+        let stylesheet = css.parse::<CssStylesheet>().unwrap();
+        assert_eq!(stylesheet.to_string(), css);
+        assert_eq!(
+            stylesheet.to_string().parse::<CssStylesheet>().unwrap(),
+            stylesheet
+        );
+    }
+
+    #[test]
+    fn css_inline_style_test() {
+        let mut tag = r#"<p style="color: red; DISPLAY:none !important">Text</p>"#
+            .parse::<HtmlTag>()
+            .unwrap();
+        let mut style = tag.inline_style();
+        assert_eq!(
+            style,
+            vec![
+                CssDeclaration::new("color", "red"),
+                CssDeclaration::new("display", "none").important(true),
+            ]
+        );
+        style.remove(0);
+        tag.set_inline_style(&style);
+        assert_eq!(tag.attributes["style"], "display: none !important");
+        tag.set_inline_style(&[]);
+        assert!(!tag.attributes.contains_key("style"));
+    }
+
+    #[test]
+    fn css_document_stylesheets_test() {
+        let mut doc = r#"<html><head><style>p { color: red }</style></head>
+<body><div><style>.x { display: none }</style></div></body></html>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let sheets = doc.stylesheets();
+        assert_eq!(sheets.len(), 2);
+        assert_eq!(doc.stylesheet().rules.len(), 2);
+        let mut tags = doc.style_tags_mut();
+        assert_eq!(tags.len(), 2);
+        let mut sheet = sheets[1].clone();
+        sheet.rules.push(CssRule::Style(CssStyleRule::new(
+            "b".parse::<CssSelector>().unwrap(),
+            vec![CssDeclaration::new("font-weight", "bold")],
+        )));
+        tags[1].set_stylesheet(&sheet);
+        assert_eq!(doc.stylesheets()[1], sheet);
+    }
+}
@@ -0,0 +1,573 @@
+use crate::css::{
+    CssAtRule, CssDeclaration, CssImportRule, CssMediaRule, CssRule, CssStyleRule, CssStylesheet,
+};
+use crate::objects::CssSelector;
+use hb_error::{context, ErrorContext};
+use hb_parse::error::ParseResult;
+use hb_parse::source::Source;
+use std::str::FromStr;
+
+/// Parsing functions for CSS stylesheets which can be used on any [`Source`].
+/// The parsing follows the CSS error handling rules, so invalid rules and declarations are
+/// skipped and blocks which are not closed are closed at the end of the source.
+pub trait CssParserFunctions {
+    /// Parses a whole stylesheet.
+    fn parse_css_stylesheet(&mut self) -> ParseResult<CssStylesheet>;
+    /// Parses a list of declarations seperated by ';', such as a style="" attribute.
+    fn parse_css_declarations(&mut self) -> ParseResult<Vec<CssDeclaration>>;
+    /// Finds the URLs of the url() functions and the @import strings, in the order they
+    /// appear. A url() inside of a string or comment is not a URL.
+    fn parse_css_urls(&mut self) -> ParseResult<Vec<String>>;
+}
+
+impl<S: Source> CssParserFunctions for S {
+    #[context("could not parse css stylesheet")]
+    fn parse_css_stylesheet(&mut self) -> ParseResult<CssStylesheet> {
+        Ok(CssStylesheet {
+            rules: self.parse_css_rule_list(false)?,
+        })
+    }
+
+    #[context("could not parse css declarations")]
+    fn parse_css_declarations(&mut self) -> ParseResult<Vec<CssDeclaration>> {
+        self.parse_css_declaration_block()
+    }
+
+    #[context("could not parse css urls")]
+    fn parse_css_urls(&mut self) -> ParseResult<Vec<String>> {
+        let mut urls = vec![];
+        // the name or at-keyword just before the current char
+        let mut name = String::new();
+        // true when only whitespace and comments have come after an @import
+        let mut import = false;
+        while let Some((_, c)) = self.next()? {
+            let is_import = import || name.eq_ignore_ascii_case("@import");
+            import = false;
+            match c {
+                '"' | '\'' => {
+                    let string = read_css_string(self, c)?;
+                    if is_import {
+                        urls.push(string);
+                    }
+                }
+                '(' if name.eq_ignore_ascii_case("url") => urls.push(read_css_url(self)?),
+                '/' if matches!(self.peek()?, Some((_, '*'))) => {
+                    self.next()?;
+                    skip_css_comment(self)?;
+                    import = is_import;
+                }
+                '\\' => {
+                    self.next()?;
+                }
+                '@' | '-' | '_' => {
+                    name.push(c);
+                    continue;
+                }
+                c if c.is_alphanumeric() => {
+                    name.push(c);
+                    continue;
+                }
+                c if c.is_whitespace() => import = is_import,
+                _ => (),
+            }
+            name.clear();
+        }
+        Ok(urls)
+    }
+}
+
+trait CssParserInnerFunctions {
+    fn parse_css_rule_list(&mut self, nested: bool) -> ParseResult<Vec<CssRule>>;
+    fn parse_css_at_rule(&mut self) -> ParseResult<(Option<CssRule>, bool)>;
+    fn parse_css_declaration_block(&mut self) -> ParseResult<Vec<CssDeclaration>>;
+    fn read_css_until(&mut self, stops: &[char]) -> ParseResult<(String, Option<char>)>;
+    fn skip_css_whitespace(&mut self) -> ParseResult<()>;
+}
+
+impl<S: Source> CssParserInnerFunctions for S {
+    /// Parses rules until the end of the source, or until the closing '}' if nested
+    /// inside another rule such as @media.
+    fn parse_css_rule_list(&mut self, nested: bool) -> ParseResult<Vec<CssRule>> {
+        let mut rules = vec![];
+        loop {
+            self.skip_css_whitespace()?;
+            match self.peek()? {
+                None => break,
+                Some((_, '}')) => {
+                    self.next()?;
+                    if nested {
+                        break;
+                    }
+                }
+                Some((_, '@')) => {
+                    self.next()?;
+                    let (rule, closed_parent) = self.parse_css_at_rule()?;
+                    if let Some(rule) = rule {
+                        rules.push(rule);
+                    }
+                    if closed_parent && nested {
+                        break;
+                    }
+                }
+                Some(_) => {
+                    let (prelude, stop) = self.read_css_until(&['{', '}'])?;
+                    match stop {
+                        // the rule was not finished so it is dropped
+                        None => break,
+                        Some('}') => {
+                            if nested {
+                                break;
+                            }
+                        }
+                        Some(_) => {
+                            let declarations = self.parse_css_declaration_block()?;
+                            let prelude = prelude.trim();
+                            match CssSelector::from_str(prelude) {
+                                Ok(selector) => {
+                                    rules.push(CssRule::Style(CssStyleRule::new(
+                                        selector,
+                                        declarations,
+                                    )));
+                                }
+                                Err(_) => rules.push(CssRule::UnparsedStyle {
+                                    selector: prelude.to_owned(),
+                                    declarations,
+                                }),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Parses an at-rule after the '@' has been read. Returns the rule and true if the rule
+    /// was ended by the '}' of the block it is inside of.
+    fn parse_css_at_rule(&mut self) -> ParseResult<(Option<CssRule>, bool)> {
+        let mut name = String::new();
+        while let Some((_, c)) = self.peek()? {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                name.push(c);
+                self.next()?;
+            } else {
+                break;
+            }
+        }
+        let name = name.to_lowercase();
+        let (prelude, stop) = self.read_css_until(&['{', ';', '}'])?;
+        let prelude = prelude.trim().to_owned();
+        if stop != Some('{') {
+            let rule = match name.as_str() {
+                "" => None,
+                "import" => parse_css_import(&prelude).map(CssRule::Import),
+                _ => Some(CssRule::Other(CssAtRule {
+                    name,
+                    prelude,
+                    block: None,
+                })),
+            };
+            return Ok((rule, stop == Some('}')));
+        }
+        let rule = match name.as_str() {
+            "media" => CssRule::Media(CssMediaRule {
+                query: prelude,
+                rules: self.parse_css_rule_list(true)?,
+            }),
+            "font-face" => CssRule::FontFace(self.parse_css_declaration_block()?),
+            _ => {
+                let (block, _) = self.read_css_until(&['}'])?;
+                CssRule::Other(CssAtRule {
+                    name,
+                    prelude,
+                    block: Some(block),
+                })
+            }
+        };
+        Ok((Some(rule), false))
+    }
+
+    /// Parses declarations until the closing '}' or the end of the source.
+    fn parse_css_declaration_block(&mut self) -> ParseResult<Vec<CssDeclaration>> {
+        let mut declarations = vec![];
+        loop {
+            let (declaration, stop) = self.read_css_until(&[';', '}'])?;
+            if let Some(dec) = parse_css_declaration(&declaration) {
+                declarations.push(dec);
+            }
+            if stop != Some(';') {
+                return Ok(declarations);
+            }
+        }
+    }
+
+    /// Reads chars until one of the stop chars is found outside of any brackets or
+    /// strings. Comments are removed from the output. The stop char is consumed and
+    /// returned, or None is returned if the end of the source was reached.
+    fn read_css_until(&mut self, stops: &[char]) -> ParseResult<(String, Option<char>)> {
+        let mut buffer = String::new();
+        let mut level = 0;
+        let mut quote = None;
+        while let Some((_, c)) = self.next()? {
+            if let Some(q) = quote {
+                buffer.push(c);
+                if c == '\\' {
+                    if let Some((_, escaped)) = self.next()? {
+                        buffer.push(escaped);
+                    }
+                } else if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            if level == 0 && stops.contains(&c) {
+                return Ok((buffer, Some(c)));
+            }
+            match c {
+                '"' | '\'' => quote = Some(c),
+                '(' | '[' | '{' => level += 1,
+                ')' | ']' | '}' if level > 0 => level -= 1,
+                '/' => {
+                    if let Some((_, '*')) = self.peek()? {
+                        self.next()?;
+                        skip_css_comment(self)?;
+                        continue;
+                    }
+                }
+                '\\' => {
+                    buffer.push(c);
+                    if let Some((_, escaped)) = self.next()? {
+                        buffer.push(escaped);
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+            buffer.push(c);
+        }
+        Ok((buffer, None))
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_css_whitespace(&mut self) -> ParseResult<()> {
+        while let Some((_, c)) = self.peek()? {
+            if c.is_whitespace() {
+                self.next()?;
+            } else if c == '/' {
+                self.next()?;
+                match self.peek()? {
+                    Some((_, '*')) => {
+                        self.next()?;
+                        skip_css_comment(self)?;
+                    }
+                    _ => {
+                        self.move_back(1)?;
+                        return Ok(());
+                    }
+                }
+            } else {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Skips the rest of a comment after the opening /* has been read.
+fn skip_css_comment<S: Source>(source: &mut S) -> ParseResult<()> {
+    let mut last = ' ';
+    while let Some((_, c)) = source.next()? {
+        if last == '*' && c == '/' {
+            break;
+        }
+        last = c;
+    }
+    Ok(())
+}
+
+/// Reads the rest of a quoted string after its opening quote has been read, removing the
+/// backslashes of any escaped chars.
+fn read_css_string<S: Source>(source: &mut S, quote: char) -> ParseResult<String> {
+    let mut value = String::new();
+    while let Some((_, c)) = source.next()? {
+        match c {
+            '\\' => {
+                if let Some((_, escaped)) = source.next()? {
+                    value.push(escaped);
+                }
+            }
+            c if c == quote => break,
+            c => value.push(c),
+        }
+    }
+    Ok(value)
+}
+
+/// Reads the URL of a url() function after the '(' has been read, up to and including the
+/// closing ')'. The URL can be quoted or not.
+fn read_css_url<S: Source>(source: &mut S) -> ParseResult<String> {
+    let mut url = String::new();
+    let mut quoted = false;
+    while let Some((_, c)) = source.next()? {
+        match c {
+            ')' => break,
+            '"' | '\'' if url.trim().is_empty() => {
+                url = read_css_string(source, c)?;
+                quoted = true;
+            }
+            c if !quoted => url.push(c),
+            _ => (),
+        }
+    }
+    Ok(match quoted {
+        true => url,
+        false => url.trim().to_owned(),
+    })
+}
+
+/// Parses a single "property: value" CSS declaration, returning None if it is not valid.
+fn parse_css_declaration(declaration: &str) -> Option<CssDeclaration> {
+    let (property, value) = declaration.split_once(':')?;
+    let property = property.trim();
+    if property.is_empty() || property.contains(char::is_whitespace) {
+        return None;
+    }
+    let mut value = value.trim();
+    let mut important = false;
+    if let Some(bang) = value.rfind('!') {
+        if value[bang + 1..].trim().eq_ignore_ascii_case("important") {
+            important = true;
+            value = value[..bang].trim_end();
+        }
+    }
+    Some(CssDeclaration::new(property, value).important(important))
+}
+
+/// Parses the prelude of an @import rule, which is a url() or string followed by an
+/// optional media query list. Returns None if there is no URL.
+fn parse_css_import(prelude: &str) -> Option<CssImportRule> {
+    if prelude.is_empty() {
+        return None;
+    }
+    let is_url = prelude
+        .get(..4)
+        .is_some_and(|start| start.eq_ignore_ascii_case("url("));
+    let (url, media) = if is_url {
+        match prelude.find(')') {
+            Some(end) => (&prelude[4..end], &prelude[end + 1..]),
+            None => (&prelude[4..], ""),
+        }
+    } else {
+        let quote = prelude.chars().next().unwrap_or(' ');
+        match prelude[quote.len_utf8()..].find(quote) {
+            Some(end) if quote == '"' || quote == '\'' => {
+                (&prelude[..end + 2], &prelude[end + 2..])
+            }
+            _ => (prelude, ""),
+        }
+    };
+    Some(CssImportRule {
+        url: url
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_owned(),
+        media: media.trim().to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod css_parsing_tests {
+    use super::*;
+    use hb_parse::StrParser;
+
+    fn style(selector: &str, declarations: Vec<CssDeclaration>) -> CssRule {
+        CssRule::Style(CssStyleRule::new(
+            selector.parse::<CssSelector>().unwrap(),
+            declarations,
+        ))
+    }
+
+    #[test]
+    fn parse_css_import_test() {
+        assert_eq!(
+            parse_css_import("abcé"),
+            Some(CssImportRule {
+                url: "abcé".to_owned(),
+                media: String::new(),
+            })
+        );
+        assert_eq!(
+            parse_css_import("'é.css' print"),
+            Some(CssImportRule {
+                url: "é.css".to_owned(),
+                media: "print".to_owned(),
+            })
+        );
+        assert_eq!(parse_css_import(""), None);
+        for css in ["@import abcé;", "@import;", "@import ;"] {
+            let stylesheet = StrParser::new(css).parse_css_stylesheet().unwrap();
+            let imports = stylesheet
+                .rules
+                .iter()
+                .filter(|r| matches!(r, CssRule::Import(_)))
+                .count();
+            assert_eq!(imports, usize::from(css.contains('é')), "{}", css);
+        }
+    }
+
+    #[test]
+    fn parse_css_urls_test() {
+        let css = r#"@import 'a.css'; @IMPORT /* c */ "b\".css" screen;
+@import url(c.css);
+body { background: URL( "d.png" ) no-repeat, url(  e.png  ); content: "url(f.png)" }
+/* url(g.png) */ .x::after { content: '\'' } div { mask: -webkit-url(h.png) }
+@font-face { src: url('i.woff') format('woff') }"#;
+        assert_eq!(
+            StrParser::new(css).parse_css_urls().unwrap(),
+            vec!["a.css", "b\".css", "c.css", "d.png", "e.png", "i.woff"]
+        );
+        assert_eq!(
+            StrParser::new("a { b: url(unclosed")
+                .parse_css_urls()
+                .unwrap(),
+            vec!["unclosed"]
+        );
+        assert!(StrParser::new("").parse_css_urls().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_css_declarations_test() {
+        assert_eq!(
+            StrParser::new("color: red; display:none !important;")
+                .parse_css_declarations()
+                .unwrap(),
+            vec![
+                CssDeclaration::new("color", "red"),
+                CssDeclaration::new("display", "none").important(true),
+            ]
+        );
+        assert_eq!(
+            StrParser::new(
+                "background: url(\"a;b.png\") /* a ; comment */; content: 'x;y' ! IMPORTANT"
+            )
+            .parse_css_declarations()
+            .unwrap(),
+            vec![
+                CssDeclaration::new("background", "url(\"a;b.png\")"),
+                CssDeclaration::new("content", "'x;y'").important(true),
+            ]
+        );
+        assert_eq!(
+            StrParser::new("COLOR: Blue;; no colon; : red; bad name: 1; font: 12px/1.5 serif")
+                .parse_css_declarations()
+                .unwrap(),
+            vec![
+                CssDeclaration::new("color", "Blue"),
+                CssDeclaration::new("font", "12px/1.5 serif"),
+            ]
+        );
+        assert_eq!(StrParser::new("").parse_css_declarations().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn parse_css_stylesheet_test() {
+        let css = r#"
+/* A comment { with braces } */
+@charset "utf-8";
+@import url('theme.css') screen and (orientation: landscape);
+@import "print.css" print;
+body, p.intro { margin: 0; color: #333 !important }
+a:hover { color: red }
+div > span.note
+{
+    font-family: "Open Sans", /* fallback */ sans-serif;
+    content: "}";
+}
+@media screen and (max-width: 600px) {
+    .sidebar { display: none; }
+    @media print { p { color: black } }
+}
+@font-face { font-family: "Test"; src: url(test.woff2) format("woff2"); }
+@keyframes fade { from { opacity: 0 } to { opacity: 1 } }
+bad rule without block"#;
+        let stylesheet = StrParser::new(css).parse_css_stylesheet().unwrap();
+        assert_eq!(
+            stylesheet.rules,
+            vec![
+                CssRule::Other(CssAtRule {
+                    name: "charset".to_owned(),
+                    prelude: "\"utf-8\"".to_owned(),
+                    block: None,
+                }),
+                CssRule::Import(CssImportRule {
+                    url: "theme.css".to_owned(),
+                    media: "screen and (orientation: landscape)".to_owned(),
+                }),
+                CssRule::Import(CssImportRule {
+                    url: "print.css".to_owned(),
+                    media: "print".to_owned(),
+                }),
+                style(
+                    "body, p.intro",
+                    vec![
+                        CssDeclaration::new("margin", "0"),
+                        CssDeclaration::new("color", "#333").important(true),
+                    ]
+                ),
+                CssRule::UnparsedStyle {
+                    selector: "a:hover".to_owned(),
+                    declarations: vec![CssDeclaration::new("color", "red")],
+                },
+                style(
+                    "div > span.note",
+                    vec![
+                        CssDeclaration::new("font-family", "\"Open Sans\",  sans-serif"),
+                        CssDeclaration::new("content", "\"}\""),
+                    ]
+                ),
+                CssRule::Media(CssMediaRule {
+                    query: "screen and (max-width: 600px)".to_owned(),
+                    rules: vec![
+                        style(".sidebar", vec![CssDeclaration::new("display", "none")]),
+                        CssRule::Media(CssMediaRule {
+                            query: "print".to_owned(),
+                            rules: vec![style("p", vec![CssDeclaration::new("color", "black")])],
+                        }),
+                    ],
+                }),
+                CssRule::FontFace(vec![
+                    CssDeclaration::new("font-family", "\"Test\""),
+                    CssDeclaration::new("src", "url(test.woff2) format(\"woff2\")"),
+                ]),
+                CssRule::Other(CssAtRule {
+                    name: "keyframes".to_owned(),
+                    prelude: "fade".to_owned(),
+                    block: Some(" from { opacity: 0 } to { opacity: 1 } ".to_owned()),
+                }),
+            ]
+        );
+        // only the style rules that apply to a screen are used
+        assert_eq!(stylesheet.style_rules().len(), 3);
+    }
+
+    #[test]
+    fn parse_css_unclosed_test() {
+        let stylesheet = StrParser::new("p { color: red; @media screen { a { color: blue")
+            .parse_css_stylesheet()
+            .unwrap();
+        assert_eq!(
+            stylesheet.rules,
+            vec![style("p", vec![CssDeclaration::new("color", "red")])]
+        );
+        let stylesheet = StrParser::new("@media screen { a { color: blue")
+            .parse_css_stylesheet()
+            .unwrap();
+        assert_eq!(
+            stylesheet.rules,
+            vec![CssRule::Media(CssMediaRule {
+                query: "screen".to_owned(),
+                rules: vec![style("a", vec![CssDeclaration::new("color", "blue")])],
+            })]
+        );
+    }
+}
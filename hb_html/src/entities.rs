@@ -0,0 +1,204 @@
+//! Decoding and encoding of HTML character references such as `&amp;` and `&#x27;`.
+//!
+//! The parser keeps character references in text and attributes as they were written,
+//! so they are decoded when the text is used outside of HTML, and text from elsewhere is
+//! encoded before it is put in the tree.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::entities::decode_entities;
+//! assert_eq!(decode_entities("Fish &amp; chips &#8211; &pound;5&nbsp;each"), "Fish & chips – £5\u{a0}each");
+//! assert_eq!(decode_entities("&unknown; & &#xZZ;"), "&unknown; & &#xZZ;");
+//! ```
+
+/// The named character references that are decoded. This is the commonly used part of
+/// the list in the HTML spec.
+const NAMED_ENTITIES: [(&str, char); 64] = [
+    ("AMP", '&'),
+    ("GT", '>'),
+    ("LT", '<'),
+    ("QUOT", '"'),
+    ("amp", '&'),
+    ("apos", '\''),
+    ("bdquo", '„'),
+    ("brvbar", '¦'),
+    ("bull", '•'),
+    ("cent", '¢'),
+    ("copy", '©'),
+    ("curren", '¤'),
+    ("dagger", '†'),
+    ("deg", '°'),
+    ("divide", '÷'),
+    ("emsp", '\u{2003}'),
+    ("ensp", '\u{2002}'),
+    ("euro", '€'),
+    ("frac12", '½'),
+    ("frac14", '¼'),
+    ("frac34", '¾'),
+    ("gt", '>'),
+    ("hellip", '…'),
+    ("iexcl", '¡'),
+    ("iquest", '¿'),
+    ("laquo", '«'),
+    ("larr", '←'),
+    ("ldquo", '“'),
+    ("lsaquo", '‹'),
+    ("lsquo", '‘'),
+    ("lt", '<'),
+    ("macr", '¯'),
+    ("mdash", '—'),
+    ("micro", 'µ'),
+    ("middot", '·'),
+    ("minus", '−'),
+    ("nbsp", '\u{a0}'),
+    ("ndash", '–'),
+    ("not", '¬'),
+    ("ordf", 'ª'),
+    ("ordm", 'º'),
+    ("para", '¶'),
+    ("permil", '‰'),
+    ("plusmn", '±'),
+    ("pound", '£'),
+    ("prime", '′'),
+    ("quot", '"'),
+    ("raquo", '»'),
+    ("rarr", '→'),
+    ("rdquo", '”'),
+    ("reg", '®'),
+    ("rsaquo", '›'),
+    ("rsquo", '’'),
+    ("sbquo", '‚'),
+    ("sect", '§'),
+    ("shy", '\u{ad}'),
+    ("sup1", '¹'),
+    ("sup2", '²'),
+    ("sup3", '³'),
+    ("thinsp", '\u{2009}'),
+    ("times", '×'),
+    ("trade", '™'),
+    ("uml", '¨'),
+    ("yen", '¥'),
+];
+
+/// Decodes the numeric character references and the common named references in text.
+/// References which are not known or not valid are left as they are.
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match decode_reference(rest) {
+            Some((c, used)) => {
+                output.push(c);
+                rest = &rest[used..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Collapses each run of white space in text into a single space, trimming it at both
+/// ends.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Decodes the character references in text and collapses its white space into single
+/// spaces, trimming it at both ends. This is how text is read from a document to be
+/// shown on its own, such as a title.
+pub fn decode_text(text: &str) -> String {
+    collapse_whitespace(&decode_entities(text))
+}
+
+/// Encodes the characters which have a meaning in HTML, so the text can be used as the
+/// text or an attribute value of a [`HtmlNode`](crate::objects::HtmlNode).
+pub fn encode_entities(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+/// Decodes the reference at the start of the text, returning the character and the
+/// number of bytes used.
+fn decode_reference(text: &str) -> Option<(char, usize)> {
+    let end = text[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '#')? + 1;
+    if !text[end..].starts_with(';') {
+        return None;
+    }
+    let name = &text[1..end];
+    let c = match name.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse::<u32>().ok()?,
+            };
+            match code {
+                0 => char::REPLACEMENT_CHARACTER,
+                code => char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER),
+            }
+        }
+        None => NAMED_ENTITIES.iter().find(|(entity, _)| *entity == name)?.1,
+    };
+    Some((c, end + 1))
+}
+
+#[cfg(test)]
+mod entities_tests {
+    use super::*;
+
+    #[test]
+    fn decode_entities_test() {
+        assert_eq!(
+            decode_entities("a &lt;b&gt; &AMP; &quot;c&quot;"),
+            "a <b> & \"c\""
+        );
+        assert_eq!(
+            decode_entities("&#65;&#x42;&#X43;&#0;&#xD800;"),
+            "ABC\u{fffd}\u{fffd}"
+        );
+        assert_eq!(
+            decode_entities("&amp &; &#; & ;&ampx;"),
+            "&amp &; &#; & ;&ampx;"
+        );
+        assert_eq!(decode_entities("tail &"), "tail &");
+        assert_eq!(decode_entities("日本 &copy; 語"), "日本 © 語");
+    }
+
+    #[test]
+    fn decode_text_test() {
+        assert_eq!(
+            decode_text("\n  Fish &amp;\tchips\n\n&lt;b&gt;  "),
+            "Fish & chips <b>"
+        );
+        assert_eq!(decode_text("a&nbsp;b &#32; c"), "a b c");
+        assert_eq!(decode_text(" \n "), "");
+    }
+
+    #[test]
+    fn encode_entities_test() {
+        assert_eq!(
+            encode_entities("<a href=\"x\">&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;'&lt;/a&gt;"
+        );
+        let text = "a < b && \"c\"";
+        assert_eq!(decode_entities(&encode_entities(text)), text);
+    }
+}
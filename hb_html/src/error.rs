@@ -1,5 +1,8 @@
+use hb_error::{hberror, ErrorContext};
 use std::error::Error;
 
+pub use hb_selector::error::ParseHtmlError;
+
 pub struct HtmlMatchError {
     msg: String,
 }
@@ -53,52 +56,97 @@ impl std::fmt::Debug for HtmlDocError {
     }
 }
 
-#[derive(PartialEq)]
-pub struct ParseHtmlError {
-    pub msg: String,
+impl Error for HtmlMatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
 }
 
-impl ParseHtmlError {
-    pub fn new(msg: String) -> ParseHtmlError {
-        ParseHtmlError { msg: msg }
+impl Error for HtmlDocError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
     }
+}
+
+/// The error returned when a value can't be extracted from a HTML document with
+/// [`crate::extract::HtmlExtract`]. It names the field and the selector that failed.
+#[hberror("{self.msg} (field '{self.field}', selector '{self.selector}'){self.because()}")]
+pub struct HtmlExtractError {
+    pub field: String,
+    pub selector: String,
+}
 
-    pub fn with_msg<S: Into<String>>(msg: S) -> ParseHtmlError {
-        return ParseHtmlError::new(msg.into());
+impl HtmlExtractError {
+    /// Creates an error with the message for the field and selector provided.
+    pub fn with_field<S: Into<String>>(field: &str, selector: &str, msg: S) -> HtmlExtractError {
+        let mut e = HtmlExtractError::new().msg(msg);
+        e.field = field.to_owned();
+        e.selector = selector.to_owned();
+        e
     }
 
-    pub fn add_context<S: Into<String>>(mut self, msg: S) -> ParseHtmlError {
-        self.msg = format!("{} because {}", msg.into(), self.msg);
-        self
+    /// Wraps an error from extracting a nested value in an error for the field and selector
+    /// the nested value came from.
+    pub fn nested(self, field: &str, selector: &str) -> HtmlExtractError {
+        let mut e =
+            HtmlExtractError::with_field(field, selector, "could not extract the nested value");
+        e.inner_msgs.push(format!("{}", self));
+        e
+    }
+
+    /// Formats the inner messages, each one on a new line.
+    fn because(&self) -> String {
+        self.inner_msgs
+            .iter()
+            .map(|m| format!("\n...because...{}", m))
+            .collect()
     }
 }
 
-impl std::fmt::Display for ParseHtmlError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "Html Doc Error: '{}'", self.msg)?;
-        Ok(())
+impl Error for HtmlExtractError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
     }
 }
-impl std::fmt::Debug for ParseHtmlError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "Html Doc Error: '{}'", self.msg)?;
-        Ok(())
+
+/// The error returned when a [`crate::form::HtmlForm`] can't be changed or submitted.
+/// It names the control that caused it.
+#[hberror("{self.msg} (control '{self.name}')")]
+pub struct HtmlFormError {
+    pub name: String,
+}
+
+impl HtmlFormError {
+    /// Creates an error with the message for the control with the name.
+    pub fn with_name<S: Into<String>>(name: &str, msg: S) -> HtmlFormError {
+        let mut e = HtmlFormError::new().msg(msg);
+        e.name = name.to_owned();
+        e
     }
 }
 
-impl Error for ParseHtmlError {
+impl Error for HtmlFormError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         None
     }
 }
 
-impl Error for HtmlMatchError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+/// The error returned when a [`crate::url::Url`] can't be parsed.
+#[hberror("{self.msg} (url '{self.url}')")]
+pub struct UrlError {
+    pub url: String,
+}
+
+impl UrlError {
+    /// Creates an error with the message for the url.
+    pub fn with_url<S: Into<String>>(url: &str, msg: S) -> UrlError {
+        let mut e = UrlError::new().msg(msg);
+        e.url = url.to_owned();
+        e
     }
 }
 
-impl Error for HtmlDocError {
+impl Error for UrlError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         None
     }
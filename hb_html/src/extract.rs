@@ -0,0 +1,502 @@
+//! Fills structs with values taken from a HTML document.
+//!
+//! The [`HtmlExtract`] trait is usually implemented with `#[derive(HtmlExtract)]`, where each
+//! field has a `#[html(...)]` attribute with the selector used to find it.
+//!
+//! * `select = "..."` - the CSS selector used to find the value, required. Inside a nested
+//!   struct `":scope"` can be used to refer to the tag the struct was extracted from. The
+//!   selector is checked when the crate is compiled.
+//! * `text` - use the decoded and trimmed text of the tag, this is the default.
+//! * `attr = "..."` - use the decoded value of an attribute of the tag.
+//! * `nested` - the field is another struct implementing [`HtmlExtract`], which is filled
+//!   using the tag that was found.
+//!
+//! The field can be any type implementing [`FromStr`], an `Option` of one, which is `None`
+//! when nothing is found, or a `Vec` of one, which contains a value for every tag found.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::extract::HtmlExtract;
+//! use hb_html::objects::HtmlDocument;
+//!
+//! #[derive(HtmlExtract)]
+//! struct Review {
+//!     #[html(select = ".stars")]
+//!     stars: u8,
+//!     #[html(select = ":scope", attr = "data-author")]
+//!     author: String,
+//! }
+//!
+//! #[derive(HtmlExtract)]
+//! struct Product {
+//!     #[html(select = "h1")]
+//!     name: String,
+//!     #[html(select = ".price")]
+//!     price: f64,
+//!     #[html(select = "a.more", attr = "href")]
+//!     more: Option<String>,
+//!     #[html(select = ".review", nested)]
+//!     reviews: Vec<Review>,
+//! }
+//!
+//! let doc = r#"<h1> Tea pot </h1><span class=price>12.5</span>
+//! <div class=review data-author=Sam><span class=stars>4</span></div>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let product = Product::from_document(&doc).unwrap();
+//! assert_eq!(product.name, "Tea pot");
+//! assert_eq!(product.price, 12.5);
+//! assert_eq!(product.more, None);
+//! assert_eq!(product.reviews[0].stars, 4);
+//! assert_eq!(product.reviews[0].author, "Sam");
+//! ```
+//!
+//! A selector which can't be parsed is a compile error:
+//!
+//! ```compile_fail
+//! #[derive(hb_html::extract::HtmlExtract)]
+//! struct Item {
+//!     // error: unknown refiner type frist-child. at column 3
+//!     #[html(select = "li:frist-child")]
+//!     name: String,
+//! }
+//! ```
+use crate::entities::decode_entities;
+pub use crate::error::HtmlExtractError;
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode};
+use crate::querying::{HtmlQueryResult, NodeRef};
+pub use hb_macros::HtmlExtract;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The selector used to refer to the tag a nested struct is extracted from.
+const SCOPE_SELECTOR: &str = ":scope";
+
+/// A type which can be filled with values from a HTML document.
+pub trait HtmlExtract: Sized {
+    /// Extracts the value from within the scope provided.
+    fn extract(scope: ExtractScope) -> Result<Self, HtmlExtractError>;
+
+    /// Extracts the value from the whole document.
+    fn from_document(doc: &HtmlDocument) -> Result<Self, HtmlExtractError> {
+        Self::extract(ExtractScope::Document(doc))
+    }
+
+    /// Extracts the value from the tags inside the node.
+    fn from_node(node: &HtmlQueryResult) -> Result<Self, HtmlExtractError> {
+        Self::extract(ExtractScope::Node(node))
+    }
+}
+
+/// What part of a tag a value is taken from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtractMode<'m> {
+    /// The decoded and trimmed text inside the tag.
+    Text,
+    /// The decoded value of an attribute of the tag.
+    Attr(&'m str),
+}
+
+impl<'m> ExtractMode<'m> {
+    /// Gets the value from the node, or None if it is not a tag or does not have the attribute.
+    pub fn value(&self, node: &HtmlQueryResult) -> Option<String> {
+        let tag = match node.get_node() {
+            Some(HtmlNode::Tag(t)) => t,
+            _ => return None,
+        };
+        let value = match self {
+            ExtractMode::Text => return Some(decode_entities(&tag.text()).trim().to_owned()),
+            // ids and classes are not stored with the other attributes
+            ExtractMode::Attr("id") if !tag.ids.is_empty() => tag.ids.join(" "),
+            ExtractMode::Attr("class") if !tag.classes.is_empty() => tag.classes.join(" "),
+            ExtractMode::Attr(name) => tag.attributes.get(*name)?.clone(),
+        };
+        Some(decode_entities(&value))
+    }
+}
+
+/// The selector of a field, which is parsed once when it is created and then used every
+/// time the struct is extracted. The derive checks its selectors when the crate is
+/// compiled. Otherwise a selector which can't be parsed is an error when it is used, so the
+/// error names the field it belongs to.
+#[derive(Debug)]
+pub struct ExtractSelector {
+    selector: String,
+    parsed: Result<Option<CssSelector>, String>,
+}
+
+impl ExtractSelector {
+    /// Parses the selector, where `":scope"` is the tag a nested struct is extracted from.
+    pub fn new(selector: &str) -> ExtractSelector {
+        let parsed = match selector {
+            SCOPE_SELECTOR => Ok(None),
+            _ => CssSelector::from_str(selector)
+                .map(Some)
+                .map_err(|e| format!("could not parse the selector: {}", e.msg)),
+        };
+        ExtractSelector {
+            selector: selector.to_owned(),
+            parsed,
+        }
+    }
+
+    /// The selector as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.selector
+    }
+}
+
+/// Where the values are searched for, either a whole document or the tags inside a node.
+#[derive(Debug, Clone, Copy)]
+pub enum ExtractScope<'d> {
+    Document(&'d HtmlDocument),
+    Node(&'d HtmlQueryResult<'d>),
+}
+
+impl<'d> ExtractScope<'d> {
+    /// Lazily finds the tags matching the selector in the scope, in document order.
+    fn select<'s>(
+        &self,
+        field: &str,
+        selector: &'s ExtractSelector,
+    ) -> Result<Box<dyn Iterator<Item = NodeRef<'d>> + 's>, HtmlExtractError>
+    where
+        'd: 's,
+    {
+        let error = |msg: &str| HtmlExtractError::with_field(field, selector.as_str(), msg);
+        Ok(match (&selector.parsed, self) {
+            (Err(e), _) => return Err(error(e)),
+            (Ok(None), ExtractScope::Node(n)) => Box::new(std::iter::once((*n).clone())),
+            (Ok(None), ExtractScope::Document(_)) => {
+                return Err(error(":scope can only be used when extracting from a node"))
+            }
+            (Ok(Some(css)), ExtractScope::Document(d)) => Box::new(d.select(css)),
+            (Ok(Some(css)), ExtractScope::Node(n)) => Box::new(n.select(css)),
+        })
+    }
+
+    /// Finds the tags matching the selector in the scope, in document order.
+    pub fn select_all(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+    ) -> Result<Vec<NodeRef<'d>>, HtmlExtractError> {
+        Ok(self.select(field, selector)?.collect())
+    }
+
+    /// Finds the first tag matching the selector in the scope, stopping the search as soon
+    /// as it is found.
+    pub fn select_first(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+    ) -> Result<Option<NodeRef<'d>>, HtmlExtractError> {
+        Ok(self.select(field, selector)?.next())
+    }
+
+    /// Extracts a value from the first tag matching the selector, failing if there isn't one.
+    pub fn extract_one<T>(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+        mode: ExtractMode,
+    ) -> Result<T, HtmlExtractError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.extract_optional(field, selector, mode)? {
+            Some(v) => Ok(v),
+            None => Err(HtmlExtractError::with_field(
+                field,
+                selector.as_str(),
+                match mode {
+                    ExtractMode::Text => "no tag matched the selector".to_owned(),
+                    ExtractMode::Attr(a) => {
+                        format!("no tag matched the selector with a {} attribute", a)
+                    }
+                },
+            )),
+        }
+    }
+
+    /// Extracts a value from the first tag matching the selector, if there is one.
+    pub fn extract_optional<T>(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+        mode: ExtractMode,
+    ) -> Result<Option<T>, HtmlExtractError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.select_first(field, selector)? {
+            None => Ok(None),
+            Some(node) => match mode.value(&node) {
+                None => Ok(None),
+                Some(value) => convert_value(field, selector.as_str(), &value).map(Some),
+            },
+        }
+    }
+
+    /// Extracts a value from every tag matching the selector. Tags without the attribute
+    /// are skipped.
+    pub fn extract_all<T>(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+        mode: ExtractMode,
+    ) -> Result<Vec<T>, HtmlExtractError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.select(field, selector)?
+            .filter_map(|node| mode.value(&node))
+            .map(|value| convert_value(field, selector.as_str(), &value))
+            .collect()
+    }
+
+    /// Extracts a nested value from the first tag matching the selector, failing if there
+    /// isn't one.
+    pub fn extract_nested<T: HtmlExtract>(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+    ) -> Result<T, HtmlExtractError> {
+        match self.extract_nested_optional(field, selector)? {
+            Some(v) => Ok(v),
+            None => Err(HtmlExtractError::with_field(
+                field,
+                selector.as_str(),
+                "no tag matched the selector",
+            )),
+        }
+    }
+
+    /// Extracts a nested value from the first tag matching the selector, if there is one.
+    pub fn extract_nested_optional<T: HtmlExtract>(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+    ) -> Result<Option<T>, HtmlExtractError> {
+        match self.select_first(field, selector)? {
+            None => Ok(None),
+            Some(node) => T::from_node(&node)
+                .map(Some)
+                .map_err(|e| e.nested(field, selector.as_str())),
+        }
+    }
+
+    /// Extracts a nested value from every tag matching the selector.
+    pub fn extract_nested_all<T: HtmlExtract>(
+        &self,
+        field: &str,
+        selector: &ExtractSelector,
+    ) -> Result<Vec<T>, HtmlExtractError> {
+        self.select(field, selector)?
+            .map(|node| T::from_node(&node).map_err(|e| e.nested(field, selector.as_str())))
+            .collect()
+    }
+}
+
+/// Converts the text taken from a tag into the type of the field.
+fn convert_value<T>(field: &str, selector: &str, value: &str) -> Result<T, HtmlExtractError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    T::from_str(value).map_err(|e| {
+        HtmlExtractError::with_field(
+            field,
+            selector,
+            format!("could not convert '{}': {}", value, e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod extract_tests {
+    use super::*;
+
+    #[derive(HtmlExtract, Debug, Clone, PartialEq)]
+    struct Link {
+        #[html(select = ":scope")]
+        text: String,
+        #[html(select = ":scope", attr = "href")]
+        href: String,
+        #[html(select = ":scope", attr = "class")]
+        class: Option<String>,
+    }
+
+    #[derive(HtmlExtract, Debug, PartialEq)]
+    struct Page {
+        #[html(select = "title")]
+        title: String,
+        #[html(select = "meta[name=count]", attr = "content")]
+        count: u32,
+        #[html(select = "h2", text)]
+        subtitle: Option<String>,
+        #[html(select = "li.num")]
+        numbers: Vec<i64>,
+        #[html(select = "nav a", nested)]
+        links: Vec<Link>,
+        #[html(select = "a.main", nested)]
+        main: Link,
+        #[html(select = "a.missing", nested)]
+        missing: Option<Link>,
+    }
+
+    const DOC: &str = r#"<html><head><title> Test Page </title><meta name=count content=3></head>
+<body><nav><a href="/a" class=main>A</a> <a href="/b">B</a></nav>
+<ul><li class=num>1</li><li class=num> -2 </li><li>x</li></ul></body></html>"#;
+
+    #[test]
+    fn extract_document_test() {
+        let doc = DOC.parse::<HtmlDocument>().unwrap();
+        let page = Page::from_document(&doc).unwrap();
+        let a = Link {
+            text: "A".to_owned(),
+            href: "/a".to_owned(),
+            class: Some("main".to_owned()),
+        };
+        assert_eq!(
+            page,
+            Page {
+                title: "Test Page".to_owned(),
+                count: 3,
+                subtitle: None,
+                numbers: vec![1, -2],
+                links: vec![
+                    a.clone(),
+                    Link {
+                        text: "B".to_owned(),
+                        href: "/b".to_owned(),
+                        class: None,
+                    }
+                ],
+                main: a,
+                missing: None,
+            }
+        );
+    }
+
+    #[test]
+    fn extract_errors_test() {
+        #[derive(HtmlExtract, Debug)]
+        #[allow(dead_code)]
+        struct Bad {
+            #[html(select = "li:not(.num)")]
+            number: i32,
+        }
+        #[derive(HtmlExtract, Debug)]
+        #[allow(dead_code)]
+        struct Missing {
+            #[html(select = "table td", attr = "title")]
+            cell: String,
+        }
+        #[derive(HtmlExtract, Debug)]
+        #[allow(dead_code)]
+        struct Outer {
+            #[html(select = "ul", nested)]
+            inner: Bad,
+        }
+        let doc = DOC.parse::<HtmlDocument>().unwrap();
+        let e = Bad::from_document(&doc).unwrap_err();
+        assert_eq!(e.field, "number");
+        assert_eq!(e.selector, "li:not(.num)");
+        assert_eq!(
+            format!("{}", e),
+            "could not convert 'x': invalid digit found in string (field 'number', selector 'li:not(.num)')"
+        );
+        let e = Missing::from_document(&doc).unwrap_err();
+        assert_eq!(
+            format!("{}", e),
+            "no tag matched the selector with a title attribute (field 'cell', selector 'table td')"
+        );
+        let e = Outer::from_document(&doc).unwrap_err();
+        assert_eq!((e.field.as_str(), e.selector.as_str()), ("inner", "ul"));
+        assert_eq!(
+            format!("{}", e),
+            "could not extract the nested value (field 'inner', selector 'ul')\n...because...could not convert 'x': invalid digit found in string (field 'number', selector 'li:not(.num)')"
+        );
+        // :scope needs a node
+        let e = Link::from_document(&doc).unwrap_err();
+        assert_eq!(e.selector, ":scope");
+    }
+
+    #[test]
+    fn extract_selector_test() {
+        #[derive(HtmlExtract, Debug)]
+        struct First<T: FromStr>
+        where
+            T::Err: Display,
+        {
+            #[html(select = "li")]
+            item: T,
+        }
+        let doc = DOC.parse::<HtmlDocument>().unwrap();
+        // the selector is parsed once, but fails every time it is used
+        let invalid = ExtractSelector::new("li[");
+        let scope = ExtractScope::Document(&doc);
+        for _ in 0..2 {
+            let e = scope
+                .extract_optional::<String>("item", &invalid, ExtractMode::Text)
+                .unwrap_err();
+            assert_eq!((e.field.as_str(), e.selector.as_str()), ("item", "li["));
+            assert!(format!("{}", e).starts_with("could not parse the selector"));
+        }
+        assert_eq!(First::<i32>::from_document(&doc).unwrap().item, 1);
+        assert_eq!(First::<String>::from_document(&doc).unwrap().item, "1");
+
+        let selector = ExtractSelector::new("li.num");
+        let first = scope.select_first("item", &selector).unwrap().unwrap();
+        assert_eq!(first, scope.select_all("item", &selector).unwrap()[0]);
+    }
+
+    #[test]
+    fn extract_entities_test() {
+        #[derive(HtmlExtract)]
+        struct Dish {
+            #[html(select = "h2")]
+            name: String,
+            #[html(select = ".price")]
+            price: u32,
+            #[html(select = ".sold")]
+            sold: String,
+            #[html(select = "a", attr = "href")]
+            link: String,
+        }
+        let doc = r#"<h2>Fish &amp; chips</h2><span class=price>&#32;12&nbsp;</span>
+<span class=sold>1&#44;000</span><a href="/dish?a=1&amp;b=2">more</a>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let dish = Dish::from_document(&doc).unwrap();
+        assert_eq!(dish.name, "Fish & chips");
+        // the text is decoded before it is trimmed and parsed
+        assert_eq!(dish.price, 12);
+        assert_eq!(dish.sold, "1,000");
+        assert_eq!(dish.link, "/dish?a=1&b=2");
+    }
+
+    #[test]
+    fn extract_from_node_test() {
+        let doc = DOC.parse::<HtmlDocument>().unwrap();
+        let ul = doc
+            .select_first(&CssSelector::from_str("ul").unwrap())
+            .unwrap();
+        #[derive(HtmlExtract)]
+        struct Numbers {
+            #[html(select = "li.num")]
+            all: Vec<String>,
+            #[html(select = "title")]
+            title: Option<String>,
+        }
+        let numbers = Numbers::from_node(&ul).unwrap();
+        assert_eq!(numbers.all, vec!["1", "-2"]);
+        // only the tags inside the node are searched
+        assert_eq!(numbers.title, None);
+    }
+}
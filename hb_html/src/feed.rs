@@ -0,0 +1,1203 @@
+//! Reads RSS 0.90, 0.91, 0.92, 1.0 and 2.0 feeds and Atom 1.0 feeds into a common [`Feed`]
+//! with its [`FeedEntry`] items.
+//!
+//! Feeds are parsed with [`HtmlDocument::parse_xml_lenient`], so the usual mistakes such
+//! as HTML entities, bare ampersands, unclosed tags or missing namespace declarations don't
+//! stop a feed from being read. Elements are matched by their namespace when they have
+//! one, and by their conventional prefix (`atom`, `dc`, `content`, `media`) when it was
+//! not declared.
+//!
+//! The values are taken from these elements, the first one found winning:
+//!
+//! | Value | RSS | Atom |
+//! |-------|-----|------|
+//! | id | `guid`, `rdf:about` | `id` |
+//! | title | `title` | `title` |
+//! | description | `description` | `subtitle` |
+//! | summary | `description` | `summary` |
+//! | content | `content:encoded` | `content` |
+//! | published | `pubDate`, `dc:date` | `published` |
+//! | updated | `lastBuildDate`, `pubDate`, `dc:date` for the channel | `updated` |
+//! | authors | `managingEditor`, `author`, `dc:creator` | `author` |
+//! | categories | `category`, `dc:subject` | `category` |
+//! | enclosures | `enclosure`, `media:content` | `link rel="enclosure"`, `media:content` |
+//!
+//! Titles are plain text, while summaries and content are HTML. Atom entries without an
+//! author have the authors of the feed. URLs are resolved against `xml:base` and the URL
+//! of the feed, and are left relative when there is no absolute base.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::feed::{Feed, FeedFormat};
+//! use hb_html::url::Url;
+//! let xml = r#"<?xml version="1.0"?>
+//! <rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+//! <channel>
+//!   <title>Recipes</title>
+//!   <link>https://example.com/</link>
+//!   <item>
+//!     <title>Pancakes &amp; syrup</title>
+//!     <link>/recipes/pancakes</link>
+//!     <description>&lt;p&gt;Quick &amp;amp; easy.&lt;/p&gt;</description>
+//!     <dc:creator>Sam</dc:creator>
+//!     <pubDate>Sat, 07 Sep 2002 09:42:31 GMT</pubDate>
+//!   </item>
+//! </channel>
+//! </rss>"#;
+//! let url = Url::parse("https://example.com/feed.xml").unwrap();
+//! let feed = Feed::parse(xml, Some(&url)).unwrap();
+//! assert_eq!(feed.format, FeedFormat::Rss20);
+//! assert_eq!(feed.title.as_deref(), Some("Recipes"));
+//! let entry = &feed.entries[0];
+//! assert_eq!(entry.title.as_deref(), Some("Pancakes & syrup"));
+//! assert_eq!(
+//!     entry.link().unwrap().to_string(),
+//!     "https://example.com/recipes/pancakes"
+//! );
+//! assert_eq!(entry.summary.as_deref(), Some("<p>Quick &amp; easy.</p>"));
+//! assert_eq!(entry.authors[0].name.as_deref(), Some("Sam"));
+//! assert_eq!(entry.published.unwrap().to_string(), "2002-09-07T09:42:31Z");
+//! ```
+use crate::entities::{decode_entities, decode_text};
+use crate::error::ParseHtmlError;
+use crate::objects::{HtmlDocument, HtmlNode, HtmlTag};
+use crate::reading::{children, resolve};
+use crate::url::Url;
+use std::fmt;
+
+const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+const ATOM_03_NAMESPACE: &str = "http://purl.org/atom/ns#";
+const RSS_090_NAMESPACE: &str = "http://my.netscape.com/rdf/simple/0.9/";
+const RSS_10_NAMESPACE: &str = "http://purl.org/rss/1.0/";
+const RDF_NAMESPACE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const DUBLIN_CORE_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+const CONTENT_NAMESPACE: &str = "http://purl.org/rss/1.0/modules/content/";
+const MEDIA_NAMESPACE: &str = "http://search.yahoo.com/mrss/";
+
+/// The format a [`Feed`] was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeedFormat {
+    /// RSS 0.90, the first RDF based version.
+    Rss090,
+    /// RSS 0.91.
+    Rss091,
+    /// RSS 0.92, 0.93 or 0.94.
+    Rss092,
+    /// RSS 1.0, which is RDF based.
+    Rss10,
+    /// RSS 2.0, or an RSS feed without a known version.
+    Rss20,
+    /// Atom 1.0, or the Atom 0.3 draft.
+    Atom,
+}
+
+/// A RSS channel or an Atom feed, see the [module documentation](self) for where each
+/// value comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub format: FeedFormat,
+    pub title: Option<String>,
+    /// The description of a RSS channel or the subtitle of an Atom feed, as plain text.
+    pub description: Option<String>,
+    pub id: Option<String>,
+    pub links: Vec<FeedLink>,
+    /// The language tag of the feed, such as "en" or "pt-BR".
+    pub language: Option<String>,
+    pub updated: Option<FeedDate>,
+    pub authors: Vec<FeedPerson>,
+    pub categories: Vec<FeedCategory>,
+    /// The image of a RSS channel, or the logo or else the icon of an Atom feed.
+    pub image: Option<Url>,
+    pub entries: Vec<FeedEntry>,
+}
+
+/// An item of a RSS channel or an entry of an Atom feed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FeedEntry {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub links: Vec<FeedLink>,
+    /// The short description of the entry, as HTML.
+    pub summary: Option<String>,
+    /// The full content of the entry, as HTML.
+    pub content: Option<String>,
+    pub published: Option<FeedDate>,
+    pub updated: Option<FeedDate>,
+    pub authors: Vec<FeedPerson>,
+    pub categories: Vec<FeedCategory>,
+    pub enclosures: Vec<FeedEnclosure>,
+}
+
+/// A link from a feed or an entry. RSS links are always "alternate" links.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedLink {
+    pub url: Url,
+    /// The relation of the link in lower case, such as "alternate", "self" or "enclosure".
+    pub rel: String,
+    /// The type attribute, such as "text/html".
+    pub mime_type: Option<String>,
+    pub title: Option<String>,
+    pub hreflang: Option<String>,
+}
+
+/// The author of a feed or an entry. RSS authors written as "email (Name)" or
+/// "Name <email>" are split into the name and the email.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FeedPerson {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub url: Option<Url>,
+}
+
+/// A category of a feed or an entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedCategory {
+    pub term: String,
+    /// The domain of a RSS category or the scheme of an Atom category.
+    pub scheme: Option<String>,
+    /// The human readable label of an Atom category.
+    pub label: Option<String>,
+}
+
+/// A file attached to an entry, such as the audio of a podcast episode or a photo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEnclosure {
+    pub url: Url,
+    /// The type of the file, such as "audio/mpeg".
+    pub mime_type: Option<String>,
+    /// The size of the file in bytes, when it is known.
+    pub length: Option<u64>,
+}
+
+/// A date and time from a feed, in the time zone it was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeedDate {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// The offset from UTC in minutes.
+    pub offset: i16,
+}
+
+impl Feed {
+    /// Parses a RSS or Atom feed, resolving its URLs against the URL of the feed.
+    pub fn parse(xml: &str, feed_url: Option<&Url>) -> Result<Feed, ParseHtmlError> {
+        HtmlDocument::parse_xml_lenient(xml)?
+            .feed(feed_url)
+            .ok_or_else(|| ParseHtmlError::with_msg("the document is not a RSS or Atom feed"))
+    }
+
+    /// Gets the URL of the website the feed is for, which is the first "alternate" link.
+    pub fn link(&self) -> Option<&Url> {
+        alternate_link(&self.links)
+    }
+
+    fn new(format: FeedFormat) -> Feed {
+        Feed {
+            format,
+            title: None,
+            description: None,
+            id: None,
+            links: vec![],
+            language: None,
+            updated: None,
+            authors: vec![],
+            categories: vec![],
+            image: None,
+            entries: vec![],
+        }
+    }
+}
+
+impl FeedEntry {
+    /// Gets the URL of the entry, which is the first "alternate" link.
+    pub fn link(&self) -> Option<&Url> {
+        alternate_link(&self.links)
+    }
+}
+
+impl FeedDate {
+    /// Parses a date in the RFC 822 format used by RSS, such as
+    /// "Sat, 07 Sep 2002 00:00:01 GMT", or the RFC 3339 format used by Atom and Dublin
+    /// Core, such as "2002-09-07T00:00:01Z". The common mistakes are allowed: a missing day
+    /// name, time or time zone, two digit years, full month names and named US time zones.
+    /// Dates without a time zone are taken to be in UTC.
+    pub fn parse(text: &str) -> Option<FeedDate> {
+        let text = text.trim();
+        match text.as_bytes().get(4) {
+            Some(b'-') => parse_iso_date(text),
+            _ => parse_rfc822_date(text),
+        }
+    }
+
+    /// Gets the number of seconds from the Unix epoch, which can be used to put dates
+    /// from different time zones in order.
+    pub fn timestamp(&self) -> i64 {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+            - self.offset as i64 * 60
+    }
+
+    /// Creates the date if every part is in range.
+    fn checked(
+        year: i32,
+        month: u8,
+        day: u8,
+        (hour, minute, second): (u8, u8, u8),
+        offset: i16,
+    ) -> Option<FeedDate> {
+        let valid = (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+            && hour < 24
+            && minute < 60
+            && second <= 60
+            && offset.abs() < 24 * 60;
+        match valid {
+            true => Some(FeedDate {
+                year,
+                month,
+                day,
+                hour,
+                minute,
+                second,
+                offset,
+            }),
+            false => None,
+        }
+    }
+}
+
+impl fmt::Display for FeedDate {
+    /// Writes the date in the RFC 3339 format.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        match self.offset {
+            0 => write!(f, "Z"),
+            offset => write!(
+                f,
+                "{}{:02}:{:02}",
+                if offset < 0 { '-' } else { '+' },
+                offset.abs() / 60,
+                offset.abs() % 60
+            ),
+        }
+    }
+}
+
+impl HtmlDocument {
+    /// Reads the document as a RSS or Atom [`Feed`], resolving its URLs against the URL of
+    /// the feed. It is None when the root element is not a feed.
+    pub fn feed(&self, feed_url: Option<&Url>) -> Option<Feed> {
+        let root = self.nodes.iter().find_map(|n| match n {
+            HtmlNode::Tag(t) => Some(t),
+            _ => None,
+        })?;
+        let base = xml_base(root, feed_url);
+        match vocabulary(root, Vocabulary::Rss) {
+            (_, "feed") => Some(atom_feed(root, base.as_ref())),
+            (_, "rss") => Some(rss_feed(root, base.as_ref())),
+            (_, "RDF") => Some(rss_feed(root, base.as_ref())),
+            _ => None,
+        }
+    }
+}
+
+/// The vocabularies that feed elements come from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Vocabulary {
+    Rss,
+    Atom,
+    Rdf,
+    DublinCore,
+    Content,
+    Media,
+    Other,
+}
+
+/// Gets the vocabulary of the tag and its local name. Tags without a namespace or prefix
+/// are in the default vocabulary, which is the one of the root element.
+fn vocabulary(tag: &HtmlTag, default: Vocabulary) -> (Vocabulary, &str) {
+    let (prefix, local) = match tag.tag.split_once(':') {
+        Some((prefix, local)) => (Some(prefix), local),
+        None => (None, tag.tag.as_str()),
+    };
+    let vocabulary = match tag.namespace.as_deref() {
+        Some(ATOM_NAMESPACE) | Some(ATOM_03_NAMESPACE) => Vocabulary::Atom,
+        Some(RSS_090_NAMESPACE) | Some(RSS_10_NAMESPACE) => Vocabulary::Rss,
+        Some(RDF_NAMESPACE) => Vocabulary::Rdf,
+        Some(DUBLIN_CORE_NAMESPACE) => Vocabulary::DublinCore,
+        Some(CONTENT_NAMESPACE) => Vocabulary::Content,
+        Some(MEDIA_NAMESPACE) => Vocabulary::Media,
+        // unknown or missing namespaces are common, so fall back to the usual prefixes
+        _ => match prefix {
+            None => default,
+            Some("atom") => Vocabulary::Atom,
+            Some("rdf") => Vocabulary::Rdf,
+            Some("dc") => Vocabulary::DublinCore,
+            Some("content") => Vocabulary::Content,
+            Some("media") => Vocabulary::Media,
+            Some(_) => Vocabulary::Other,
+        },
+    };
+    (vocabulary, local)
+}
+
+fn rss_feed(root: &HtmlTag, base: Option<&Url>) -> Feed {
+    let channel = children(root).find(|c| vocabulary(c, Vocabulary::Rss).1 == "channel");
+    let format = match (
+        root.tag.as_str(),
+        channel.and_then(|c| c.namespace.as_deref()),
+    ) {
+        ("rss", _) => match root.attributes.get("version").map(|v| v.trim()) {
+            Some("0.91") | Some("0.90") => FeedFormat::Rss091,
+            Some("0.92") | Some("0.93") | Some("0.94") => FeedFormat::Rss092,
+            _ => FeedFormat::Rss20,
+        },
+        (_, Some(RSS_090_NAMESPACE)) => FeedFormat::Rss090,
+        _ => FeedFormat::Rss10,
+    };
+    let mut feed = Feed::new(format);
+    let mut last_build_date = None;
+    let mut published = None;
+    // RSS 1.0 items and images are outside of the channel
+    let tags = channel.into_iter().flat_map(children).chain(children(root));
+    for tag in tags {
+        match vocabulary(tag, Vocabulary::Rss) {
+            (Vocabulary::Rss, "title") => set_text(&mut feed.title, plain_text(tag)),
+            (Vocabulary::Rss, "link") => feed.links.extend(rss_link(tag, base)),
+            (Vocabulary::Rss, "description") => {
+                set_text(&mut feed.description, html_to_text(&rss_html(tag)))
+            }
+            (Vocabulary::Rss, "language") | (Vocabulary::DublinCore, "language") => {
+                set_text(&mut feed.language, plain_text(tag))
+            }
+            (Vocabulary::Rss, "lastBuildDate") => set_date(&mut last_build_date, tag),
+            (Vocabulary::Rss, "pubDate") | (Vocabulary::DublinCore, "date") => {
+                set_date(&mut published, tag)
+            }
+            (Vocabulary::Rss, "managingEditor") | (Vocabulary::DublinCore, "creator") => {
+                feed.authors.extend(rss_person(tag))
+            }
+            (Vocabulary::Rss, "category") | (Vocabulary::DublinCore, "subject") => {
+                feed.categories.extend(rss_category(tag))
+            }
+            (Vocabulary::Rss, "image") if feed.image.is_none() => {
+                feed.image = children(tag)
+                    .find(|c| vocabulary(c, Vocabulary::Rss) == (Vocabulary::Rss, "url"))
+                    .and_then(|url| resolve(base, &decode_entities(&url.text())))
+            }
+            (Vocabulary::Atom, "link") => feed.links.extend(atom_link(tag, base)),
+            (Vocabulary::Atom, "id") => set_text(&mut feed.id, plain_text(tag)),
+            (Vocabulary::Rss, "item") => feed.entries.push(rss_item(tag, base)),
+            _ => (),
+        }
+    }
+    feed.updated = last_build_date.or(published);
+    feed
+}
+
+fn rss_item(item: &HtmlTag, base: Option<&Url>) -> FeedEntry {
+    let base = xml_base(item, base);
+    let base = base.as_ref();
+    let mut entry = FeedEntry::default();
+    let mut permalink = None;
+    for tag in children(item) {
+        match vocabulary(tag, Vocabulary::Rss) {
+            (Vocabulary::Rss, "title") => set_text(&mut entry.title, plain_text(tag)),
+            (Vocabulary::Rss, "link") => entry.links.extend(rss_link(tag, base)),
+            (Vocabulary::Rss, "description") => set_text(&mut entry.summary, rss_html(tag)),
+            (Vocabulary::Content, "encoded") => set_text(&mut entry.content, rss_html(tag)),
+            (Vocabulary::Rss, "guid") if entry.id.is_none() => {
+                entry.id = Some(plain_text(tag)).filter(|id| !id.is_empty());
+                let is_permalink = tag.attributes.get("isPermaLink").map(|p| p.trim());
+                if is_permalink != Some("false") {
+                    permalink = entry.id.as_deref().and_then(|id| Url::parse(id).ok());
+                }
+            }
+            (Vocabulary::Rss, "pubDate") | (Vocabulary::DublinCore, "date") => {
+                set_date(&mut entry.published, tag)
+            }
+            (Vocabulary::Atom, "updated") | (Vocabulary::DublinCore, "modified") => {
+                set_date(&mut entry.updated, tag)
+            }
+            (Vocabulary::Rss, "author") | (Vocabulary::DublinCore, "creator") => {
+                entry.authors.extend(rss_person(tag))
+            }
+            (Vocabulary::Rss, "category") | (Vocabulary::DublinCore, "subject") => {
+                entry.categories.extend(rss_category(tag))
+            }
+            (Vocabulary::Rss, "enclosure") => {
+                let url = attribute(tag, "url").and_then(|url| resolve(base, &url));
+                if let Some(url) = url {
+                    entry.enclosures.push(FeedEnclosure {
+                        url,
+                        mime_type: attribute(tag, "type"),
+                        length: attribute(tag, "length").and_then(|l| parse_length(&l)),
+                    });
+                }
+            }
+            (Vocabulary::Atom, "link") => add_atom_link(&mut entry, tag, base),
+            (Vocabulary::Media, _) => add_media(&mut entry, tag, base),
+            _ => (),
+        }
+    }
+    if entry.id.is_none() {
+        entry.id = attribute(item, "rdf:about");
+    }
+    if entry.links.is_empty() {
+        entry.links.extend(permalink.map(|url| FeedLink {
+            url,
+            rel: "alternate".to_owned(),
+            mime_type: None,
+            title: None,
+            hreflang: None,
+        }));
+    }
+    entry
+}
+
+fn atom_feed(root: &HtmlTag, base: Option<&Url>) -> Feed {
+    let mut feed = Feed::new(FeedFormat::Atom);
+    feed.language = attribute(root, "xml:lang");
+    let mut icon = None;
+    let mut entries = vec![];
+    for tag in children(root) {
+        match vocabulary(tag, Vocabulary::Atom) {
+            (Vocabulary::Atom, "title") => set_text(&mut feed.title, atom_text(tag)),
+            (Vocabulary::Atom, "subtitle") | (Vocabulary::Atom, "tagline") => {
+                set_text(&mut feed.description, atom_text(tag))
+            }
+            (Vocabulary::Atom, "id") => set_text(&mut feed.id, plain_text(tag)),
+            (Vocabulary::Atom, "link") => feed.links.extend(atom_link(tag, base)),
+            (Vocabulary::Atom, "updated") | (Vocabulary::Atom, "modified") => {
+                set_date(&mut feed.updated, tag)
+            }
+            (Vocabulary::Atom, "author") => feed.authors.extend(atom_person(tag, base)),
+            (Vocabulary::Atom, "category") => feed.categories.extend(atom_category(tag)),
+            (Vocabulary::Atom, "logo") if feed.image.is_none() => {
+                feed.image = resolve(base, &decode_entities(&tag.text()))
+            }
+            (Vocabulary::Atom, "icon") if icon.is_none() => {
+                icon = resolve(base, &decode_entities(&tag.text()))
+            }
+            (Vocabulary::Atom, "entry") => entries.push(tag),
+            _ => (),
+        }
+    }
+    feed.image = feed.image.or(icon);
+    feed.entries = entries
+        .into_iter()
+        .map(|tag| atom_entry(tag, base, &feed.authors))
+        .collect();
+    feed
+}
+
+fn atom_entry(tag: &HtmlTag, base: Option<&Url>, authors: &[FeedPerson]) -> FeedEntry {
+    let base = xml_base(tag, base);
+    let base = base.as_ref();
+    let mut entry = FeedEntry::default();
+    for tag in children(tag) {
+        match vocabulary(tag, Vocabulary::Atom) {
+            (Vocabulary::Atom, "id") => set_text(&mut entry.id, plain_text(tag)),
+            (Vocabulary::Atom, "title") => set_text(&mut entry.title, atom_text(tag)),
+            (Vocabulary::Atom, "link") => add_atom_link(&mut entry, tag, base),
+            (Vocabulary::Atom, "summary") => set_text(&mut entry.summary, atom_html(tag)),
+            (Vocabulary::Atom, "content") if !tag.attributes.contains_key("src") => {
+                set_text(&mut entry.content, atom_html(tag))
+            }
+            (Vocabulary::Atom, "published")
+            | (Vocabulary::Atom, "issued")
+            | (Vocabulary::Atom, "created") => set_date(&mut entry.published, tag),
+            (Vocabulary::Atom, "updated") | (Vocabulary::Atom, "modified") => {
+                set_date(&mut entry.updated, tag)
+            }
+            (Vocabulary::Atom, "author") => entry.authors.extend(atom_person(tag, base)),
+            (Vocabulary::Atom, "category") => entry.categories.extend(atom_category(tag)),
+            (Vocabulary::Media, _) => add_media(&mut entry, tag, base),
+            _ => (),
+        }
+    }
+    if entry.authors.is_empty() {
+        entry.authors = authors.to_vec();
+    }
+    entry
+}
+
+/// Adds an Atom link to the entry, which is also an enclosure when its rel is
+/// "enclosure".
+fn add_atom_link(entry: &mut FeedEntry, tag: &HtmlTag, base: Option<&Url>) {
+    if let Some(link) = atom_link(tag, base) {
+        if link.rel == "enclosure" {
+            entry.enclosures.push(FeedEnclosure {
+                url: link.url.clone(),
+                mime_type: link.mime_type.clone(),
+                length: attribute(tag, "length").and_then(|l| parse_length(&l)),
+            });
+        }
+        entry.links.push(link);
+    }
+}
+
+/// Adds the files of a Media RSS `media:content` or `media:group` to the enclosures.
+fn add_media(entry: &mut FeedEntry, tag: &HtmlTag, base: Option<&Url>) {
+    match vocabulary(tag, Vocabulary::Other).1 {
+        "group" => {
+            for content in children(tag) {
+                add_media(entry, content, base);
+            }
+        }
+        "content" => {
+            let url = attribute(tag, "url").and_then(|url| resolve(base, &url));
+            if let Some(url) = url {
+                if entry.enclosures.iter().all(|e| e.url != url) {
+                    entry.enclosures.push(FeedEnclosure {
+                        url,
+                        mime_type: attribute(tag, "type"),
+                        length: attribute(tag, "fileSize").and_then(|l| parse_length(&l)),
+                    });
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn rss_link(tag: &HtmlTag, base: Option<&Url>) -> Option<FeedLink> {
+    Some(FeedLink {
+        url: resolve(base, &decode_entities(&tag.text()))?,
+        rel: "alternate".to_owned(),
+        mime_type: None,
+        title: None,
+        hreflang: None,
+    })
+}
+
+fn atom_link(tag: &HtmlTag, base: Option<&Url>) -> Option<FeedLink> {
+    let base = xml_base(tag, base);
+    Some(FeedLink {
+        url: resolve(base.as_ref(), &attribute(tag, "href")?)?,
+        rel: attribute(tag, "rel")
+            .map(|r| r.to_ascii_lowercase())
+            .unwrap_or_else(|| "alternate".to_owned()),
+        mime_type: attribute(tag, "type"),
+        title: attribute(tag, "title"),
+        hreflang: attribute(tag, "hreflang"),
+    })
+}
+
+/// Reads a RSS author, which is usually "email (Name)", but can be "Name <email>", only
+/// an email or only a name.
+fn rss_person(tag: &HtmlTag) -> Option<FeedPerson> {
+    let text = plain_text(tag);
+    let (name, email) = if let Some((email, name)) = text.split_once('(') {
+        (name.trim_end_matches(')').trim(), email.trim())
+    } else if let Some((name, email)) = text.split_once('<') {
+        (name.trim(), email.trim_end_matches('>').trim())
+    } else if text.contains('@') && !text.contains(' ') {
+        ("", text.as_str())
+    } else {
+        (text.as_str(), "")
+    };
+    let person = FeedPerson {
+        name: Some(name.to_owned()).filter(|n| !n.is_empty()),
+        email: Some(email.to_owned()).filter(|e| !e.is_empty()),
+        url: None,
+    };
+    match person.name.is_some() || person.email.is_some() {
+        true => Some(person),
+        false => None,
+    }
+}
+
+fn atom_person(tag: &HtmlTag, base: Option<&Url>) -> Option<FeedPerson> {
+    let mut person = FeedPerson::default();
+    for child in children(tag) {
+        match vocabulary(child, Vocabulary::Atom) {
+            (Vocabulary::Atom, "name") => set_text(&mut person.name, plain_text(child)),
+            (Vocabulary::Atom, "email") => set_text(&mut person.email, plain_text(child)),
+            (Vocabulary::Atom, "uri") | (Vocabulary::Atom, "url") if person.url.is_none() => {
+                person.url = resolve(base, &decode_entities(&child.text()))
+            }
+            _ => (),
+        }
+    }
+    match person.name.is_some() || person.email.is_some() || person.url.is_some() {
+        true => Some(person),
+        false => None,
+    }
+}
+
+fn rss_category(tag: &HtmlTag) -> Option<FeedCategory> {
+    Some(FeedCategory {
+        term: Some(plain_text(tag)).filter(|t| !t.is_empty())?,
+        scheme: attribute(tag, "domain"),
+        label: None,
+    })
+}
+
+fn atom_category(tag: &HtmlTag) -> Option<FeedCategory> {
+    Some(FeedCategory {
+        term: attribute(tag, "term")?,
+        scheme: attribute(tag, "scheme"),
+        label: attribute(tag, "label"),
+    })
+}
+
+/// Gets the HTML of a RSS description. It is usually escaped HTML, but some feeds have
+/// the HTML tags written directly in the element.
+fn rss_html(tag: &HtmlTag) -> String {
+    match tag.contents.iter().any(|n| matches!(n, HtmlNode::Tag(_))) {
+        true => inner_html(&tag.contents),
+        false => decode_entities(&tag.text()).trim().to_owned(),
+    }
+}
+
+/// Gets the HTML of an Atom text construct from its type.
+fn atom_html(tag: &HtmlTag) -> String {
+    let kind = tag
+        .attributes
+        .get("type")
+        .map(|t| t.trim().to_ascii_lowercase());
+    match kind.as_deref() {
+        Some("html") | Some("text/html") => decode_entities(&tag.text()).trim().to_owned(),
+        Some("xhtml") | Some("application/xhtml+xml") => {
+            // the content is wrapped in a div which is not part of it
+            match children(tag).next() {
+                Some(div) => inner_html(&div.contents),
+                None => inner_html(&tag.contents),
+            }
+        }
+        // text is already escaped, so it can be used as HTML
+        _ => tag.text().trim().to_owned(),
+    }
+}
+
+/// Gets the plain text of an Atom text construct from its type.
+fn atom_text(tag: &HtmlTag) -> String {
+    let kind = tag
+        .attributes
+        .get("type")
+        .map(|t| t.trim().to_ascii_lowercase());
+    match kind.as_deref() {
+        Some("html") | Some("text/html") => html_to_text(&decode_entities(&tag.text())),
+        _ => plain_text(tag),
+    }
+}
+
+fn inner_html(nodes: &[HtmlNode]) -> String {
+    let html: String = nodes
+        .iter()
+        .filter(|n| !matches!(n, HtmlNode::Comment(_)))
+        .map(|n| n.to_html_string())
+        .collect();
+    html.trim().to_owned()
+}
+
+/// Removes the tags from HTML, then decodes the text and collapses its white space.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    decode_text(&text)
+}
+
+/// Gets the text of the tag, decoded and with its white space collapsed.
+fn plain_text(tag: &HtmlTag) -> String {
+    decode_text(&tag.text())
+}
+
+/// Gets the decoded and trimmed value of an attribute, if it is not empty.
+fn attribute(tag: &HtmlTag, name: &str) -> Option<String> {
+    let value = tag.attributes.get(name)?;
+    Some(decode_entities(value).trim().to_owned()).filter(|v| !v.is_empty())
+}
+
+/// Sets the text if it is not set yet and the new text is not empty.
+fn set_text(value: &mut Option<String>, text: String) {
+    if value.is_none() && !text.is_empty() {
+        *value = Some(text);
+    }
+}
+
+fn set_date(value: &mut Option<FeedDate>, tag: &HtmlTag) {
+    if value.is_none() {
+        *value = FeedDate::parse(&plain_text(tag));
+    }
+}
+
+fn parse_length(length: &str) -> Option<u64> {
+    // a length of zero is the usual way to say it is not known
+    length.parse::<u64>().ok().filter(|l| *l > 0)
+}
+
+/// Gets the base URL of the tag from its `xml:base` attribute and the base URL of its
+/// parent.
+fn xml_base(tag: &HtmlTag, base: Option<&Url>) -> Option<Url> {
+    match tag.attributes.get("xml:base") {
+        Some(href) => resolve(base, &decode_entities(href)),
+        None => base.cloned(),
+    }
+}
+
+fn alternate_link(links: &[FeedLink]) -> Option<&Url> {
+    links.iter().find(|l| l.rel == "alternate").map(|l| &l.url)
+}
+
+/// Parses a RFC 3339 date, allowing a space instead of the "T", no seconds, no time zone
+/// or no time.
+fn parse_iso_date(text: &str) -> Option<FeedDate> {
+    let (date, time) = match text.find(['T', 't', ' ']) {
+        Some(i) => (&text[..i], Some(text[i + 1..].trim())),
+        None => (text, None),
+    };
+    let mut parts = date.split('-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next()?.parse::<u8>().ok()?;
+    let day = parts.next()?.parse::<u8>().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let (clock, offset) = match time {
+        Some(time) => match time.find(['Z', 'z', '+', '-']) {
+            Some(i) => (parse_clock(&time[..i])?, parse_offset(&time[i..])?),
+            None => (parse_clock(time)?, 0),
+        },
+        None => ((0, 0, 0), 0),
+    };
+    FeedDate::checked(year, month, day, clock, offset)
+}
+
+/// Parses a RFC 822 date, allowing the mistakes described in [`FeedDate::parse`].
+fn parse_rfc822_date(text: &str) -> Option<FeedDate> {
+    let mut words = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .peekable();
+    if words.peek()?.starts_with(|c: char| c.is_alphabetic()) {
+        words.next();
+    }
+    let day = words.next()?.parse::<u8>().ok()?;
+    let month = month_number(words.next()?)?;
+    let year = match words.next()? {
+        year if year.len() == 2 => match year.parse::<i32>().ok()? {
+            year if year < 50 => year + 2000,
+            year => year + 1900,
+        },
+        year => year.parse::<i32>().ok()?,
+    };
+    let clock = match words.next() {
+        Some(clock) => parse_clock(clock)?,
+        None => (0, 0, 0),
+    };
+    let offset = match words.next() {
+        Some(zone) => zone_offset(zone)?,
+        None => 0,
+    };
+    FeedDate::checked(year, month, day, clock, offset)
+}
+
+/// Parses a time such as "09:42", "09:42:31" or "09:42:31.25", ignoring any fraction.
+fn parse_clock(clock: &str) -> Option<(u8, u8, u8)> {
+    let mut parts = clock.split(':');
+    let hour = parts.next()?.parse::<u8>().ok()?;
+    let minute = parts.next()?.parse::<u8>().ok()?;
+    let second = match parts.next() {
+        Some(second) => second.split('.').next()?.parse::<u8>().ok()?,
+        None => 0,
+    };
+    match parts.next() {
+        Some(_) => None,
+        None => Some((hour, minute, second)),
+    }
+}
+
+/// Parses a time zone offset such as "Z", "+01:00", "-0500" or "+01" into minutes.
+fn parse_offset(zone: &str) -> Option<i16> {
+    let (sign, digits) = match zone.chars().next()? {
+        'Z' | 'z' if zone.len() == 1 => return Some(0),
+        '+' => (1, &zone[1..]),
+        '-' => (-1, &zone[1..]),
+        _ => return None,
+    };
+    let digits = digits.replace(':', "");
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let minutes = match digits.len() {
+        2 => digits.parse::<i16>().ok()? * 60,
+        4 => digits[..2].parse::<i16>().ok()? * 60 + digits[2..].parse::<i16>().ok()?,
+        _ => return None,
+    };
+    Some(sign * minutes)
+}
+
+/// Gets the offset of a RFC 822 time zone, which is a name or an offset like "-0500".
+/// Unknown names are taken to be UTC.
+fn zone_offset(zone: &str) -> Option<i16> {
+    let hours = match zone.to_ascii_uppercase().as_str() {
+        "EST" => -5,
+        "EDT" => -4,
+        "CST" => -6,
+        "CDT" => -5,
+        "MST" => -7,
+        "MDT" => -6,
+        "PST" => -8,
+        "PDT" => -7,
+        _ if zone.starts_with(['+', '-']) => return parse_offset(zone),
+        _ => 0,
+    };
+    Some(hours * 60)
+}
+
+fn month_number(month: &str) -> Option<u8> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let month = month.get(..3)?.to_ascii_lowercase();
+    MONTHS.iter().position(|m| *m == month).map(|i| i as u8 + 1)
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Counts the days from 1970-01-01 to the date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod feed_tests {
+    use super::*;
+
+    #[test]
+    fn rss_feed_test() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"
+  xmlns:content="http://purl.org/rss/1.0/modules/content/">
+<channel>
+  <title>Recipes &amp; more</title>
+  <link>https://example.com/</link>
+  <atom:link href="https://example.com/feed.xml" rel="self" type="application/rss+xml"/>
+  <description>New &lt;b&gt;recipes&lt;/b&gt; every week</description>
+  <language>en-gb</language>
+  <pubDate>Mon, 02 Sep 2002 10:00:00 +0100</pubDate>
+  <lastBuildDate>Tue, 10 Sep 2002 08:30:00 EST</lastBuildDate>
+  <managingEditor>editor@example.com (Alex Smith)</managingEditor>
+  <category domain="https://example.com/c">Food</category>
+  <image><url>/logo.png</url><title>Recipes</title></image>
+  <item>
+    <title>Soup</title>
+    <guid>https://example.com/recipes/soup</guid>
+    <description><![CDATA[<p>Hot & tasty</p>]]></description>
+    <content:encoded><![CDATA[<p>All of the <b>soup</b>.</p>]]></content:encoded>
+    <author>Sam &lt;sam@example.com&gt;</author>
+    <category>Soups</category>
+    <enclosure url="/audio/soup.mp3" length="12345" type="audio/mpeg"/>
+    <pubDate>Sun, 8 Sep 02 18:00 GMT</pubDate>
+  </item>
+  <item>
+    <description>Only a description with a <a href="/x">raw link</a></description>
+    <guid isPermaLink="false">item-2</guid>
+    <enclosure url="/img.jpg" length="0" type="image/jpeg"/>
+  </item>
+</channel>
+</rss>"#;
+        let base = Url::parse("https://example.com/feeds/main").unwrap();
+        let feed = Feed::parse(xml, Some(&base)).unwrap();
+        assert_eq!(feed.format, FeedFormat::Rss20);
+        assert_eq!(feed.title.as_deref(), Some("Recipes & more"));
+        assert_eq!(feed.description.as_deref(), Some("New recipes every week"));
+        assert_eq!(feed.language.as_deref(), Some("en-gb"));
+        assert_eq!(feed.link().unwrap().to_string(), "https://example.com/");
+        assert_eq!(feed.links[1].rel, "self");
+        assert_eq!(
+            feed.links[1].mime_type.as_deref(),
+            Some("application/rss+xml")
+        );
+        assert_eq!(
+            feed.updated.unwrap().to_string(),
+            "2002-09-10T08:30:00-05:00"
+        );
+        assert_eq!(
+            feed.authors,
+            vec![FeedPerson {
+                name: Some("Alex Smith".to_owned()),
+                email: Some("editor@example.com".to_owned()),
+                url: None
+            }]
+        );
+        assert_eq!(
+            feed.categories[0].scheme.as_deref(),
+            Some("https://example.com/c")
+        );
+        assert_eq!(
+            feed.image.as_ref().unwrap().to_string(),
+            "https://example.com/logo.png"
+        );
+        assert_eq!(feed.entries.len(), 2);
+
+        let soup = &feed.entries[0];
+        assert_eq!(soup.title.as_deref(), Some("Soup"));
+        assert_eq!(soup.id.as_deref(), Some("https://example.com/recipes/soup"));
+        assert_eq!(
+            soup.link().unwrap().to_string(),
+            "https://example.com/recipes/soup"
+        );
+        assert_eq!(soup.summary.as_deref(), Some("<p>Hot & tasty</p>"));
+        assert_eq!(
+            soup.content.as_deref(),
+            Some("<p>All of the <b>soup</b>.</p>")
+        );
+        assert_eq!(soup.authors[0].name.as_deref(), Some("Sam"));
+        assert_eq!(soup.authors[0].email.as_deref(), Some("sam@example.com"));
+        assert_eq!(soup.categories[0].term, "Soups");
+        assert_eq!(
+            soup.enclosures,
+            vec![FeedEnclosure {
+                url: Url::parse("https://example.com/audio/soup.mp3").unwrap(),
+                mime_type: Some("audio/mpeg".to_owned()),
+                length: Some(12345),
+            }]
+        );
+        assert_eq!(soup.published.unwrap().to_string(), "2002-09-08T18:00:00Z");
+
+        let second = &feed.entries[1];
+        assert_eq!(second.id.as_deref(), Some("item-2"));
+        assert!(second.links.is_empty());
+        assert_eq!(
+            second.summary.as_deref(),
+            Some(r#"Only a description with a <a href="/x">raw link</a>"#)
+        );
+        assert_eq!(second.enclosures[0].length, None);
+    }
+
+    #[test]
+    fn rdf_feed_test() {
+        let xml = r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+  xmlns="http://purl.org/rss/1.0/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<channel rdf:about="https://example.com/rss">
+  <title>Example</title>
+  <link>https://example.com/</link>
+  <dc:date>2003-12-13T18:30:02+01:00</dc:date>
+</channel>
+<image rdf:about="https://example.com/logo.png"><url>https://example.com/logo.png</url></image>
+<item rdf:about="https://example.com/one">
+  <title>One</title>
+  <link>https://example.com/one</link>
+  <dc:creator>Kim</dc:creator>
+  <dc:subject>News</dc:subject>
+  <dc:date>2003-12-13</dc:date>
+</item>
+</rdf:RDF>"#;
+        let feed = Feed::parse(xml, None).unwrap();
+        assert_eq!(feed.format, FeedFormat::Rss10);
+        assert_eq!(feed.title.as_deref(), Some("Example"));
+        assert_eq!(feed.updated.unwrap().offset, 60);
+        assert_eq!(
+            feed.image.unwrap().to_string(),
+            "https://example.com/logo.png"
+        );
+        let entry = &feed.entries[0];
+        assert_eq!(entry.id.as_deref(), Some("https://example.com/one"));
+        assert_eq!(entry.authors[0].name.as_deref(), Some("Kim"));
+        assert_eq!(entry.categories[0].term, "News");
+        assert_eq!(entry.published.unwrap().to_string(), "2003-12-13T00:00:00Z");
+
+        let old = r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+  xmlns="http://my.netscape.com/rdf/simple/0.9/">
+<channel><title>Old</title></channel><item><title>A</title></item></rdf:RDF>"#;
+        let feed = Feed::parse(old, None).unwrap();
+        assert_eq!(feed.format, FeedFormat::Rss090);
+        assert_eq!(feed.entries[0].title.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn atom_feed_test() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="en"
+  xml:base="https://example.com/blog/" xmlns:media="http://search.yahoo.com/mrss/">
+  <title type="html">Kitchen &lt;em&gt;notes&lt;/em&gt;</title>
+  <subtitle>Cooking &amp; eating</subtitle>
+  <id>urn:uuid:60a76c80-d399-11d9-b93C-0003939e0af6</id>
+  <link href="./"/>
+  <link rel="self" href="feed.atom"/>
+  <updated>2003-12-13T18:30:02Z</updated>
+  <author><name>Jo</name><uri>/about</uri></author>
+  <icon>/favicon.ico</icon>
+  <entry>
+    <title>Bread</title>
+    <link rel="alternate" type="text/html" href="bread"/>
+    <link rel="enclosure" type="audio/mpeg" length="1337" href="bread.mp3"/>
+    <id>tag:example.com,2003:bread</id>
+    <published>2003-12-13T08:29:29-04:00</published>
+    <updated>2003-12-13T18:30:02.25Z</updated>
+    <summary>Flour &lt; water</summary>
+    <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><p>Knead.</p></div></content>
+    <category term="baking" scheme="https://example.com/tags" label="Baking"/>
+    <media:group><media:content url="bread.jpg" type="image/jpeg" fileSize="99"/></media:group>
+  </entry>
+  <entry xml:base="/other/">
+    <title>Cake</title>
+    <link href="cake"/>
+    <author><name>Lee</name><email>lee@example.com</email></author>
+    <content type="html">&lt;p&gt;Sweet&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+        let feed = Feed::parse(xml, None).unwrap();
+        assert_eq!(feed.format, FeedFormat::Atom);
+        assert_eq!(feed.title.as_deref(), Some("Kitchen notes"));
+        assert_eq!(feed.description.as_deref(), Some("Cooking & eating"));
+        assert_eq!(feed.language.as_deref(), Some("en"));
+        assert_eq!(
+            feed.link().unwrap().to_string(),
+            "https://example.com/blog/"
+        );
+        assert_eq!(feed.links[1].rel, "self");
+        assert_eq!(
+            feed.image.as_ref().unwrap().to_string(),
+            "https://example.com/favicon.ico"
+        );
+        assert_eq!(
+            feed.authors[0].url.as_ref().unwrap().to_string(),
+            "https://example.com/about"
+        );
+
+        let bread = &feed.entries[0];
+        assert_eq!(bread.id.as_deref(), Some("tag:example.com,2003:bread"));
+        assert_eq!(
+            bread.link().unwrap().to_string(),
+            "https://example.com/blog/bread"
+        );
+        assert_eq!(bread.summary.as_deref(), Some("Flour &lt; water"));
+        assert_eq!(bread.content.as_deref(), Some("<p>Knead.</p>"));
+        assert_eq!(bread.published.unwrap().offset, -240);
+        assert_eq!(
+            bread.updated.unwrap().timestamp(),
+            feed.updated.unwrap().timestamp()
+        );
+        assert_eq!(bread.authors[0].name.as_deref(), Some("Jo"));
+        assert_eq!(bread.categories[0].label.as_deref(), Some("Baking"));
+        assert_eq!(bread.enclosures.len(), 2);
+        assert_eq!(bread.enclosures[0].length, Some(1337));
+        assert_eq!(
+            bread.enclosures[1].url.to_string(),
+            "https://example.com/blog/bread.jpg"
+        );
+
+        let cake = &feed.entries[1];
+        assert_eq!(
+            cake.link().unwrap().to_string(),
+            "https://example.com/other/cake"
+        );
+        assert_eq!(cake.authors[0].email.as_deref(), Some("lee@example.com"));
+        assert_eq!(cake.content.as_deref(), Some("<p>Sweet</p>"));
+    }
+
+    #[test]
+    fn malformed_feed_test() {
+        let xml = "\n\n<rss version=\"0.91\"><channel><title>Fish & Chips&nbsp;Daily</title>\
+            <item><title>Cod &amp; chips</title><link>http://example.com/cod</link>\
+            <description>Crispy <br> batter</description>\
+            <dc:creator>Pat</dc:creator><media:content url=\"http://example.com/cod.jpg?a=1&amp;amp;b=2\"/>\
+            </item><item><title>Unclosed";
+        let feed = Feed::parse(xml, None).unwrap();
+        assert_eq!(feed.format, FeedFormat::Rss091);
+        assert_eq!(feed.title.as_deref(), Some("Fish & Chips Daily"));
+        assert_eq!(feed.entries.len(), 2);
+        let cod = &feed.entries[0];
+        assert_eq!(cod.title.as_deref(), Some("Cod & chips"));
+        assert_eq!(cod.authors[0].name.as_deref(), Some("Pat"));
+        assert_eq!(cod.enclosures.len(), 1);
+        // the attribute is only decoded once
+        assert_eq!(
+            cod.enclosures[0].url.to_string(),
+            "http://example.com/cod.jpg?a=1&amp;b=2"
+        );
+        assert_eq!(feed.entries[1].title.as_deref(), Some("Unclosed"));
+
+        assert!(Feed::parse("<html><body>Not a feed</body></html>", None).is_err());
+        assert!(Feed::parse("", None).is_err());
+    }
+
+    #[test]
+    fn feed_date_test() {
+        let tests = vec![
+            ("Sat, 07 Sep 2002 00:00:01 GMT", "2002-09-07T00:00:01Z"),
+            ("07 Sep 2002 00:00:01 +0000", "2002-09-07T00:00:01Z"),
+            (
+                "Tuesday, 10 June 2003 04:00:00 EDT",
+                "2003-06-10T04:00:00-04:00",
+            ),
+            ("Wed, 1 Jan 97 12:00 -0130", "1997-01-01T12:00:00-01:30"),
+            ("Thu, 29 Feb 2024", "2024-02-29T00:00:00Z"),
+            ("2003-12-13T18:30:02Z", "2003-12-13T18:30:02Z"),
+            ("2003-12-13T18:30:02.25+01:00", "2003-12-13T18:30:02+01:00"),
+            ("2003-12-13 18:30", "2003-12-13T18:30:00Z"),
+            ("2003-12-13t18:30:02-0800", "2003-12-13T18:30:02-08:00"),
+        ];
+        for (text, expected) in tests {
+            let date = FeedDate::parse(text).unwrap_or_else(|| panic!("{}", text));
+            assert_eq!(date.to_string(), expected, "{}", text);
+        }
+        for bad in [
+            "",
+            "yesterday",
+            "2003-13-01",
+            "2003-02-29",
+            "Sat, 32 Sep 2002",
+            "Sat, 07 Foo 2002",
+            "2003-12-13T25:00:00Z",
+            "2003-12-13T18:30:02+1",
+        ] {
+            assert_eq!(FeedDate::parse(bad), None, "{}", bad);
+        }
+        assert_eq!(
+            FeedDate::parse("1970-01-01T00:00:00Z").unwrap().timestamp(),
+            0
+        );
+        assert_eq!(
+            FeedDate::parse("Sat, 07 Sep 2002 02:00:00 +0200")
+                .unwrap()
+                .timestamp(),
+            FeedDate::parse("2002-09-07T00:00:00Z").unwrap().timestamp()
+        );
+        assert_eq!(
+            FeedDate::parse("2000-03-01").unwrap().timestamp(),
+            951868800
+        );
+        assert_eq!(
+            FeedDate::parse("1969-12-31T23:59:59Z").unwrap().timestamp(),
+            -1
+        );
+    }
+}
@@ -0,0 +1,1073 @@
+//! A model of HTML forms which can be filled in and turned into a submission.
+//!
+//! [`HtmlForm`] is a copy of a `<form>` and the controls associated with it, so changing
+//! the values of the controls does not change the document. The submission is built
+//! following the "constructing the entry list" and encoding algorithms in the HTML spec.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::form::FormMethod;
+//! use hb_html::objects::HtmlDocument;
+//! let doc = r#"<form action="/search" method="get">
+//! <input name="q" value="old">
+//! <input type="checkbox" name="safe">
+//! <select name="lang"><option>en</option><option value="fr">French</option></select>
+//! <button name="go" value="1">Search</button>
+//! </form>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let mut form = doc.forms().remove(0);
+//! form.set_value("q", "rust html").unwrap();
+//! form.check("safe", "on").unwrap();
+//! form.select_option("lang", "fr").unwrap();
+//! let submission = form.submission(None).unwrap();
+//! assert_eq!(submission.method, FormMethod::Get);
+//! assert_eq!(submission.url(), "/search?q=rust+html&safe=on&lang=fr");
+//! ```
+use crate::entities::{decode_entities, decode_text};
+use crate::error::HtmlFormError;
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use std::collections::HashMap;
+
+/// The types of `<input>` that the HTML spec defines. Other types are treated as "text".
+const INPUT_TYPES: [&str; 22] = [
+    "hidden",
+    "text",
+    "search",
+    "tel",
+    "url",
+    "email",
+    "password",
+    "date",
+    "month",
+    "week",
+    "time",
+    "datetime-local",
+    "number",
+    "range",
+    "color",
+    "checkbox",
+    "radio",
+    "file",
+    "submit",
+    "image",
+    "reset",
+    "button",
+];
+
+/// How a form is submitted, from the `method` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormMethod {
+    Get,
+    Post,
+    Dialog,
+}
+
+impl FormMethod {
+    /// Reads a method attribute. Missing or invalid values are `Get`.
+    pub fn from_attribute(value: Option<&String>) -> FormMethod {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("post") => FormMethod::Post,
+            Some("dialog") => FormMethod::Dialog,
+            _ => FormMethod::Get,
+        }
+    }
+}
+
+/// How the data of a form is encoded, from the `enctype` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormEnctype {
+    /// `application/x-www-form-urlencoded`
+    UrlEncoded,
+    /// `multipart/form-data`
+    Multipart,
+    /// `text/plain`
+    TextPlain,
+}
+
+impl FormEnctype {
+    /// Reads an enctype attribute. Missing or invalid values are `UrlEncoded`.
+    pub fn from_attribute(value: Option<&String>) -> FormEnctype {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("multipart/form-data") => FormEnctype::Multipart,
+            Some("text/plain") => FormEnctype::TextPlain,
+            _ => FormEnctype::UrlEncoded,
+        }
+    }
+
+    /// The MIME type of the encoding.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            FormEnctype::UrlEncoded => "application/x-www-form-urlencoded",
+            FormEnctype::Multipart => "multipart/form-data",
+            FormEnctype::TextPlain => "text/plain",
+        }
+    }
+}
+
+/// A file selected in a `<input type="file">`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FormFile {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// The value of an entry in the data submitted by a form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormValue {
+    Text(String),
+    File(FormFile),
+}
+
+/// An `<option>` of a `<select>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormOption {
+    /// The decoded value attribute, or the label if there isn't one.
+    pub value: String,
+    /// The decoded text of the option with the whitespace collapsed.
+    pub label: String,
+    pub selected: bool,
+    /// True if the option or its `<optgroup>` is disabled.
+    pub disabled: bool,
+}
+
+/// An `<input>`, `<select>`, `<textarea>` or `<button>` associated with a form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormControl {
+    /// The tag name of the control.
+    pub tag: String,
+    /// The type of the control. It is the lowercase type of inputs and buttons, "textarea"
+    /// for textareas, and "select-one" or "select-multiple" for selects.
+    pub control_type: String,
+    /// The decoded name attribute of the control, which is empty if it doesn't have one.
+    pub name: String,
+    /// The current value, with the character references decoded. Selects use the options
+    /// instead.
+    pub value: String,
+    /// True if the checkbox or radio button is checked.
+    pub checked: bool,
+    /// True if the control or a `<fieldset>` it is in is disabled.
+    pub disabled: bool,
+    pub readonly: bool,
+    pub required: bool,
+    /// The options of a select, including those inside `<optgroup>`.
+    pub options: Vec<FormOption>,
+    /// The file chosen for a file input.
+    pub file: Option<FormFile>,
+    /// All of the attributes of the control apart from id and class.
+    pub attributes: HashMap<String, String>,
+}
+
+impl FormControl {
+    /// True if the control can be used to submit the form.
+    pub fn is_submit_button(&self) -> bool {
+        match self.tag.as_str() {
+            "button" => self.control_type == "submit",
+            "input" => self.control_type == "submit" || self.control_type == "image",
+            _ => false,
+        }
+    }
+
+    /// True if the control is a checkbox or radio button.
+    pub fn is_checkable(&self) -> bool {
+        self.control_type == "checkbox" || self.control_type == "radio"
+    }
+
+    /// True if the control is a select.
+    pub fn is_select(&self) -> bool {
+        self.tag == "select"
+    }
+
+    /// The values of the selected options of a select.
+    pub fn selected_values(&self) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|o| o.selected)
+            .map(|o| o.value.as_str())
+            .collect()
+    }
+
+    fn new(tag: &HtmlTag, disabled: bool) -> FormControl {
+        let name = tag.tag.to_ascii_lowercase();
+        let type_attr = tag
+            .attributes
+            .get("type")
+            .map(|t| t.trim().to_ascii_lowercase());
+        let control_type = match name.as_str() {
+            "input" => match type_attr {
+                Some(t) if INPUT_TYPES.contains(&t.as_str()) => t,
+                _ => "text".to_owned(),
+            },
+            "button" => match type_attr.as_deref() {
+                Some("reset") | Some("button") => type_attr.unwrap(),
+                _ => "submit".to_owned(),
+            },
+            "select" if flag(tag, "multiple") => "select-multiple".to_owned(),
+            "select" => "select-one".to_owned(),
+            _ => name.clone(),
+        };
+        let value = match name.as_str() {
+            // the newline straight after the start tag is not part of the value
+            "textarea" => {
+                let text = decode_entities(&tag.text());
+                match text
+                    .strip_prefix("\r\n")
+                    .or_else(|| text.strip_prefix('\n'))
+                {
+                    Some(t) => t.to_owned(),
+                    None => text,
+                }
+            }
+            "select" => String::new(),
+            _ => match tag.attributes.get("value") {
+                Some(v) => decode_entities(v),
+                None if control_type == "checkbox" || control_type == "radio" => "on".to_owned(),
+                None => String::new(),
+            },
+        };
+        let mut options = vec![];
+        if name == "select" {
+            collect_options(&tag.contents, false, &mut options);
+            fix_selectedness(&mut options, control_type == "select-multiple");
+        }
+        FormControl {
+            tag: name,
+            checked: (control_type == "checkbox" || control_type == "radio")
+                && flag(tag, "checked"),
+            control_type,
+            name: tag
+                .attributes
+                .get("name")
+                .map(|n| decode_entities(n))
+                .unwrap_or_default(),
+            value,
+            disabled: disabled || flag(tag, "disabled"),
+            readonly: flag(tag, "readonly"),
+            required: flag(tag, "required"),
+            options,
+            file: None,
+            attributes: tag.attributes.clone(),
+        }
+    }
+}
+
+/// A form and the controls associated with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlForm {
+    /// The first id of the `<form>`.
+    pub id: Option<String>,
+    /// The name attribute of the `<form>`.
+    pub name: Option<String>,
+    /// The decoded action attribute, which is empty if it is missing. It is not resolved
+    /// against the URL of the document.
+    pub action: String,
+    pub method: FormMethod,
+    pub enctype: FormEnctype,
+    /// The controls of the form in document order. This includes controls outside of the
+    /// `<form>` which name it in their form attribute.
+    pub controls: Vec<FormControl>,
+}
+
+/// The data set of a submitted form with how and where it is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormSubmission {
+    pub method: FormMethod,
+    /// The action of the form, or the formaction of the submit button.
+    pub action: String,
+    pub enctype: FormEnctype,
+    /// The value for the Content-Type header, including the boundary of multipart bodies.
+    pub content_type: String,
+    /// The entry list of the form.
+    pub entries: Vec<(String, FormValue)>,
+    /// The encoded entries. It is empty for `Get`, where the entries go in [`Self::url`].
+    pub body: Vec<u8>,
+}
+
+impl FormSubmission {
+    /// The URL the form is sent to. For `Get` the query of the action is replaced with
+    /// the url encoded entries.
+    pub fn url(&self) -> String {
+        match self.method {
+            FormMethod::Get => {
+                let (base, fragment) = match self.action.find('#') {
+                    Some(i) => self.action.split_at(i),
+                    None => (self.action.as_str(), ""),
+                };
+                let base = base.split('?').next().unwrap_or_default();
+                format!("{}?{}{}", base, url_encode_entries(&self.entries), fragment)
+            }
+            _ => self.action.clone(),
+        }
+    }
+}
+
+impl HtmlForm {
+    /// Creates the model of a `<form>` in the document. The form must be a tag inside
+    /// the document so that the controls which refer to it with a form attribute are found.
+    pub fn new(document: &HtmlDocument, form: &HtmlTag) -> HtmlForm {
+        let mut collector = ControlCollector {
+            form,
+            form_id: form.ids.first().map(|i| i.as_str()),
+            controls: vec![],
+        };
+        collector.walk(&document.nodes, None, false);
+        HtmlForm {
+            id: form.ids.first().cloned(),
+            name: form.attributes.get("name").cloned(),
+            action: form
+                .attributes
+                .get("action")
+                .map(|a| decode_entities(a))
+                .unwrap_or_default(),
+            method: FormMethod::from_attribute(form.attributes.get("method")),
+            enctype: FormEnctype::from_attribute(form.attributes.get("enctype")),
+            controls: collector.controls,
+        }
+    }
+
+    /// Gets the first control with the name.
+    pub fn control(&self, name: &str) -> Option<&FormControl> {
+        self.controls.iter().find(|c| c.name == name)
+    }
+
+    /// Gets the first control with the name to change it.
+    pub fn control_mut(&mut self, name: &str) -> Option<&mut FormControl> {
+        self.controls.iter_mut().find(|c| c.name == name)
+    }
+
+    /// Gets the controls with the name, such as a group of radio buttons.
+    pub fn controls_named<'f>(&'f self, name: &'f str) -> impl Iterator<Item = &'f FormControl> {
+        self.controls.iter().filter(move |c| c.name == name)
+    }
+
+    /// Gets the buttons that can submit the form.
+    pub fn submit_buttons(&self) -> impl Iterator<Item = (usize, &FormControl)> {
+        self.controls
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_submit_button())
+    }
+
+    /// Sets the value of the first control with the name. Selects have the option with the
+    /// value selected and checkboxes or radio buttons with the value are checked.
+    pub fn set_value<S: Into<String>>(
+        &mut self,
+        name: &str,
+        value: S,
+    ) -> Result<&mut HtmlForm, HtmlFormError> {
+        let value = value.into();
+        let index = self
+            .controls
+            .iter()
+            .position(|c| {
+                c.name == name
+                    && c.tag != "button"
+                    && !matches!(
+                        c.control_type.as_str(),
+                        "submit" | "image" | "reset" | "button" | "file"
+                    )
+            })
+            .ok_or_else(|| HtmlFormError::with_name(name, "no control can have its value set"))?;
+        let control = &mut self.controls[index];
+        if control.is_select() {
+            self.set_selected(name, &value, true, true)?;
+        } else if control.is_checkable() {
+            self.check(name, &value)?;
+        } else {
+            control.value = value;
+        }
+        Ok(self)
+    }
+
+    /// Checks the checkbox or radio button with the name and value. The other radio buttons
+    /// with the same name are unchecked.
+    pub fn check(&mut self, name: &str, value: &str) -> Result<&mut HtmlForm, HtmlFormError> {
+        self.set_checked(name, value, true)
+    }
+
+    /// Unchecks the checkbox or radio button with the name and value.
+    pub fn uncheck(&mut self, name: &str, value: &str) -> Result<&mut HtmlForm, HtmlFormError> {
+        self.set_checked(name, value, false)
+    }
+
+    /// Selects the option with the value in the select with the name. The other options
+    /// are deselected unless the select allows multiple options.
+    pub fn select_option(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> Result<&mut HtmlForm, HtmlFormError> {
+        self.set_selected(name, value, true, false)
+    }
+
+    /// Deselects the option with the value in the select with the name.
+    pub fn deselect_option(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> Result<&mut HtmlForm, HtmlFormError> {
+        self.set_selected(name, value, false, false)
+    }
+
+    /// Chooses the file for the file input with the name.
+    pub fn set_file(&mut self, name: &str, file: FormFile) -> Result<&mut HtmlForm, HtmlFormError> {
+        let control = self
+            .controls
+            .iter_mut()
+            .find(|c| c.name == name && c.control_type == "file")
+            .ok_or_else(|| HtmlFormError::with_name(name, "there is no file input"))?;
+        control.file = Some(file);
+        Ok(self)
+    }
+
+    fn set_checked(
+        &mut self,
+        name: &str,
+        value: &str,
+        checked: bool,
+    ) -> Result<&mut HtmlForm, HtmlFormError> {
+        let index = self
+            .controls
+            .iter()
+            .position(|c| c.name == name && c.is_checkable() && c.value == value)
+            .ok_or_else(|| {
+                HtmlFormError::with_name(
+                    name,
+                    format!(
+                        "there is no checkbox or radio button with the value '{}'",
+                        value
+                    ),
+                )
+            })?;
+        if checked && self.controls[index].control_type == "radio" {
+            for control in self.controls.iter_mut() {
+                if control.name == name && control.control_type == "radio" {
+                    control.checked = false;
+                }
+            }
+        }
+        self.controls[index].checked = checked;
+        Ok(self)
+    }
+
+    fn set_selected(
+        &mut self,
+        name: &str,
+        value: &str,
+        selected: bool,
+        only: bool,
+    ) -> Result<&mut HtmlForm, HtmlFormError> {
+        let control = self
+            .controls
+            .iter_mut()
+            .find(|c| c.name == name && c.is_select())
+            .ok_or_else(|| HtmlFormError::with_name(name, "there is no select"))?;
+        let index = control
+            .options
+            .iter()
+            .position(|o| o.value == value)
+            .ok_or_else(|| {
+                HtmlFormError::with_name(name, format!("there is no option '{}'", value))
+            })?;
+        if selected && (only || control.control_type == "select-one") {
+            for option in control.options.iter_mut() {
+                option.selected = false;
+            }
+        }
+        control.options[index].selected = selected;
+        Ok(self)
+    }
+
+    /// Builds the entry list of the form. The submitter is the index in
+    /// [`HtmlForm::controls`] of the button used to submit the form, if there is one.
+    pub fn entries(
+        &self,
+        submitter: Option<usize>,
+    ) -> Result<Vec<(String, FormValue)>, HtmlFormError> {
+        if let Some(index) = submitter {
+            match self.controls.get(index) {
+                Some(c) if c.is_submit_button() => (),
+                Some(c) => {
+                    return Err(HtmlFormError::with_name(
+                        &c.name,
+                        "the submitter is not a submit button",
+                    ))
+                }
+                None => {
+                    return Err(HtmlFormError::with_name(
+                        "",
+                        format!("there is no control {}", index),
+                    ))
+                }
+            }
+        }
+        let mut entries = vec![];
+        for (i, control) in self.controls.iter().enumerate() {
+            if control.disabled {
+                continue;
+            }
+            if control.is_submit_button() && submitter != Some(i) {
+                continue;
+            }
+            if control.control_type == "image" {
+                let prefix = match control.name.is_empty() {
+                    true => String::new(),
+                    false => format!("{}.", control.name),
+                };
+                for axis in ["x", "y"] {
+                    entries.push((format!("{}{}", prefix, axis), FormValue::Text("0".into())));
+                }
+                continue;
+            }
+            if control.name.is_empty() {
+                continue;
+            }
+            let name = control.name.clone();
+            match control.control_type.as_str() {
+                "reset" | "button" => continue,
+                "checkbox" | "radio" if !control.checked => continue,
+                "select-one" | "select-multiple" => {
+                    for option in control.options.iter().filter(|o| o.selected && !o.disabled) {
+                        entries.push((name.clone(), FormValue::Text(option.value.clone())));
+                    }
+                    continue;
+                }
+                "file" => {
+                    let file = control.file.clone().unwrap_or_else(|| FormFile {
+                        content_type: "application/octet-stream".to_owned(),
+                        ..FormFile::default()
+                    });
+                    entries.push((name, FormValue::File(file)));
+                    continue;
+                }
+                "hidden" if name.eq_ignore_ascii_case("_charset_") => {
+                    entries.push((name, FormValue::Text("UTF-8".to_owned())));
+                    continue;
+                }
+                _ => entries.push((name, FormValue::Text(control.value.clone()))),
+            }
+            let dirname = control.attributes.get("dirname").filter(|d| !d.is_empty());
+            if let Some(dirname) = dirname {
+                if control.tag == "textarea" || control.tag == "input" {
+                    entries.push((decode_entities(dirname), FormValue::Text("ltr".to_owned())));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Builds the submission of the form. The submitter is the index in
+    /// [`HtmlForm::controls`] of the button used to submit the form, and its formaction,
+    /// formmethod and formenctype attributes replace those of the form.
+    pub fn submission(&self, submitter: Option<usize>) -> Result<FormSubmission, HtmlFormError> {
+        let entries = self.entries(submitter)?;
+        let button = submitter.map(|i| &self.controls[i]);
+        let attribute = |name: &str| button.and_then(|b| b.attributes.get(name));
+        let action = attribute("formaction")
+            .map(|a| decode_entities(a))
+            .unwrap_or_else(|| self.action.clone());
+        let method = match attribute("formmethod") {
+            Some(m) => FormMethod::from_attribute(Some(m)),
+            None => self.method,
+        };
+        let enctype = match attribute("formenctype") {
+            Some(e) => FormEnctype::from_attribute(Some(e)),
+            None => self.enctype,
+        };
+        let (content_type, body) = match (method, enctype) {
+            (FormMethod::Get, _) | (FormMethod::Dialog, _) => {
+                (FormEnctype::UrlEncoded.mime_type().to_owned(), vec![])
+            }
+            (_, FormEnctype::UrlEncoded) => (
+                enctype.mime_type().to_owned(),
+                url_encode_entries(&entries).into_bytes(),
+            ),
+            (_, FormEnctype::Multipart) => {
+                let boundary = multipart_boundary(&entries);
+                (
+                    format!("multipart/form-data; boundary={}", boundary),
+                    multipart_encode_entries(&entries, &boundary),
+                )
+            }
+            (_, FormEnctype::TextPlain) => (
+                enctype.mime_type().to_owned(),
+                text_plain_encode_entries(&entries).into_bytes(),
+            ),
+        };
+        Ok(FormSubmission {
+            method,
+            action,
+            enctype,
+            content_type,
+            entries,
+            body,
+        })
+    }
+}
+
+/// Finds the controls associated with a form while keeping track of the disabled fieldsets.
+struct ControlCollector<'f> {
+    form: &'f HtmlTag,
+    form_id: Option<&'f str>,
+    controls: Vec<FormControl>,
+}
+
+impl<'f> ControlCollector<'f> {
+    fn walk(&mut self, nodes: &[HtmlNode], owner: Option<*const HtmlTag>, disabled: bool) {
+        let mut first_legend = true;
+        for node in nodes {
+            let tag = match node {
+                HtmlNode::Tag(t) => t,
+                _ => continue,
+            };
+            match tag.tag.to_ascii_lowercase().as_str() {
+                "input" | "select" | "textarea" | "button" => {
+                    let owned = match tag.attributes.get("form") {
+                        Some(id) => self.form_id == Some(id.as_str()),
+                        None => owner == Some(self.form as *const HtmlTag),
+                    };
+                    if owned {
+                        self.controls.push(FormControl::new(tag, disabled));
+                    }
+                }
+                "form" => self.walk(&tag.contents, Some(tag as *const HtmlTag), disabled),
+                // controls in a datalist are never submitted
+                "datalist" => (),
+                "fieldset" if flag(tag, "disabled") => {
+                    self.walk_disabled_fieldset(&tag.contents, owner, disabled)
+                }
+                "legend" if first_legend => {
+                    first_legend = false;
+                    self.walk(&tag.contents, owner, disabled);
+                }
+                _ => self.walk(&tag.contents, owner, disabled),
+            }
+        }
+    }
+
+    /// Everything inside a disabled fieldset is disabled apart from its first legend.
+    fn walk_disabled_fieldset(
+        &mut self,
+        nodes: &[HtmlNode],
+        owner: Option<*const HtmlTag>,
+        disabled: bool,
+    ) {
+        let mut first_legend = true;
+        for node in nodes {
+            match node {
+                HtmlNode::Tag(t) if first_legend && t.tag.eq_ignore_ascii_case("legend") => {
+                    first_legend = false;
+                    self.walk(std::slice::from_ref(node), owner, disabled);
+                }
+                _ => self.walk(std::slice::from_ref(node), owner, true),
+            }
+        }
+    }
+}
+
+/// Checks a boolean attribute, treating the value "false" as missing like the refiners do.
+fn flag(tag: &HtmlTag, name: &str) -> bool {
+    tag.attributes
+        .get(name)
+        .map(|v| v != "false")
+        .unwrap_or(false)
+}
+
+fn collect_options(nodes: &[HtmlNode], disabled: bool, options: &mut Vec<FormOption>) {
+    for node in nodes {
+        if let HtmlNode::Tag(t) = node {
+            match t.tag.to_ascii_lowercase().as_str() {
+                "option" => {
+                    let label = decode_text(&t.text());
+                    options.push(FormOption {
+                        value: match t.attributes.get("value") {
+                            Some(v) => decode_entities(v),
+                            None => label.clone(),
+                        },
+                        label,
+                        selected: flag(t, "selected"),
+                        disabled: disabled || flag(t, "disabled"),
+                    });
+                }
+                "optgroup" => {
+                    collect_options(&t.contents, disabled || flag(t, "disabled"), options)
+                }
+                _ => collect_options(&t.contents, disabled, options),
+            }
+        }
+    }
+}
+
+/// A select which only allows one option keeps the last selected option, or selects the
+/// first enabled option if none are selected.
+fn fix_selectedness(options: &mut [FormOption], multiple: bool) {
+    if multiple {
+        return;
+    }
+    match options.iter().rposition(|o| o.selected) {
+        Some(last) => {
+            for option in options[..last].iter_mut() {
+                option.selected = false;
+            }
+        }
+        None => {
+            if let Some(option) = options.iter_mut().find(|o| !o.disabled) {
+                option.selected = true;
+            }
+        }
+    }
+}
+
+/// Converts lone CR and LF characters to CRLF.
+fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\n', "\r\n")
+}
+
+/// Gets the text of an entry value, using the filename for files.
+fn entry_text(value: &FormValue) -> &str {
+    match value {
+        FormValue::Text(t) => t,
+        FormValue::File(f) => &f.filename,
+    }
+}
+
+/// Percent encodes text with the application/x-www-form-urlencoded byte serializer.
+pub fn url_encode(text: &str) -> String {
+    let mut output = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b' ' => output.push('+'),
+            b'*' | b'-' | b'.' | b'_' => output.push(byte as char),
+            b if b.is_ascii_alphanumeric() => output.push(b as char),
+            b => output.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    output
+}
+
+fn url_encode_entries(entries: &[(String, FormValue)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                url_encode(&normalize_newlines(name)),
+                url_encode(&normalize_newlines(entry_text(value)))
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+fn text_plain_encode_entries(entries: &[(String, FormValue)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}\r\n",
+                normalize_newlines(name),
+                normalize_newlines(entry_text(value))
+            )
+        })
+        .collect()
+}
+
+/// Picks a boundary which does not appear in any of the entries.
+fn multipart_boundary(entries: &[(String, FormValue)]) -> String {
+    let contains = |boundary: &[u8]| {
+        entries.iter().any(|(name, value)| {
+            let data = match value {
+                FormValue::Text(t) => t.as_bytes(),
+                FormValue::File(f) => &f.data,
+            };
+            [name.as_bytes(), data]
+                .iter()
+                .any(|d| d.windows(boundary.len()).any(|w| w == boundary))
+        })
+    };
+    let mut count = 0u32;
+    loop {
+        let boundary = format!("----hbFormBoundary{:08x}", count);
+        if !contains(boundary.as_bytes()) {
+            return boundary;
+        }
+        count += 1;
+    }
+}
+
+/// Escapes a name or filename in a Content-Disposition header.
+fn escape_disposition(text: &str) -> String {
+    text.replace('\n', "%0A")
+        .replace('\r', "%0D")
+        .replace('"', "%22")
+}
+
+fn multipart_encode_entries(entries: &[(String, FormValue)], boundary: &str) -> Vec<u8> {
+    let mut body = vec![];
+    for (name, value) in entries {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        let name = escape_disposition(&normalize_newlines(name));
+        match value {
+            FormValue::Text(text) => {
+                body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}",
+                        name,
+                        normalize_newlines(text)
+                    )
+                    .as_bytes(),
+                );
+            }
+            FormValue::File(file) => {
+                body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                        name,
+                        escape_disposition(&file.filename),
+                        file.content_type
+                    )
+                    .as_bytes(),
+                );
+                body.extend_from_slice(&file.data);
+            }
+        }
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+impl HtmlDocument {
+    /// Gets every form in the document in document order.
+    pub fn forms(&self) -> Vec<HtmlForm> {
+        let selector = "form".parse::<CssSelector>().unwrap();
+        self.form_tags(&selector)
+            .into_iter()
+            .map(|f| HtmlForm::new(self, f))
+            .collect()
+    }
+
+    /// Gets the first form in the document which matches the selector.
+    pub fn form(&self, selector: &CssSelector) -> Option<HtmlForm> {
+        self.form_tags(selector)
+            .into_iter()
+            .next()
+            .map(|f| HtmlForm::new(self, f))
+    }
+
+    fn form_tags(&self, selector: &CssSelector) -> Vec<&HtmlTag> {
+        self.select(selector)
+            .filter_map(|n| match n.get_node() {
+                Some(HtmlNode::Tag(t)) if t.tag.eq_ignore_ascii_case("form") => Some(t),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod form_tests {
+    use super::*;
+
+    fn form(html: &str) -> HtmlForm {
+        html.parse::<HtmlDocument>().unwrap().forms().remove(0)
+    }
+
+    fn texts(entries: &[(String, FormValue)]) -> Vec<(&str, &str)> {
+        entries
+            .iter()
+            .map(|(n, v)| (n.as_str(), entry_text(v)))
+            .collect()
+    }
+
+    #[test]
+    fn form_controls_test() {
+        let f = form(
+            r#"<form id="login" action="/login" method="POST">
+<input name="user" value="ann" required>
+<input type="password" name="pass">
+<input type="bogus" name="other">
+<textarea name="bio">
+hello</textarea>
+<select name="colour"><option disabled>none</option><optgroup label="g"><option value="r">Red</option></optgroup></select>
+<fieldset disabled><legend><input name="in_legend"></legend><input name="off"></fieldset>
+<button>Go</button>
+</form>
+<input name="outside" form="login" value="x">
+<input name="unrelated">"#,
+        );
+        assert_eq!(f.id, Some("login".to_owned()));
+        assert_eq!(f.method, FormMethod::Post);
+        assert_eq!(f.action, "/login");
+        let names = f
+            .controls
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            names,
+            vec![
+                "user",
+                "pass",
+                "other",
+                "bio",
+                "colour",
+                "in_legend",
+                "off",
+                "",
+                "outside"
+            ]
+        );
+        assert!(f.control("user").unwrap().required);
+        assert_eq!(f.control("other").unwrap().control_type, "text");
+        assert_eq!(f.control("bio").unwrap().value, "hello");
+        // the first enabled option is selected by default
+        assert_eq!(f.control("colour").unwrap().selected_values(), vec!["r"]);
+        assert!(!f.control("in_legend").unwrap().disabled);
+        assert!(f.control("off").unwrap().disabled);
+        assert_eq!(f.controls[7].control_type, "submit");
+    }
+
+    #[test]
+    fn form_entries_test() {
+        let mut f = form(
+            r#"<form>
+<input type="hidden" name="_charset_">
+<input type="checkbox" name="a" value="1" checked><input type="checkbox" name="a" value="2">
+<input type="radio" name="r" value="x" checked><input type="radio" name="r" value="y">
+<select name="m" multiple><option selected>one</option><option>two</option><option selected disabled>three</option></select>
+<input name="t" dirname="t.dir" value="v">
+<input type="reset" name="reset"><input type="button" name="b">
+<button name="go" value="yes">Go</button><input type="image" name="map">
+<input name="off" disabled value="z">
+</form>"#,
+        );
+        f.check("a", "2").unwrap().check("r", "y").unwrap();
+        f.select_option("m", "two").unwrap();
+        assert_eq!(
+            texts(&f.entries(None).unwrap()),
+            vec![
+                ("_charset_", "UTF-8"),
+                ("a", "1"),
+                ("a", "2"),
+                ("r", "y"),
+                ("m", "one"),
+                ("m", "two"),
+                ("t", "v"),
+                ("t.dir", "ltr"),
+            ]
+        );
+        let (go, _) = f.submit_buttons().next().unwrap();
+        let (map, _) = f.submit_buttons().nth(1).unwrap();
+        assert_eq!(texts(&f.entries(Some(go)).unwrap())[8], ("go", "yes"));
+        assert_eq!(
+            texts(&f.entries(Some(map)).unwrap())[8..],
+            vec![("map.x", "0"), ("map.y", "0")]
+        );
+        assert!(f.entries(Some(0)).is_err());
+        assert!(f.check("a", "3").is_err());
+        assert!(f.set_value("missing", "1").is_err());
+    }
+
+    #[test]
+    fn form_submission_test() {
+        let mut f = form(
+            r#"<form action="/post?old=1#top" method="post">
+<input name="q" value="a b&c">
+<textarea name="note"></textarea>
+<button>Send</button><button formmethod="get" formaction="/other">Get</button>
+</form>"#,
+        );
+        f.set_value("note", "line1\nline2").unwrap();
+        let post = f.submission(None).unwrap();
+        assert_eq!(post.url(), "/post?old=1#top");
+        assert_eq!(post.content_type, "application/x-www-form-urlencoded");
+        assert_eq!(
+            String::from_utf8(post.body).unwrap(),
+            "q=a+b%26c&note=line1%0D%0Aline2"
+        );
+        let (get, _) = f.submit_buttons().nth(1).unwrap();
+        let get = f.submission(Some(get)).unwrap();
+        assert_eq!(get.method, FormMethod::Get);
+        assert!(get.body.is_empty());
+        assert_eq!(get.url(), "/other?q=a+b%26c&note=line1%0D%0Aline2");
+    }
+
+    #[test]
+    fn form_entities_test() {
+        let f = form(
+            r#"<form action="/find?x=1&amp;y=2" method="post">
+<input name="q&amp;a" value="a&amp;b">
+<textarea name="t">&lt;p&gt; &#38;</textarea>
+<select name="s"><option>Fish &amp;  chips</option><option value="&#49;" selected>One</option></select>
+<select name="d"><option>Fish &amp;  chips</option></select>
+<button formaction="/send?a=1&amp;b=2">Send</button>
+</form>"#,
+        );
+        assert_eq!(f.action, "/find?x=1&y=2");
+        assert_eq!(f.control("q&a").unwrap().value, "a&b");
+        assert_eq!(f.control("t").unwrap().value, "<p> &");
+        assert_eq!(f.control("s").unwrap().options[0].label, "Fish & chips");
+        assert_eq!(f.control("s").unwrap().selected_values(), vec!["1"]);
+        let (send, _) = f.submit_buttons().next().unwrap();
+        let submission = f.submission(Some(send)).unwrap();
+        assert_eq!(submission.action, "/send?a=1&b=2");
+        assert_eq!(
+            String::from_utf8(submission.body).unwrap(),
+            "q%26a=a%26b&t=%3Cp%3E+%26&s=1&d=Fish+%26+chips"
+        );
+    }
+
+    #[test]
+    fn form_set_value_button_test() {
+        let mut f = form(
+            r#"<form>
+<button name="q" value="button">Go</button><input type="submit" name="q" value="submit">
+<input name="q" value="old">
+</form>"#,
+        );
+        f.set_value("q", "new").unwrap();
+        let values = f
+            .controls
+            .iter()
+            .map(|c| c.value.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(values, vec!["button", "submit", "new"]);
+    }
+
+    #[test]
+    fn form_multipart_test() {
+        let mut f = form(
+            r#"<form method="post" enctype="multipart/form-data">
+<input name="title" value="x">
+<input type="file" name="upload">
+</form>"#,
+        );
+        f.set_file(
+            "upload",
+            FormFile {
+                filename: "a\"b.txt".to_owned(),
+                content_type: "text/plain".to_owned(),
+                data: b"data".to_vec(),
+            },
+        )
+        .unwrap();
+        let submission = f.submission(None).unwrap();
+        let boundary = "----hbFormBoundary00000000";
+        assert_eq!(
+            submission.content_type,
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nx\r\n\
+--{b}\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"a%22b.txt\"\r\nContent-Type: text/plain\r\n\r\ndata\r\n\
+--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(String::from_utf8(submission.body).unwrap(), expected);
+    }
+}
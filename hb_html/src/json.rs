@@ -0,0 +1,143 @@
+//! A small JSON model used for the JSON-LD in HTML documents.
+//!
+//! The parser is lenient in the same ways as browsers are with JSON-LD found in the wild:
+//! trailing commas in arrays and objects are ignored, and control characters such as new
+//! lines are allowed inside strings.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::json::JsonValue;
+//! let json = r#"{"name": "Pancakes", "tags": ["easy", "sweet",], "serves": 4}"#
+//!     .parse::<JsonValue>()
+//!     .unwrap();
+//! assert_eq!(json.get("name").and_then(|n| n.as_str()), Some("Pancakes"));
+//! assert_eq!(json.get("serves").and_then(|s| s.as_f64()), Some(4.0));
+//! assert_eq!(json.to_string(), r#"{"name":"Pancakes","tags":["easy","sweet"],"serves":4}"#);
+//! ```
+use crate::error::ParseHtmlError;
+use crate::json_parsing::JsonParserFunctions;
+use hb_parse::StrParser;
+use std::fmt;
+use std::str::FromStr;
+
+/// A JSON value. Objects keep their keys in the order they were written, and can have
+/// the same key more than once.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Gets the first value with the key if this is an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+}
+
+impl FromStr for JsonValue {
+    type Err = ParseHtmlError;
+    fn from_str(json: &str) -> Result<Self, <Self as std::str::FromStr>::Err> {
+        StrParser::new(json)
+            .parse_json()
+            .map_err(|e| ParseHtmlError::with_msg(format!("{}", e)))
+    }
+}
+
+/// Writes a string as a quoted JSON string.
+fn write_json_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Formats the value as compact JSON.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonValue::Null => write!(f, "null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) if !n.is_finite() => write!(f, "null"),
+            JsonValue::Number(n) => write!(f, "{}", n),
+            JsonValue::String(s) => write_json_string(f, s),
+            JsonValue::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            JsonValue::Object(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::json::JsonValue;
+use hb_error::{context, ErrorContext};
+use hb_parse::error::{ParseError, ParseResult};
+use hb_parse::source::Source;
+
+/// How deep arrays and objects can be nested before the parser gives up.
+const MAX_JSON_DEPTH: usize = 64;
+
+/// Parsing functions for JSON which can be used on any [`Source`].
+pub trait JsonParserFunctions {
+    /// Parses a single JSON value which must be the only thing in the source apart from
+    /// whitespace.
+    fn parse_json(&mut self) -> ParseResult<JsonValue>;
+}
+
+impl<S: Source> JsonParserFunctions for S {
+    #[context("could not parse json")]
+    fn parse_json(&mut self) -> ParseResult<JsonValue> {
+        let value = self.parse_json_value(0)?;
+        self.skip_json_whitespace()?;
+        match self.peek()? {
+            None => Ok(value),
+            Some((_, c)) => Err(json_error(
+                self,
+                format!("unexpected '{}' after the value", c),
+            )),
+        }
+    }
+}
+
+trait JsonParserInnerFunctions {
+    fn parse_json_value(&mut self, depth: usize) -> ParseResult<JsonValue>;
+    fn parse_json_string(&mut self) -> ParseResult<String>;
+    fn parse_json_number(&mut self) -> ParseResult<f64>;
+    fn parse_json_hex(&mut self) -> ParseResult<u32>;
+    fn skip_json_whitespace(&mut self) -> ParseResult<()>;
+}
+
+impl<S: Source> JsonParserInnerFunctions for S {
+    fn parse_json_value(&mut self, depth: usize) -> ParseResult<JsonValue> {
+        if depth > MAX_JSON_DEPTH {
+            return Err(json_error(self, "the json is nested too deeply"));
+        }
+        self.skip_json_whitespace()?;
+        match self.peek()? {
+            None => Err(json_error(self, "expected a value but found the end")),
+            Some((_, '"')) => Ok(JsonValue::String(self.parse_json_string()?)),
+            Some((_, '-')) | Some((_, '0'..='9')) => {
+                Ok(JsonValue::Number(self.parse_json_number()?))
+            }
+            Some((_, '[')) => {
+                self.next()?;
+                let mut values = vec![];
+                self.skip_json_whitespace()?;
+                if let Some((_, ']')) = self.peek()? {
+                    self.next()?;
+                    return Ok(JsonValue::Array(values));
+                }
+                // each value is followed by a ',' or the closing ']', and the last value can
+                // have a ',' after it as well
+                loop {
+                    values.push(self.parse_json_value(depth + 1)?);
+                    self.skip_json_whitespace()?;
+                    match self.next()? {
+                        Some((_, ',')) => {
+                            self.skip_json_whitespace()?;
+                            if let Some((_, ']')) = self.peek()? {
+                                self.next()?;
+                                return Ok(JsonValue::Array(values));
+                            }
+                        }
+                        Some((_, ']')) => return Ok(JsonValue::Array(values)),
+                        _ => return Err(json_error(self, "expected ',' or ']' in the array")),
+                    }
+                }
+            }
+            Some((_, '{')) => {
+                self.next()?;
+                let mut entries = vec![];
+                self.skip_json_whitespace()?;
+                if let Some((_, '}')) = self.peek()? {
+                    self.next()?;
+                    return Ok(JsonValue::Object(entries));
+                }
+                // each entry is followed by a ',' or the closing '}', and the last entry can
+                // have a ',' after it as well
+                loop {
+                    self.skip_json_whitespace()?;
+                    match self.peek()? {
+                        Some((_, '"')) => (),
+                        Some((_, '}')) if !entries.is_empty() => {
+                            self.next()?;
+                            return Ok(JsonValue::Object(entries));
+                        }
+                        _ => return Err(json_error(self, "expected a key in the object")),
+                    }
+                    let key = self.parse_json_string()?;
+                    self.skip_json_whitespace()?;
+                    match self.next()? {
+                        Some((_, ':')) => (),
+                        _ => return Err(json_error(self, "expected ':' after the key")),
+                    }
+                    entries.push((key, self.parse_json_value(depth + 1)?));
+                    self.skip_json_whitespace()?;
+                    match self.next()? {
+                        Some((_, ',')) => (),
+                        Some((_, '}')) => return Ok(JsonValue::Object(entries)),
+                        _ => return Err(json_error(self, "expected ',' or '}' in the object")),
+                    }
+                }
+            }
+            Some((_, c)) if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some((_, c)) = self.peek()? {
+                    if !c.is_ascii_alphabetic() {
+                        break;
+                    }
+                    word.push(c);
+                    self.next()?;
+                }
+                match word.as_str() {
+                    "true" => Ok(JsonValue::Bool(true)),
+                    "false" => Ok(JsonValue::Bool(false)),
+                    "null" => Ok(JsonValue::Null),
+                    _ => Err(json_error(self, format!("unknown literal '{}'", word))),
+                }
+            }
+            Some((_, c)) => Err(json_error(self, format!("unexpected '{}'", c))),
+        }
+    }
+
+    /// Parses a string starting at the opening quote.
+    fn parse_json_string(&mut self) -> ParseResult<String> {
+        self.next()?;
+        let mut value = String::new();
+        loop {
+            match self.next()? {
+                None => return Err(json_error(self, "the string was not closed")),
+                Some((_, '"')) => return Ok(value),
+                Some((_, '\\')) => match self.next()? {
+                    Some((_, 'b')) => value.push('\u{8}'),
+                    Some((_, 'f')) => value.push('\u{c}'),
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, 'u')) => {
+                        let mut code = self.parse_json_hex()?;
+                        // characters outside the BMP are written as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code) {
+                            let start = self.get_pointer_loc();
+                            match (self.next()?, self.next()?) {
+                                (Some((_, '\\')), Some((_, 'u'))) => {
+                                    let low = self.parse_json_hex()?;
+                                    match (0xDC00..0xE000).contains(&low) {
+                                        true => {
+                                            code =
+                                                0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+                                        }
+                                        false => {
+                                            value.push(char::REPLACEMENT_CHARACTER);
+                                            code = low;
+                                        }
+                                    }
+                                }
+                                _ => self.set_pointer_loc(start)?,
+                            }
+                        }
+                        value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    Some((_, c)) => value.push(c),
+                    None => return Err(json_error(self, "the string was not closed")),
+                },
+                Some((_, c)) => value.push(c),
+            }
+        }
+    }
+
+    fn parse_json_number(&mut self) -> ParseResult<f64> {
+        let mut number = String::new();
+        while let Some((_, c)) = self.peek()? {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            number.push(c);
+            self.next()?;
+        }
+        number
+            .parse::<f64>()
+            .map_err(|_| json_error(self, format!("invalid number '{}'", number)))
+    }
+
+    /// Parses the four hex digits of a \u escape.
+    fn parse_json_hex(&mut self) -> ParseResult<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.next()?.and_then(|(_, c)| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(json_error(self, "invalid \\u escape")),
+            }
+        }
+        Ok(code)
+    }
+
+    fn skip_json_whitespace(&mut self) -> ParseResult<()> {
+        while let Some((_, c)) = self.peek()? {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.next()?;
+        }
+        Ok(())
+    }
+}
+
+fn json_error<S: Source, M: Into<String>>(source: &S, msg: M) -> ParseError {
+    ParseError::new().msg(format!(
+        "{} at {}\n{}",
+        msg.into(),
+        source.get_pointer_loc(),
+        source.get_context()
+    ))
+}
+
+#[cfg(test)]
+mod json_parsing_tests {
+    use super::*;
+    use hb_parse::StrParser;
+
+    fn parse(json: &str) -> ParseResult<JsonValue> {
+        StrParser::new(json).parse_json()
+    }
+
+    #[test]
+    fn parse_json_test() {
+        assert_eq!(
+            parse(r#" {"a": [1, -2.5e2, true, false, null], "b": {"c": ""}} "#).unwrap(),
+            JsonValue::Object(vec![
+                (
+                    "a".to_owned(),
+                    JsonValue::Array(vec![
+                        JsonValue::Number(1.0),
+                        JsonValue::Number(-250.0),
+                        JsonValue::Bool(true),
+                        JsonValue::Bool(false),
+                        JsonValue::Null,
+                    ])
+                ),
+                (
+                    "b".to_owned(),
+                    JsonValue::Object(vec![("c".to_owned(), JsonValue::String(String::new()))])
+                ),
+            ])
+        );
+        assert_eq!(
+            parse(r#""q\"\\\/\né😀""#).unwrap(),
+            JsonValue::String("q\"\\/\né😀".to_owned())
+        );
+        assert_eq!(parse("[]").unwrap(), JsonValue::Array(vec![]));
+        assert_eq!(parse("{}").unwrap(), JsonValue::Object(vec![]));
+    }
+
+    #[test]
+    fn parse_json_lenient_test() {
+        assert_eq!(
+            parse("[1, 2,]").unwrap(),
+            JsonValue::Array(vec![JsonValue::Number(1.0), JsonValue::Number(2.0)])
+        );
+        assert_eq!(
+            parse("{\"a\": \"line\nbreak\",}").unwrap(),
+            JsonValue::Object(vec![(
+                "a".to_owned(),
+                JsonValue::String("line\nbreak".to_owned())
+            )])
+        );
+    }
+
+    #[test]
+    fn parse_json_errors_test() {
+        for bad in [
+            "",
+            "[1 2]",
+            "{a: 1}",
+            "\"open",
+            "[,1]",
+            "nul",
+            "1 2",
+            "{\"a\" 1}",
+            "--1",
+            "[1,,2]",
+            "[,]",
+            "{,\"a\":1}",
+            "{\"a\":1,,\"b\":2}",
+            "{,}",
+            "[1,,]",
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+        assert!(parse(&"[".repeat(1000)).is_err());
+    }
+}
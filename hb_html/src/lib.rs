@@ -21,8 +21,38 @@
 //! query.find_with_tag("div").find_with_tag("p");
 //! ```
 
+// allows the code generated by the hb_macros derive macros to refer to this crate by name
+extern crate self as hb_html;
+
+pub mod borrowed;
+pub mod build;
+pub mod cascade;
+pub mod css;
+mod css_parsing;
+pub mod entities;
 pub mod error;
+pub mod extract;
+pub mod feed;
+pub mod form;
+pub mod json;
+mod json_parsing;
+pub mod links;
+pub mod lint;
+pub mod metadata;
 pub mod objects;
 mod parsing;
 mod parsing_new;
 pub mod querying;
+pub mod readability;
+mod reading;
+pub mod render;
+pub mod sanitize;
+pub mod selector_set;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod sitemap;
+pub mod structured;
+pub mod table;
+pub mod url;
+pub mod visit;
+mod xml_parsing;
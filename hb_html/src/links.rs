@@ -0,0 +1,480 @@
+//! Finds the links and resources referenced by a document and resolves them.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::links::LinkKind;
+//! use hb_html::objects::HtmlDocument;
+//! use hb_html::url::Url;
+//! let doc = r#"<head><base href="/docs/"></head>
+//! <body><a href="intro.html">Intro</a><img src="logo.png" srcset="logo@2x.png 2x"></body>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let page = Url::parse("https://example.com/index.html").unwrap();
+//! let links = doc.links(Some(&page));
+//! assert_eq!(links[0].kind, LinkKind::Anchor);
+//! assert_eq!(links[0].url.as_ref().unwrap().to_string(), "https://example.com/docs/intro.html");
+//! assert_eq!(links[2].kind, LinkKind::Srcset);
+//! assert_eq!(links[2].descriptor.as_deref(), Some("2x"));
+//! ```
+use crate::css_parsing::CssParserFunctions;
+use crate::entities::{collapse_whitespace, decode_entities};
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use crate::querying::NodeRef;
+use crate::url::Url;
+use hb_parse::StrParser;
+
+/// Where a link was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    /// The href of `<a>` or `<area>`.
+    Anchor,
+    /// The src of `<img>` or `<input type="image">`.
+    Image,
+    /// A candidate in the srcset of `<img>` or `<source>`.
+    Srcset,
+    /// The href of `<link>`.
+    Link,
+    /// The src of `<script>`.
+    Script,
+    /// The src of `<iframe>` or `<frame>`.
+    Frame,
+    /// The src of `<audio>`, `<video>`, `<source>`, `<track>` or `<embed>`, the poster of
+    /// `<video>`, or the data of `<object>`.
+    Media,
+    /// The action of `<form>` or the formaction of a submit button.
+    FormAction,
+    /// A `url()` or `@import` in a `<style>` tag or style attribute.
+    Css,
+}
+
+/// A link or resource found in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlLink<'a> {
+    pub kind: LinkKind,
+    /// The value as it was written in the document. For a srcset or CSS it is the URL
+    /// which was found in them.
+    pub raw: String,
+    /// The absolute URL of the link, after decoding its character references. It is None
+    /// when the link is relative and there is no absolute base URL to resolve it against.
+    pub url: Option<Url>,
+    /// The rel attribute of `<a>`, `<area>` and `<link>` tags.
+    pub rel: Option<String>,
+    /// The width or density descriptor of a srcset candidate, such as "2x" or "100w".
+    pub descriptor: Option<String>,
+    /// The tag the link was found in.
+    pub node: NodeRef<'a>,
+}
+
+impl HtmlDocument {
+    /// Gets the URL that relative links are resolved against. It is the href of the first
+    /// `<base>` tag resolved against the page URL, or the page URL if there isn't one.
+    /// Returns None if neither gives an absolute URL.
+    pub fn base_url(&self, page_url: Option<&Url>) -> Option<Url> {
+        let selector = "base[href]".parse::<CssSelector>().unwrap();
+        let base = self
+            .select_first(&selector)
+            .and_then(|n| match n.get_node() {
+                Some(HtmlNode::Tag(t)) => t
+                    .attributes
+                    .get("href")
+                    .map(|h| clean_url(&decode_entities(h))),
+                _ => None,
+            });
+        let resolved = match (page_url, base) {
+            (Some(page), Some(base)) => page.join(&base),
+            (None, Some(base)) => Url::parse_reference(&base),
+            (Some(page), None) => page.clone(),
+            (None, None) => return None,
+        };
+        match resolved.is_absolute() {
+            true => Some(resolved),
+            false => None,
+        }
+    }
+
+    /// Gets every link and resource in the document in document order, resolved against
+    /// [`HtmlDocument::base_url`].
+    pub fn links(&self, page_url: Option<&Url>) -> Vec<HtmlLink<'_>> {
+        let base = self.base_url(page_url);
+        let selector = "*".parse::<CssSelector>().unwrap();
+        let mut links = vec![];
+        for node in self.select(&selector) {
+            let tag = match node.get_node() {
+                Some(HtmlNode::Tag(t)) => t,
+                _ => continue,
+            };
+            for TagLink {
+                kind,
+                raw,
+                href,
+                descriptor,
+            } in tag_links(tag)
+            {
+                let url = match &base {
+                    Some(base) => Some(base.join(&clean_url(&href))),
+                    None => {
+                        Some(Url::parse_reference(&clean_url(&href))).filter(|u| u.is_absolute())
+                    }
+                };
+                let rel = match kind {
+                    LinkKind::Anchor | LinkKind::Link => tag.attributes.get("rel").cloned(),
+                    _ => None,
+                };
+                links.push(HtmlLink {
+                    kind,
+                    raw,
+                    url,
+                    rel,
+                    descriptor,
+                    node: node.clone(),
+                });
+            }
+        }
+        links
+    }
+}
+
+/// A link found in a tag, before it is resolved.
+struct TagLink {
+    kind: LinkKind,
+    /// The value as it was written.
+    raw: String,
+    /// The value with its character references decoded, unless it is from the text of a
+    /// `<style>` tag where they are not decoded.
+    href: String,
+    descriptor: Option<String>,
+}
+
+impl TagLink {
+    fn new(kind: LinkKind, raw: String, href: String) -> TagLink {
+        TagLink {
+            kind,
+            raw,
+            href,
+            descriptor: None,
+        }
+    }
+}
+
+/// Gets the links from the attributes and contents of a tag.
+fn tag_links(tag: &HtmlTag) -> Vec<TagLink> {
+    let mut links = vec![];
+    let mut attribute = |kind: LinkKind, name: &str| {
+        if let Some(value) = tag.attributes.get(name) {
+            links.push(TagLink::new(kind, value.clone(), decode_entities(value)));
+        }
+    };
+    let type_attr = tag.attributes.get("type").map(|t| t.to_ascii_lowercase());
+    match tag.tag.to_ascii_lowercase().as_str() {
+        "a" | "area" => attribute(LinkKind::Anchor, "href"),
+        "img" => attribute(LinkKind::Image, "src"),
+        "input" if type_attr.as_deref() == Some("image") => {
+            attribute(LinkKind::Image, "src");
+            attribute(LinkKind::FormAction, "formaction");
+        }
+        "input" if type_attr.as_deref() == Some("submit") => {
+            attribute(LinkKind::FormAction, "formaction")
+        }
+        "button" => attribute(LinkKind::FormAction, "formaction"),
+        "link" => attribute(LinkKind::Link, "href"),
+        "script" => attribute(LinkKind::Script, "src"),
+        "iframe" | "frame" => attribute(LinkKind::Frame, "src"),
+        "audio" | "source" | "track" | "embed" => attribute(LinkKind::Media, "src"),
+        "video" => {
+            attribute(LinkKind::Media, "src");
+            attribute(LinkKind::Media, "poster");
+        }
+        "object" => attribute(LinkKind::Media, "data"),
+        "form" => attribute(LinkKind::FormAction, "action"),
+        "style" => {
+            for url in css_urls(&tag.text()) {
+                links.push(TagLink::new(LinkKind::Css, url.clone(), url));
+            }
+        }
+        _ => (),
+    }
+    if let Some(srcset) = tag.attributes.get("srcset") {
+        for (url, descriptor) in parse_srcset(srcset) {
+            let href = decode_entities(&url);
+            links.push(TagLink {
+                descriptor,
+                ..TagLink::new(LinkKind::Srcset, url, href)
+            });
+        }
+    }
+    // the quotes of the urls in a style attribute can be written as &quot;
+    if let Some(style) = tag.attributes.get("style") {
+        for url in css_urls(&decode_entities(style)) {
+            links.push(TagLink::new(LinkKind::Css, url.clone(), url));
+        }
+    }
+    links
+}
+
+/// Removes the leading and trailing whitespace and any tabs or new lines from a URL,
+/// like browsers do.
+fn clean_url(url: &str) -> String {
+    url.trim()
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect()
+}
+
+/// Splits a srcset attribute into the URLs of its candidates and their descriptors.
+pub fn parse_srcset(srcset: &str) -> Vec<(String, Option<String>)> {
+    let mut candidates = vec![];
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return candidates;
+        }
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let url = &rest[..end];
+        rest = &rest[end..];
+        // a url ending in commas has no descriptors
+        let trimmed = url.trim_end_matches(',');
+        if trimmed.len() != url.len() {
+            candidates.push((trimmed.to_owned(), None));
+            continue;
+        }
+        // the descriptors run until a comma which is not inside brackets
+        let mut depth = 0;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                ',' if depth == 0 => {
+                    end = i;
+                    break;
+                }
+                _ => (),
+            }
+        }
+        let descriptor = collapse_whitespace(&rest[..end]);
+        candidates.push((url.to_owned(), Some(descriptor).filter(|d| !d.is_empty())));
+        rest = &rest[end..];
+    }
+}
+
+/// Finds the `url()` references and `@import` strings in CSS.
+pub fn css_urls(css: &str) -> Vec<String> {
+    StrParser::new(css).parse_css_urls().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod links_tests {
+    use super::*;
+
+    fn summary<'a>(links: &'a [HtmlLink]) -> Vec<(LinkKind, &'a str, String)> {
+        links
+            .iter()
+            .map(|l| {
+                (
+                    l.kind,
+                    l.raw.as_str(),
+                    l.url.as_ref().map(|u| u.to_string()).unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn links_test() {
+        let doc = r#"<html><head>
+<link rel="stylesheet" href="/main.css">
+<script src="app.js"></script>
+<style>body { background: url( "bg.png" ) } @import 'print.css'; /* url(skip.png) */</style>
+</head><body>
+<a href=" page
+2.html " rel="next">Next</a>
+<img src="a.png" srcset="a-1x.png 1x, a-2x.png 2x">
+<picture><source srcset="b.webp, c.webp 100w"></picture>
+<iframe src="https://other.org/embed"></iframe>
+<video src="v.mp4" poster="p.jpg"></video>
+<form action="?search"><button formaction="alt">Go</button></form>
+<div style="background-image: URL(div.png)"></div>
+</body></html>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let page = Url::parse("http://example.com/dir/index.html?x").unwrap();
+        let links = doc.links(Some(&page));
+        assert_eq!(
+            summary(&links),
+            vec![
+                (
+                    LinkKind::Link,
+                    "/main.css",
+                    "http://example.com/main.css".to_owned()
+                ),
+                (
+                    LinkKind::Script,
+                    "app.js",
+                    "http://example.com/dir/app.js".to_owned()
+                ),
+                (
+                    LinkKind::Css,
+                    "bg.png",
+                    "http://example.com/dir/bg.png".to_owned()
+                ),
+                (
+                    LinkKind::Css,
+                    "print.css",
+                    "http://example.com/dir/print.css".to_owned()
+                ),
+                (
+                    LinkKind::Anchor,
+                    " page\n2.html ",
+                    "http://example.com/dir/page2.html".to_owned()
+                ),
+                (
+                    LinkKind::Image,
+                    "a.png",
+                    "http://example.com/dir/a.png".to_owned()
+                ),
+                (
+                    LinkKind::Srcset,
+                    "a-1x.png",
+                    "http://example.com/dir/a-1x.png".to_owned()
+                ),
+                (
+                    LinkKind::Srcset,
+                    "a-2x.png",
+                    "http://example.com/dir/a-2x.png".to_owned()
+                ),
+                (
+                    LinkKind::Srcset,
+                    "b.webp",
+                    "http://example.com/dir/b.webp".to_owned()
+                ),
+                (
+                    LinkKind::Srcset,
+                    "c.webp",
+                    "http://example.com/dir/c.webp".to_owned()
+                ),
+                (
+                    LinkKind::Frame,
+                    "https://other.org/embed",
+                    "https://other.org/embed".to_owned()
+                ),
+                (
+                    LinkKind::Media,
+                    "v.mp4",
+                    "http://example.com/dir/v.mp4".to_owned()
+                ),
+                (
+                    LinkKind::Media,
+                    "p.jpg",
+                    "http://example.com/dir/p.jpg".to_owned()
+                ),
+                (
+                    LinkKind::FormAction,
+                    "?search",
+                    "http://example.com/dir/index.html?search".to_owned()
+                ),
+                (
+                    LinkKind::FormAction,
+                    "alt",
+                    "http://example.com/dir/alt".to_owned()
+                ),
+                (
+                    LinkKind::Css,
+                    "div.png",
+                    "http://example.com/dir/div.png".to_owned()
+                ),
+            ]
+        );
+        assert_eq!(links[0].rel.as_deref(), Some("stylesheet"));
+        assert_eq!(links[4].rel.as_deref(), Some("next"));
+        assert_eq!(links[9].descriptor.as_deref(), Some("100w"));
+        assert_eq!(links[8].descriptor, None);
+        match links[4].node.get_node() {
+            Some(HtmlNode::Tag(t)) => assert_eq!(t.tag, "a"),
+            _ => panic!("expected the a tag"),
+        }
+    }
+
+    #[test]
+    fn links_base_test() {
+        let doc = r#"<base href="https://cdn.example.com/assets/"><a href="x">x</a><a href="//h/y">y</a>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let links = doc.links(None);
+        assert_eq!(
+            links[0].url.as_ref().unwrap().to_string(),
+            "https://cdn.example.com/assets/x"
+        );
+        assert_eq!(links[1].url.as_ref().unwrap().to_string(), "https://h/y");
+        // without an absolute base only absolute links are resolved
+        let doc =
+            r#"<a href="x">x</a><a href="mailto:a@b.c">m</a>"#.parse::<HtmlDocument>().unwrap();
+        let links = doc.links(None);
+        assert_eq!(links[0].url, None);
+        assert_eq!(links[1].url.as_ref().unwrap().to_string(), "mailto:a@b.c");
+    }
+
+    #[test]
+    fn links_entities_test() {
+        let doc = r#"<base href="https://example.com/a&amp;b/">
+<a href="/s?a=1&amp;b=2">search</a><a href="c&#x3F;d=1">c</a>
+<img srcset="i.png?w=1&amp;h=2 2x">
+<div style="background: url(&quot;bg.png?a=1&amp;b=2&quot;)"></div>
+<style>p { background: url(s.png?a=1&amp;b=2) }</style>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        assert_eq!(
+            doc.base_url(None).unwrap().to_string(),
+            "https://example.com/a&b/"
+        );
+        let links = doc.links(None);
+        assert_eq!(
+            summary(&links),
+            vec![
+                (
+                    LinkKind::Anchor,
+                    "/s?a=1&amp;b=2",
+                    "https://example.com/s?a=1&b=2".to_owned()
+                ),
+                (
+                    LinkKind::Anchor,
+                    "c&#x3F;d=1",
+                    "https://example.com/a&b/c?d=1".to_owned()
+                ),
+                (
+                    LinkKind::Srcset,
+                    "i.png?w=1&amp;h=2",
+                    "https://example.com/a&b/i.png?w=1&h=2".to_owned()
+                ),
+                (
+                    LinkKind::Css,
+                    "bg.png?a=1&b=2",
+                    "https://example.com/a&b/bg.png?a=1&b=2".to_owned()
+                ),
+                // the text of a style tag does not have character references
+                (
+                    LinkKind::Css,
+                    "s.png?a=1&amp;b=2",
+                    "https://example.com/a&b/s.png?a=1&amp;b=2".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_srcset_test() {
+        assert_eq!(
+            parse_srcset(" a.png 1x,b.png, c, d.png 100w 2x , e.png (x, y)"),
+            vec![
+                ("a.png".to_owned(), Some("1x".to_owned())),
+                ("b.png".to_owned(), None),
+                ("c".to_owned(), None),
+                ("d.png".to_owned(), Some("100w 2x".to_owned())),
+                ("e.png".to_owned(), Some("(x, y)".to_owned())),
+            ]
+        );
+    }
+}
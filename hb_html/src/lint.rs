@@ -0,0 +1,681 @@
+//! Checks a HTML document for conformance problems, such as duplicate ids, tags inside
+//! tags which can't contain them, missing required attributes, obsolete tags and attribute
+//! values which aren't allowed.
+//!
+//! The checks are [`LintRule`]s, which find the tags to report with a [`CssSelector`] and
+//! can refine each match with a function. All of the rules of a [`Linter`] are matched in
+//! a single traversal with a [`CompiledSelectorSet`], and more rules can be added to the
+//! default ones. Each problem is reported as a [`LintDiagnostic`] with the path to the tag.
+//! The parsed tree doesn't keep the positions of the tags in the source, so the path is
+//! a selector which finds the tag again.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::lint::{LintRule, LintSeverity, Linter};
+//! use hb_html::objects::{HtmlDocument, HtmlNode};
+//! let doc = r#"<html lang=en><body><p id=a>Hi <div id=a>there</div></p>
+//! <img src="/cat.png"><center>old</center><a href="/">home</a></body></html>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let linter = Linter::default()
+//!     .add_rule(
+//!         LintRule::with_check("absolute-links", LintSeverity::Warning, "a[href]", |node| {
+//!             match node.get_node() {
+//!                 Some(HtmlNode::Tag(a)) if a.attributes["href"].starts_with('/') => {
+//!                     Some("links should be absolute".to_owned())
+//!                 }
+//!                 _ => None,
+//!             }
+//!         })
+//!         .unwrap(),
+//!     );
+//! let diagnostics = doc.lint(&linter);
+//! let rules: Vec<&str> = diagnostics.iter().map(|d| d.rule.as_str()).collect();
+//! assert_eq!(
+//!     rules,
+//!     vec!["duplicate-id", "invalid-nesting", "missing-attribute", "obsolete-element", "absolute-links"]
+//! );
+//! ```
+use crate::error::ParseHtmlError;
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use crate::querying::NodeRef;
+use crate::selector_set::CompiledSelectorSet;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The tags which can only contain phrasing content, so block tags can't be inside them.
+const PHRASING_TAGS: [&str; 26] = [
+    "abbr", "b", "bdi", "bdo", "cite", "code", "data", "dfn", "em", "h1", "h2", "h3", "h4", "h5",
+    "h6", "i", "kbd", "label", "mark", "p", "q", "s", "samp", "small", "span", "strong",
+];
+/// The tags which are block content.
+const BLOCK_TAGS: &str = "address, article, aside, blockquote, details, dialog, dd, div, \
+    dl, dt, fieldset, figcaption, figure, footer, form, h1, h2, h3, h4, h5, h6, header, \
+    hgroup, hr, li, main, nav, ol, p, pre, section, table, ul";
+/// The tags which are obsolete in HTML.
+const OBSOLETE_TAGS: &str = "acronym, applet, basefont, bgsound, big, blink, center, dir, \
+    font, frame, frameset, isindex, keygen, listing, marquee, menuitem, multicol, nextid, \
+    nobr, noembed, noframes, plaintext, rb, rtc, spacer, strike, tt, xmp";
+/// The values allowed for the type of an input.
+const INPUT_TYPES: [&str; 22] = [
+    "button",
+    "checkbox",
+    "color",
+    "date",
+    "datetime-local",
+    "email",
+    "file",
+    "hidden",
+    "image",
+    "month",
+    "number",
+    "password",
+    "radio",
+    "range",
+    "reset",
+    "search",
+    "submit",
+    "tel",
+    "text",
+    "time",
+    "url",
+    "week",
+];
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintSeverity {
+    /// The document doesn't conform to HTML.
+    Error,
+    /// The document conforms but is likely to be a mistake.
+    Warning,
+}
+
+impl fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintSeverity::Error => write!(f, "error"),
+            LintSeverity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintDiagnostic {
+    /// The name of the rule which found the problem.
+    pub rule: String,
+    pub severity: LintSeverity,
+    pub message: String,
+    /// The name of the tag with the problem.
+    pub tag: String,
+    /// A selector which finds the tag, such as "html:nth-child(1) > body:nth-child(2)".
+    pub path: String,
+    /// The index of each node on the way to the tag, starting with the top level nodes of
+    /// the document.
+    pub indexes: Vec<usize>,
+}
+
+impl LintDiagnostic {
+    fn new(rule: &str, severity: LintSeverity, message: String, node: &NodeRef) -> LintDiagnostic {
+        let tag = match node.get_node() {
+            Some(HtmlNode::Tag(t)) => t.tag.clone(),
+            _ => String::new(),
+        };
+        LintDiagnostic {
+            rule: rule.to_owned(),
+            severity,
+            message,
+            tag,
+            path: node_path(node),
+            indexes: node.path.iter().map(|(_, i)| *i).collect(),
+        }
+    }
+}
+
+impl fmt::Display for LintDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            self.severity, self.rule, self.path, self.message
+        )
+    }
+}
+
+type LintCheck = Box<dyn Fn(&NodeRef) -> Option<String> + Send + Sync>;
+
+/// A rule which reports the tags matching its selector.
+///
+/// A rule made with [`LintRule::new`] reports every matching tag with its message, where
+/// "{tag}" is replaced by the name of the tag. A rule made with [`LintRule::with_check`]
+/// calls its check for every matching tag, which returns the message when there is a
+/// problem.
+pub struct LintRule {
+    pub name: String,
+    pub severity: LintSeverity,
+    pub selector: CssSelector,
+    message: String,
+    check: Option<LintCheck>,
+}
+
+impl fmt::Debug for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LintRule")
+            .field("name", &self.name)
+            .field("severity", &self.severity)
+            .field("selector", &self.selector)
+            .field("message", &self.message)
+            .field("check", &self.check.is_some())
+            .finish()
+    }
+}
+
+impl LintRule {
+    /// Creates a rule which reports every tag matching the selector with the message.
+    pub fn new(
+        name: &str,
+        severity: LintSeverity,
+        selector: &str,
+        message: &str,
+    ) -> Result<LintRule, ParseHtmlError> {
+        Ok(LintRule {
+            name: name.to_owned(),
+            severity,
+            selector: selector.parse::<CssSelector>()?,
+            message: message.to_owned(),
+            check: None,
+        })
+    }
+
+    /// Creates a rule which calls the check for every tag matching the selector. The check
+    /// returns the message to report, or None if the tag is fine.
+    pub fn with_check<F>(
+        name: &str,
+        severity: LintSeverity,
+        selector: &str,
+        check: F,
+    ) -> Result<LintRule, ParseHtmlError>
+    where
+        F: Fn(&NodeRef) -> Option<String> + Send + Sync + 'static,
+    {
+        Ok(LintRule {
+            name: name.to_owned(),
+            severity,
+            selector: selector.parse::<CssSelector>()?,
+            message: String::new(),
+            check: Some(Box::new(check)),
+        })
+    }
+
+    /// Checks a tag which matched the selector.
+    fn diagnostic(&self, node: &NodeRef) -> Option<LintDiagnostic> {
+        let message = match &self.check {
+            Some(check) => check(node)?,
+            None => match node.get_node() {
+                Some(HtmlNode::Tag(t)) => self.message.replace("{tag}", t.local_name()),
+                _ => self.message.clone(),
+            },
+        };
+        Some(LintDiagnostic::new(
+            &self.name,
+            self.severity,
+            message,
+            node,
+        ))
+    }
+}
+
+/// A set of rules to check documents with.
+///
+/// [`Linter::new`] has no rules, while the default linter has the built in rules:
+/// duplicate-id, invalid-nesting, missing-attribute, obsolete-element and
+/// invalid-attribute-value.
+pub struct Linter {
+    rules: Vec<LintRule>,
+    selectors: CompiledSelectorSet,
+    /// Whether an id used by more than one tag is reported, by the duplicate-id rule.
+    pub duplicate_ids: bool,
+}
+
+impl fmt::Debug for Linter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Linter")
+            .field("rules", &self.rules)
+            .field("duplicate_ids", &self.duplicate_ids)
+            .finish()
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Linter {
+        let mut linter = Linter::new();
+        linter.duplicate_ids = true;
+        linter
+            .add_rule(built_in(LintRule::with_check(
+                "invalid-nesting",
+                LintSeverity::Error,
+                BLOCK_TAGS,
+                check_block_nesting,
+            )))
+            .add_rule(built_in(LintRule::new(
+                "invalid-nesting",
+                LintSeverity::Error,
+                "a a, a button, button a, button button, form form, label label",
+                "<{tag}> can't be inside another interactive tag of the same kind",
+            )))
+            .add_rule(built_in(LintRule::new(
+                "invalid-nesting",
+                LintSeverity::Error,
+                "ul > :not(li, script, template), ol > :not(li, script, template)",
+                "<{tag}> can't be directly inside a list, only <li> can",
+            )))
+            .add_rule(built_in(LintRule::new(
+                "missing-attribute",
+                LintSeverity::Error,
+                "img:not([alt]), area[href]:not([alt]), input[type=image]:not([alt])",
+                "<{tag}> needs an alt attribute",
+            )))
+            .add_rule(built_in(LintRule::new(
+                "missing-attribute",
+                LintSeverity::Warning,
+                "html:not([lang])",
+                "<html> should have a lang attribute",
+            )))
+            .add_rule(built_in(LintRule::new(
+                "missing-attribute",
+                LintSeverity::Error,
+                "optgroup:not([label])",
+                "<optgroup> needs a label attribute",
+            )))
+            .add_rule(built_in(LintRule::new(
+                "obsolete-element",
+                LintSeverity::Error,
+                OBSOLETE_TAGS,
+                "<{tag}> is obsolete",
+            )))
+            .add_rule(built_in(LintRule::with_check(
+                "invalid-attribute-value",
+                LintSeverity::Error,
+                "input[type]",
+                |node| check_value(node, "type", |v| INPUT_TYPES.contains(&v)),
+            )))
+            .add_rule(built_in(LintRule::with_check(
+                "invalid-attribute-value",
+                LintSeverity::Error,
+                "button[type]",
+                |node| check_value(node, "type", |v| ["submit", "reset", "button"].contains(&v)),
+            )))
+            .add_rule(built_in(LintRule::with_check(
+                "invalid-attribute-value",
+                LintSeverity::Error,
+                "form[method]",
+                |node| check_value(node, "method", |v| ["get", "post", "dialog"].contains(&v)),
+            )))
+            .add_rule(built_in(LintRule::with_check(
+                "invalid-attribute-value",
+                LintSeverity::Error,
+                "[dir]",
+                |node| check_value(node, "dir", |v| ["ltr", "rtl", "auto"].contains(&v)),
+            )))
+            .add_rule(built_in(LintRule::with_check(
+                "invalid-attribute-value",
+                LintSeverity::Error,
+                "[tabindex]",
+                |node| check_value(node, "tabindex", |v| v.parse::<i32>().is_ok()),
+            )))
+            .add_rule(built_in(LintRule::with_check(
+                "invalid-attribute-value",
+                LintSeverity::Error,
+                "img[width], img[height], canvas[width], canvas[height], video[width], video[height]",
+                |node| {
+                    check_value(node, "width", |v| v.parse::<u32>().is_ok())
+                        .or_else(|| check_value(node, "height", |v| v.parse::<u32>().is_ok()))
+                },
+            )))
+    }
+}
+
+impl Linter {
+    /// Creates a linter without any rules.
+    pub fn new() -> Linter {
+        Linter {
+            rules: vec![],
+            selectors: CompiledSelectorSet::new(),
+            duplicate_ids: false,
+        }
+    }
+
+    /// Adds a rule, which is reported after the rules already added for the same tag.
+    pub fn add_rule(mut self, rule: LintRule) -> Linter {
+        self.selectors.add(rule.selector.clone());
+        self.rules.push(rule);
+        self
+    }
+
+    /// Removes every rule with the name. The duplicate-id rule is turned off with
+    /// `duplicate_ids`.
+    pub fn without_rule(mut self, name: &str) -> Linter {
+        self.rules.retain(|r| r.name != name);
+        self.selectors = CompiledSelectorSet::new();
+        for rule in &self.rules {
+            self.selectors.add(rule.selector.clone());
+        }
+        if name == "duplicate-id" {
+            self.duplicate_ids = false;
+        }
+        self
+    }
+
+    /// The rules of the linter, other than duplicate-id.
+    pub fn rules(&self) -> &[LintRule] {
+        &self.rules
+    }
+
+    /// Checks the nodes, returning the problems found in document order.
+    pub fn lint(&self, nodes: &Vec<HtmlNode>) -> Vec<LintDiagnostic> {
+        let mut diagnostics = vec![];
+        let mut ids: HashMap<&str, String> = HashMap::new();
+        if self.duplicate_ids {
+            for node in all_tags(nodes) {
+                let tag = match node.get_node() {
+                    Some(HtmlNode::Tag(t)) => t,
+                    _ => continue,
+                };
+                for id in &tag.ids {
+                    match ids.get(id.as_str()) {
+                        Some(first) => diagnostics.push(LintDiagnostic::new(
+                            "duplicate-id",
+                            LintSeverity::Error,
+                            format!("the id '{}' is already used by {}", id, first),
+                            &node,
+                        )),
+                        None => {
+                            ids.insert(id, node_path(&node));
+                        }
+                    }
+                }
+            }
+        }
+        for matched in self.selectors.match_nodes(nodes) {
+            for index in matched.selectors {
+                if let Some(diagnostic) = self.rules[index].diagnostic(&matched.node) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+        // the sort is stable, so the problems with the same tag stay in the order above
+        diagnostics.sort_by(|a, b| a.indexes.cmp(&b.indexes));
+        diagnostics
+    }
+}
+
+impl HtmlDocument {
+    /// Checks the document with the linter, returning the problems found in document
+    /// order. Use `Linter::default()` for the built in rules.
+    pub fn lint(&self, linter: &Linter) -> Vec<LintDiagnostic> {
+        linter.lint(&self.nodes)
+    }
+}
+
+fn built_in(rule: Result<LintRule, ParseHtmlError>) -> LintRule {
+    rule.expect("the selectors of the built in lint rules are valid")
+}
+
+/// Finds the nearest ancestor which can only contain phrasing content. Only the first block
+/// tag below that ancestor is reported, the block tags inside of it are the same mistake.
+fn check_block_nesting(node: &NodeRef) -> Option<String> {
+    let tag = node_tag(node)?;
+    for n in node.get_path_iter().skip(1) {
+        if let HtmlNode::Tag(parent) = n {
+            let name = parent.local_name();
+            if PHRASING_TAGS.contains(&name) {
+                return Some(format!(
+                    "<{}> can't be inside <{}>, which can only contain phrasing content",
+                    tag.local_name(),
+                    name
+                ));
+            }
+            if BLOCK_TAGS.split(", ").any(|b| b == name) {
+                return None;
+            }
+        }
+    }
+    None
+}
+
+/// Reports the value of the attribute if it isn't valid. Values are compared in lower case.
+fn check_value(node: &NodeRef, attribute: &str, valid: impl Fn(&str) -> bool) -> Option<String> {
+    let value = node_tag(node)?.attributes.get(attribute)?;
+    match valid(value.trim().to_ascii_lowercase().as_str()) {
+        true => None,
+        false => Some(format!(
+            "'{}' is not a valid value for the {} attribute",
+            value, attribute
+        )),
+    }
+}
+
+fn node_tag<'a>(node: &NodeRef<'a>) -> Option<&'a HtmlTag> {
+    match node.get_node() {
+        Some(HtmlNode::Tag(t)) => Some(t),
+        _ => None,
+    }
+}
+
+/// Gets references to every tag in the nodes, in document order.
+fn all_tags(nodes: &Vec<HtmlNode>) -> Vec<NodeRef<'_>> {
+    let mut tags = vec![];
+    if nodes.is_empty() {
+        return tags;
+    }
+    let mut pointer = NodeRef {
+        path: vec![(nodes, 0)],
+    };
+    loop {
+        if let Some(HtmlNode::Tag(_)) = pointer.get_node() {
+            tags.push(pointer.clone());
+        }
+        if pointer.move_to_first_child().is_some() {
+            continue;
+        }
+        while pointer.move_to_next_sibling().is_none() {
+            if pointer.move_to_parent().is_none() {
+                return tags;
+            }
+        }
+    }
+}
+
+/// Gets a selector which finds the node, with the position of each tag among the tags
+/// next to it.
+fn node_path(node: &NodeRef) -> String {
+    node.path
+        .iter()
+        .filter_map(|(nodes, index)| match nodes.get(*index) {
+            Some(HtmlNode::Tag(t)) => {
+                let position = nodes[..*index]
+                    .iter()
+                    .filter(|n| matches!(n, HtmlNode::Tag(_)))
+                    .count()
+                    + 1;
+                Some(format!("{}:nth-child({})", t.local_name(), position))
+            }
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join(" > ")
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"<html><head><title>Test</title></head>
+<body>
+<p id=intro>Some <span><div>block</div></span> text</p>
+<div id="main intro">
+<img src="/a.png"><img src="/b.png" alt="">
+<ul><li>One</li><p>Two</p></ul>
+<a href="/"><a href="/x">nested</a></a>
+<font color=red>old</font>
+<input type=txt tabindex=one><input type=TEXT>
+<button type=submit dir=sideways>Go</button>
+<img src="/c.png" alt="C" width=100px height=20>
+</div>
+</body></html>"#;
+
+    #[test]
+    fn lint_default_test() {
+        let doc = DOCUMENT.parse::<HtmlDocument>().unwrap();
+        let diagnostics = doc.lint(&Linter::default());
+        let found: Vec<(&str, &str, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.rule.as_str(), d.tag.as_str(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("missing-attribute", "html", "<html> should have a lang attribute"),
+                (
+                    "invalid-nesting",
+                    "div",
+                    "<div> can't be inside <span>, which can only contain phrasing content"
+                ),
+                ("duplicate-id", "div", "the id 'intro' is already used by html:nth-child(1) > body:nth-child(2) > p:nth-child(1)"),
+                ("missing-attribute", "img", "<img> needs an alt attribute"),
+                (
+                    "invalid-nesting",
+                    "p",
+                    "<p> can't be directly inside a list, only <li> can"
+                ),
+                (
+                    "invalid-nesting",
+                    "a",
+                    "<a> can't be inside another interactive tag of the same kind"
+                ),
+                ("obsolete-element", "font", "<font> is obsolete"),
+                (
+                    "invalid-attribute-value",
+                    "input",
+                    "'txt' is not a valid value for the type attribute"
+                ),
+                (
+                    "invalid-attribute-value",
+                    "input",
+                    "'one' is not a valid value for the tabindex attribute"
+                ),
+                (
+                    "invalid-attribute-value",
+                    "button",
+                    "'sideways' is not a valid value for the dir attribute"
+                ),
+                (
+                    "invalid-attribute-value",
+                    "img",
+                    "'100px' is not a valid value for the width attribute"
+                ),
+            ]
+        );
+        assert_eq!(diagnostics[0].severity, LintSeverity::Warning);
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "error[invalid-nesting] html:nth-child(1) > body:nth-child(2) > p:nth-child(1) > span:nth-child(1) > div:nth-child(1): <div> can't be inside <span>, which can only contain phrasing content"
+        );
+        assert_eq!(diagnostics[1].indexes, vec![0, 2, 1, 1, 0]);
+
+        let clean =
+            r#"<html lang=en><body><p>Fine <b>text</b></p><img src=a alt=""></body></html>"#
+                .parse::<HtmlDocument>()
+                .unwrap();
+        assert_eq!(clean.lint(&Linter::default()), vec![]);
+    }
+
+    #[test]
+    fn lint_nesting_test() {
+        let doc = r#"<html lang=en><body>
+<span><div><section><p>x</p></section></div></span>
+<ul><li>One</li><script>run()</script><template><li>Two</li></template></ul>
+<ol><li>One</li><div>Two</div></ol>
+</body></html>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let found: Vec<(String, String)> = doc
+            .lint(&Linter::default())
+            .into_iter()
+            .map(|d| (d.tag, d.message))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "div".to_owned(),
+                    "<div> can't be inside <span>, which can only contain phrasing content"
+                        .to_owned()
+                ),
+                (
+                    "div".to_owned(),
+                    "<div> can't be directly inside a list, only <li> can".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_path_test() {
+        let doc = DOCUMENT.parse::<HtmlDocument>().unwrap();
+        for diagnostic in doc.lint(&Linter::default()) {
+            let selector = diagnostic.path.parse::<CssSelector>().unwrap();
+            let found = doc.select_first(&selector).unwrap();
+            let indexes: Vec<usize> = found.path.iter().map(|(_, i)| *i).collect();
+            assert_eq!(indexes, diagnostic.indexes, "{}", diagnostic);
+        }
+    }
+
+    #[test]
+    fn lint_custom_rules_test() {
+        let doc = DOCUMENT.parse::<HtmlDocument>().unwrap();
+        let linter = Linter::new()
+            .add_rule(
+                LintRule::new(
+                    "no-images",
+                    LintSeverity::Warning,
+                    "div img",
+                    "remove the <{tag}>",
+                )
+                .unwrap(),
+            )
+            .add_rule(
+                LintRule::with_check("short-text", LintSeverity::Warning, "li", |node| {
+                    let text = node.get_node()?.text();
+                    match text.len() < 4 {
+                        true => Some(format!("'{}' is too short", text)),
+                        false => None,
+                    }
+                })
+                .unwrap(),
+            );
+        let messages: Vec<String> = doc.lint(&linter).into_iter().map(|d| d.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "remove the <img>",
+                "remove the <img>",
+                "'One' is too short",
+                "remove the <img>"
+            ]
+        );
+        assert_eq!(linter.rules().len(), 2);
+        assert!(doc.lint(&linter.without_rule("no-images")).len() == 1);
+        assert!(LintRule::new("bad", LintSeverity::Error, "p[", "bad").is_err());
+
+        let linter = Linter::default()
+            .without_rule("duplicate-id")
+            .without_rule("invalid-attribute-value");
+        assert!(doc
+            .lint(&linter)
+            .iter()
+            .all(|d| d.rule != "duplicate-id" && d.rule != "invalid-attribute-value"));
+    }
+}
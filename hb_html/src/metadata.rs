@@ -0,0 +1,378 @@
+//! Collects the metadata of a document: the title, meta tags, OpenGraph and Twitter card
+//! properties, canonical URL, language, icons and hreflang alternates.
+//!
+//! The common values are resolved from the places they can be written, in this order:
+//!
+//! * title: `<title>`, then `og:title`, then `twitter:title`.
+//! * description: `<meta name="description">`, then `og:description`, then
+//!   `twitter:description`.
+//! * canonical: `<link rel="canonical">`, then `og:url`.
+//! * language: `<html lang>`, then `<meta http-equiv="content-language">`, then
+//!   `og:locale`.
+//! * image: `og:image`, then `og:image:url`, then `twitter:image`, then `twitter:image:src`.
+//!
+//! The first tag wins when a value is written more than once. Text is decoded and has its
+//! white space collapsed, and URLs are resolved against [`HtmlDocument::base_url`].
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::HtmlDocument;
+//! use hb_html::url::Url;
+//! let doc = r#"<html lang="en"><head>
+//! <title>Pancakes &amp; syrup | Recipes</title>
+//! <meta property="og:title" content="Pancakes &amp; syrup">
+//! <meta property="og:image" content="/img/pancakes.jpg">
+//! <meta name="description" content="Quick pancakes.">
+//! <link rel="canonical" href="/recipes/pancakes">
+//! <link rel="icon" href="/favicon.png" sizes="32x32" type="image/png">
+//! <link rel="alternate" hreflang="fr" href="/fr/recipes/pancakes">
+//! </head></html>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let page = Url::parse("https://example.com/recipes/pancakes?ref=home").unwrap();
+//! let metadata = doc.metadata(Some(&page));
+//! assert_eq!(metadata.title.as_deref(), Some("Pancakes & syrup | Recipes"));
+//! assert_eq!(metadata.open_graph("title"), Some("Pancakes & syrup"));
+//! assert_eq!(metadata.description.as_deref(), Some("Quick pancakes."));
+//! assert_eq!(metadata.language.as_deref(), Some("en"));
+//! assert_eq!(
+//!     metadata.canonical.unwrap().to_string(),
+//!     "https://example.com/recipes/pancakes"
+//! );
+//! assert_eq!(
+//!     metadata.image.unwrap().to_string(),
+//!     "https://example.com/img/pancakes.jpg"
+//! );
+//! assert_eq!(metadata.icons[0].sizes.as_deref(), Some("32x32"));
+//! assert_eq!(metadata.alternates[0].hreflang, "fr");
+//! ```
+use crate::entities::{collapse_whitespace, decode_entities, decode_text};
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use crate::reading::resolve;
+use crate::url::Url;
+
+/// The metadata of a document, see the [module documentation](self) for where each value
+/// comes from.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub canonical: Option<Url>,
+    /// The language tag of the document, such as "en" or "pt-BR".
+    pub language: Option<String>,
+    pub image: Option<Url>,
+    /// Every `<meta>` tag with a name or property and a content, in document order. The
+    /// names are in lower case.
+    pub meta: Vec<(String, String)>,
+    pub icons: Vec<MetadataIcon>,
+    pub alternates: Vec<MetadataAlternate>,
+}
+
+/// An icon from a `<link>` tag with a rel of "icon", "shortcut icon", "apple-touch-icon"
+/// or "mask-icon".
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataIcon {
+    pub url: Url,
+    /// The rel attribute in lower case.
+    pub rel: String,
+    /// The sizes attribute, such as "32x32" or "any".
+    pub sizes: Option<String>,
+    /// The type attribute, such as "image/png".
+    pub mime_type: Option<String>,
+}
+
+/// A translation of the page from a `<link rel="alternate" hreflang="..">` tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataAlternate {
+    /// The language of the translation, such as "de", "en-GB" or "x-default".
+    pub hreflang: String,
+    pub url: Url,
+}
+
+impl DocumentMetadata {
+    /// Gets the content of the first meta tag with the name, ignoring case.
+    pub fn meta(&self, name: &str) -> Option<&str> {
+        self.meta
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, content)| content.as_str())
+    }
+
+    /// Gets the content of every meta tag with the name, ignoring case.
+    pub fn meta_all<'m>(&'m self, name: &'m str) -> impl Iterator<Item = &'m str> {
+        self.meta
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, content)| content.as_str())
+    }
+
+    /// Gets an OpenGraph property by its name without the "og:" prefix, such as "title"
+    /// or "image:width".
+    pub fn open_graph(&self, property: &str) -> Option<&str> {
+        self.meta(&format!("og:{}", property))
+    }
+
+    /// Gets a Twitter card property by its name without the "twitter:" prefix, such as
+    /// "card" or "site".
+    pub fn twitter(&self, property: &str) -> Option<&str> {
+        self.meta(&format!("twitter:{}", property))
+    }
+
+    /// Gets the first non-empty meta value from the names, in order.
+    fn first_meta(&self, names: &[&str]) -> Option<&str> {
+        names
+            .iter()
+            .filter_map(|name| self.meta(name))
+            .find(|content| !content.is_empty())
+    }
+}
+
+impl HtmlDocument {
+    /// Collects the [`DocumentMetadata`] of the document, resolving URLs against the
+    /// [`HtmlDocument::base_url`]. URLs are left relative when there is no absolute base.
+    pub fn metadata(&self, page_url: Option<&Url>) -> DocumentMetadata {
+        let base = self.base_url(page_url);
+        let resolve = |href: &str| resolve(base.as_ref(), href);
+        let mut metadata = DocumentMetadata::default();
+        let mut content_language = None;
+        let mut canonical = None;
+        for tag in self.metadata_tags("meta") {
+            let content = match tag.attributes.get("content") {
+                Some(content) => decode_text(content),
+                None => continue,
+            };
+            if let Some(name) = tag
+                .attributes
+                .get("name")
+                .or(tag.attributes.get("property"))
+            {
+                metadata
+                    .meta
+                    .push((name.trim().to_ascii_lowercase(), content));
+            } else if let Some(equiv) = tag.attributes.get("http-equiv") {
+                if equiv.trim().eq_ignore_ascii_case("content-language")
+                    && content_language.is_none()
+                {
+                    content_language = Some(content);
+                }
+            }
+        }
+        for tag in self.metadata_tags("link[href]") {
+            let href = &decode_entities(&tag.attributes["href"]);
+            let rel = match tag.attributes.get("rel") {
+                Some(rel) => collapse_whitespace(rel).to_ascii_lowercase(),
+                None => continue,
+            };
+            let rels = rel.split(' ').collect::<Vec<_>>();
+            if rels.contains(&"canonical") && canonical.is_none() {
+                canonical = resolve(href);
+            }
+            if rels.iter().any(|r| {
+                matches!(
+                    *r,
+                    "icon" | "apple-touch-icon" | "apple-touch-icon-precomposed" | "mask-icon"
+                )
+            }) {
+                metadata.icons.extend(resolve(href).map(|url| MetadataIcon {
+                    url,
+                    rel: rel.clone(),
+                    sizes: tag.attributes.get("sizes").map(|s| s.trim().to_owned()),
+                    mime_type: tag.attributes.get("type").map(|t| t.trim().to_owned()),
+                }));
+            }
+            if rels.contains(&"alternate") {
+                if let (Some(hreflang), Some(url)) = (tag.attributes.get("hreflang"), resolve(href))
+                {
+                    metadata.alternates.push(MetadataAlternate {
+                        hreflang: hreflang.trim().to_owned(),
+                        url,
+                    });
+                }
+            }
+        }
+        let title = self
+            .metadata_tags("title")
+            .first()
+            .map(|t| decode_text(&t.text()))
+            .filter(|t| !t.is_empty());
+        metadata.title = title.or_else(|| {
+            metadata
+                .first_meta(&["og:title", "twitter:title"])
+                .map(|t| t.to_owned())
+        });
+        metadata.description = metadata
+            .first_meta(&["description", "og:description", "twitter:description"])
+            .map(|d| d.to_owned());
+        metadata.canonical =
+            canonical.or_else(|| metadata.first_meta(&["og:url"]).and_then(resolve));
+        let lang = self
+            .metadata_tags("html[lang]")
+            .first()
+            .map(|t| t.attributes["lang"].trim().to_owned());
+        metadata.language = lang
+            .into_iter()
+            .chain(content_language)
+            .chain(metadata.open_graph("locale").map(|l| l.replace('_', "-")))
+            .find(|l| !l.is_empty());
+        metadata.image = metadata
+            .first_meta(&[
+                "og:image",
+                "og:image:url",
+                "twitter:image",
+                "twitter:image:src",
+            ])
+            .and_then(resolve);
+        metadata
+    }
+
+    /// Gets the tags which match the selector in document order.
+    fn metadata_tags(&self, selector: &str) -> Vec<&HtmlTag> {
+        let selector = selector.parse::<CssSelector>().unwrap();
+        self.select(&selector)
+            .filter_map(|n| match n.get_node() {
+                Some(HtmlNode::Tag(t)) => Some(t),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod metadata_tests {
+    use super::*;
+
+    fn metadata(html: &str, page: Option<&str>) -> DocumentMetadata {
+        let page = page.map(|p| Url::parse(p).unwrap());
+        html.parse::<HtmlDocument>()
+            .unwrap()
+            .metadata(page.as_ref())
+    }
+
+    #[test]
+    fn metadata_test() {
+        let m = metadata(
+            r#"<html lang=" de "><head>
+            <base href="https://cdn.example.com/site/">
+            <title>
+                Kaffee   &amp; Kuchen
+            </title>
+            <meta charset="utf-8">
+            <meta name="Description" content=" Alles über  Kaffee ">
+            <meta property="og:image" content="img/a.jpg">
+            <meta property="og:image" content="img/b.jpg">
+            <meta name="twitter:card" content="summary">
+            <meta name="twitter:image" content="img/t.jpg">
+            <meta http-equiv="Content-Language" content="fr">
+            <link rel="Shortcut Icon" href="favicon.ico">
+            <link rel="apple-touch-icon" href="/touch.png" sizes="180x180">
+            <link rel="stylesheet" href="style.css">
+            <link rel="canonical" href="https://example.com/kaffee">
+            <link rel="canonical" href="https://example.com/other">
+            <link rel="alternate" hreflang="en" href="https://example.com/en/coffee">
+            <link rel="alternate" hreflang="x-default" href="/kaffee">
+            <link rel="alternate" type="application/rss+xml" href="/feed.xml">
+            </head><body><svg><title>icon</title></svg></body></html>"#,
+            Some("https://example.com/kaffee"),
+        );
+        assert_eq!(m.title.as_deref(), Some("Kaffee & Kuchen"));
+        assert_eq!(m.description.as_deref(), Some("Alles über Kaffee"));
+        assert_eq!(m.language.as_deref(), Some("de"));
+        assert_eq!(
+            m.canonical.as_ref().map(|c| c.to_string()).as_deref(),
+            Some("https://example.com/kaffee")
+        );
+        assert_eq!(
+            m.image.as_ref().map(|i| i.to_string()).as_deref(),
+            Some("https://cdn.example.com/site/img/a.jpg")
+        );
+        assert_eq!(
+            m.meta_all("og:image").collect::<Vec<_>>(),
+            vec!["img/a.jpg", "img/b.jpg"]
+        );
+        assert_eq!(m.twitter("card"), Some("summary"));
+        assert_eq!(m.meta("description"), Some("Alles über Kaffee"));
+        assert_eq!(m.icons.len(), 2);
+        assert_eq!(m.icons[0].rel, "shortcut icon");
+        assert_eq!(
+            m.icons[0].url.to_string(),
+            "https://cdn.example.com/site/favicon.ico"
+        );
+        assert_eq!(
+            m.icons[1].url.to_string(),
+            "https://cdn.example.com/touch.png"
+        );
+        assert_eq!(m.icons[1].sizes.as_deref(), Some("180x180"));
+        assert_eq!(m.alternates.len(), 2);
+        assert_eq!(m.alternates[1].hreflang, "x-default");
+        assert_eq!(
+            m.alternates[1].url.to_string(),
+            "https://cdn.example.com/kaffee"
+        );
+    }
+
+    #[test]
+    fn metadata_fallback_test() {
+        let m = metadata(
+            r#"<html><head><title> </title>
+            <meta property="og:title" content="">
+            <meta name="twitter:title" content="Tweet title">
+            <meta property="og:description" content="From OG">
+            <meta property="og:url" content="/page">
+            <meta property="og:locale" content="en_GB">
+            <meta name="twitter:image:src" content="t.png">
+            </head></html>"#,
+            None,
+        );
+        assert_eq!(m.title.as_deref(), Some("Tweet title"));
+        assert_eq!(m.description.as_deref(), Some("From OG"));
+        assert_eq!(m.language.as_deref(), Some("en-GB"));
+        assert_eq!(
+            m.canonical.as_ref().map(|c| c.to_string()).as_deref(),
+            Some("/page")
+        );
+        assert_eq!(
+            m.image.as_ref().map(|i| i.to_string()).as_deref(),
+            Some("t.png")
+        );
+
+        let m = metadata(
+            r#"<html><head><meta http-equiv="content-language" content="pt-BR"></head></html>"#,
+            None,
+        );
+        assert_eq!(m.language.as_deref(), Some("pt-BR"));
+        assert_eq!(m.title, None);
+        assert_eq!(metadata("<p>text</p>", None), DocumentMetadata::default());
+    }
+
+    #[test]
+    fn metadata_entities_test() {
+        let m = metadata(
+            r#"<html><head>
+            <base href="/a&amp;b/">
+            <link rel="canonical" href="/page?id=1&amp;lang=en">
+            <link rel="icon" href="icon.png?v=1&amp;s=2">
+            <link rel="alternate" hreflang="de" href="/de?x=1&#38;y=2">
+            <meta property="og:image" content="img.jpg?w=1&amp;h=2">
+            </head></html>"#,
+            Some("https://example.com/"),
+        );
+        let url = |u: Option<&Url>| u.map(|u| u.to_string());
+        assert_eq!(
+            url(m.canonical.as_ref()).as_deref(),
+            Some("https://example.com/page?id=1&lang=en")
+        );
+        assert_eq!(
+            m.icons[0].url.to_string(),
+            "https://example.com/a&b/icon.png?v=1&s=2"
+        );
+        assert_eq!(
+            m.alternates[0].url.to_string(),
+            "https://example.com/de?x=1&y=2"
+        );
+        assert_eq!(
+            url(m.image.as_ref()).as_deref(),
+            Some("https://example.com/a&b/img.jpg?w=1&h=2")
+        );
+    }
+}
@@ -1,8 +1,15 @@
 use crate::error::ParseHtmlError;
-use crate::parsing::{parse_css_selector_rule, parse_html_tag, ParsedTagType};
+use crate::parsing::{parse_html_tag, ParsedTagType};
 use crate::querying::{HtmlQuery, HtmlQueryable};
+use crate::xml_parsing::XmlParserFunctions;
+use hb_parse::StrParser;
+use hb_selector::selector::split_css_type;
+pub use hb_selector::selector::{
+    CssAttributeCompareType, CssRefiner, CssRefinerNumberType, CssRegex, CssSelector,
+    CssSelectorItem, CssSelectorParseOptions, CssSelectorRelationship, CssSelectorRule,
+    CssSpecificity,
+};
 use std::collections::HashMap;
-use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
 
@@ -58,11 +65,14 @@ pub struct HtmlTag {
     pub attributes: HashMap<String, String>,
     /// The contents of the HTML tag, stores as [`HtmlNode`] objects.
     pub contents: Vec<HtmlNode>,
+    /// The namespace URI of the tag when it was parsed as XML, see
+    /// [`HtmlDocument::parse_xml`]. It is None for HTML and for XML tags without a namespace.
+    pub namespace: Option<String>,
 }
 
 impl PartialEq for HtmlTag {
     fn eq(&self, other: &HtmlTag) -> bool {
-        if self.tag != other.tag {
+        if self.tag != other.tag || self.namespace != other.namespace {
             return false;
         }
         if self.ids.len() != other.ids.len() {
@@ -101,6 +111,7 @@ impl HtmlTag {
             classes: vec![],
             contents: vec![],
             attributes: HashMap::new(),
+            namespace: None,
         }
     }
 
@@ -136,6 +147,32 @@ impl HtmlTag {
         }
         self
     }
+    pub fn namespace<T: Into<String>>(mut self, namespace: T) -> HtmlTag {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Gets the name of the tag without its namespace prefix, so "atom:link" gives "link"
+    /// for a tag parsed as XML. HTML tag names are returned as they are.
+    pub fn local_name(&self) -> &str {
+        match self.namespace {
+            Some(_) => self.tag.rsplit(':').next().unwrap_or(&self.tag),
+            None => &self.tag,
+        }
+    }
+
+    /// Checks if the tag matches the type part of a [`CssSelectorItem`]. It is a tag name,
+    /// `*`, or a name in a namespace written as `{uri}name`, where `{}name` is a name in no
+    /// namespace.
+    pub(crate) fn matches_type(&self, selector_tag: &str) -> bool {
+        let (namespace, name) = split_css_type(selector_tag);
+        if let Some(namespace) = namespace {
+            if self.namespace.as_deref().unwrap_or("") != namespace {
+                return false;
+            }
+        }
+        name == "*" || name == self.local_name()
+    }
 }
 
 impl FromStr for HtmlTag {
@@ -349,6 +386,39 @@ impl HtmlDocument {
             nodes: v,
         }
     }
+    /// Parses a XML document, such as a feed, a sitemap or XHTML, using the XML rules
+    /// instead of the HTML ones. Tags keep their prefixes and get the namespace they are
+    /// in, so they can be found with namespaced selectors.
+    ///
+    /// ```rust
+    /// use hb_html::objects::{CssSelector, HtmlDocument, HtmlNode};
+    ///
+    /// let doc = HtmlDocument::parse_xml(
+    ///     r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"><rect/></svg>"#,
+    /// )
+    /// .unwrap();
+    /// let selector = "rect".parse::<CssSelector>().unwrap();
+    /// match doc.select_first(&selector).and_then(|n| n.get_node()) {
+    ///     Some(HtmlNode::Tag(rect)) => {
+    ///         assert_eq!(rect.namespace.as_deref(), Some("http://www.w3.org/2000/svg"))
+    ///     }
+    ///     _ => panic!("rect not found"),
+    /// }
+    /// assert!(HtmlDocument::parse_xml("<p>not closed").is_err());
+    /// ```
+    pub fn parse_xml(xml: &str) -> Result<HtmlDocument, ParseHtmlError> {
+        StrParser::new(xml)
+            .parse_xml()
+            .map_err(|e| ParseHtmlError::with_msg(format!("{}", e)))
+    }
+    /// Parses a XML document like [`HtmlDocument::parse_xml`], but recovers from the
+    /// mistakes that are common in feeds and sitemaps found on the web, such as a bare `&`,
+    /// HTML entities, unclosed tags or undeclared namespace prefixes.
+    pub fn parse_xml_lenient(xml: &str) -> Result<HtmlDocument, ParseHtmlError> {
+        StrParser::new(xml)
+            .parse_xml_lenient()
+            .map_err(|e| ParseHtmlError::with_msg(format!("{}", e)))
+    }
     pub fn find(&self, selector: &str) -> HtmlQuery {
         let mut query = self.query();
         query.find_str(selector);
@@ -413,151 +483,97 @@ impl FromStr for HtmlDocument {
 // when it finds a node that matches (or exiting early if the
 // specific node did not match - eg parent/sibling)
 
-#[derive(Debug, PartialEq, Clone)]
-/// Represents the relationship that is next to be matched in the list of selector items.
-pub enum CssSelectorRelationship {
-    Parent(CssSelectorItem),
-    Ancestor(CssSelectorItem),
-    PreviousSibling(CssSelectorItem),
-    PreviousSiblingOnce(CssSelectorItem),
-    Current(CssSelectorItem),
-}
-
-#[derive(Debug, PartialEq, Clone)]
-/// represents all of the CSS selectors which follow a :, for example :last-child
-pub enum CssRefiner {
-    Checked,
-    Default,
-    Disabled,
-    Enabled,
-    Optional,
-    Required,
-    ReadOnly,
-    ReadWrite,
-    Empty,
-    FirstChild,
-    LastChild,
-    NthChild(CssRefinerNumberType),
-    NthLastChild(CssRefinerNumberType),
-    OnlyChild,
-    FirstOfType,
-    LastOfType,
-    NthOfType(CssRefinerNumberType),
-    NthLastOfType(CssRefinerNumberType),
-    OnlyOfType,
-    Not(CssSelector),
-    Root,
-}
-
-#[derive(Debug, PartialEq, Clone)]
-/// Used for CSS Selectors such as :nth-child(x) where x can be odd, even or a specific number
-pub enum CssRefinerNumberType {
-    Odd,
-    Even,
-    Specific(usize),
-    Functional((i32, i32)),
-}
-
-#[derive(Debug, PartialEq, Clone)]
-/// Used to represents the different types of attributes selections for example [attribute=value]
-pub enum CssAttributeCompareType {
-    /// [attribute]
-    Present(String),
-    /// [attribute=value]
-    Equals((String, String)),
-    /// [attribute|=value]
-    EqualsOrBeingsWith((String, String)),
-    /// [attribute^=value]
-    BeginsWith((String, String)),
-    /// [attribute$=value]
-    EndsWith((String, String)),
-    /// [attribute*=value]
-    Contains((String, String)),
-    /// [attribute~=value]
-    ContainsWord((String, String)),
-}
-
-#[derive(Debug, PartialEq, Clone)]
-/// Represents a CSS selector for a particular node
-pub struct CssSelectorItem {
-    pub tag: Option<String>,
-    pub classes: Option<Vec<String>>,
-    pub ids: Option<Vec<String>>,
-    pub refiners: Option<Vec<CssRefiner>>, // anything like :... eg :only-child
-    pub attributes: Option<Vec<CssAttributeCompareType>>,
-}
-
-impl CssSelectorItem {
-    pub fn new() -> CssSelectorItem {
-        CssSelectorItem {
-            tag: None,
-            classes: None,
-            ids: None,
-            refiners: None,
-            attributes: None,
-        }
-    }
-}
-
-#[derive(Debug, PartialEq, Clone)]
-/// Represents a rule that must match for a CSS selector
-pub struct CssSelectorRule {
-    pub rules: Vec<CssSelectorRelationship>,
-}
-
-impl CssSelectorRule {
-    pub fn new() -> CssSelectorRule {
-        CssSelectorRule { rules: vec![] }
-    }
-}
+/// Creates a [`CssSelector`] at compile time, so mistakes in the selector are compile
+/// errors.
+///
+/// # Example
+///
+/// ```
+/// use hb_html::objects::{css, CssSelector};
+/// let selector: CssSelector = css!("div.item > a[href]");
+/// assert_eq!(selector, "div.item > a[href]".parse::<CssSelector>().unwrap());
+/// ```
+///
+/// The error points at the column of the mistake:
+///
+/// ```compile_fail
+/// // error: unknown refiner type frist-child. at column 5
+/// let selector = hb_html::objects::css!("li a:frist-child");
+/// ```
+pub use hb_macros::css;
 
-#[derive(Debug, PartialEq, Clone)]
-/// Represents a CSS selector which could be anything (*) or based on a some selection rules.
-/// CSS selectors all multiple different match rules seperated by a comma. This is handle by
-/// having each matching rule in a vector.
-pub enum CssSelector {
-    Any,
-    Specific(Vec<CssSelectorRule>),
-}
+#[cfg(test)]
+mod css_macro_tests {
+    use super::*;
 
-impl FromStr for CssSelector {
-    type Err = ParseHtmlError;
-    fn from_str(selector: &str) -> Result<Self, <Self as std::str::FromStr>::Err> {
-        if selector == "*" {
-            return Ok(CssSelector::Any);
-        }
-        let mut rules: Vec<CssSelectorRule> = vec![];
-        for s in selector.split(",").map(|x| x.trim()) {
-            //parse rule and add to rules;
-            rules.push(parse_css_selector_rule(s)?);
-        }
-        if rules.len() > 0 {
-            return Ok(CssSelector::Specific(rules));
+    #[test]
+    fn css_macro_test() {
+        let tests = vec![
+            (css!("*"), "*"),
+            (css!(""), ""),
+            (css!("div"), "div"),
+            (css!("div#main.big.wide"), "div#main.big.wide"),
+            (
+                css!("  ul  >  li:nth-child(2n+1) "),
+                "  ul  >  li:nth-child(2n+1) ",
+            ),
+            (
+                css!("li:nth-last-child(odd), li:nth-of-type(3)"),
+                "li:nth-last-child(odd), li:nth-of-type(3)",
+            ),
+            (
+                css!("h1 + p ~ a[href^=http][target]"),
+                "h1 + p ~ a[href^=http][target]",
+            ),
+            (
+                css!("a[rel|=x][b$=c][d*=e][f~=g][h=i]"),
+                "a[rel|=x][b$=c][d*=e][f~=g][h=i]",
+            ),
+            (
+                css!("body div:not(.hidden, #x) p:first-of-type"),
+                "body div:not(.hidden, #x) p:first-of-type",
+            ),
+            (
+                css!("input:checked, option:default:enabled:read-only"),
+                "input:checked, option:default:enabled:read-only",
+            ),
+            (css!("a..b"), "a..b"),
+            (
+                css!(r#"a:contains("Next, page"):not(.x)"#),
+                r#"a:contains("Next, page"):not(.x)"#,
+            ),
+            (
+                css!(r#"p:icontains("say \"hi\"")"#),
+                r#"p:icontains("say \"hi\"")"#,
+            ),
+            (
+                css!(r#"td:text-equals(Price) + td:matches(/^\d+(\.\d+)? \/ kg$/i)"#),
+                r#"td:text-equals(Price) + td:matches(/^\d+(\.\d+)? \/ kg$/i)"#,
+            ),
+            (
+                css!(":root > :only-child:empty"),
+                ":root > :only-child:empty",
+            ),
+        ];
+        for (selector, text) in tests {
+            assert_eq!(selector, text.parse::<CssSelector>().unwrap(), "{}", text);
         }
-        Err(ParseHtmlError::with_msg(format!(
-            "No valid CSS selector found in {}",
-            selector
-        )))
     }
-}
 
-impl TryFrom<&str> for CssSelector {
-    type Error = ParseHtmlError;
-    fn try_from(selector: &str) -> Result<Self, Self::Error> {
-        if selector == "*" {
-            return Ok(CssSelector::Any);
-        }
-        let mut rules: Vec<CssSelectorRule> = vec![];
-        for s in selector.split(",").map(|x| x.trim()) {
-            //parse rule and add to rules;
-            rules.push(parse_css_selector_rule(s)?);
-        }
-        if rules.len() > 0 {
-            return Ok(CssSelector::Specific(rules));
+    #[test]
+    fn css_macro_namespace_test() {
+        let options = CssSelectorParseOptions::default()
+            .namespace("", "urn:d")
+            .namespace("s", "http://www.w3.org/2000/svg");
+        let tests = vec![
+            (css!("{http://www.w3.org/2000/svg}rect"), "s|rect"),
+            (css!("*|rect > |g"), "*|rect > |g"),
+            (css!("{urn:d}a {}b"), "a |b"),
+        ];
+        for (selector, text) in tests {
+            let parsed = CssSelector::parse_with_options(text, &options).unwrap();
+            assert_eq!(selector, parsed, "{}", text);
         }
-        Err(ParseHtmlError::with_msg(format!(
-            "No valid CSS selector found in {}",
-            selector
-        )))
+        assert!(CssSelector::from_str("s|rect").is_err());
     }
 }
@@ -1,219 +1,144 @@
+use crate::borrowed::{BorrowedNode, BorrowedTag};
 use crate::error::ParseHtmlError;
-use crate::objects::{
-    CssAttributeCompareType, CssRefiner, CssRefinerNumberType, CssSelector, CssSelectorItem,
-    CssSelectorRelationship, CssSelectorRule, HtmlNode, HtmlTag,
-};
-use std::collections::VecDeque;
-use std::str::FromStr;
+use crate::objects::HtmlTag;
+use hb_selector::elements::VOID_ELEMENTS;
+use std::borrow::Cow;
+
+/// Gets the part of `start` which has been read since `chs` was at `start`.
+fn read_since<'a>(start: &'a str, chs: &std::str::Chars<'a>) -> &'a str {
+    &start[..start.len() - chs.as_str().len()]
+}
+
+/// Gets the part of `start` which has been read, leaving out the ending char which was
+/// read last unless it should be included.
+fn read_until_ending<'a>(
+    start: &'a str,
+    chs: &std::str::Chars<'a>,
+    ending: char,
+    include_ending: bool,
+) -> &'a str {
+    let read = read_since(start, chs);
+    match include_ending {
+        true => read,
+        false => &read[..read.len() - ending.len_utf8()],
+    }
+}
+
+/// Gets the input from the char which was just read by `chs`, given the input from
+/// before it was read.
+fn from_last_char<'a>(before: &'a str, chs: &std::str::Chars<'a>, ch: char) -> &'a str {
+    &before[before.len() - chs.as_str().len() - ch.len_utf8()..]
+}
 
 // Read from the iterator until a quoted string or word is found (ignoring leading whitespace) then return the string and the character that ended the string
 // Endings of a single word can be whitespace or >
-pub fn parse_string(chs: &mut std::str::Chars) -> Result<(String, char), ParseHtmlError> {
-    let mut buffer = String::with_capacity(50);
-    let mut is_quoted = false;
+pub fn parse_string<'a>(chs: &mut std::str::Chars<'a>) -> Result<(&'a str, char), ParseHtmlError> {
     //consume leading whitespace
+    let before = chs.as_str();
     let ch = get_next_non_whitespace(chs).map_err(|e| e.add_context("could not get string"))?;
     //check if first char is a "
-    if ch == '"' {
-        is_quoted = true;
-    } else {
-        buffer.push(ch);
-    }
+    let is_quoted = ch == '"';
+    let start = match is_quoted {
+        true => chs.as_str(),
+        false => from_last_char(before, chs, ch),
+    };
     while let Some(ch) = chs.next() {
-        if is_quoted {
-            if ch == '"' {
-                return Ok((buffer, ch));
-            }
-        } else {
-            if ch.is_ascii_whitespace() || ch == '>' {
-                return Ok((buffer, ch));
-            }
+        let is_ending = match is_quoted {
+            true => ch == '"',
+            false => ch.is_ascii_whitespace() || ch == '>',
+        };
+        if is_ending {
+            return Ok((read_until_ending(start, chs, ch, false), ch));
         }
-        buffer.push(ch);
     }
     if is_quoted {
         return Err(ParseHtmlError::new(format!(
             "Closing '\"' for string '{}' not found",
-            buffer
+            start
         )));
     }
-    if buffer.len() == 0 {
-        return Err(ParseHtmlError::with_msg("No chars found."));
-    }
-    Ok((buffer, ' '))
+    Ok((start, ' '))
 }
 
-pub fn parse_until_one_of(
-    chs: &mut std::str::Chars,
+pub fn parse_until_one_of<'a>(
+    chs: &mut std::str::Chars<'a>,
     end_chars: Vec<char>,
     include_ending: bool,
-) -> Result<String, ParseHtmlError> {
-    let mut buffer = String::with_capacity(50);
+) -> Result<&'a str, ParseHtmlError> {
+    let start = chs.as_str();
     while let Some(ch) = chs.next() {
-        for &end_char in &end_chars {
-            if ch == end_char {
-                if include_ending {
-                    buffer.push(ch);
-                }
-                return Ok(buffer);
-            }
+        if end_chars.contains(&ch) {
+            return Ok(read_until_ending(start, chs, ch, include_ending));
         }
-        buffer.push(ch);
     }
-    return Err(ParseHtmlError::new(format!(
+    Err(ParseHtmlError::new(format!(
         "end of string '{}' encountered before any end char '{:?}' was found",
-        buffer, end_chars
-    )));
-}
-
-pub fn parse_until_end_or_one_of_peekable(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-    end_chars: Vec<char>,
-) -> Option<String> {
-    let mut buffer = String::new();
-    while let Some(ch) = chs.peek() {
-        for end_char in &end_chars {
-            if ch == end_char {
-                return Some(buffer);
-            }
-        }
-        buffer.push(chs.next().unwrap());
-    }
-    if buffer.len() > 0 {
-        return Some(buffer);
-    }
-    None
+        start, end_chars
+    )))
 }
 
-pub fn parse_until_one_of_peekable(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-    end_chars: Vec<char>,
-) -> Result<String, ParseHtmlError> {
-    let mut buffer = String::new();
-    while let Some(ch) = chs.peek() {
-        for end_char in &end_chars {
-            if ch == end_char {
-                return Ok(buffer);
-            }
-        }
-        buffer.push(chs.next().unwrap());
-    }
-    return Err(ParseHtmlError::new(format!(
-        "end of string '{}' encountered before any end char '{:?}' was found",
-        buffer, end_chars
-    )));
-}
-
-pub fn parse_until_char(
-    chs: &mut std::str::Chars,
+pub fn parse_until_char<'a>(
+    chs: &mut std::str::Chars<'a>,
     end_char: char,
     include_ending: bool,
-) -> Result<String, ParseHtmlError> {
-    let mut buffer = String::with_capacity(50);
+) -> Result<&'a str, ParseHtmlError> {
+    let start = chs.as_str();
     while let Some(ch) = chs.next() {
         if ch == end_char {
-            if include_ending {
-                buffer.push(ch);
-            }
-            return Ok(buffer);
+            return Ok(read_until_ending(start, chs, ch, include_ending));
         }
-        buffer.push(ch);
     }
-    return Err(ParseHtmlError::new(format!(
+    Err(ParseHtmlError::new(format!(
         "end of string '{}' encountered before end char '{}' was found",
-        buffer, end_char
-    )));
+        start, end_char
+    )))
 }
 
-pub fn parse_until_str(
-    chs: &mut std::str::Chars,
+pub fn parse_until_str<'a>(
+    chs: &mut std::str::Chars<'a>,
     end_str: &str,
     include_ending: bool,
-) -> Result<String, ParseHtmlError> {
-    let mut ending_buffer = VecDeque::with_capacity(end_str.len());
-    let mut buffer = String::with_capacity(50);
+) -> Result<&'a str, ParseHtmlError> {
+    let start = chs.as_str();
     let end_chars = end_str.chars().collect::<Vec<char>>();
+    // the number of chars of the end string which have just been read
+    let mut matched = 0;
     while let Some(ch) = chs.next() {
-        if ch == end_chars[ending_buffer.len()] {
-            ending_buffer.push_front(ch);
-            if ending_buffer.len() == end_str.len() {
-                if include_ending {
-                    while ending_buffer.len() > 0 {
-                        buffer.push(ending_buffer.pop_back().unwrap());
-                    }
-                }
-                return Ok(buffer);
+        if ch == end_chars[matched] {
+            matched += 1;
+            if matched == end_chars.len() {
+                let read = read_since(start, chs);
+                return Ok(match include_ending {
+                    true => read,
+                    false => &read[..read.len() - end_str.len()],
+                });
             }
         } else {
-            //flush ending_buffer into the buffer as we no longer
-            while ending_buffer.len() > 0 {
-                buffer.push(ending_buffer.pop_back().unwrap());
-            }
-            buffer.push(ch);
+            //the chars which were matched are part of the string
+            matched = 0;
         }
     }
-    return Err(ParseHtmlError::new(format!(
-        "end of string '{}{}' encountered before any end string '{}' was found",
-        buffer,
-        ending_buffer.iter().collect::<String>(),
-        end_str
-    )));
+    Err(ParseHtmlError::new(format!(
+        "end of string '{}' encountered before any end string '{}' was found",
+        start, end_str
+    )))
 }
 
-pub fn parse_until<F: Fn(&char) -> bool>(
-    chs: &mut std::str::Chars,
+pub fn parse_until<'a, F: Fn(&char) -> bool>(
+    chs: &mut std::str::Chars<'a>,
     check_ending: F,
     include_ending: bool,
-) -> Result<String, ParseHtmlError> {
-    let mut buffer = String::with_capacity(50);
+) -> Result<&'a str, ParseHtmlError> {
+    let start = chs.as_str();
     while let Some(ch) = chs.next() {
         if check_ending(&ch) {
-            if include_ending {
-                buffer.push(ch);
-            }
-            return Ok(buffer);
+            return Ok(read_until_ending(start, chs, ch, include_ending));
         }
-        buffer.push(ch);
     }
-    return Err(ParseHtmlError::new(format!(
+    Err(ParseHtmlError::new(format!(
         "end of string encountered without terminating character in string '{}'",
-        buffer
-    )));
-}
-
-pub fn parse_until_and_including_char(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-    ending: char,
-) -> Result<String, ParseHtmlError> {
-    let mut buffer = String::new();
-    while let Some(ch) = chs.peek() {
-        if ch == &ending {
-            buffer.push(chs.next().unwrap());
-            return Ok(buffer);
-        }
-        buffer.push(chs.next().unwrap());
-    }
-    return Err(ParseHtmlError::new(format!(
-        "end of string '{}' encountered before ending '{}' was found",
-        buffer, ending
-    )));
-}
-
-pub fn parse_until_char_peekable(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-    ending: char,
-) -> Result<String, ParseHtmlError> {
-    let mut buffer = String::new();
-    while let Some(ch) = chs.peek() {
-        if ch == &ending {
-            return Ok(buffer);
-        }
-        buffer.push(chs.next().unwrap());
-    }
-    return Err(ParseHtmlError::new(format!(
-        "end of string '{}' encountered before ending '{}' was found",
-        buffer, ending
-    )));
+        start
+    )))
 }
 
 pub fn parse_contents_of_braces(
@@ -251,12 +176,8 @@ pub fn parse_contents_of_braces(
     )));
 }
 
-pub fn parse_attibute_value(attr_value: String) -> Vec<String> {
-    let mut v = vec![];
-    for val in attr_value.split_ascii_whitespace() {
-        v.push(val.to_string());
-    }
-    return v;
+pub fn parse_attibute_value(attr_value: &str) -> Vec<Cow<'_, str>> {
+    attr_value.split_ascii_whitespace().map(Cow::from).collect()
 }
 
 pub fn get_next_non_whitespace(chs: &mut std::str::Chars) -> Result<char, ParseHtmlError> {
@@ -320,21 +241,15 @@ mod parse_iter_tests {
             "Something "
         );
 
-        assert_eq!(
-            parse_string(&mut " test ".chars()).unwrap(),
-            ("test".to_string(), ' ')
-        );
-        assert_eq!(
-            parse_string(&mut " test>".chars()).unwrap(),
-            ("test".to_string(), '>')
-        );
+        assert_eq!(parse_string(&mut " test ".chars()).unwrap(), ("test", ' '));
+        assert_eq!(parse_string(&mut " test>".chars()).unwrap(), ("test", '>'));
         assert_eq!(
             parse_string(&mut " \"test \"".chars()).unwrap(),
-            ("test ".to_string(), '"')
+            ("test ", '"')
         );
         assert_eq!(
             parse_string(&mut "\"test \"".chars()).unwrap(),
-            ("test ".to_string(), '"')
+            ("test ", '"')
         );
     }
 }
@@ -347,91 +262,109 @@ pub enum ParsedTagType {
     DocType(String),
 }
 
+/// A tag read by [`parse_borrowed_html_tag`], whose strings are slices of the input.
+#[derive(Debug, PartialEq)]
+pub enum ParsedBorrowedTagType<'a> {
+    EndTag(&'a str),
+    NewTag(BorrowedTag<'a>),
+    Comment(&'a str),
+    DocType(&'a str),
+}
+
+impl<'a> ParsedBorrowedTagType<'a> {
+    pub fn into_owned(self) -> ParsedTagType {
+        match self {
+            ParsedBorrowedTagType::EndTag(t) => ParsedTagType::EndTag(t.to_owned()),
+            ParsedBorrowedTagType::NewTag(t) => ParsedTagType::NewTag(t.into_owned()),
+            ParsedBorrowedTagType::Comment(c) => ParsedTagType::Comment(c.to_owned()),
+            ParsedBorrowedTagType::DocType(t) => ParsedTagType::DocType(t.to_owned()),
+        }
+    }
+}
+
 pub fn parse_html_tag(chs: &mut std::str::Chars) -> Result<ParsedTagType, ParseHtmlError> {
-    // read chars into the buffer until a > or ' ' is found
-    let mut buffer = String::with_capacity(50);
+    parse_borrowed_html_tag(chs).map(ParsedBorrowedTagType::into_owned)
+}
+
+/// Parses the tag after a '<', including its contents, without copying any of its strings.
+/// This is the grammar of both [`HtmlDocument`](crate::objects::HtmlDocument) and
+/// [`BorrowedDocument`](crate::borrowed::BorrowedDocument).
+pub fn parse_borrowed_html_tag<'a>(
+    chs: &mut std::str::Chars<'a>,
+) -> Result<ParsedBorrowedTagType<'a>, ParseHtmlError> {
+    let start = chs.as_str();
     //read first character and determine if this is an end tag
-    match chs.next() {
-        Some(ch) => {
-            if ch == '/' {
-                return Ok(ParsedTagType::EndTag(
-                    parse_until_char(chs, '>', false)
-                        .map_err(|e| e.add_context(format!("Could not parse end tag")))?
-                        .trim_end()
-                        .to_owned(),
-                ));
-            } else {
-                buffer.push(ch);
-            }
+    let first = match chs.next() {
+        Some('/') => {
+            return Ok(ParsedBorrowedTagType::EndTag(
+                parse_until_char(chs, '>', false)
+                    .map_err(|e| e.add_context("Could not parse end tag"))?
+                    .trim_end(),
+            ));
         }
+        Some(ch) => ch,
         None => {
-            return Err(ParseHtmlError::new(format!(
-                "End of file without reading any chars in this tag."
-            )));
+            return Err(ParseHtmlError::with_msg(
+                "End of file without reading any chars in this tag.",
+            ));
         }
-    }
+    };
     //Parse until we get the end of tag or a space
-    buffer.push_str(
-        parse_until_one_of(chs, vec![' ', '>'], true)
-            .map_err(|e| e.add_context(format!("Could parse starting tag after {}", buffer)))?
-            .as_str(),
-    );
-    if buffer.starts_with("!--") {
+    parse_until_one_of(chs, vec![' ', '>'], true)
+        .map_err(|e| e.add_context(format!("Could parse starting tag after {}", first)))?;
+    let buffer = read_since(start, chs);
+    if let Some(comment) = buffer.strip_prefix("!--") {
         // comment - parse the rest of the comment
-        buffer.drain(0..3);
-        if buffer.ends_with("-->") {
-            buffer.truncate(buffer.len() - 4);
-            return Ok(ParsedTagType::Comment(buffer));
-        }
-        buffer.push_str(
-            parse_until_str(chs, &"-->", false)
-                .map_err(|e| e.add_context("Could not parse comment tag"))?
-                .as_str(),
-        );
-        return Ok(ParsedTagType::Comment(buffer));
+        if let Some(comment) = comment.strip_suffix("-->") {
+            return Ok(ParsedBorrowedTagType::Comment(comment));
+        }
+        parse_until_str(chs, "-->", false)
+            .map_err(|e| e.add_context("Could not parse comment tag"))?;
+        let comment = read_since(start, chs);
+        return Ok(ParsedBorrowedTagType::Comment(
+            &comment["!--".len()..comment.len() - "-->".len()],
+        ));
     } else if buffer.starts_with("!DOCTYPE ") {
-        buffer.clear();
-        return Ok(ParsedTagType::DocType(
+        return Ok(ParsedBorrowedTagType::DocType(
             parse_until_char(chs, '>', false)
                 .map_err(|e| e.add_context("Could not parse DOCTYPE"))?,
         ));
     }
     let (tag_str, ending) = buffer.split_at(buffer.len() - 1);
-    let tag = tag_str.trim().to_owned();
-    let mut node = HtmlTag::new(tag_str);
+    let tag = tag_str.trim();
+    let mut node = BorrowedTag {
+        tag: tag_str.into(),
+        ..BorrowedTag::default()
+    };
     let mut is_a_closed_tag = false;
     if ending != ">" {
-        //define the some checking closures
-        let is_ws_eq_or_gt = |ch: &char| -> bool {
-            return ch.is_ascii_whitespace() || *ch == '=' || *ch == '>';
-        };
+        let is_ws_eq_or_gt =
+            |ch: &char| -> bool { ch.is_ascii_whitespace() || *ch == '=' || *ch == '>' };
+        // the start of the next attribute, when its first char was read after the last one
         let mut residual = None;
         loop {
-            buffer.clear();
-            match residual {
+            let attr_start = match residual.take() {
                 None => {
-                    buffer.push(get_next_non_whitespace(chs).map_err(|e| {
+                    let before = chs.as_str();
+                    let ch = get_next_non_whitespace(chs).map_err(|e| {
                         e.add_context(format!(
                             "Could not get next attribute or '>' for node {}",
-                            node
+                            node.clone().into_owned()
                         ))
-                    })?);
+                    })?;
+                    from_last_char(before, chs, ch)
                 }
-                Some(c) => {
-                    buffer.push(c);
-                }
-            }
-            if buffer == ">" {
+                Some(attr_start) => attr_start,
+            };
+            let first = read_since(attr_start, chs);
+            if first == ">" {
                 //didn't get an attribute - just got the end of tag symbol
                 break;
             }
-            buffer.push_str(
-                parse_until(chs, is_ws_eq_or_gt, true)
-                    .map_err(|e| {
-                        e.add_context(format!("Could not get find end of attribute '{}'", buffer))
-                    })?
-                    .as_str(),
-            );
+            parse_until(chs, is_ws_eq_or_gt, true).map_err(|e| {
+                e.add_context(format!("Could not get find end of attribute '{}'", first))
+            })?;
+            let buffer = read_since(attr_start, chs);
             if buffer == "/>" {
                 is_a_closed_tag = true;
                 break;
@@ -439,6 +372,7 @@ pub fn parse_html_tag(chs: &mut std::str::Chars) -> Result<ParsedTagType, ParseH
             let (attr_str, attr_ending_str) = buffer.split_at(buffer.len() - 1);
             let mut attr_ending = attr_ending_str.chars().next().unwrap();
             if attr_ending.is_ascii_whitespace() {
+                let before = chs.as_str();
                 let ch = get_next_non_whitespace(chs).map_err(|e| {
                     e.add_context(format!(
                         "could not get non-whitespace char after attribute '{}'",
@@ -446,84 +380,52 @@ pub fn parse_html_tag(chs: &mut std::str::Chars) -> Result<ParsedTagType, ParseH
                     ))
                 })?;
                 if ch != '>' && ch != '=' {
-                    residual = Some(ch);
+                    residual = Some(from_last_char(before, chs, ch));
                 } else {
                     attr_ending = ch;
                 }
             }
             if attr_ending == '>' {
-                if attr_str.len() > 0 {
-                    if attr_str == "class" {
-                        return Err(ParseHtmlError::new(format!(
-                            "Expected value for the class attribute attribute '{}', got '{}' instead of '='.",
-                            attr_str, attr_ending
-                        )));
-                    } else if attr_str == "id" {
-                        return Err(ParseHtmlError::new(format!(
-                            "Expected value for the id attribute attribute '{}', got '{}' instead of '='.",
-                            attr_str, attr_ending
-                        )));
-                    } else {
-                        node.attributes.insert(attr_str.to_string(), String::new());
-                    }
+                if attr_str == "class" || attr_str == "id" {
+                    return Err(ParseHtmlError::new(format!(
+                        "Expected value for the {} attribute attribute '{}', got '{}' instead of '='.",
+                        attr_str, attr_str, attr_ending
+                    )));
+                } else if !attr_str.is_empty() {
+                    node.attributes.insert(attr_str.into(), Cow::Borrowed(""));
                 }
                 //didn't get an attribute - just got the end of tag symbol
                 break;
             } else if attr_ending == '=' {
                 //We have 'attr =' now need to read in the value
-                let (attr_value_string, attr_value_ending) = parse_string(chs).map_err(|e| {
+                let (attr_value, attr_value_ending) = parse_string(chs).map_err(|e| {
                     e.add_context(format!("could not get value of attribute '{}'", attr_str))
                 })?;
-                if attr_str == "class" {
-                    node.classes = parse_attibute_value(attr_value_string);
-                } else if attr_str == "id" {
-                    node.ids = parse_attibute_value(attr_value_string);
-                } else {
-                    node.attributes
-                        .insert(attr_str.to_string(), attr_value_string);
+                match attr_str {
+                    "class" => node.classes = parse_attibute_value(attr_value),
+                    "id" => node.ids = parse_attibute_value(attr_value),
+                    _ => {
+                        node.attributes.insert(attr_str.into(), attr_value.into());
+                    }
                 }
                 if attr_value_ending == '>' {
                     break;
                 }
-            } else {
-                if attr_str == "class" {
-                    //do nothing
-                } else if attr_str == "id" {
-                    //do nothing
-                } else {
-                    node.attributes.insert(attr_str.to_string(), String::new());
-                }
+            } else if attr_str != "class" && attr_str != "id" {
+                node.attributes.insert(attr_str.into(), Cow::Borrowed(""));
             }
         }
     }
     //Return the node without content if it is a singleton tag
-    match tag.as_str() {
-        "area" => return Ok(ParsedTagType::NewTag(node)),
-        "base" => return Ok(ParsedTagType::NewTag(node)),
-        "br" => return Ok(ParsedTagType::NewTag(node)),
-        "col" => return Ok(ParsedTagType::NewTag(node)),
-        "command" => return Ok(ParsedTagType::NewTag(node)),
-        "embed" => return Ok(ParsedTagType::NewTag(node)),
-        "hr" => return Ok(ParsedTagType::NewTag(node)),
-        "img" => return Ok(ParsedTagType::NewTag(node)),
-        "input" => return Ok(ParsedTagType::NewTag(node)),
-        "keygen" => return Ok(ParsedTagType::NewTag(node)),
-        "link" => return Ok(ParsedTagType::NewTag(node)),
-        "meta" => return Ok(ParsedTagType::NewTag(node)),
-        "param" => return Ok(ParsedTagType::NewTag(node)),
-        "source" => return Ok(ParsedTagType::NewTag(node)),
-        "track" => return Ok(ParsedTagType::NewTag(node)),
-        "wbr" => return Ok(ParsedTagType::NewTag(node)),
-        _ => (),
+    if !is_a_closed_tag && !VOID_ELEMENTS.contains(&tag) {
+        node.contents = parse_borrowed_html_content(chs, tag)?;
     }
-    if !is_a_closed_tag {
-        node.contents = parse_html_content(chs, tag)?;
-    }
-    Ok(ParsedTagType::NewTag(node))
+    Ok(ParsedBorrowedTagType::NewTag(node))
 }
 #[cfg(test)]
 mod parse_html_tag_tests {
     use super::*;
+    use crate::objects::HtmlNode;
 
     #[test]
     fn parse_html_start_tag_test() {
@@ -546,6 +448,15 @@ mod parse_html_tag_tests {
             .unwrap(),
             ParsedTagType::NewTag(tag)
         );
+        let mut tag = HtmlTag::new("p");
+        for (attribute, value) in [("a", ""), ("b", "1"), ("c", "2")] {
+            tag.attributes
+                .insert(attribute.to_string(), value.to_string());
+        }
+        assert_eq!(
+            parse_html_tag(&mut "p a b=1 c=2></p>".chars()).unwrap(),
+            ParsedTagType::NewTag(tag)
+        );
     }
 
     #[test]
@@ -570,6 +481,14 @@ mod parse_html_tag_tests {
             parse_html_tag(&mut "!-- something\n something else -->".chars()).unwrap(),
             ParsedTagType::Comment(" something\n something else ".to_string())
         );
+        assert_eq!(
+            parse_html_tag(&mut "!--something-->".chars()).unwrap(),
+            ParsedTagType::Comment("something".to_string())
+        );
+        assert_eq!(
+            parse_html_tag(&mut "!---->".chars()).unwrap(),
+            ParsedTagType::Comment(String::new())
+        );
     }
 
     #[test]
@@ -609,62 +528,57 @@ mod parse_html_tag_tests {
     }
 }
 
-pub fn parse_html_content(
-    chs: &mut std::str::Chars,
-    tag: String,
-) -> Result<Vec<HtmlNode>, ParseHtmlError> {
-    let mut text_content = String::new();
-    let mut content: Vec<HtmlNode> = Vec::new();
+/// Parses the contents of a tag until its end tag, without copying any of their strings.
+pub fn parse_borrowed_html_content<'a>(
+    chs: &mut std::str::Chars<'a>,
+    tag: &str,
+) -> Result<Vec<BorrowedNode<'a>>, ParseHtmlError> {
+    let mut content = Vec::new();
+    let mut text_start = chs.as_str();
     while let Some(cur_char) = chs.next() {
-        if cur_char == '<' {
-            if text_content.len() > 0 {
-                content.push(HtmlNode::Text(text_content));
-                text_content = String::new();
-            }
-            //Read rest of tag - passing along any errors that were encountered.
-            match parse_html_tag(chs)? {
-                ParsedTagType::EndTag(end_tag) => {
-                    if end_tag != tag {
-                        return Err(ParseHtmlError::new(format!(
-                            "Incorrect end tag found {} but expected {}.",
-                            end_tag, tag
-                        )));
-                    }
-                    //Got the correct end tag
-                    if content.len() > 0 {
-                        return Ok(content);
-                    } else {
-                        return Ok(vec![]);
-                    }
-                }
-                ParsedTagType::NewTag(node_rc) => {
-                    content.push(HtmlNode::Tag(node_rc));
-                }
-                ParsedTagType::Comment(comment) => {
-                    content.push(HtmlNode::Comment(comment));
-                }
-                ParsedTagType::DocType(t) => {
+        if cur_char != '<' {
+            continue;
+        }
+        let text_content = read_until_ending(text_start, chs, cur_char, false);
+        if !text_content.is_empty() {
+            content.push(BorrowedNode::Text(text_content.into()));
+        }
+        //Read rest of tag - passing along any errors that were encountered.
+        match parse_borrowed_html_tag(chs)? {
+            ParsedBorrowedTagType::EndTag(end_tag) => {
+                if end_tag != tag {
                     return Err(ParseHtmlError::new(format!(
-                        "'DOCTYPE {}' element found in middle of content",
-                        t
-                    )))
+                        "Incorrect end tag found {} but expected {}.",
+                        end_tag, tag
+                    )));
                 }
+                //Got the correct end tag
+                return Ok(content);
+            }
+            ParsedBorrowedTagType::NewTag(node) => content.push(BorrowedNode::Tag(node)),
+            ParsedBorrowedTagType::Comment(comment) => {
+                content.push(BorrowedNode::Comment(comment.into()))
+            }
+            ParsedBorrowedTagType::DocType(t) => {
+                return Err(ParseHtmlError::new(format!(
+                    "'DOCTYPE {}' element found in middle of content",
+                    t
+                )))
             }
-        } else {
-            text_content.push(cur_char);
         }
+        text_start = chs.as_str();
     }
     //Parse HTML until end tag </tag> is found
-    return Err(ParseHtmlError::new(format!(
+    Err(ParseHtmlError::new(format!(
         "End of file without finding tag {}.",
         tag
-    )));
+    )))
 }
 
 #[cfg(test)]
 mod parse_html_document_tests {
     use super::*;
-    use crate::objects::HtmlDocument;
+    use crate::objects::{HtmlDocument, HtmlNode};
 
     #[test]
     fn parse_test_document() {
@@ -788,876 +702,6 @@ mod parse_html_document_tests {
     }
 }
 
-#[derive(PartialEq, Debug)]
-enum CssSelectorRelationshipType {
-    Current,
-    Parent,
-    Ancestor,
-    PreviousSibling,
-    PreviousSiblingOnce,
-}
-
-pub fn parse_css_selector_rule(selector_rule: &str) -> Result<CssSelectorRule, ParseHtmlError> {
-    let mut css_rule = CssSelectorRule::new();
-    let mut current_relationship = CssSelectorRelationshipType::Current;
-    let mut selector_chs = selector_rule.chars().peekable();
-    loop {
-        match selector_chs.peek() {
-            Some(c) => match c {
-                ',' => {
-                    return Err(ParseHtmlError::with_msg(format!(
-                        "unexpected ',' in css selector rule {}",
-                        selector_rule
-                    )))
-                }
-                _ => (),
-            },
-            None => break,
-        }
-        let item = match parse_css_selector_item(&mut selector_chs)? {
-            //check following characters to work out what the relationship should be
-            Some(item) => match parse_css_selector_relationship(&mut selector_chs)? {
-                CssSelectorRelationshipType::Current => {
-                    css_rule.rules.push(CssSelectorRelationship::Current(item))
-                }
-                CssSelectorRelationshipType::Ancestor => {
-                    css_rule.rules.push(CssSelectorRelationship::Ancestor(item))
-                }
-                CssSelectorRelationshipType::Parent => {
-                    css_rule.rules.push(CssSelectorRelationship::Parent(item))
-                }
-                CssSelectorRelationshipType::PreviousSibling => css_rule
-                    .rules
-                    .push(CssSelectorRelationship::PreviousSibling(item)),
-                CssSelectorRelationshipType::PreviousSiblingOnce => css_rule
-                    .rules
-                    .push(CssSelectorRelationship::PreviousSiblingOnce(item)),
-            },
-
-            None => break,
-        };
-    }
-    Ok(css_rule)
-}
-
-fn parse_css_selector_relationship(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<CssSelectorRelationshipType, ParseHtmlError> {
-    let mut rel = CssSelectorRelationshipType::Current;
-    while let Some(c) = chs.peek() {
-        match *c {
-            ' ' => {
-                if rel == CssSelectorRelationshipType::Current {
-                    rel = CssSelectorRelationshipType::Ancestor;
-                }
-                chs.next();
-            }
-            '>' => {
-                //if we have already read a > or a ~
-                if rel != CssSelectorRelationshipType::Ancestor
-                    && rel != CssSelectorRelationshipType::Current
-                {
-                    return Err(ParseHtmlError::with_msg(format!(
-                        "found multiple relationship seperators in selector first {:?} and now {:?}",
-                        rel,
-                        CssSelectorRelationshipType::Parent
-                    )));
-                }
-                chs.next();
-                rel = CssSelectorRelationshipType::Parent;
-            }
-            '~' => {
-                //if we have already read a > or a ~
-                if rel != CssSelectorRelationshipType::Ancestor
-                    && rel != CssSelectorRelationshipType::Current
-                {
-                    return Err(ParseHtmlError::with_msg(format!(
-                        "found multiple relationship seperators in selector first {:?} and now {:?}",
-                        rel,
-                        CssSelectorRelationshipType::PreviousSibling
-                    )));
-                }
-                chs.next();
-                rel = CssSelectorRelationshipType::PreviousSibling;
-            }
-            '+' => {
-                //if we have already read a > or a ~
-                if rel != CssSelectorRelationshipType::Ancestor
-                    && rel != CssSelectorRelationshipType::Current
-                {
-                    return Err(ParseHtmlError::with_msg(format!(
-                        "found multiple relationship seperators in selector first {:?} and now {:?}",
-                        rel,
-                        CssSelectorRelationshipType::PreviousSiblingOnce
-                    )));
-                }
-                chs.next();
-                rel = CssSelectorRelationshipType::PreviousSiblingOnce;
-            }
-            _ => break,
-        }
-    }
-    Ok(rel)
-}
-
-pub fn parse_css_selector_item(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<Option<CssSelectorItem>, ParseHtmlError> {
-    //consume whitespace
-    loop {
-        match chs.peek() {
-            None => {
-                return Err(ParseHtmlError::with_msg(
-                    "Could not parse a CSS selector item - only whitespace in string.",
-                ))
-            }
-            Some(ch) => {
-                if ch.is_ascii_whitespace() {
-                    chs.next();
-                } else {
-                    break;
-                }
-            }
-        }
-    }
-
-    // read until one of " " + > ~
-    let mut item_str = String::new();
-    let mut level = 0; //for handling ( and )
-    #[derive(PartialEq)]
-    enum LastLevel {
-        Round,
-        Square,
-        None,
-    }
-    let mut last_level = LastLevel::None;
-    loop {
-        match chs.peek() {
-            None => {
-                break;
-            }
-            Some(ch) => {
-                if *ch == '(' {
-                    level = level + 1;
-                    last_level = LastLevel::Round;
-                } else if *ch == ')' {
-                    if last_level != LastLevel::Round {
-                        return Err(ParseHtmlError::with_msg("Found ')' without a opening '('"));
-                    }
-                    level = level - 1;
-                } else if *ch == '[' {
-                    level = level + 1;
-                    last_level = LastLevel::Square;
-                } else if *ch == ']' {
-                    if last_level != LastLevel::Square {
-                        return Err(ParseHtmlError::with_msg("Found ']' without a opening '['"));
-                    }
-                    level = level - 1;
-                } else if (*ch == ' ' || *ch == '+' || *ch == '>' || *ch == '~') && (level == 0) {
-                    break;
-                }
-                item_str.push(chs.next().unwrap());
-            }
-        }
-    }
-
-    if item_str.len() == 0 {
-        return Ok(None);
-    }
-
-    //parse the selector item
-    let mut item_chars = item_str.chars().peekable();
-    // check prefix:
-    let mut item = CssSelectorItem::new();
-    loop {
-        match item_chars.peek() {
-            None => break,
-            Some(c) => match c {
-                '.' => {
-                    item_chars.next(); //consume the .
-                    match parse_until_end_or_one_of_peekable(
-                        &mut item_chars,
-                        vec!['.', '#', ':', '['],
-                    ) {
-                        Some(class) => match &mut item.classes {
-                            Some(classes) => classes.push(class),
-                            None => item.classes = Some(vec![class]),
-                        },
-                        None => (),
-                    }
-                }
-                '#' => {
-                    item_chars.next(); //consume the #
-                    match parse_until_end_or_one_of_peekable(
-                        &mut item_chars,
-                        vec!['.', '#', ':', '['],
-                    ) {
-                        Some(id) => match &mut item.ids {
-                            Some(ids) => ids.push(id),
-                            None => item.ids = Some(vec![id]),
-                        },
-                        None => (),
-                    }
-                }
-                ':' => {
-                    item_chars.next(); //consume the :
-                    let refiner = parse_css_refiner(&mut item_chars)?;
-                    match &mut item.refiners {
-                        Some(refiners) => refiners.push(refiner),
-                        None => item.refiners = Some(vec![refiner]),
-                    }
-                }
-                '[' => {
-                    item_chars.next(); //consume the [
-                    match &mut item.attributes {
-                        None => {
-                            let mut attributes = vec![];
-                            attributes.push(parse_css_attribute_rule(&mut item_chars)?);
-                            item.attributes = Some(attributes);
-                        }
-                        Some(attributes) => {
-                            attributes.push(parse_css_attribute_rule(&mut item_chars)?)
-                        }
-                    }
-                }
-                _ => {
-                    match parse_until_end_or_one_of_peekable(
-                        &mut item_chars,
-                        vec!['.', '#', ':', '['],
-                    ) {
-                        Some(tag) => item.tag = Some(tag),
-                        None => (),
-                    }
-                } //tag
-            },
-        }
-    }
-    Ok(Some(item))
-}
-
-/// Parses a peekable chars iterator for a CSS selector attribute rule.
-/// CSS selector attribute rules refers to the modifiers in a CSS selector that are
-/// contained in square brackets "[]", for example [attr=value].
-///
-/// # Example
-///
-/// ```ignore
-/// use hb_html::parsing::parse_css_attribute_rule;
-/// use hb_html::objects::CssAttributeCompareType;
-/// let css_attr_rule = parse_css_attribute_rule("[attr=val]".chars().peekable()).expect();
-/// assert_eq!(css_attr_rule, CssAttributeCompareType::Equals(("attr".to_owned(), "val".to_owned())));
-/// ```
-fn parse_css_attribute_rule(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<CssAttributeCompareType, ParseHtmlError> {
-    let attr = parse_until_one_of_peekable(chs, vec![']', '=', '|', '^', '$', '*', '~'])
-        .map_err(|e| e.add_context("could not parse css selector attribute rule"))?;
-    let mut sep = String::new();
-    match chs.peek() {
-        None => {
-            return Err(ParseHtmlError::with_msg(
-                "No attribute rule found in between [].",
-            ));
-        }
-        Some(c) => match c {
-            ']' => {
-                chs.next(); //consume ]
-                return Ok(CssAttributeCompareType::Present(attr));
-            }
-            _ => sep.push(chs.next().unwrap()),
-        },
-    }
-    if sep != "=" {
-        match chs.peek() {
-            None => {
-                return Err(ParseHtmlError::with_msg(format!(
-                    "Attribute rule not finished {}{}.",
-                    attr, sep
-                )));
-            }
-            Some(c) => {
-                if *c != '=' {
-                    return Err(ParseHtmlError::with_msg(format!(
-                        "unknown attribute rule qualifier {}{}.",
-                        sep, c
-                    )));
-                }
-                sep.push(chs.next().unwrap());
-            }
-        }
-    }
-    let value = parse_until_char_peekable(chs, ']')?;
-    chs.next(); // consume ]
-    match sep.as_str() {
-        "=" => Ok(CssAttributeCompareType::Equals((attr, value))),
-        "|=" => Ok(CssAttributeCompareType::EqualsOrBeingsWith((attr, value))),
-        "^=" => Ok(CssAttributeCompareType::BeginsWith((attr, value))),
-        "$=" => Ok(CssAttributeCompareType::EndsWith((attr, value))),
-        "*=" => Ok(CssAttributeCompareType::Contains((attr, value))),
-        "~=" => Ok(CssAttributeCompareType::ContainsWord((attr, value))),
-        _ => {
-            return Err(ParseHtmlError::with_msg(format!(
-                "unknown attribute rule qualifier {}.",
-                sep
-            )));
-        }
-    }
-}
-
-/// Parses a peekable chars iterator for a CSS selector refiner.
-/// CSS selector refiners are refering to the modifiers in a CSS selector that follow a ":",
-/// this includes things such a ":first-of-type".
-///
-/// # Example
-///
-/// ```ignore
-/// use hb_html::parsing::parse_css_refiner;
-/// use hb_html::objects::CssRefiner;
-/// let css_ref = parse_css_refiner(":first-of-type".chars().peekable()).expect();
-/// assert_eq!(css_ref, CssRefiner::FirstOfType);
-/// ```
-fn parse_css_refiner(
-    chs: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<CssRefiner, ParseHtmlError> {
-    let refiner = match parse_until_end_or_one_of_peekable(chs, vec!['.', '#', ':', '[', '(']) {
-        None => return Err(ParseHtmlError::with_msg("No refiner found after :")),
-        Some(r) => r,
-    };
-    if refiner == "checked" {
-        return Ok(CssRefiner::Checked);
-    } else if refiner == "default" {
-        return Ok(CssRefiner::Default);
-    } else if refiner == "disabled" {
-        return Ok(CssRefiner::Disabled);
-    } else if refiner == "enabled" {
-        return Ok(CssRefiner::Enabled);
-    } else if refiner == "optional" {
-        return Ok(CssRefiner::Optional);
-    } else if refiner == "required" {
-        return Ok(CssRefiner::Required);
-    } else if refiner == "read-only" {
-        return Ok(CssRefiner::ReadOnly);
-    } else if refiner == "read-write" {
-        return Ok(CssRefiner::ReadWrite);
-    } else if refiner == "empty" {
-        return Ok(CssRefiner::Empty);
-    } else if refiner == "first-child" {
-        return Ok(CssRefiner::FirstChild);
-    } else if refiner == "last-child" {
-        return Ok(CssRefiner::LastChild);
-    } else if refiner.starts_with("nth-child") {
-        return Ok(CssRefiner::NthChild(parse_css_refiner_number(
-            &parse_until_and_including_char(chs, ')').map_err(|e| {
-                e.add_context(format!(
-                    "error while trying to read CSS refiner number after {}",
-                    refiner
-                ))
-            })?,
-        )?));
-    } else if refiner.starts_with("nth-last-child") {
-        return Ok(CssRefiner::NthLastChild(parse_css_refiner_number(
-            &parse_until_and_including_char(chs, ')').map_err(|e| {
-                e.add_context(format!(
-                    "error while trying to read CSS refiner number after {}",
-                    refiner
-                ))
-            })?,
-        )?));
-    } else if refiner == "only-child" {
-        return Ok(CssRefiner::OnlyChild);
-    } else if refiner == "first-of-type" {
-        return Ok(CssRefiner::FirstOfType);
-    } else if refiner == "last-of-type" {
-        return Ok(CssRefiner::LastOfType);
-    } else if refiner.starts_with("nth-of-type") {
-        return Ok(CssRefiner::NthOfType(parse_css_refiner_number(
-            &parse_until_and_including_char(chs, ')').map_err(|e| {
-                e.add_context(format!(
-                    "error while trying to read CSS refiner number after {}",
-                    refiner
-                ))
-            })?,
-        )?));
-    } else if refiner.starts_with("nth-last-of-type") {
-        return Ok(CssRefiner::NthLastOfType(
-            parse_css_refiner_number(&parse_until_and_including_char(chs, ')').map_err(|e| {
-                e.add_context(format!(
-                    "error while trying to read CSS refiner number after {}",
-                    refiner
-                ))
-            })?)
-            .map_err(|e| {
-                e.add_context(format!(
-                    "error while trying to read CSS refiner number after {}",
-                    refiner
-                ))
-            })?,
-        ));
-    } else if refiner == "only-of-type" {
-        return Ok(CssRefiner::OnlyOfType);
-    } else if refiner == "not" {
-        return Ok(CssRefiner::Not(
-            CssSelector::from_str(
-                parse_contents_of_braces(chs)
-                    .map_err(|e| e.add_context("could not find closing brace for :not( refiner"))?
-                    .as_str(),
-            )
-            .map_err(|e| {
-                e.add_context("could not parse css selector inside the :not(..) refiner")
-            })?,
-        ));
-    } else if refiner == "root" {
-        return Ok(CssRefiner::Root);
-    }
-    return Err(ParseHtmlError::with_msg(format!(
-        "unknown refiner type {}.",
-        refiner
-    )));
-}
-
-/// Parses a peekable chars iterator for a number or function used in a CSS selector refiner.
-/// CSS selector refiners are refering to the modifiers in a CSS selector that follow a ":".
-/// The number or function that this parses is used in specific refiners such as ":nth-of-type".
-///
-/// # Example
-///
-/// ```ignore
-/// use hb_html::parsing::parse_css_refiner_number;
-/// use hb_html::objects::CssRefinerNumberType;
-/// let css_ref_num = parse_css_refiner_number("(2n+1)".chars().peekable()).expect();
-/// assert_eq!(css_ref_num, CssRefinerNumberType::Functional((2,1)));
-/// ```
-fn parse_css_refiner_number(raw_str: &str) -> Result<CssRefinerNumberType, ParseHtmlError> {
-    let mut str_iter = raw_str.chars();
-    match str_iter.next() {
-        None => {
-            return Err(ParseHtmlError::with_msg(format!(
-                "No number found for refiner, expected a ( at the start of {}.",
-                raw_str
-            )))
-        }
-        Some(c) => {
-            if c != '(' {
-                return Err(ParseHtmlError::with_msg(format!(
-                    "Expected ( after refiner which needs a number/even/odd/function but got {}",
-                    c
-                )));
-            }
-        }
-    }
-    match str_iter.last() {
-        None => {
-            return Err(ParseHtmlError::with_msg(format!(
-                "No ) found after ( in refiner {}.",
-                raw_str
-            )))
-        }
-        Some(c) => {
-            if c != ')' {
-                return Err(ParseHtmlError::with_msg(format!(
-                    "Expected ) at end of refiner number/even/odd/function but got {}",
-                    c
-                )));
-            }
-        }
-    }
-
-    let num_str = &raw_str[1..raw_str.len() - 1];
-    let parts: Vec<&str> = num_str.split('+').map(|x| x.trim()).collect();
-    if parts.len() > 2 {
-        return Err(ParseHtmlError::with_msg(format!(
-            "too many +'s present in refiner number {}",
-            raw_str
-        )));
-    }
-
-    if parts.len() == 1 {
-        match parts[0] {
-            "odd" => return Ok(CssRefinerNumberType::Odd),
-            "even" => return Ok(CssRefinerNumberType::Even),
-            p => match p.parse::<usize>() {
-                Err(_) => {
-                    return Err(ParseHtmlError::with_msg(format!(
-                        "could not parse number in refiner {}",
-                        raw_str
-                    )))
-                }
-                Ok(i) => return Ok(CssRefinerNumberType::Specific(i)),
-            },
-        }
-    }
-
-    if parts[0].chars().last().unwrap() != 'n' {
-        return Err(ParseHtmlError::with_msg(format!(
-            "error parsing functional refiner, expected a 'n' at the end of {}",
-            parts[0]
-        )));
-    }
-
-    let multi = match parts[0][0..parts[0].len() - 1].parse::<i32>() {
-        Err(_) => {
-            return Err(ParseHtmlError::with_msg(format!(
-                "could not parse int before the n in {}",
-                parts[0]
-            )))
-        }
-        Ok(i) => i,
-    };
-    let b = match parts[1].parse::<i32>() {
-        Err(_) => {
-            return Err(ParseHtmlError::with_msg(format!(
-                "could not parse int in {}",
-                parts[1]
-            )))
-        }
-        Ok(i) => i,
-    };
-
-    Ok(CssRefinerNumberType::Functional((multi, b)))
-}
-
-#[cfg(test)]
-mod parse_css_selector_tests {
-    use super::*;
-
-    #[test]
-    fn parse_css_refiner_test() {
-        let tests = vec![
-            ("checked", CssRefiner::Checked),
-            ("default", CssRefiner::Default),
-            ("disabled", CssRefiner::Disabled),
-            ("enabled", CssRefiner::Enabled),
-            ("optional", CssRefiner::Optional),
-            ("required", CssRefiner::Required),
-            ("read-only", CssRefiner::ReadOnly),
-            ("read-write", CssRefiner::ReadWrite),
-            ("empty", CssRefiner::Empty),
-            ("first-child", CssRefiner::FirstChild),
-            ("last-child", CssRefiner::LastChild),
-            (
-                "nth-child(1)",
-                CssRefiner::NthChild(CssRefinerNumberType::Specific(1)),
-            ),
-            (
-                "nth-child(2)",
-                CssRefiner::NthChild(CssRefinerNumberType::Specific(2)),
-            ),
-            (
-                "nth-child(2n+1)",
-                CssRefiner::NthChild(CssRefinerNumberType::Functional((2, 1))),
-            ),
-            (
-                "nth-child(odd)",
-                CssRefiner::NthChild(CssRefinerNumberType::Odd),
-            ),
-            (
-                "nth-child(even)",
-                CssRefiner::NthChild(CssRefinerNumberType::Even),
-            ),
-            (
-                "nth-last-child(1)",
-                CssRefiner::NthLastChild(CssRefinerNumberType::Specific(1)),
-            ),
-            ("only-child", CssRefiner::OnlyChild),
-            ("first-of-type", CssRefiner::FirstOfType),
-            ("last-of-type", CssRefiner::LastOfType),
-            (
-                "nth-of-type(1)",
-                CssRefiner::NthOfType(CssRefinerNumberType::Specific(1)),
-            ),
-            (
-                "nth-last-of-type(1)",
-                CssRefiner::NthLastOfType(CssRefinerNumberType::Specific(1)),
-            ),
-            ("only-of-type", CssRefiner::OnlyOfType),
-            ("root", CssRefiner::Root),
-            ("empty:checked", CssRefiner::Empty),
-            ("empty[attr]", CssRefiner::Empty),
-            ("empty#id", CssRefiner::Empty),
-            (
-                "not(p#id)",
-                CssRefiner::Not(CssSelector::Specific(vec![CssSelectorRule {
-                    rules: vec![CssSelectorRelationship::Current(CssSelectorItem {
-                        tag: Some("p".to_owned()),
-                        classes: None,
-                        ids: Some(vec!["id".to_owned()]),
-                        refiners: None,
-                        attributes: None,
-                    })],
-                }])),
-            ),
-        ];
-
-        for t in tests {
-            assert_eq!(parse_css_refiner(&mut t.0.chars().peekable()).unwrap(), t.1);
-        }
-    }
-
-    #[test]
-    fn parse_css_refiner_errors_test() {
-        let tests = vec![(
-            "nth-last-of-type(1a)",
-            ParseHtmlError::with_msg(
-                "error while trying to read CSS refiner number after nth-last-of-type because could not parse number in refiner (1a)",
-            )),
-            ("nth-last-of-type(1",
-            ParseHtmlError::with_msg(
-                "error while trying to read CSS refiner number after nth-last-of-type because end of string '(1' encountered before ending ')' was found",
-            )),
-            ("something-not-a-refiner",
-             ParseHtmlError::with_msg("unknown refiner type something-not-a-refiner.")
-            ),
-        ];
-
-        for t in tests {
-            assert_eq!(
-                parse_css_refiner(&mut t.0.chars().peekable()).unwrap_err(),
-                t.1
-            );
-        }
-    }
-
-    #[test]
-    fn parse_css_selector_relationship_test() {
-        let tests = vec![
-            (" > ", Ok(CssSelectorRelationshipType::Parent)),
-            (">", Ok(CssSelectorRelationshipType::Parent)),
-            ("   ", Ok(CssSelectorRelationshipType::Ancestor)),
-            ("  ", Ok(CssSelectorRelationshipType::Ancestor)),
-            (" ", Ok(CssSelectorRelationshipType::Ancestor)),
-            (" ~ ", Ok(CssSelectorRelationshipType::PreviousSibling)),
-            ("~", Ok(CssSelectorRelationshipType::PreviousSibling)),
-            (" + ", Ok(CssSelectorRelationshipType::PreviousSiblingOnce)),
-            ("+", Ok(CssSelectorRelationshipType::PreviousSiblingOnce)),
-            (" > ~ ", Err(ParseHtmlError::with_msg("found multiple relationship seperators in selector first Parent and now PreviousSibling"))),
-            ("~>", Err(ParseHtmlError::with_msg("found multiple relationship seperators in selector first PreviousSibling and now Parent"))),
-        ];
-
-        for t in tests {
-            assert_eq!(
-                parse_css_selector_relationship(&mut t.0.chars().peekable()),
-                t.1
-            );
-        }
-    }
-
-    #[test]
-    fn parse_css_selector_item_test() {
-        let tests = vec![
-            (
-                "div",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: None,
-                    ids: None,
-                    refiners: None,
-                    attributes: None,
-                })),
-            ),
-            (
-                "div.c1",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: Some(vec!["c1".to_owned()]),
-                    ids: None,
-                    refiners: None,
-                    attributes: None,
-                })),
-            ),
-            (
-                "div.c1.c2",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: Some(vec!["c1".to_owned(), "c2".to_owned()]),
-                    ids: None,
-                    refiners: None,
-                    attributes: None,
-                })),
-            ),
-            (
-                "div#first",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: None,
-                    ids: Some(vec!["first".to_owned()]),
-                    refiners: None,
-                    attributes: None,
-                })),
-            ),
-            (
-                "div#first#second",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: None,
-                    ids: Some(vec!["first".to_owned(), "second".to_owned()]),
-                    refiners: None,
-                    attributes: None,
-                })),
-            ),
-            (
-                "div[attr]",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: None,
-                    ids: None,
-                    refiners: None,
-                    attributes: Some(vec![CssAttributeCompareType::Present("attr".to_owned())]),
-                })),
-            ),
-            (
-                "div:first-child[attr]",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: None,
-                    ids: None,
-                    refiners: Some(vec![CssRefiner::FirstChild]),
-                    attributes: Some(vec![CssAttributeCompareType::Present("attr".to_owned())]),
-                })),
-            ),
-            (
-                "div.c1:first-child[attr][attr2=1].c2#first:nth-of-type(2n+1)#second",
-                Ok(Some(CssSelectorItem {
-                    tag: Some("div".to_owned()),
-                    classes: Some(vec!["c1".to_owned(), "c2".to_owned()]),
-                    ids: Some(vec!["first".to_owned(), "second".to_owned()]),
-                    refiners: Some(vec![
-                        CssRefiner::FirstChild,
-                        CssRefiner::NthOfType(CssRefinerNumberType::Functional((2, 1))),
-                    ]),
-                    attributes: Some(vec![
-                        CssAttributeCompareType::Present("attr".to_owned()),
-                        CssAttributeCompareType::Equals(("attr2".to_owned(), "1".to_owned())),
-                    ]),
-                })),
-            ),
-        ];
-
-        for t in tests {
-            assert_eq!(parse_css_selector_item(&mut t.0.chars().peekable()), t.1);
-        }
-    }
-
-    #[test]
-    fn parse_css_selector_rule_test() {
-        let tests = vec![
-            (
-                "div",
-                CssSelectorRule {
-                    rules: vec![CssSelectorRelationship::Current(CssSelectorItem {
-                        tag: Some("div".to_owned()),
-                        classes: None,
-                        ids: None,
-                        refiners: None,
-                        attributes: None,
-                    })],
-                },
-            ),
-            (
-                "div p",
-                CssSelectorRule {
-                    rules: vec![
-                        CssSelectorRelationship::Ancestor(CssSelectorItem {
-                            tag: Some("div".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                        CssSelectorRelationship::Current(CssSelectorItem {
-                            tag: Some("p".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                    ],
-                },
-            ),
-            (
-                "div > p",
-                CssSelectorRule {
-                    rules: vec![
-                        CssSelectorRelationship::Parent(CssSelectorItem {
-                            tag: Some("div".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                        CssSelectorRelationship::Current(CssSelectorItem {
-                            tag: Some("p".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                    ],
-                },
-            ),
-            (
-                "div a > p",
-                CssSelectorRule {
-                    rules: vec![
-                        CssSelectorRelationship::Ancestor(CssSelectorItem {
-                            tag: Some("div".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                        CssSelectorRelationship::Parent(CssSelectorItem {
-                            tag: Some("a".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                        CssSelectorRelationship::Current(CssSelectorItem {
-                            tag: Some("p".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                    ],
-                },
-            ),
-            (
-                "div a ~ p",
-                CssSelectorRule {
-                    rules: vec![
-                        CssSelectorRelationship::Ancestor(CssSelectorItem {
-                            tag: Some("div".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                        CssSelectorRelationship::PreviousSibling(CssSelectorItem {
-                            tag: Some("a".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                        CssSelectorRelationship::Current(CssSelectorItem {
-                            tag: Some("p".to_owned()),
-                            classes: None,
-                            ids: None,
-                            refiners: None,
-                            attributes: None,
-                        }),
-                    ],
-                },
-            ),
-        ];
-
-        for t in tests {
-            assert_eq!(parse_css_selector_rule(t.0).unwrap(), t.1);
-        }
-    }
-}
-
 // *IMPROVEMENT IDEAS*
 
 // 1.
@@ -1,8 +1,9 @@
 use crate::error::{HtmlMatchError, ParseHtmlError};
 use crate::objects::{
     CssAttributeCompareType, CssRefiner, CssRefinerNumberType, CssSelector, CssSelectorItem,
-    CssSelectorRelationship, CssSelectorRule, HtmlDocument, HtmlNode, HtmlTag,
+    CssSelectorRelationship, CssSelectorRule, CssSpecificity, HtmlDocument, HtmlNode, HtmlTag,
 };
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::str::FromStr;
@@ -161,7 +162,7 @@ impl<'a> HtmlQueryResult<'a> {
         HtmlQueryResultIter::new(self)
     }
 
-    fn matches_item(&self, selector_item: &CssSelectorItem) -> bool {
+    pub(crate) fn matches_item(&self, selector_item: &CssSelectorItem) -> bool {
         // make sure it is a Html tag node
         let tag_node = match self.get_node() {
             None => {
@@ -177,13 +178,9 @@ impl<'a> HtmlQueryResult<'a> {
         };
         //Compare the tag selector
         match &selector_item.tag {
-            None => (),
-            Some(tag) => {
-                if *tag != tag_node.tag {
-                    //failed to match the tag, this selector rule failed
-                    return false;
-                }
-            }
+            //failed to match the tag, this selector rule failed
+            Some(tag) if !tag_node.matches_type(tag) => return false,
+            _ => (),
         }
 
         // check selector's classes
@@ -986,6 +983,34 @@ impl<'a> HtmlQueryResult<'a> {
                                 break;
                             }
                         }
+                        CssRefiner::Contains(text) => {
+                            if !tag_node.text().contains(text.as_str()) {
+                                all_found = false;
+                                break;
+                            }
+                        }
+                        CssRefiner::IContains(text) => {
+                            if !tag_node
+                                .text()
+                                .to_lowercase()
+                                .contains(text.to_lowercase().as_str())
+                            {
+                                all_found = false;
+                                break;
+                            }
+                        }
+                        CssRefiner::Matches(regex) => {
+                            if !regex.is_match(&tag_node.text()) {
+                                all_found = false;
+                                break;
+                            }
+                        }
+                        CssRefiner::TextEquals(text) => {
+                            if tag_node.text().trim() != text.trim() {
+                                all_found = false;
+                                break;
+                            }
+                        }
                     }
                 }
                 if all_found != true {
@@ -1086,66 +1111,56 @@ impl<'a> HtmlQueryResult<'a> {
         true
     }
 
-    fn matches_selector_rule(&self, selector_rule: &CssSelectorRule) -> bool {
-        let mut moveable_pointer = self.clone();
-        let mut mut_selector_rule = selector_rule.clone();
-        while let Some(rule) = mut_selector_rule.rules.pop() {
-            match rule {
-                CssSelectorRelationship::Current(selector_item) => {
-                    if !self.matches_item(&selector_item) {
-                        return false;
-                    }
-                }
-                CssSelectorRelationship::Parent(selector_item) => {
-                    match moveable_pointer.move_to_parent() {
-                        Some(_) => {
-                            if !moveable_pointer.matches_item(&selector_item) {
-                                return false;
-                            }
-                        }
-                        None => {
-                            return false;
-                        }
-                    }
-                }
-                CssSelectorRelationship::Ancestor(selector_item) => {
-                    while let Some(_) = moveable_pointer.move_to_parent() {
-                        if moveable_pointer.matches_item(&selector_item) {
-                            match moveable_pointer.matches_selector_rule(&mut_selector_rule) {
-                                true => return true, // We checked the rest of the rules using the moveable_pointer
-                                false => (),
-                            }
-                        }
-                    }
-                    return false;
-                }
-                CssSelectorRelationship::PreviousSibling(selector_item) => {
-                    let mut one_matches = false;
-                    while let Some(_) = moveable_pointer.move_to_previous_sibling() {
-                        if moveable_pointer.matches_item(&selector_item) {
-                            one_matches = true;
-                            break;
-                        }
-                    }
-                    if !one_matches {
-                        return false;
+    /// Checks that the node matches the rightmost item in the rule and then that the rest of
+    /// the rule matches moving right to left. This is the one matcher used by `find`, `select`,
+    /// `filter` and [`crate::selector_set::CompiledSelectorSet`].
+    pub(crate) fn matches_selector_rule(&self, selector_rule: &CssSelectorRule) -> bool {
+        match selector_rule.rules.split_last() {
+            None => false,
+            Some((last, rest)) => self.matches_item(last.item()) && self.matches_left(rest),
+        }
+    }
+
+    /// Checks the rest of the rule against the nodes related to a node which has already
+    /// matched the item to the right of the rest of the rule. Backtracks when an ancestor or
+    /// sibling matches but the rest of the rule does not.
+    fn matches_left(&self, rules: &[CssSelectorRelationship]) -> bool {
+        let (relationship, rest) = match rules.split_last() {
+            None => return true,
+            Some(r) => r,
+        };
+        let mut pointer = self.clone();
+        match relationship {
+            CssSelectorRelationship::Current(item) => {
+                pointer.matches_item(item) && pointer.matches_left(rest)
+            }
+            CssSelectorRelationship::Parent(item) => {
+                pointer.move_to_parent().is_some()
+                    && pointer.matches_item(item)
+                    && pointer.matches_left(rest)
+            }
+            CssSelectorRelationship::PreviousSiblingOnce(item) => {
+                pointer.move_to_previous_sibling().is_some()
+                    && pointer.matches_item(item)
+                    && pointer.matches_left(rest)
+            }
+            CssSelectorRelationship::Ancestor(item) => {
+                while pointer.move_to_parent().is_some() {
+                    if pointer.matches_item(item) && pointer.matches_left(rest) {
+                        return true;
                     }
                 }
-                CssSelectorRelationship::PreviousSiblingOnce(selector_item) => {
-                    match moveable_pointer.move_to_previous_sibling() {
-                        None => {
-                            return false;
-                        }
-                        Some(_) => {
-                            if !moveable_pointer.matches_item(&selector_item) {
-                                return false;
-                            }
-                        }
+                false
+            }
+            CssSelectorRelationship::PreviousSibling(item) => {
+                while pointer.move_to_previous_sibling().is_some() {
+                    if pointer.matches_item(item) && pointer.matches_left(rest) {
+                        return true;
                     }
                 }
+                false
             }
         }
-        true
     }
 
     /// Checks if the node pointed to matches the CSS style selector provided.
@@ -1164,12 +1179,60 @@ impl<'a> HtmlQueryResult<'a> {
             }
         }
     }
+
+    /// Checks if the node pointed to matches the CSS style selector provided and returns
+    /// the specificity of the most specific rule in the selector which matched.
+    /// Returns None if the node does not match the selector.
+    pub fn matched_specificity(&self, selector: &CssSelector) -> Option<CssSpecificity> {
+        match selector {
+            CssSelector::Any => match self.get_node() {
+                Some(HtmlNode::Tag(_)) => Some(CssSpecificity::default()),
+                _ => None,
+            },
+            CssSelector::Specific(v) => v
+                .iter()
+                .filter(|rule| self.matches_selector_rule(rule))
+                .map(|rule| rule.specificity())
+                .max(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod html_match_tests {
     use super::*;
 
+    #[test]
+    fn html_matching_text_refiners_test() {
+        let doc = HtmlDocument::from_str(
+            r#"<table><tr><td>Price</td><td> 12.50 </td></tr><tr><td>Weight (kg)</td><td>3</td></tr></table>
+<a href="/2">Next <b>Page</b></a><a href="/0">Previous page</a>"#,
+        )
+        .unwrap();
+        let text = |s: &str| {
+            doc.find(s)
+                .nodes()
+                .iter()
+                .map(|n| n.text())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(text(r#"a:contains("Next Page")"#), vec!["Next Page"]);
+        assert_eq!(text(r#"a:contains("page")"#), vec!["Previous page"]);
+        assert_eq!(
+            text(r#"a:icontains("PAGE")"#),
+            vec!["Next Page", "Previous page"]
+        );
+        assert_eq!(text(r#"td:text-equals("Price") + td"#), vec![" 12.50 "]);
+        assert_eq!(text(r#"td:text-equals("12.50")"#), vec![" 12.50 "]);
+        assert_eq!(text(r#"td:text-equals("12.5")"#), Vec::<String>::new());
+        assert_eq!(
+            text(r#"td:matches(/^\s*\d+(\.\d+)?\s*$/)"#),
+            vec![" 12.50 ", "3"]
+        );
+        assert_eq!(text(r#"tr:contains("(kg)") td:matches(/^\d$/)"#), vec!["3"]);
+        assert_eq!(text(r#"a:not(:contains("Next"))"#), vec!["Previous page"]);
+    }
+
     #[test]
     fn html_matching_basic_test() {
         let doc = HtmlDocument::from_str(
@@ -1195,10 +1258,12 @@ mod html_match_tests {
                         ids: [].to_vec(),
                         classes: ["bold".to_owned()].to_vec(),
                         attributes: HashMap::new(),
-                        contents: [HtmlNode::Text("app".to_owned())].to_vec()
+                        contents: [HtmlNode::Text("app".to_owned())].to_vec(),
+                        namespace: None,
                     })
                 ]
-                .to_vec()
+                .to_vec(),
+                namespace: None,
             })]
         );
         q.find_str("p").unwrap();
@@ -1212,7 +1277,8 @@ mod html_match_tests {
                 ids: [].to_vec(),
                 classes: ["bold".to_owned()].to_vec(),
                 attributes: HashMap::new(),
-                contents: [HtmlNode::Text("app".to_owned())].to_vec()
+                contents: [HtmlNode::Text("app".to_owned())].to_vec(),
+                namespace: None,
             })]
         );
     }
@@ -3050,6 +3116,275 @@ impl<'a> Iterator for HtmlQueryResultIter<'a> {
     }
 }
 
+/// A reference to a node in a HTML tree. It is a [`HtmlQueryResult`] so it keeps the path
+/// to the node and can be used to move around the tree.
+pub type NodeRef<'a> = HtmlQueryResult<'a>;
+
+/// A lazy iterator over the tags that match a CSS selector, in document order.
+/// Created with the `select` methods on [`HtmlDocument`], [`HtmlTag`] and [`HtmlQueryResult`].
+/// Nothing is searched until the iterator is advanced, and only the path to the current
+/// node is kept while walking the tree.
+pub struct Select<'a, 's> {
+    selector: &'s CssSelector,
+    pointer: Option<HtmlQueryResult<'a>>,
+    /// The length of the path at the top level of the tree being searched.
+    /// The walk never moves above this level.
+    depth: usize,
+    started: bool,
+}
+
+impl<'a, 's> Select<'a, 's> {
+    fn new(selector: &'s CssSelector, pointer: Option<HtmlQueryResult<'a>>) -> Select<'a, 's> {
+        let depth = pointer.as_ref().map(|p| p.path.len()).unwrap_or(0);
+        Select {
+            selector,
+            pointer,
+            depth,
+            started: false,
+        }
+    }
+
+    /// Starts a search over a list of nodes with nothing above them.
+    fn from_nodes(selector: &'s CssSelector, nodes: &'a Vec<HtmlNode>) -> Select<'a, 's> {
+        let pointer = match nodes.is_empty() {
+            true => None,
+            false => Some(HtmlQueryResult {
+                path: vec![(nodes, 0)],
+            }),
+        };
+        Select::new(selector, pointer)
+    }
+
+    /// Moves the pointer to the next node in the tree being searched.
+    fn advance(pointer: &mut HtmlQueryResult<'a>, depth: usize) -> Option<()> {
+        if pointer.move_to_first_child().is_some() {
+            return Some(());
+        }
+        loop {
+            if pointer.move_to_next_sibling().is_some() {
+                return Some(());
+            }
+            if pointer.path.len() <= depth {
+                return None;
+            }
+            pointer.move_to_parent()?;
+        }
+    }
+}
+
+impl<'a, 's> Iterator for Select<'a, 's> {
+    type Item = NodeRef<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let depth = self.depth;
+        let mut pointer = self.pointer.take()?;
+        if self.started && Self::advance(&mut pointer, depth).is_none() {
+            return None;
+        }
+        self.started = true;
+        loop {
+            if pointer.matches_tag(self.selector) {
+                let found = pointer.clone();
+                self.pointer = Some(pointer);
+                return Some(found);
+            }
+            Self::advance(&mut pointer, depth)?;
+        }
+    }
+}
+
+impl<'a> HtmlQueryResult<'a> {
+    /// Lazily finds the tags below the node pointed to which match the selector.
+    /// The node itself is not included but its ancestors are used when matching.
+    pub fn select<'s>(&self, selector: &'s CssSelector) -> Select<'a, 's> {
+        let pointer = match self.get_node() {
+            Some(HtmlNode::Tag(t)) if !t.contents.is_empty() => {
+                let mut pointer = self.clone();
+                pointer.path.push((&t.contents, 0));
+                Some(pointer)
+            }
+            _ => None,
+        };
+        Select::new(selector, pointer)
+    }
+
+    /// Finds the first tag below the node pointed to which matches the selector.
+    pub fn select_first(&self, selector: &CssSelector) -> Option<NodeRef<'a>> {
+        self.select(selector).next()
+    }
+}
+
+impl HtmlDocument {
+    /// Lazily finds the tags in the document which match the selector, in document order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hb_html::objects::{CssSelector, HtmlDocument};
+    /// let doc = "<ul><li>One</li><li class=two>Two</li></ul>"
+    ///     .parse::<HtmlDocument>()
+    ///     .unwrap();
+    /// let selector = "li".parse::<CssSelector>().unwrap();
+    /// let texts = doc
+    ///     .select(&selector)
+    ///     .map(|n| n.get_node().unwrap().text())
+    ///     .collect::<Vec<String>>();
+    /// assert_eq!(texts, vec!["One", "Two"]);
+    /// ```
+    pub fn select<'s>(&self, selector: &'s CssSelector) -> Select<'_, 's> {
+        Select::from_nodes(selector, &self.nodes)
+    }
+
+    /// Finds the first tag in the document which matches the selector, stopping the
+    /// search as soon as it is found.
+    pub fn select_first(&self, selector: &CssSelector) -> Option<NodeRef<'_>> {
+        self.select(selector).next()
+    }
+}
+
+impl HtmlTag {
+    /// Lazily finds the tags inside this tag which match the selector, in document order.
+    /// The tag itself is not included, and the search can't see anything outside of the tag.
+    pub fn select<'s>(&self, selector: &'s CssSelector) -> Select<'_, 's> {
+        Select::from_nodes(selector, &self.contents)
+    }
+
+    /// Finds the first tag inside this tag which matches the selector.
+    pub fn select_first(&self, selector: &CssSelector) -> Option<NodeRef<'_>> {
+        self.select(selector).next()
+    }
+}
+
+#[cfg(test)]
+mod select_tests {
+    use super::*;
+    use crate::objects::CssSelectorParseOptions;
+
+    const DOC: &str = r#"<html><body>
+<div id=a>Text <p>One</p><div id=b><p class=x>Two</p></div></div>
+<p class=x>Three</p>
+</body></html>"#;
+
+    fn texts(nodes: Select) -> Vec<String> {
+        nodes
+            .map(|n| n.get_node().unwrap().text())
+            .collect::<Vec<String>>()
+    }
+
+    #[test]
+    fn select_document_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let selector = CssSelector::from_str("p").unwrap();
+        assert_eq!(texts(doc.select(&selector)), vec!["One", "Two", "Three"]);
+        for s in ["p", "div p", "div > p.x", "p.x", "*", "span", "body > *"] {
+            let selector = CssSelector::from_str(s).unwrap();
+            let expected = doc
+                .find(s)
+                .results
+                .into_iter()
+                .filter(|r| matches!(r.get_node(), Some(HtmlNode::Tag(_))))
+                .collect::<Vec<HtmlQueryResult>>();
+            assert_eq!(doc.select(&selector).collect::<Vec<_>>(), expected, "{}", s);
+        }
+    }
+
+    #[test]
+    fn select_first_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let first = doc.select_first(&CssSelector::from_str("p.x").unwrap());
+        assert_eq!(first.unwrap().get_node().unwrap().text(), "Two");
+        assert!(doc
+            .select_first(&CssSelector::from_str("span").unwrap())
+            .is_none());
+        let empty = HtmlDocument::from_str("").unwrap();
+        assert!(empty.select_first(&CssSelector::Any).is_none());
+    }
+
+    #[test]
+    fn select_from_result_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let div = doc
+            .select_first(&CssSelector::from_str("#a").unwrap())
+            .unwrap();
+        // the node itself is not included, siblings after it are not searched
+        assert_eq!(
+            texts(div.select(&CssSelector::from_str("p").unwrap())),
+            vec!["One", "Two"]
+        );
+        assert_eq!(
+            texts(div.select(&CssSelector::from_str("div").unwrap())),
+            vec!["Two"]
+        );
+        // ancestors above the node are still used for matching
+        assert_eq!(
+            texts(div.select(&CssSelector::from_str("body p.x").unwrap())),
+            vec!["Two"]
+        );
+        let leaf = div
+            .select_first(&CssSelector::from_str("p.x").unwrap())
+            .unwrap();
+        let mut text = leaf.clone();
+        text.move_to_first_child().unwrap();
+        assert!(leaf.select_first(&CssSelector::Any).is_none());
+        assert!(text.select_first(&CssSelector::Any).is_none());
+    }
+
+    #[test]
+    fn select_tag_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let div = match doc
+            .select_first(&CssSelector::from_str("#a").unwrap())
+            .unwrap()
+            .get_node()
+        {
+            Some(HtmlNode::Tag(t)) => t.clone(),
+            _ => panic!("Expected a tag"),
+        };
+        assert_eq!(
+            texts(div.select(&CssSelector::from_str("p").unwrap())),
+            vec!["One", "Two"]
+        );
+        // the tag is the top of the tree so body can't be matched
+        assert!(div
+            .select_first(&CssSelector::from_str("body p").unwrap())
+            .is_none());
+        assert_eq!(
+            texts(div.select(&CssSelector::from_str("#b > p").unwrap())),
+            vec!["Two"]
+        );
+    }
+
+    #[test]
+    fn select_namespace_test() {
+        let doc = HtmlDocument::parse_xml(
+            r#"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:svg="http://www.w3.org/2000/svg">
+            <p>Text</p><svg:svg><svg:title>Icon</svg:title></svg:svg><title xmlns="">Other</title>
+            </html>"#,
+        )
+        .unwrap();
+        let options = CssSelectorParseOptions::default()
+            .namespace("", "http://www.w3.org/1999/xhtml")
+            .namespace("svg", "http://www.w3.org/2000/svg");
+        let tests = vec![
+            ("title", vec!["Icon", "Other"]),
+            ("svg|title", vec!["Icon"]),
+            ("svg|*", vec!["Icon", "Icon"]),
+            ("|title", vec!["Other"]),
+            ("*|title", vec!["Icon", "Other"]),
+            ("svg > title", vec!["Icon"]),
+            ("svg|svg > svg|title", vec!["Icon"]),
+        ];
+        for (s, expected) in tests {
+            let selector = CssSelector::from_str(s)
+                .or_else(|_| CssSelector::parse_with_options(s, &options))
+                .unwrap();
+            assert_eq!(texts(doc.select(&selector)), expected, "{}", s);
+        }
+        // the default namespace only applies to selectors parsed with it
+        let selector = CssSelector::parse_with_options("p, title", &options).unwrap();
+        assert_eq!(texts(doc.select(&selector)), vec!["Text"]);
+    }
+}
+
 /// Allows searching through HTML documents using various search functions.
 /// Results are stores as HtmlQueryResults.
 ///
@@ -3082,6 +3417,8 @@ impl<'a> Iterator for HtmlQueryResultIter<'a> {
 pub struct HtmlQuery<'a> {
     pub root: &'a Vec<HtmlNode>,
     pub results: Vec<HtmlQueryResult<'a>>,
+    /// The previous sets of results, so that [`HtmlQuery::end`] can go back to them.
+    previous: Vec<Vec<HtmlQueryResult<'a>>>,
 }
 
 impl<'a> HtmlQuery<'a> {
@@ -3095,29 +3432,38 @@ impl<'a> HtmlQuery<'a> {
         HtmlQuery {
             root: root,
             results: vec![],
+            previous: vec![],
         }
     }
 
     /// Clears ther results list
     pub fn reset_result(&mut self) {
         self.results.clear();
+        self.previous.clear();
     }
 
     /// Find all elements with the tag provided in the Html structure.
-    pub fn find_str(&mut self, selector: &str) -> Result<&HtmlQuery, ParseHtmlError> {
+    /// Use [`HtmlQuery::find`] to chain more calls after the search.
+    pub fn find_str(&mut self, selector: &str) -> Result<&HtmlQuery<'_>, ParseHtmlError> {
         match CssSelector::from_str(selector) {
             Err(e) => Err(e),
             Ok(s) => Ok(self.find(&s)),
         }
     }
 
-    /// Search through either the root HTML nodes if there are no results stored,
-    /// otherwise search through the current results.
-    pub fn find(&mut self, selector: &CssSelector) -> &HtmlQuery {
-        if self.results.len() == 0 {
-            self.find_from_root(selector);
-        } else {
+    /// Search through the root HTML nodes if nothing has been searched for yet, otherwise
+    /// search through the current results. Once the results have been narrowed down to
+    /// nothing, for example by a [`HtmlQuery::filter`] which matched nothing, finding
+    /// anything else also finds nothing.
+    ///
+    /// The query is returned so the traversal methods can be chained after it.
+    pub fn find(&mut self, selector: &CssSelector) -> &mut HtmlQuery<'a> {
+        let narrowed = !self.results.is_empty() || !self.previous.is_empty();
+        self.previous.push(self.results.clone());
+        if narrowed {
             self.find_from_results(selector);
+        } else {
+            self.find_from_root(selector);
         }
         self
     }
@@ -3138,41 +3484,41 @@ impl<'a> HtmlQuery<'a> {
         }
     }
 
+    /// Searches the current results and everything below them. Results which are inside
+    /// another result are only searched once, so every node is found at most once and the
+    /// new results are in document order.
     fn find_from_results(&mut self, selector: &CssSelector) {
-        let mut results = self.results.clone();
-        self.results = vec![];
-        for res in results {
+        let mut previous = std::mem::take(&mut self.results);
+        previous.sort_by(|a, b| a.cmp_document_order(b));
+        let mut searched: Option<HtmlQueryResult> = None;
+        for res in previous {
+            if let Some(s) = &searched {
+                if s.node_key() == res.node_key() || res.is_descendant_of(s) {
+                    continue;
+                }
+            }
             //result itself matches
             if res.matches(selector) {
                 self.results.push(res.clone());
             }
             //check children of result only
-            match res.get_node() {
-                None => (),
-                Some(n) => match n {
-                    HtmlNode::Tag(t) => {
-                        if t.contents.len() > 0 {
-                            let mut new_res = HtmlQueryResult {
-                                path: vec![(&t.contents, 0)],
-                            };
-                            // walk the tree below the current result and check for matches
-                            loop {
-                                if new_res.matches(selector) {
-                                    //add the relative path to the path of the top level
-                                    let mut add_res = res.clone();
-                                    add_res.path.extend_from_slice(&new_res.path);
-                                    self.results.push(add_res);
-                                }
-                                match new_res.walk_next() {
-                                    None => return,
-                                    Some(_) => (),
-                                }
-                            }
+            if let Some(HtmlNode::Tag(t)) = res.get_node() {
+                if !t.contents.is_empty() {
+                    let mut pointer = res.clone();
+                    pointer.path.push((&t.contents, 0));
+                    let depth = pointer.path.len();
+                    // walk the tree below the current result and check for matches
+                    loop {
+                        if pointer.matches(selector) {
+                            self.results.push(pointer.clone());
+                        }
+                        if Select::advance(&mut pointer, depth).is_none() {
+                            break;
                         }
                     }
-                    _ => (),
-                },
+                }
             }
+            searched = Some(res);
         }
     }
 
@@ -3186,7 +3532,423 @@ impl<'a> HtmlQuery<'a> {
         }
         v
     }
+
+    /// Replaces the results with a new set in document order, removing any duplicate nodes and keeping
+    /// the old set so [`HtmlQuery::end`] can return to it.
+    fn push_results(&mut self, results: Vec<HtmlQueryResult<'a>>) -> &mut HtmlQuery<'a> {
+        let mut results = results;
+        results.sort_by(|a, b| a.cmp_document_order(b));
+        results.dedup_by_key(|r| r.node_key());
+        let previous = std::mem::replace(&mut self.results, results);
+        self.previous.push(previous);
+        self
+    }
+
+    /// Creates a new set of results by running the function on each current result.
+    fn map_results<F>(&mut self, f: F) -> &mut HtmlQuery<'a>
+    where
+        F: Fn(&HtmlQueryResult<'a>) -> Vec<HtmlQueryResult<'a>>,
+    {
+        let results = self.results.iter().flat_map(f).collect();
+        self.push_results(results)
+    }
+
+    /// Keeps only the results which match the selector.
+    pub fn filter(&mut self, selector: &CssSelector) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| match r.matches_tag(selector) {
+            true => vec![r.clone()],
+            false => vec![],
+        })
+    }
+
+    /// Keeps only the results which do not match the selector.
+    pub fn not(&mut self, selector: &CssSelector) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| match r.matches_tag(selector) {
+            true => vec![],
+            false => vec![r.clone()],
+        })
+    }
+
+    /// Keeps only the results which have a descendant matching the selector.
+    pub fn has(&mut self, selector: &CssSelector) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| match r.select_first(selector) {
+            Some(_) => vec![r.clone()],
+            None => vec![],
+        })
+    }
+
+    /// Replaces each result with its parent tag.
+    pub fn parent(&mut self) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| {
+            let mut pointer = r.clone();
+            match pointer.move_to_parent() {
+                Some(_) => vec![pointer],
+                None => vec![],
+            }
+        })
+    }
+
+    /// Replaces each result with all of its ancestors, from the closest up to the top.
+    pub fn parents(&mut self) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| {
+            let mut pointer = r.clone();
+            let mut parents = vec![];
+            while pointer.move_to_parent().is_some() {
+                parents.push(pointer.clone());
+            }
+            parents
+        })
+    }
+
+    /// Replaces each result with the first tag matching the selector found by starting at
+    /// the result itself and moving up through its ancestors.
+    pub fn closest(&mut self, selector: &CssSelector) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| {
+            let mut pointer = r.clone();
+            loop {
+                if pointer.matches_tag(selector) {
+                    return vec![pointer];
+                }
+                if pointer.move_to_parent().is_none() {
+                    return vec![];
+                }
+            }
+        })
+    }
+
+    /// Replaces each result with the tags directly inside of it.
+    pub fn children(&mut self) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| {
+            let mut pointer = r.clone();
+            let mut children = vec![];
+            if pointer.move_to_first_child().is_some() {
+                if let Some(HtmlNode::Tag(_)) = pointer.get_node() {
+                    children.push(pointer.clone());
+                }
+                while pointer.move_to_next_sibling().is_some() {
+                    children.push(pointer.clone());
+                }
+            }
+            children
+        })
+    }
+
+    /// Replaces each result with the other tags that share its parent.
+    pub fn siblings(&mut self) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| {
+            let mut siblings = r.previous_siblings();
+            siblings.reverse();
+            siblings.extend(r.next_siblings());
+            siblings
+        })
+    }
+
+    /// Replaces each result with the tag directly after it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| {
+            let mut pointer = r.clone();
+            match pointer.move_to_next_sibling() {
+                Some(_) => vec![pointer],
+                None => vec![],
+            }
+        })
+    }
+
+    /// Replaces each result with the tag directly before it.
+    pub fn prev(&mut self) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| {
+            let mut pointer = r.clone();
+            match pointer.move_to_previous_sibling() {
+                Some(_) => vec![pointer],
+                None => vec![],
+            }
+        })
+    }
+
+    /// Replaces each result with all of the tags after it that share its parent.
+    pub fn next_all(&mut self) -> &mut HtmlQuery<'a> {
+        self.map_results(|r| r.next_siblings())
+    }
+
+    /// Keeps only the first result.
+    pub fn first(&mut self) -> &mut HtmlQuery<'a> {
+        let results = self.results.first().cloned().into_iter().collect();
+        self.push_results(results)
+    }
+
+    /// Keeps only the last result.
+    pub fn last(&mut self) -> &mut HtmlQuery<'a> {
+        let results = self.results.last().cloned().into_iter().collect();
+        self.push_results(results)
+    }
+
+    /// Keeps only the result at the index provided.
+    pub fn eq(&mut self, index: usize) -> &mut HtmlQuery<'a> {
+        let results = self.results.get(index).cloned().into_iter().collect();
+        self.push_results(results)
+    }
+
+    /// Adds the tags from the whole document which match the selector to the results.
+    pub fn add(&mut self, selector: &CssSelector) -> &mut HtmlQuery<'a> {
+        let mut results = self.results.clone();
+        results.extend(Select::from_nodes(selector, self.root));
+        self.push_results(results)
+    }
+
+    /// Goes back to the set of results from before the last search or change to the results.
+    /// The results are left as they are if there is nothing to go back to.
+    pub fn end(&mut self) -> &mut HtmlQuery<'a> {
+        if let Some(previous) = self.previous.pop() {
+            self.results = previous;
+        }
+        self
+    }
+}
+
+impl<'a> HtmlQueryResult<'a> {
+    /// Checks if the node pointed to is a tag that matches the selector.
+    pub(crate) fn matches_tag(&self, selector: &CssSelector) -> bool {
+        match self.get_node() {
+            Some(HtmlNode::Tag(_)) => (),
+            _ => return false,
+        }
+        match selector {
+            CssSelector::Any => true,
+            CssSelector::Specific(rules) => {
+                rules.iter().any(|rule| self.matches_selector_rule(rule))
+            }
+        }
+    }
+
+    /// Compares the positions of two nodes from the same tree in document order.
+    /// A node comes before its descendants, and only the indexes along the paths are
+    /// compared so the contents of the tree are never looked at.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hb_html::objects::HtmlDocument;
+    /// use std::cmp::Ordering;
+    /// let doc = "<div><p>One</p></div><p>Two</p>".parse::<HtmlDocument>().unwrap();
+    /// let div = &doc.find("div").results[0];
+    /// let ps = doc.find("p").results;
+    /// assert_eq!(div.cmp_document_order(&ps[0]), Ordering::Less);
+    /// assert_eq!(ps[1].cmp_document_order(&ps[0]), Ordering::Greater);
+    /// ```
+    pub fn cmp_document_order(&self, other: &HtmlQueryResult) -> Ordering {
+        for (a, b) in self.path.iter().zip(other.path.iter()) {
+            match a.1.cmp(&b.1) {
+                Ordering::Equal => (),
+                o => return o,
+            }
+        }
+        self.path.len().cmp(&other.path.len())
+    }
+
+    /// Checks if the node pointed to is below the node the other result points to.
+    pub fn is_descendant_of(&self, other: &HtmlQueryResult) -> bool {
+        other.path.len() < self.path.len()
+            && other
+                .path
+                .iter()
+                .zip(self.path.iter())
+                .all(|(a, b)| std::ptr::eq(a.0, b.0) && a.1 == b.1)
+    }
+
+    /// Identifies the node pointed to without comparing the contents of the tree.
+    pub(crate) fn node_key(&self) -> (*const Vec<HtmlNode>, usize) {
+        match self.path.last() {
+            Some((v, i)) => (*v as *const Vec<HtmlNode>, *i),
+            None => (std::ptr::null(), 0),
+        }
+    }
+
+    /// Gets the tags before the node pointed to that share its parent, closest first.
+    fn previous_siblings(&self) -> Vec<HtmlQueryResult<'a>> {
+        let mut pointer = self.clone();
+        let mut siblings = vec![];
+        while pointer.move_to_previous_sibling().is_some() {
+            siblings.push(pointer.clone());
+        }
+        siblings
+    }
+
+    /// Gets the tags after the node pointed to that share its parent, closest first.
+    fn next_siblings(&self) -> Vec<HtmlQueryResult<'a>> {
+        let mut pointer = self.clone();
+        let mut siblings = vec![];
+        while pointer.move_to_next_sibling().is_some() {
+            siblings.push(pointer.clone());
+        }
+        siblings
+    }
 }
+
+#[cfg(test)]
+mod html_query_traversal_tests {
+    use super::*;
+
+    const DOC: &str = r#"<html><body>
+<div id=a class=box><p id=p1>One</p><p id=p2 class=x>Two <b>bold</b></p><span id=s1>Three</span></div>
+<div id=b><p id=p3 class=x>Four</p></div>
+</body></html>"#;
+
+    fn sel(s: &str) -> CssSelector {
+        CssSelector::from_str(s).unwrap()
+    }
+
+    fn ids(query: &HtmlQuery) -> Vec<String> {
+        query
+            .nodes()
+            .iter()
+            .map(|n| match n {
+                HtmlNode::Tag(t) => t.ids.first().cloned().unwrap_or_else(|| t.tag.clone()),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn query_filter_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let mut q = doc.find("p");
+        assert_eq!(ids(&q), vec!["p1", "p2", "p3"]);
+        assert_eq!(ids(q.filter(&sel(".x"))), vec!["p2", "p3"]);
+        assert_eq!(ids(q.end().not(&sel(".x"))), vec!["p1"]);
+        assert_eq!(ids(q.end().has(&sel("b"))), vec!["p2"]);
+        assert_eq!(ids(q.end().first()), vec!["p1"]);
+        assert_eq!(ids(q.end().last()), vec!["p3"]);
+        assert_eq!(ids(q.end().eq(1)), vec!["p2"]);
+        assert_eq!(ids(q.end().eq(5)), Vec::<String>::new());
+        assert_eq!(ids(q.end()), vec!["p1", "p2", "p3"]);
+    }
+
+    #[test]
+    fn query_filter_nothing_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let mut q = doc.find("p");
+        assert_eq!(ids(q.not(&sel("p")).find(&sel("b"))), Vec::<String>::new());
+        assert_eq!(ids(q.end().end().filter(&sel("div"))), Vec::<String>::new());
+        assert_eq!(ids(q.find(&sel("span"))), Vec::<String>::new());
+        assert_eq!(ids(q.end().children()), Vec::<String>::new());
+        assert_eq!(ids(q.end().parent()), Vec::<String>::new());
+        // a filter which matched nothing does not add the whole document
+        assert_eq!(ids(q.end().add(&sel("span"))), vec!["s1"]);
+        assert_eq!(ids(q.end().end().has(&sel("b"))), vec!["p2"]);
+    }
+
+    #[test]
+    fn query_empty_results_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        // an empty set of results stays empty instead of searching the whole document
+        let mut q = doc.find("p");
+        assert_eq!(
+            ids(q.filter(&sel(".nope")).find(&sel("b"))),
+            Vec::<String>::new()
+        );
+        let mut q = doc.find("nope");
+        assert_eq!(ids(q.find(&sel("p"))), Vec::<String>::new());
+        // going back to before the first search starts from the root again
+        assert_eq!(ids(q.end().end().find(&sel("b"))), vec!["b"]);
+        q.reset_result();
+        assert_eq!(ids(q.find(&sel("span"))), vec!["s1"]);
+        // the query returned can still be used where a &HtmlQuery was returned before
+        let mut q = doc.query();
+        let found: &HtmlQuery = q.find(&sel("p.x"));
+        assert_eq!(ids(found), vec!["p2", "p3"]);
+        let found: Result<&HtmlQuery, ParseHtmlError> = q.find_str("b");
+        assert_eq!(ids(found.unwrap()), vec!["b"]);
+    }
+
+    #[test]
+    fn query_navigation_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let mut q = doc.find("p");
+        // duplicate parents are removed
+        assert_eq!(ids(q.parent()), vec!["a", "b"]);
+        assert_eq!(ids(q.end().parents()), vec!["html", "body", "a", "b"]);
+        assert_eq!(ids(q.end().closest(&sel("div.box"))), vec!["a"]);
+        assert_eq!(ids(q.end().closest(&sel("p"))), vec!["p1", "p2", "p3"]);
+        q.end();
+        assert_eq!(ids(q.parent().children()), vec!["p1", "p2", "s1", "p3"]);
+        q.end().end();
+        assert_eq!(ids(q.eq(1).siblings()), vec!["p1", "s1"]);
+        q.end().end();
+        assert_eq!(ids(q.next()), vec!["p2", "s1"]);
+        assert_eq!(ids(q.end().prev()), vec!["p1"]);
+        assert_eq!(ids(q.end().next_all()), vec!["p2", "s1"]);
+        // the top level tag has no parent
+        let mut q = doc.find("html");
+        assert_eq!(ids(q.parent()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn query_add_end_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let mut q = doc.find("span");
+        assert_eq!(ids(q.add(&sel("p.x"))), vec!["p2", "s1", "p3"]);
+        assert_eq!(ids(q.add(&sel("span"))), vec!["p2", "s1", "p3"]);
+        assert_eq!(ids(q.end().end()), vec!["s1"]);
+        // chaining with find and going back
+        let mut q = doc.query();
+        q.find(&sel("#a")).find(&sel("p")).filter(&sel(".x"));
+        assert_eq!(ids(&q), vec!["p2"]);
+        assert_eq!(ids(q.end().end()), vec!["a"]);
+        // nothing left to go back to
+        assert_eq!(ids(q.end().end().end()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn query_find_nested_test() {
+        let doc = HtmlDocument::from_str(
+            "<div id=d1><div id=d2><p id=p1>A</p><div id=d3><p id=p2>B</p></div></div><p id=p3>C</p></div>",
+        )
+        .unwrap();
+        let mut q = doc.find("div");
+        assert_eq!(ids(&q), vec!["d1", "d2", "d3"]);
+        // each p is found once even though it is under several divs
+        assert_eq!(ids(q.find(&sel("p"))), vec!["p1", "p2", "p3"]);
+        q.end();
+        assert_eq!(ids(q.find(&sel("div"))), vec!["d1", "d2", "d3"]);
+        // results given out of order are still searched in document order
+        let mut q = doc.query();
+        q.results = doc.find("#d3, #d1").results;
+        q.results.reverse();
+        assert_eq!(ids(q.find(&sel("p"))), vec!["p1", "p2", "p3"]);
+    }
+
+    #[test]
+    fn query_find_siblings_test() {
+        let doc = HtmlDocument::from_str(
+            "<div id=d1><p id=p1>A</p></div><div id=d2></div><div id=d3><span><p id=p2>B</p></span></div><p id=p3>C</p>",
+        )
+        .unwrap();
+        // every previous result is searched, not only the first
+        let mut q = doc.find("div");
+        assert_eq!(ids(q.find(&sel("p"))), vec!["p1", "p2"]);
+        let mut q = doc.find("div");
+        assert_eq!(ids(q.find(&sel("span p"))), vec!["p2"]);
+    }
+
+    #[test]
+    fn document_order_test() {
+        let doc = HtmlDocument::from_str(DOC).unwrap();
+        let all = doc.find("*").results;
+        for (i, a) in all.iter().enumerate() {
+            for (j, b) in all.iter().enumerate() {
+                assert_eq!(a.cmp_document_order(b), i.cmp(&j));
+            }
+        }
+        let div = doc.find("#a").results.remove(0);
+        let b = doc.find("b").results.remove(0);
+        let p3 = doc.find("#p3").results.remove(0);
+        assert!(b.is_descendant_of(&div));
+        assert!(!p3.is_descendant_of(&div));
+        assert!(!div.is_descendant_of(&div));
+    }
+}
+
 pub struct HtmlQueryResultMut<'a> {
     pub path: Vec<(&'a mut Vec<HtmlNode>, usize)>,
 }
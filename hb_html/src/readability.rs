@@ -0,0 +1,509 @@
+//! Finds the main content of a page, such as the text of an article or recipe, without
+//! the navigation, adverts and footers around it.
+//!
+//! The algorithm is based on the one used by the reader views of browsers. Paragraphs
+//! add a score to their parent and grandparent tags based on how much text they have.
+//! Scores go up or down with hints from the tag name and the class and id, and are
+//! reduced by the proportion of the text that is in links. The tag with the best score,
+//! along with any siblings which look like they are part of the same content, is cleaned
+//! and returned.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::HtmlDocument;
+//! let doc = r#"<html><head><title>Soup | Recipes</title></head><body>
+//! <nav><a href="/">Home</a> <a href="/more">More</a></nav>
+//! <div class="article">
+//!   <h1>Soup</h1>
+//!   <p class="byline">By Ann Smith</p>
+//!   <p>Soup is a warm dish, made by boiling vegetables, meat or fish in water or stock.</p>
+//!   <p>It can be served hot or cold, with bread, crackers or on its own as a starter.</p>
+//! </div>
+//! <div class="footer"><a href="/about">About</a></div>
+//! </body></html>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let article = doc.readable_content().unwrap();
+//! assert_eq!(article.title.as_deref(), Some("Soup"));
+//! assert_eq!(article.byline.as_deref(), Some("By Ann Smith"));
+//! assert!(article.content.text().contains("warm dish"));
+//! assert!(!article.content.text().contains("Home"));
+//! ```
+use crate::entities::decode_text;
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use crate::querying::HtmlQueryResult;
+use std::collections::HashMap;
+
+/// Class and id fragments for parts of a page that are not likely to be the content.
+const UNLIKELY_HINTS: [&str; 22] = [
+    "ad-break",
+    "agegate",
+    "banner",
+    "combx",
+    "comment",
+    "community",
+    "cookie",
+    "disqus",
+    "footer",
+    "header",
+    "menu",
+    "nav",
+    "pager",
+    "pagination",
+    "popup",
+    "related",
+    "remark",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "social",
+    "sponsor",
+];
+/// Class and id fragments which stop a tag being removed for an unlikely hint.
+const MAYBE_HINTS: [&str; 7] = [
+    "and", "article", "body", "column", "content", "main", "recipe",
+];
+/// Class and id fragments which add to the score of a tag.
+const POSITIVE_HINTS: [&str; 11] = [
+    "article", "blog", "body", "content", "entry", "hentry", "main", "page", "post", "recipe",
+    "story",
+];
+/// Class and id fragments which take away from the score of a tag.
+const NEGATIVE_HINTS: [&str; 13] = [
+    "ad-", "advert", "comment", "footer", "footnote", "masthead", "meta", "promo", "related",
+    "scroll", "shoutbox", "sidebar", "widget",
+];
+/// Tags which are never part of the content.
+const REMOVED_TAGS: [&str; 15] = [
+    "aside", "button", "embed", "footer", "form", "iframe", "input", "nav", "noscript", "object",
+    "script", "select", "style", "svg", "textarea",
+];
+/// The attributes kept on tags in the cleaned content.
+const KEPT_ATTRIBUTES: [&str; 8] = [
+    "alt", "colspan", "datetime", "href", "rowspan", "src", "srcset", "title",
+];
+
+/// Settings for [`HtmlDocument::readable_content_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadabilityOptions {
+    /// Paragraphs with less text than this are not scored.
+    pub min_paragraph_length: usize,
+    /// The content is not returned if it has less text than this.
+    pub min_content_length: usize,
+    /// Keeps the classes and ids of the tags in the content.
+    pub keep_classes: bool,
+}
+
+impl Default for ReadabilityOptions {
+    fn default() -> ReadabilityOptions {
+        ReadabilityOptions {
+            min_paragraph_length: 25,
+            min_content_length: 100,
+            keep_classes: false,
+        }
+    }
+}
+
+/// The main content of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadableContent {
+    pub title: Option<String>,
+    /// The author line, such as "By Ann Smith".
+    pub byline: Option<String>,
+    /// The src of the image that represents the page, from the og:image meta tag or the
+    /// first image in the content.
+    pub lead_image: Option<String>,
+    /// A `<div>` holding the cleaned content.
+    pub content: HtmlTag,
+    /// The score of the tag the content was taken from.
+    pub score: f64,
+}
+
+impl HtmlDocument {
+    /// Finds the main content of the document with the default options.
+    /// Returns None if no part of the document looks like content.
+    pub fn readable_content(&self) -> Option<ReadableContent> {
+        self.readable_content_with(&ReadabilityOptions::default())
+    }
+
+    /// Finds the main content of the document.
+    pub fn readable_content_with(&self, options: &ReadabilityOptions) -> Option<ReadableContent> {
+        let byline = self.byline();
+        let scores = self.score_candidates(options, byline.as_deref());
+        let (best, score) = scores
+            .values()
+            .filter_map(|(node, score)| Some((node, *score * (1.0 - link_density(tag_of(node)?)))))
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        let mut content = HtmlTag::new("div");
+        let threshold = f64::max(10.0, score * 0.2);
+        let (siblings, index) = *best.path.last()?;
+        for (i, sibling) in siblings.iter().enumerate() {
+            let tag = match sibling {
+                HtmlNode::Tag(t) => t,
+                _ => continue,
+            };
+            let mut pointer = best.clone();
+            pointer.path.last_mut()?.1 = i;
+            let sibling_score = scores
+                .get(&pointer.node_key())
+                .map(|(_, s)| *s * (1.0 - link_density(tag)))
+                .unwrap_or(0.0);
+            let text_length = tag.text().trim().len();
+            let include = i == index
+                || sibling_score >= threshold
+                || (tag.tag == "p"
+                    && link_density(tag) < 0.25
+                    && (text_length > 80
+                        || (text_length > 0 && tag.text().trim_end().ends_with('.'))));
+            if include {
+                content.contents.push(sibling.clone());
+            }
+        }
+        clean_content(&mut content, options, byline.as_deref());
+        if content.text().trim().len() < options.min_content_length {
+            return None;
+        }
+        let lead_image = self.lead_image(&content);
+        Some(ReadableContent {
+            title: self.article_title(),
+            byline,
+            lead_image,
+            content,
+            score,
+        })
+    }
+
+    /// Adds the scores of the paragraphs to their ancestors, returning the score of each
+    /// candidate keyed by the node.
+    #[allow(clippy::type_complexity)]
+    fn score_candidates(
+        &self,
+        options: &ReadabilityOptions,
+        byline: Option<&str>,
+    ) -> HashMap<(*const Vec<HtmlNode>, usize), (HtmlQueryResult<'_>, f64)> {
+        let mut scores: HashMap<_, (HtmlQueryResult, f64)> = HashMap::new();
+        let query = self.find("p, pre, td");
+        for paragraph in &query.results {
+            if is_unlikely(paragraph) {
+                continue;
+            }
+            let text = match tag_of(paragraph) {
+                Some(t) => t.text(),
+                None => continue,
+            };
+            let text = text.trim();
+            if text.len() < options.min_paragraph_length
+                || (byline.is_some() && Some(decode_text(text).as_str()) == byline)
+            {
+                continue;
+            }
+            let score =
+                1.0 + text.matches(',').count() as f64 + f64::min((text.len() / 100) as f64, 3.0);
+            let mut ancestor = paragraph.clone();
+            for level in 0..5 {
+                if ancestor.move_to_parent().is_none() {
+                    break;
+                }
+                let tag = match tag_of(&ancestor) {
+                    Some(t) => t,
+                    None => break,
+                };
+                let entry = scores
+                    .entry(ancestor.node_key())
+                    .or_insert_with(|| (ancestor.clone(), initial_score(tag)));
+                entry.1 += match level {
+                    0 => score,
+                    1 => score / 2.0,
+                    l => score / (l as f64 * 3.0),
+                };
+            }
+        }
+        scores
+    }
+
+    /// Gets the title from the og:title meta tag, or from the `<title>` with the name of the
+    /// site removed.
+    fn article_title(&self) -> Option<String> {
+        if let Some(title) = self.meta_content("meta[property=og:title]") {
+            return Some(title);
+        }
+        let title = self.first_text("title")?;
+        let heading = self.first_text("h1");
+        if let Some(heading) = &heading {
+            if title.contains(heading.as_str()) {
+                return Some(heading.clone());
+            }
+        }
+        for separator in [" | ", " - ", " – ", " — ", " :: ", " » "] {
+            if let Some((first, _)) = title.split_once(separator) {
+                return Some(first.trim().to_owned());
+            }
+        }
+        Some(title)
+    }
+
+    /// Gets the author from the meta tags, or from a short tag marked as the author.
+    fn byline(&self) -> Option<String> {
+        if let Some(author) = self.meta_content("meta[name=author]") {
+            return Some(author);
+        }
+        let selector = "[rel=author], [itemprop~=author], .byline, .author"
+            .parse::<CssSelector>()
+            .unwrap();
+        self.select(&selector).find_map(|node| {
+            let text = decode_text(&tag_of(&node)?.text());
+            match !text.is_empty() && text.len() < 100 {
+                true => Some(text),
+                false => None,
+            }
+        })
+    }
+
+    fn lead_image(&self, content: &HtmlTag) -> Option<String> {
+        if let Some(image) = self.meta_content("meta[property=og:image]") {
+            return Some(image);
+        }
+        let selector = "img[src]".parse::<CssSelector>().unwrap();
+        content
+            .select_first(&selector)
+            .and_then(|n| tag_of(&n)?.attributes.get("src").cloned())
+    }
+
+    fn meta_content(&self, selector: &str) -> Option<String> {
+        let selector = selector.parse::<CssSelector>().ok()?;
+        self.select(&selector).find_map(|n| {
+            let content = decode_text(tag_of(&n)?.attributes.get("content")?);
+            Some(content).filter(|c| !c.is_empty())
+        })
+    }
+
+    fn first_text(&self, selector: &str) -> Option<String> {
+        let selector = selector.parse::<CssSelector>().ok()?;
+        let text = decode_text(&tag_of(&self.select_first(&selector)?)?.text());
+        Some(text).filter(|t| !t.is_empty())
+    }
+}
+
+fn tag_of<'a>(node: &HtmlQueryResult<'a>) -> Option<&'a HtmlTag> {
+    match node.get_node() {
+        Some(HtmlNode::Tag(t)) => Some(t),
+        _ => None,
+    }
+}
+
+/// Joins the classes and ids of a tag into a lowercase string to look for hints in.
+fn class_and_id(tag: &HtmlTag) -> String {
+    let mut names = tag.classes.join(" ");
+    names.push(' ');
+    names.push_str(&tag.ids.join(" "));
+    names.to_ascii_lowercase()
+}
+
+/// Gets the score for the hints in the class and id of a tag.
+fn class_weight(tag: &HtmlTag) -> f64 {
+    let names = class_and_id(tag);
+    let mut weight = 0.0;
+    if POSITIVE_HINTS.iter().any(|h| names.contains(h)) {
+        weight += 25.0;
+    }
+    if NEGATIVE_HINTS.iter().any(|h| names.contains(h)) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+/// Gets the starting score of a candidate from its tag name and class weight.
+fn initial_score(tag: &HtmlTag) -> f64 {
+    let score = match tag.tag.as_str() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    score + class_weight(tag)
+}
+
+/// True if the node or one of its ancestors is a part of the page which is not content.
+fn is_unlikely(node: &HtmlQueryResult) -> bool {
+    let mut pointer = node.clone();
+    loop {
+        if let Some(tag) = tag_of(&pointer) {
+            if REMOVED_TAGS.contains(&tag.tag.as_str()) || tag.tag == "header" {
+                return true;
+            }
+            let names = class_and_id(tag);
+            if UNLIKELY_HINTS.iter().any(|h| names.contains(h))
+                && !MAYBE_HINTS.iter().any(|h| names.contains(h))
+            {
+                return true;
+            }
+        }
+        if pointer.move_to_parent().is_none() {
+            return false;
+        }
+    }
+}
+
+/// Gets the proportion of the text of a tag that is inside links.
+fn link_density(tag: &HtmlTag) -> f64 {
+    let length = tag.text().trim().len();
+    if length == 0 {
+        return 0.0;
+    }
+    let selector = "a".parse::<CssSelector>().unwrap();
+    let link_length: usize = tag
+        .select(&selector)
+        .filter_map(|n| tag_of(&n).map(|t| t.text().trim().len()))
+        .sum();
+    link_length as f64 / length as f64
+}
+
+/// Removes the tags that are not part of the content and the attributes used for styling.
+fn clean_content(tag: &mut HtmlTag, options: &ReadabilityOptions, byline: Option<&str>) {
+    tag.contents.retain(|node| match node {
+        HtmlNode::Comment(_) => false,
+        HtmlNode::Text(_) => true,
+        HtmlNode::Tag(t) => {
+            if REMOVED_TAGS.contains(&t.tag.as_str()) || class_weight(t) < 0.0 {
+                return false;
+            }
+            let text = decode_text(&t.text());
+            if byline.is_some() && Some(text.as_str()) == byline {
+                return false;
+            }
+            // lists and blocks which are mostly links are navigation
+            if matches!(t.tag.as_str(), "div" | "section" | "ul" | "ol" | "table")
+                && link_density(t) > 0.5
+            {
+                return false;
+            }
+            // keep empty tags which show something
+            let has_media = ["img", "picture", "video", "audio", "br", "hr"]
+                .iter()
+                .any(|m| {
+                    t.tag == *m || t.select_first(&m.parse::<CssSelector>().unwrap()).is_some()
+                });
+            !text.is_empty() || has_media
+        }
+    });
+    for node in tag.contents.iter_mut() {
+        if let HtmlNode::Tag(t) = node {
+            t.attributes
+                .retain(|name, _| KEPT_ATTRIBUTES.contains(&name.as_str()));
+            if !options.keep_classes {
+                t.classes.clear();
+                t.ids.clear();
+            }
+            clean_content(t, options, byline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod readability_tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head>
+<title>Tomato Soup Recipe - Good Food Site</title>
+<meta name="author" content="Ann Smith">
+</head><body>
+<header class="masthead"><a href="/">Good Food</a><ul class="menu"><li><a href="/a">Recipes</a></li><li><a href="/b">Videos</a></li></ul></header>
+<div id="main-content">
+  <div class="sidebar"><p>Sign up for our newsletter, it is free and full of great recipes, tips and offers.</p></div>
+  <article class="post">
+    <h2>Tomato Soup Recipe</h2>
+    <p>This tomato soup is rich, smooth and full of flavour, and it takes just thirty minutes to make.</p>
+    <img src="/soup.jpg" class="hero" style="width: 100%" alt="Soup">
+    <p>Start by roasting the tomatoes, onions and garlic in a hot oven until they are soft and sweet.</p>
+    <div class="share"><a href="/fb">Share</a> <a href="/tw">Tweet</a></div>
+    <p>Blend everything with the stock, season with salt and pepper, then serve with crusty bread.</p>
+    <script>track();</script>
+    <!-- a comment -->
+  </article>
+  <p>Nutrition information is an estimate, and will change with the brands and amounts used.</p>
+  <ul class="links"><li><a href="/x">Other soup recipes you might like to try this week</a></li></ul>
+</div>
+<footer><p>Copyright Good Food Site, all rights reserved, no copying of the recipes please.</p></footer>
+</body></html>"#;
+
+    #[test]
+    fn readable_content_test() {
+        let doc = PAGE.parse::<HtmlDocument>().unwrap();
+        let article = doc.readable_content().unwrap();
+        assert_eq!(article.title.as_deref(), Some("Tomato Soup Recipe"));
+        assert_eq!(article.byline.as_deref(), Some("Ann Smith"));
+        assert_eq!(article.lead_image.as_deref(), Some("/soup.jpg"));
+        let text = article.content.text();
+        assert!(text.contains("thirty minutes"));
+        assert!(text.contains("crusty bread"));
+        // a sibling paragraph that reads like content is included
+        assert!(text.contains("Nutrition information"));
+        for unwanted in [
+            "newsletter",
+            "Videos",
+            "Share",
+            "track()",
+            "Copyright",
+            "Other soup",
+        ] {
+            assert!(!text.contains(unwanted), "{}", unwanted);
+        }
+        let html = HtmlNode::Tag(article.content).to_html_string();
+        assert!(!html.contains("class="));
+        assert!(!html.contains("style="));
+        assert!(html.contains("alt=\"Soup\""));
+        assert!(!html.contains("comment"));
+    }
+
+    #[test]
+    fn readable_content_options_test() {
+        let doc = PAGE.parse::<HtmlDocument>().unwrap();
+        let options = ReadabilityOptions {
+            keep_classes: true,
+            ..ReadabilityOptions::default()
+        };
+        let article = doc.readable_content_with(&options).unwrap();
+        assert!(HtmlNode::Tag(article.content)
+            .to_html_string()
+            .contains("class=\"hero\""));
+        let options = ReadabilityOptions {
+            min_content_length: 10_000,
+            ..ReadabilityOptions::default()
+        };
+        assert!(doc.readable_content_with(&options).is_none());
+        let empty = "<div><a href=\"/\">Home</a></div>"
+            .parse::<HtmlDocument>()
+            .unwrap();
+        assert!(empty.readable_content().is_none());
+    }
+
+    #[test]
+    fn readable_title_test() {
+        let doc = "<title>Soup | Site</title><h1>Other</h1>"
+            .parse::<HtmlDocument>()
+            .unwrap();
+        assert_eq!(doc.article_title().as_deref(), Some("Soup"));
+        let doc = r#"<meta property="og:title" content="OG"><title>T</title>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        assert_eq!(doc.article_title().as_deref(), Some("OG"));
+        let doc = "<p class=\"byline\">\n  By   Bob </p>"
+            .parse::<HtmlDocument>()
+            .unwrap();
+        assert_eq!(doc.byline().as_deref(), Some("By Bob"));
+        let doc = r#"<title>Fish &amp; Chips | Site</title><p class="author">Zo&#235;</p>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        assert_eq!(doc.article_title().as_deref(), Some("Fish & Chips"));
+        assert_eq!(doc.byline().as_deref(), Some("Zoë"));
+        let doc =
+            r#"<meta property="og:title" content="Q&amp;A"><meta name=author content="A &amp; B">"#
+                .parse::<HtmlDocument>()
+                .unwrap();
+        assert_eq!(doc.article_title().as_deref(), Some("Q&A"));
+        assert_eq!(doc.byline().as_deref(), Some("A & B"));
+    }
+}
@@ -0,0 +1,25 @@
+//! Helpers shared by the modules which read values out of a document, such as feeds,
+//! sitemaps and metadata.
+use crate::objects::{HtmlNode, HtmlTag};
+use crate::url::Url;
+
+/// Gets the tags directly inside the tag, skipping text and comments.
+pub(crate) fn children(tag: &HtmlTag) -> impl Iterator<Item = &HtmlTag> {
+    tag.contents.iter().filter_map(|n| match n {
+        HtmlNode::Tag(t) => Some(t),
+        _ => None,
+    })
+}
+
+/// Resolves a href against the base URL, or parses it as a reference when there is no
+/// base. The href must already be decoded. It is None when the href is empty.
+pub(crate) fn resolve(base: Option<&Url>, href: &str) -> Option<Url> {
+    let href = href.trim();
+    if href.is_empty() {
+        return None;
+    }
+    Some(match base {
+        Some(base) => base.join(href),
+        None => Url::parse_reference(href),
+    })
+}
@@ -0,0 +1,826 @@
+//! Renders HTML as plain text or as CommonMark Markdown.
+//!
+//! Unlike [`HtmlNode::text`], the renderers know which tags are blocks, so paragraphs are
+//! separated by blank lines, list items get markers, tables are laid out in columns and
+//! whitespace is collapsed like a browser would. The Markdown renderer uses the GitHub
+//! extensions for tables and strikethrough.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::HtmlDocument;
+//! let doc = r#"<h2>Method</h2><p>Mix <b>well</b>, then see <a href="/tips">the tips</a>.</p>
+//! <ol><li>Boil</li><li>Serve</li></ol>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! assert_eq!(
+//!     doc.to_plain_text(),
+//!     "Method\n------\n\nMix well, then see the tips (/tips).\n\n1. Boil\n2. Serve\n"
+//! );
+//! assert_eq!(
+//!     doc.to_markdown(),
+//!     "## Method\n\nMix **well**, then see [the tips](/tips).\n\n1. Boil\n2. Serve\n"
+//! );
+//! ```
+use crate::entities::{collapse_whitespace, decode_entities, decode_text};
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use crate::table::HtmlTable;
+
+/// Tags whose contents are never rendered.
+const SKIPPED_TAGS: [&str; 10] = [
+    "head", "link", "meta", "noscript", "script", "style", "template", "title", "button", "select",
+];
+/// Tags which start a new line but are not separated by a blank line.
+const LINE_TAGS: [&str; 19] = [
+    "address",
+    "article",
+    "aside",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "header",
+    "main",
+    "nav",
+    "section",
+    "summary",
+    "tr",
+];
+
+/// Settings for [`HtmlDocument::to_plain_text_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOptions {
+    /// The width to wrap lines at, or None to not wrap them.
+    pub width: Option<usize>,
+    /// Writes the href of links after their text, like "text (href)".
+    pub link_targets: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> TextOptions {
+        TextOptions {
+            width: Some(80),
+            link_targets: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Text,
+    Markdown,
+}
+
+/// Builds the output one block at a time. Inline text is collected until the block ends,
+/// when it is wrapped and written with the prefixes of the lists and quotes it is in.
+struct Writer {
+    mode: Mode,
+    width: Option<usize>,
+    link_targets: bool,
+    out: String,
+    inline: String,
+    space: bool,
+    /// The prefix for each level of list or quote the writer is in.
+    prefixes: Vec<String>,
+    /// The marker of a list item, used instead of the last prefix on its first line.
+    marker: Option<String>,
+    /// The number of new lines to write before the next block.
+    breaks: usize,
+    /// The prefixes of the last block that was written.
+    last_prefix: String,
+    /// Set inside `<pre>` so whitespace is kept.
+    preformatted: bool,
+}
+
+impl Writer {
+    fn new(mode: Mode, options: &TextOptions) -> Writer {
+        Writer {
+            mode,
+            width: options.width,
+            link_targets: options.link_targets,
+            out: String::new(),
+            inline: String::new(),
+            space: false,
+            prefixes: vec![],
+            marker: None,
+            breaks: 0,
+            last_prefix: String::new(),
+            preformatted: false,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out
+    }
+
+    /// True if nothing has been written on the current line of the block.
+    fn at_line_start(&self) -> bool {
+        self.inline.is_empty() || self.inline.ends_with('\n')
+    }
+
+    /// Adds text, collapsing the whitespace.
+    fn text(&mut self, text: &str) {
+        let text = decode_entities(text);
+        if self.preformatted {
+            self.inline.push_str(&text);
+            return;
+        }
+        for (i, word) in text.split(|c: char| c.is_ascii_whitespace()).enumerate() {
+            if i > 0 {
+                self.space = true;
+            }
+            if word.is_empty() {
+                continue;
+            }
+            self.separate();
+            match self.mode {
+                Mode::Text => self.inline.push_str(word),
+                Mode::Markdown => {
+                    let escaped = escape_markdown(word, self.at_line_start());
+                    self.inline.push_str(&escaped);
+                }
+            }
+        }
+    }
+
+    /// Writes the pending space before more inline content.
+    fn separate(&mut self) {
+        if self.space && !self.at_line_start() {
+            self.inline.push(' ');
+        }
+        self.space = false;
+    }
+
+    /// Adds markup, such as the start of a link, without escaping it.
+    fn open(&mut self, markup: &str) {
+        self.separate();
+        self.inline.push_str(markup);
+    }
+
+    /// Adds markup at the end of an inline tag. A pending space stays pending so that it
+    /// goes after the markup.
+    fn close(&mut self, markup: &str) {
+        self.inline.push_str(markup);
+    }
+
+    fn hard_break(&mut self) {
+        if self.mode == Mode::Markdown && !self.at_line_start() {
+            self.inline.push('\\');
+        }
+        self.inline.push('\n');
+        self.space = false;
+    }
+
+    /// Ends the current block and asks for at least the number of new lines before the next.
+    fn block(&mut self, breaks: usize) {
+        self.flush();
+        self.breaks = self.breaks.max(breaks);
+    }
+
+    /// Writes the inline text collected for the block.
+    fn flush(&mut self) {
+        let inline = std::mem::take(&mut self.inline);
+        self.space = false;
+        if inline.trim().is_empty() {
+            return;
+        }
+        let inline = match self.preformatted {
+            true => inline,
+            false => inline.trim().to_owned(),
+        };
+        let mut lines = vec![];
+        for line in inline.split('\n') {
+            match (self.width, self.preformatted) {
+                (Some(width), false) => {
+                    let width = width.saturating_sub(self.prefix_width()).max(20);
+                    lines.extend(wrap(line.trim(), width));
+                }
+                _ => lines.push(line.to_owned()),
+            }
+        }
+        self.write_lines(&lines);
+    }
+
+    fn prefix_width(&self) -> usize {
+        self.prefixes.iter().map(|p| p.chars().count()).sum()
+    }
+
+    /// Writes finished lines with the prefixes, after the new lines between blocks.
+    fn write_lines(&mut self, lines: &[String]) {
+        let continuation = self.prefixes.concat();
+        if !self.out.is_empty() {
+            // blank lines only keep the prefixes shared with the block before them
+            let shared = continuation
+                .chars()
+                .zip(self.last_prefix.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect::<String>();
+            for i in 0..self.breaks.max(1) {
+                if i > 0 {
+                    self.out.push_str(shared.trim_end());
+                }
+                self.out.push('\n');
+            }
+        }
+        self.breaks = 0;
+        self.last_prefix = continuation.clone();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                self.out.push('\n');
+            }
+            let prefix = match (i, self.marker.take()) {
+                (0, Some(marker)) => {
+                    let outer = self.prefixes[..self.prefixes.len() - 1].concat();
+                    format!("{}{}", outer, marker)
+                }
+                _ => continuation.clone(),
+            };
+            let line = format!("{}{}", prefix, line);
+            self.out.push_str(line.trim_end());
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[HtmlNode]) {
+        for node in nodes {
+            match node {
+                HtmlNode::Text(t) => self.text(t),
+                HtmlNode::Tag(t) => self.tag(t),
+                HtmlNode::Comment(_) => (),
+            }
+        }
+    }
+
+    fn tag(&mut self, tag: &HtmlTag) {
+        let name = tag.tag.to_ascii_lowercase();
+        let markdown = self.mode == Mode::Markdown;
+        match name.as_str() {
+            n if SKIPPED_TAGS.contains(&n) => (),
+            "br" => self.hard_break(),
+            "p" => {
+                // a paragraph at the start of a list item stays on the marker's line
+                if self.marker.is_none() {
+                    self.block(2);
+                }
+                self.nodes(&tag.contents);
+                self.block(2);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                self.block(2);
+                if markdown {
+                    self.open(&format!("{} ", "#".repeat(level)));
+                    self.nodes(&tag.contents);
+                    self.flush();
+                } else {
+                    self.nodes(&tag.contents);
+                    let width = self.inline.trim().chars().count();
+                    self.flush();
+                    if level <= 2 && width > 0 {
+                        let underline = if level == 1 { "=" } else { "-" };
+                        self.write_lines(&[underline.repeat(width)]);
+                    }
+                }
+                self.block(2);
+            }
+            "hr" => {
+                self.block(2);
+                let rule = if markdown { "* * *" } else { "----------" };
+                self.write_lines(&[rule.to_owned()]);
+                self.block(2);
+            }
+            "blockquote" => {
+                self.block(2);
+                self.prefixes.push("> ".to_owned());
+                self.nodes(&tag.contents);
+                self.flush();
+                self.prefixes.pop();
+                self.block(2);
+            }
+            "ul" | "ol" => self.list(tag, name == "ol"),
+            "li" => {
+                // an item outside of a list
+                self.block(1);
+                self.nodes(&tag.contents);
+                self.block(1);
+            }
+            "pre" => {
+                self.block(2);
+                if markdown {
+                    let code = tag.text();
+                    let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+                    let mut lines = vec![fence.clone()];
+                    lines.extend(
+                        decode_entities(code.trim_end_matches('\n'))
+                            .trim_start_matches('\n')
+                            .split('\n')
+                            .map(|l| l.to_owned()),
+                    );
+                    lines.push(fence);
+                    self.write_lines(&lines);
+                } else {
+                    self.preformatted = true;
+                    self.nodes(&tag.contents);
+                    let text = std::mem::take(&mut self.inline);
+                    let lines = text
+                        .trim_start_matches('\n')
+                        .trim_end()
+                        .split('\n')
+                        .map(|l| l.to_owned())
+                        .collect::<Vec<String>>();
+                    self.preformatted = false;
+                    self.write_lines(&lines);
+                }
+                self.block(2);
+            }
+            "table" => {
+                self.block(2);
+                if let Some(table) = HtmlTable::from_tag(tag) {
+                    let lines = match markdown {
+                        true => markdown_table(&table),
+                        false => text_table(&table),
+                    };
+                    if !lines.is_empty() {
+                        self.write_lines(&lines);
+                    }
+                }
+                self.block(2);
+            }
+            "a" => {
+                let href = tag.attributes.get("href").map(|h| h.trim().to_owned());
+                match (href, markdown) {
+                    (Some(href), true) if !href.is_empty() => {
+                        self.open("[");
+                        self.nodes(&tag.contents);
+                        let title = tag.attributes.get("title").map(|t| decode_entities(t));
+                        self.close(&format!("]({})", markdown_link(&href, title.as_deref())));
+                    }
+                    (Some(href), false) => {
+                        self.nodes(&tag.contents);
+                        let text = decode_text(&tag.text());
+                        let internal = href.starts_with('#') || href.starts_with("javascript:");
+                        if self.link_targets && !href.is_empty() && !internal && text != href {
+                            self.space = true;
+                            self.open(&format!("({})", href));
+                        }
+                    }
+                    _ => self.nodes(&tag.contents),
+                }
+            }
+            "img" => {
+                let alt = decode_text(tag.attributes.get("alt").map(|a| a.as_str()).unwrap_or(""));
+                let src = tag.attributes.get("src").map(|s| s.trim()).unwrap_or("");
+                if markdown && !src.is_empty() {
+                    let title = tag.attributes.get("title").map(|t| decode_entities(t));
+                    self.open(&format!(
+                        "![{}]({})",
+                        escape_markdown(&alt, false),
+                        markdown_link(src, title.as_deref())
+                    ));
+                } else if !alt.is_empty() {
+                    self.open(&format!("[{}]", alt));
+                }
+            }
+            "em" | "i" | "cite" | "var" | "dfn" if markdown => self.inline_markup(tag, "*"),
+            "strong" | "b" if markdown => self.inline_markup(tag, "**"),
+            "del" | "s" | "strike" if markdown => self.inline_markup(tag, "~~"),
+            "code" | "kbd" | "samp" | "tt" if markdown => {
+                let code = decode_text(&tag.text());
+                if !code.is_empty() {
+                    let ticks = "`".repeat(longest_run(&code, '`') + 1);
+                    let pad = if code.starts_with('`') || code.ends_with('`') {
+                        " "
+                    } else {
+                        ""
+                    };
+                    self.open(&format!("{}{}{}{}{}", ticks, pad, code, pad, ticks));
+                }
+            }
+            "td" | "th" => {
+                // cells outside of a table
+                self.nodes(&tag.contents);
+                self.space = true;
+            }
+            n if LINE_TAGS.contains(&n) => {
+                self.block(1);
+                self.nodes(&tag.contents);
+                self.block(1);
+            }
+            _ => self.nodes(&tag.contents),
+        }
+    }
+
+    /// Writes an inline tag wrapped in markup such as "**", leaving out empty tags.
+    fn inline_markup(&mut self, tag: &HtmlTag, markup: &str) {
+        if collapse_whitespace(&tag.text()).is_empty() {
+            self.nodes(&tag.contents);
+            return;
+        }
+        self.open(markup);
+        self.nodes(&tag.contents);
+        self.close(markup);
+    }
+
+    fn list(&mut self, list: &HtmlTag, ordered: bool) {
+        let nested = !self.prefixes.is_empty();
+        self.block(if nested { 1 } else { 2 });
+        let mut number = list
+            .attributes
+            .get("start")
+            .and_then(|s| s.trim().parse::<i64>().ok())
+            .unwrap_or(1);
+        for item in list.contents.iter() {
+            let item = match item {
+                HtmlNode::Tag(t) if t.tag.eq_ignore_ascii_case("li") => t,
+                HtmlNode::Tag(t) => {
+                    self.tag(t);
+                    continue;
+                }
+                _ => continue,
+            };
+            let marker = match (ordered, self.mode) {
+                (true, _) => format!("{}. ", number),
+                (false, Mode::Markdown) => "- ".to_owned(),
+                (false, Mode::Text) => "* ".to_owned(),
+            };
+            number += 1;
+            self.block(1);
+            self.prefixes.push(" ".repeat(marker.chars().count()));
+            self.marker = Some(marker);
+            self.nodes(&item.contents);
+            self.flush();
+            if let Some(marker) = self.marker.take() {
+                // the item was empty, so the marker is written on its own
+                self.marker = Some(marker.trim_end().to_owned());
+                self.write_lines(&[String::new()]);
+            }
+            self.prefixes.pop();
+            self.block(1);
+        }
+        self.block(if nested { 1 } else { 2 });
+    }
+}
+
+/// Gets the length of the longest run of a character in text.
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in text.chars() {
+        match ch == c {
+            true => {
+                run += 1;
+                longest = longest.max(run);
+            }
+            false => run = 0,
+        }
+    }
+    longest
+}
+
+/// Wraps text into lines no longer than the width, unless a word is longer.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split(' ').filter(|w| !w.is_empty()) {
+        let length = line.chars().count();
+        if length > 0 && length + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+}
+
+/// Escapes the characters that have a meaning in Markdown. At the start of a line, the
+/// characters that would start a block such as a heading or list are escaped too.
+fn escape_markdown(text: &str, line_start: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    if line_start {
+        if escaped.starts_with(['#', '-', '+', '=']) {
+            escaped.insert(0, '\\');
+        } else {
+            let digits = escaped.chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 && escaped[digits..].starts_with(['.', ')']) {
+                escaped.insert(digits, '\\');
+            }
+        }
+    }
+    escaped
+}
+
+/// Formats the destination and title of a Markdown link.
+fn markdown_link(href: &str, title: Option<&str>) -> String {
+    let href = match href.contains([' ', '(', ')', '<', '>']) {
+        true => format!("<{}>", href.replace('<', "%3C").replace('>', "%3E")),
+        false => href.to_owned(),
+    };
+    match title {
+        Some(title) if !title.is_empty() => {
+            format!("{} \"{}\"", href, title.replace('"', "\\\""))
+        }
+        _ => href,
+    }
+}
+
+/// Lays out a table in columns with a line under the header rows.
+fn text_table(table: &HtmlTable) -> Vec<String> {
+    let rows = table_text(table);
+    let widths = column_widths(&rows);
+    let mut lines = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        if i == table.header_rows && i > 0 {
+            lines.push(
+                widths
+                    .iter()
+                    .map(|w| "-".repeat(*w))
+                    .collect::<Vec<String>>()
+                    .join("  "),
+            );
+        }
+        let cells = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| pad(cell, *width))
+            .collect::<Vec<String>>();
+        lines.push(cells.join("  ").trim_end().to_owned());
+    }
+    lines
+}
+
+/// Writes a table with the GitHub table extension. Tables without a header row use the
+/// first row as the header because the extension needs one.
+fn markdown_table(table: &HtmlTable) -> Vec<String> {
+    let rows = table_text(table)
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|cell| cell.replace('\\', "\\\\").replace('|', "\\|"))
+                .collect::<Vec<String>>()
+        })
+        .collect::<Vec<Vec<String>>>();
+    if rows.is_empty() || table.width() == 0 {
+        return vec![];
+    }
+    let widths = column_widths(&rows)
+        .into_iter()
+        .map(|w| w.max(3))
+        .collect::<Vec<usize>>();
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    // only one header row can be written, so any others become body rows
+    let mut lines = vec![];
+    lines.push(line(
+        rows[0]
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| pad(c, *w))
+            .collect(),
+    ));
+    lines.push(line(widths.iter().map(|w| "-".repeat(*w)).collect()));
+    for row in rows.iter().skip(1) {
+        lines.push(line(
+            row.iter().zip(&widths).map(|(c, w)| pad(c, *w)).collect(),
+        ));
+    }
+    lines
+}
+
+/// Gets the decoded text of every cell. Cells covered by a span are only written in the
+/// first position so the content isn't repeated.
+fn table_text(table: &HtmlTable) -> Vec<Vec<String>> {
+    table
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(c, cell)| match cell.origin {
+                    Some(origin) if origin == (r, c) => decode_entities(&cell.text),
+                    _ => String::new(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths = vec![0; rows.first().map(|r| r.len()).unwrap_or(0)];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    widths
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(text.chars().count()))
+    )
+}
+
+/// Gets the nodes to render for a document, which are the contents of the body if it
+/// has one.
+fn document_nodes(document: &HtmlDocument) -> &[HtmlNode] {
+    let selector = "body".parse::<CssSelector>().unwrap();
+    match document.select_first(&selector).and_then(|n| n.get_node()) {
+        Some(HtmlNode::Tag(body)) => &body.contents,
+        _ => &document.nodes,
+    }
+}
+
+fn render(nodes: &[HtmlNode], mode: Mode, options: &TextOptions) -> String {
+    let mut writer = Writer::new(mode, options);
+    writer.nodes(nodes);
+    writer.finish()
+}
+
+impl HtmlNode {
+    /// Renders the node as plain text with the default [`TextOptions`].
+    pub fn to_plain_text(&self) -> String {
+        self.to_plain_text_with(&TextOptions::default())
+    }
+
+    /// Renders the node as plain text.
+    pub fn to_plain_text_with(&self, options: &TextOptions) -> String {
+        render(std::slice::from_ref(self), Mode::Text, options)
+    }
+
+    /// Renders the node as CommonMark Markdown.
+    pub fn to_markdown(&self) -> String {
+        let options = TextOptions {
+            width: None,
+            link_targets: false,
+        };
+        render(std::slice::from_ref(self), Mode::Markdown, &options)
+    }
+}
+
+impl HtmlDocument {
+    /// Renders the body of the document as plain text with the default [`TextOptions`].
+    pub fn to_plain_text(&self) -> String {
+        self.to_plain_text_with(&TextOptions::default())
+    }
+
+    /// Renders the body of the document as plain text.
+    pub fn to_plain_text_with(&self, options: &TextOptions) -> String {
+        render(document_nodes(self), Mode::Text, options)
+    }
+
+    /// Renders the body of the document as CommonMark Markdown.
+    pub fn to_markdown(&self) -> String {
+        let options = TextOptions {
+            width: None,
+            link_targets: false,
+        };
+        render(document_nodes(self), Mode::Markdown, &options)
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+
+    fn doc(html: &str) -> HtmlDocument {
+        html.parse::<HtmlDocument>().unwrap()
+    }
+
+    #[test]
+    fn plain_text_test() {
+        let d = doc(
+            r##"<html><head><title>T</title><style>p {}</style></head><body>
+<h1>Title &amp; more</h1>
+<p>First   paragraph
+  with <em>inline</em> text.</p><p>Second<br>line</p>
+<ul><li>One</li><li>Two<ul><li>Nested</li></ul></li></ul>
+<ol start="3"><li>Three</li><li></li></ol>
+<blockquote><p>Quoted</p><p>Twice</p></blockquote>
+<pre>  keep
+    this</pre>
+<div>a</div><div>b <a href="#top">top</a> <a href="http://x.org">http://x.org</a> <img alt="pic" src="p.png"></div>
+<script>ignored()</script>
+</body></html>"##,
+        );
+        assert_eq!(
+            d.to_plain_text(),
+            "Title & more
+============
+
+First paragraph with inline text.
+
+Second
+line
+
+* One
+* Two
+  * Nested
+
+3. Three
+4.
+
+> Quoted
+>
+> Twice
+
+  keep
+    this
+
+a
+b top http://x.org [pic]
+"
+        );
+    }
+
+    #[test]
+    fn plain_text_wrap_test() {
+        let d =
+            doc("<ul><li>The quick brown fox jumps over the lazy dog and keeps running</li></ul>");
+        let options = TextOptions {
+            width: Some(30),
+            link_targets: true,
+        };
+        assert_eq!(
+            d.to_plain_text_with(&options),
+            "* The quick brown fox jumps\n  over the lazy dog and keeps\n  running\n"
+        );
+        let options = TextOptions {
+            width: None,
+            ..options
+        };
+        assert_eq!(
+            d.to_plain_text_with(&options),
+            "* The quick brown fox jumps over the lazy dog and keeps running\n"
+        );
+    }
+
+    #[test]
+    fn text_table_test() {
+        let d = doc("<table><tr><th>Name</th><th>Qty</th></tr><tr><td>Flour</td><td>200g</td></tr><tr><td colspan=2>Salt</td></tr></table>");
+        assert_eq!(
+            d.to_plain_text(),
+            "Name   Qty\n-----  ----\nFlour  200g\nSalt\n"
+        );
+        assert_eq!(
+            d.to_markdown(),
+            "| Name  | Qty  |\n| ----- | ---- |\n| Flour | 200g |\n| Salt  |      |\n"
+        );
+    }
+
+    #[test]
+    fn markdown_test() {
+        let d = doc(r#"<h3>Step <i>one</i></h3>
+<p>Use <strong>2*3</strong> cups of <code>flour</code>, <del>not</del> <b></b>sugar.</p>
+<p>1. not a list # and <a href="/a b" title="A &quot;t&quot;">link</a> <img src="i.png" alt="alt [x]"></p>
+<ul><li>a<br>b</li><li><p>para</p></li></ul>
+<blockquote>quote</blockquote>
+<pre><code>let x = `y`;
+</code></pre>
+<hr>"#);
+        assert_eq!(
+            d.to_markdown(),
+            r##"### Step *one*
+
+Use **2\*3** cups of `flour`, ~~not~~ sugar.
+
+1\. not a list # and [link](</a b> "A \"t\"") ![alt \[x\]](i.png)
+
+- a\
+  b
+- para
+
+> quote
+
+```
+let x = `y`;
+```
+
+* * *
+"##
+        );
+    }
+}
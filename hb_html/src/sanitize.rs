@@ -0,0 +1,676 @@
+//! Removes anything which isn't allowed by a [`SanitizePolicy`] from HTML, so that HTML
+//! from elsewhere can be displayed safely.
+//!
+//! The policy is an allowlist: tags, attributes, URL schemes, classes and style properties
+//! are removed unless the policy allows them. Tags which aren't allowed are unwrapped so
+//! their contents stay, except for the tags the policy drops with their contents such as
+//! `<script>`. Everything that is removed is listed in a [`SanitizeReport`].
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::HtmlDocument;
+//! use hb_html::sanitize::SanitizePolicy;
+//! let doc = r#"<p onclick="steal()">Hi <blink>there</blink><script>steal()</script>
+//! <a href="javascript:steal()">click</a> <a href="/ok">ok</a></p>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let (clean, report) = doc.sanitize(&SanitizePolicy::default());
+//! assert_eq!(
+//!     clean.nodes[0].to_html_string(),
+//!     "<p>Hi there\n<a>click</a> <a href=\"/ok\">ok</a></p>"
+//! );
+//! assert_eq!(report.removed.len(), 4);
+//! ```
+use crate::entities::{decode_entities, encode_entities};
+use crate::links::parse_srcset;
+use crate::objects::{HtmlDocument, HtmlNode, HtmlTag};
+use crate::url::Url;
+use std::collections::{HashMap, HashSet};
+
+/// The tags which are dropped with their contents by default, because their contents
+/// isn't meant to be read as text or can't be made safe.
+const DROPPED_TAGS: [&str; 19] = [
+    "applet",
+    "embed",
+    "frame",
+    "frameset",
+    "head",
+    "iframe",
+    "math",
+    "noembed",
+    "noframes",
+    "noscript",
+    "object",
+    "plaintext",
+    "script",
+    "select",
+    "style",
+    "svg",
+    "template",
+    "textarea",
+    "title",
+];
+/// The attributes which contain a URL.
+const URL_ATTRIBUTES: [&str; 13] = [
+    "action",
+    "background",
+    "cite",
+    "codebase",
+    "data",
+    "formaction",
+    "href",
+    "longdesc",
+    "manifest",
+    "ping",
+    "poster",
+    "src",
+    "srcset",
+];
+/// Text in a style value which could load or run something.
+const UNSAFE_STYLE_VALUES: [&str; 6] = [
+    "url(",
+    "expression(",
+    "javascript:",
+    "@import",
+    "behavior:",
+    "\\",
+];
+
+/// What happens to a tag when it is sanitized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagAction {
+    /// The tag is kept, but its attributes are still sanitized.
+    Keep,
+    /// The tag is removed but its contents are kept in its place.
+    Unwrap,
+    /// The tag is removed along with its contents.
+    Drop,
+}
+
+/// Something which was removed by the sanitizer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Removal {
+    /// A tag which was unwrapped or dropped.
+    Tag { tag: String, action: TagAction },
+    /// An attribute which wasn't allowed on the tag or had an unsafe URL.
+    Attribute {
+        tag: String,
+        name: String,
+        value: String,
+    },
+    /// A class which wasn't allowed.
+    Class { tag: String, class: String },
+    /// A declaration in a style attribute which wasn't allowed.
+    Style { tag: String, declaration: String },
+    /// A comment.
+    Comment(String),
+}
+
+/// Lists everything removed by the sanitizer, in document order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SanitizeReport {
+    pub removed: Vec<Removal>,
+}
+
+impl SanitizeReport {
+    /// True if nothing was removed.
+    pub fn is_clean(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+/// The rules for what the sanitizer keeps.
+///
+/// [`SanitizePolicy::new`] allows nothing, while the default policy allows the common
+/// formatting tags, links and images. Both drop the tags in `drop_tags` with their contents.
+///
+/// # Example
+///
+/// ```
+/// use hb_html::objects::HtmlDocument;
+/// use hb_html::sanitize::SanitizePolicy;
+/// let policy = SanitizePolicy::new()
+///     .allow_tags(&["code", "span"])
+///     .allow_attributes("*", &["class"])
+///     .allow_classes(&["language-*"]);
+/// let doc = r#"<code class="language-rust big">x</code>"#
+///     .parse::<HtmlDocument>()
+///     .unwrap();
+/// let (nodes, _) = policy.sanitize(&doc.nodes);
+/// assert_eq!(nodes[0].to_html_string(), r#"<code class="language-rust">x</code>"#);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizePolicy {
+    /// The tags which are kept.
+    pub tags: HashSet<String>,
+    /// The tags which are dropped with their contents if they aren't in `tags`. Other tags
+    /// are unwrapped.
+    pub drop_tags: HashSet<String>,
+    /// The attributes allowed on each tag. Attributes for the tag "*" are allowed on every
+    /// tag. Classes and ids are allowed with the "class" and "id" attributes.
+    pub attributes: HashMap<String, HashSet<String>>,
+    /// The URL schemes allowed in attributes which contain a URL, such as "https".
+    pub url_schemes: HashSet<String>,
+    /// Whether URLs without a scheme, such as "/page" or "#top", are allowed.
+    pub relative_urls: bool,
+    /// The classes which are kept, or None to keep every class. A class ending in '*'
+    /// allows every class starting with the rest of it.
+    pub classes: Option<HashSet<String>>,
+    /// The properties kept in style attributes, or None to keep every property. Values
+    /// which could load a URL or run a script are always removed.
+    pub styles: Option<HashSet<String>>,
+    /// Whether comments are kept.
+    pub comments: bool,
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> SanitizePolicy {
+        SanitizePolicy::new()
+            .allow_tags(&[
+                "a",
+                "abbr",
+                "b",
+                "blockquote",
+                "br",
+                "caption",
+                "cite",
+                "code",
+                "col",
+                "colgroup",
+                "dd",
+                "del",
+                "details",
+                "dfn",
+                "div",
+                "dl",
+                "dt",
+                "em",
+                "figcaption",
+                "figure",
+                "h1",
+                "h2",
+                "h3",
+                "h4",
+                "h5",
+                "h6",
+                "hr",
+                "i",
+                "img",
+                "ins",
+                "kbd",
+                "li",
+                "mark",
+                "ol",
+                "p",
+                "pre",
+                "q",
+                "s",
+                "samp",
+                "small",
+                "span",
+                "strong",
+                "sub",
+                "summary",
+                "sup",
+                "table",
+                "tbody",
+                "td",
+                "tfoot",
+                "th",
+                "thead",
+                "time",
+                "tr",
+                "u",
+                "ul",
+            ])
+            .allow_attributes("*", &["dir", "lang", "title"])
+            .allow_attributes("a", &["href"])
+            .allow_attributes("img", &["alt", "height", "src", "width"])
+            .allow_attributes("blockquote", &["cite"])
+            .allow_attributes("del", &["cite", "datetime"])
+            .allow_attributes("ins", &["cite", "datetime"])
+            .allow_attributes("q", &["cite"])
+            .allow_attributes("ol", &["reversed", "start", "type"])
+            .allow_attributes("td", &["colspan", "rowspan"])
+            .allow_attributes("th", &["colspan", "rowspan", "scope"])
+            .allow_attributes("col", &["span"])
+            .allow_attributes("colgroup", &["span"])
+            .allow_attributes("time", &["datetime"])
+            .allow_url_schemes(&["http", "https", "mailto"])
+    }
+}
+
+impl SanitizePolicy {
+    /// Creates a policy which allows nothing and drops tags such as `<script>` with
+    /// their contents.
+    pub fn new() -> SanitizePolicy {
+        SanitizePolicy {
+            tags: HashSet::new(),
+            drop_tags: DROPPED_TAGS.iter().map(|t| t.to_string()).collect(),
+            attributes: HashMap::new(),
+            url_schemes: HashSet::new(),
+            relative_urls: true,
+            classes: None,
+            styles: None,
+            comments: false,
+        }
+    }
+
+    /// Allows the tags.
+    pub fn allow_tags(mut self, tags: &[&str]) -> SanitizePolicy {
+        self.tags
+            .extend(tags.iter().map(|t| t.to_ascii_lowercase()));
+        self
+    }
+
+    /// Drops the tags with their contents instead of unwrapping them.
+    pub fn drop_tags(mut self, tags: &[&str]) -> SanitizePolicy {
+        self.drop_tags
+            .extend(tags.iter().map(|t| t.to_ascii_lowercase()));
+        self
+    }
+
+    /// Allows the attributes on a tag, or on every tag if the tag is "*".
+    pub fn allow_attributes(mut self, tag: &str, attributes: &[&str]) -> SanitizePolicy {
+        self.attributes
+            .entry(tag.to_ascii_lowercase())
+            .or_default()
+            .extend(attributes.iter().map(|a| a.to_ascii_lowercase()));
+        self
+    }
+
+    /// Allows the URL schemes, such as "https".
+    pub fn allow_url_schemes(mut self, schemes: &[&str]) -> SanitizePolicy {
+        self.url_schemes
+            .extend(schemes.iter().map(|s| s.to_ascii_lowercase()));
+        self
+    }
+
+    /// Only keeps these classes, and any allowed before. See [`SanitizePolicy::classes`].
+    pub fn allow_classes(mut self, classes: &[&str]) -> SanitizePolicy {
+        self.classes
+            .get_or_insert_with(HashSet::new)
+            .extend(classes.iter().map(|c| c.to_string()));
+        self
+    }
+
+    /// Only keeps these style properties, and any allowed before.
+    pub fn allow_styles(mut self, properties: &[&str]) -> SanitizePolicy {
+        self.styles
+            .get_or_insert_with(HashSet::new)
+            .extend(properties.iter().map(|p| p.to_ascii_lowercase()));
+        self
+    }
+
+    /// Gets what happens to a tag.
+    pub fn tag_action(&self, tag: &str) -> TagAction {
+        let tag = tag.to_ascii_lowercase();
+        if self.tags.contains(&tag) {
+            TagAction::Keep
+        } else if self.drop_tags.contains(&tag) {
+            TagAction::Drop
+        } else {
+            TagAction::Unwrap
+        }
+    }
+
+    /// True if the attribute is allowed on the tag. This doesn't check its value.
+    pub fn allows_attribute(&self, tag: &str, attribute: &str) -> bool {
+        let attribute = attribute.to_ascii_lowercase();
+        [tag.to_ascii_lowercase().as_str(), "*"]
+            .iter()
+            .filter_map(|t| self.attributes.get(*t))
+            .any(|allowed| allowed.contains(&attribute))
+    }
+
+    /// True if the URL can be used in an attribute.
+    pub fn allows_url(&self, url: &str) -> bool {
+        let url = decode_entities(url)
+            .chars()
+            .filter(|c| !c.is_ascii_control() && *c != ' ')
+            .collect::<String>();
+        match Url::parse_reference(&url).scheme {
+            Some(scheme) => self.url_schemes.contains(&scheme),
+            // a colon before the path means something that only looks like a scheme
+            None => {
+                let first = url.split(['/', '?', '#']).next().unwrap_or("");
+                self.relative_urls && !first.contains(':')
+            }
+        }
+    }
+
+    /// True if the class is kept.
+    pub fn allows_class(&self, class: &str) -> bool {
+        match &self.classes {
+            None => true,
+            Some(classes) => classes
+                .iter()
+                .any(|allowed| match allowed.strip_suffix('*') {
+                    Some(prefix) => class.starts_with(prefix),
+                    None => allowed == class,
+                }),
+        }
+    }
+
+    /// Sanitizes the nodes, returning the clean nodes and what was removed.
+    pub fn sanitize(&self, nodes: &[HtmlNode]) -> (Vec<HtmlNode>, SanitizeReport) {
+        let mut report = SanitizeReport::default();
+        let mut clean = vec![];
+        self.sanitize_nodes(nodes, &mut clean, &mut report);
+        (clean, report)
+    }
+
+    fn sanitize_nodes(
+        &self,
+        nodes: &[HtmlNode],
+        clean: &mut Vec<HtmlNode>,
+        report: &mut SanitizeReport,
+    ) {
+        for node in nodes {
+            match node {
+                HtmlNode::Text(t) => clean.push(HtmlNode::Text(t.clone())),
+                HtmlNode::Comment(c) if self.comments => clean.push(HtmlNode::Comment(c.clone())),
+                HtmlNode::Comment(c) => report.removed.push(Removal::Comment(c.clone())),
+                HtmlNode::Tag(tag) => match self.tag_action(&tag.tag) {
+                    TagAction::Keep => clean.push(HtmlNode::Tag(self.sanitize_tag(tag, report))),
+                    action => {
+                        report.removed.push(Removal::Tag {
+                            tag: tag.tag.clone(),
+                            action,
+                        });
+                        if action == TagAction::Unwrap {
+                            self.sanitize_nodes(&tag.contents, clean, report);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn sanitize_tag(&self, tag: &HtmlTag, report: &mut SanitizeReport) -> HtmlTag {
+        let mut clean = HtmlTag::new(&tag.tag);
+        let removed_attribute = |name: &str, value: &str| Removal::Attribute {
+            tag: tag.tag.clone(),
+            name: name.to_owned(),
+            value: value.to_owned(),
+        };
+        match self.allows_attribute(&tag.tag, "id") {
+            true => clean.ids = tag.ids.iter().map(|id| escape_value(id)).collect(),
+            false if !tag.ids.is_empty() => {
+                report
+                    .removed
+                    .push(removed_attribute("id", &tag.ids.join(" ")));
+            }
+            false => (),
+        }
+        match self.allows_attribute(&tag.tag, "class") {
+            true => {
+                for class in tag.classes.iter() {
+                    match self.allows_class(class) {
+                        true => clean.classes.push(escape_value(class)),
+                        false => report.removed.push(Removal::Class {
+                            tag: tag.tag.clone(),
+                            class: class.clone(),
+                        }),
+                    }
+                }
+            }
+            false if !tag.classes.is_empty() => {
+                report
+                    .removed
+                    .push(removed_attribute("class", &tag.classes.join(" ")));
+            }
+            false => (),
+        }
+        // sorted so the report is in the same order every time
+        let mut attributes = tag.attributes.iter().collect::<Vec<(&String, &String)>>();
+        attributes.sort();
+        for (name, value) in attributes {
+            let lower = name.to_ascii_lowercase();
+            let allowed = self.allows_attribute(&tag.tag, &lower)
+                && match lower.as_str() {
+                    "srcset" => parse_srcset(value)
+                        .iter()
+                        .all(|(url, _)| self.allows_url(url)),
+                    n if URL_ATTRIBUTES.contains(&n) => self.allows_url(value),
+                    _ => true,
+                };
+            if !allowed {
+                report.removed.push(removed_attribute(name, value));
+            } else if lower == "style" {
+                let style = self.sanitize_style(tag, value, report);
+                if !style.is_empty() {
+                    clean.attributes.insert(name.clone(), escape_value(&style));
+                }
+            } else {
+                clean.attributes.insert(name.clone(), escape_value(value));
+            }
+        }
+        self.sanitize_nodes(&tag.contents, &mut clean.contents, report);
+        clean
+    }
+
+    /// Removes the declarations of a style attribute which aren't allowed.
+    fn sanitize_style(&self, tag: &HtmlTag, style: &str, report: &mut SanitizeReport) -> String {
+        let mut kept = vec![];
+        for declaration in style.split(';').map(|d| d.trim()).filter(|d| !d.is_empty()) {
+            let allowed = match declaration.split_once(':') {
+                Some((property, value)) => {
+                    let property = property.trim().to_ascii_lowercase();
+                    let value = decode_entities(value).to_ascii_lowercase();
+                    let value = value.split_whitespace().collect::<String>();
+                    let safe = !UNSAFE_STYLE_VALUES.iter().any(|u| value.contains(u));
+                    let allowed = match &self.styles {
+                        None => true,
+                        Some(styles) => styles.contains(&property),
+                    };
+                    safe && allowed
+                }
+                None => false,
+            };
+            match allowed {
+                true => kept.push(declaration),
+                false => report.removed.push(Removal::Style {
+                    tag: tag.tag.clone(),
+                    declaration: declaration.to_owned(),
+                }),
+            }
+        }
+        kept.join("; ")
+    }
+}
+
+/// Encodes the characters which could end a quoted attribute value or start a tag, as
+/// values are written inside `"` without being escaped. The value is decoded first, so
+/// references which are already encoded aren't encoded twice.
+fn escape_value(value: &str) -> String {
+    encode_entities(&decode_entities(value))
+}
+
+impl HtmlDocument {
+    /// Creates a copy of the document with everything removed that the policy doesn't
+    /// allow, along with a report of what was removed.
+    pub fn sanitize(&self, policy: &SanitizePolicy) -> (HtmlDocument, SanitizeReport) {
+        let (nodes, report) = policy.sanitize(&self.nodes);
+        let document = HtmlDocument {
+            doctype: self.doctype.clone(),
+            nodes,
+        };
+        (document, report)
+    }
+}
+
+#[cfg(test)]
+mod sanitize_tests {
+    use super::*;
+
+    fn sanitize(html: &str, policy: &SanitizePolicy) -> (Vec<HtmlNode>, SanitizeReport) {
+        let doc = html.parse::<HtmlDocument>().unwrap();
+        let (clean, report) = doc.sanitize(policy);
+        (clean.nodes, report)
+    }
+
+    fn nodes(html: &str) -> Vec<HtmlNode> {
+        html.parse::<HtmlDocument>().unwrap().nodes
+    }
+
+    #[test]
+    fn sanitize_default_test() {
+        let policy = SanitizePolicy::default();
+        let (html, report) = sanitize(
+            r##"<html><head><title>T</title></head><body><!-- c --><div id="x" class="y"><img src="a.png" onerror="x()" alt="a"><form action="/f"><input name="q"></form><iframe src="https://x.org">no</iframe><style>p{}</style><a href=" JaVa&#x09;script:alert(1)">j</a><a href="data:text/html,x">d</a><a href="vbscript:x">v</a><a href="mailto:a@b.org">m</a><a href="#top">t</a></div></body></html>"##,
+            &policy,
+        );
+        assert_eq!(
+            html,
+            nodes(
+                r##"<div><img src="a.png" alt="a"><a>j</a><a>d</a><a>v</a><a href="mailto:a@b.org">m</a><a href="#top">t</a></div>"##
+            )
+        );
+        let removed_tags = report
+            .removed
+            .iter()
+            .filter_map(|r| match r {
+                Removal::Tag { tag, action } => Some((tag.as_str(), *action)),
+                _ => None,
+            })
+            .collect::<Vec<(&str, TagAction)>>();
+        assert_eq!(
+            removed_tags,
+            vec![
+                ("html", TagAction::Unwrap),
+                ("head", TagAction::Drop),
+                ("body", TagAction::Unwrap),
+                ("form", TagAction::Unwrap),
+                ("input", TagAction::Unwrap),
+                ("iframe", TagAction::Drop),
+                ("style", TagAction::Drop),
+            ]
+        );
+        assert!(report.removed.contains(&Removal::Comment(" c ".to_owned())));
+        assert!(report.removed.contains(&Removal::Attribute {
+            tag: "img".to_owned(),
+            name: "onerror".to_owned(),
+            value: "x()".to_owned()
+        }));
+        assert!(report.removed.contains(&Removal::Attribute {
+            tag: "div".to_owned(),
+            name: "class".to_owned(),
+            value: "y".to_owned()
+        }));
+        assert!(!report.is_clean());
+        let (_, report) = sanitize("<p>fine <b>text</b></p>", &policy);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn sanitize_policy_test() {
+        let policy = SanitizePolicy::new()
+            .allow_tags(&["p", "span", "img", "script"])
+            .allow_attributes("*", &["class", "style", "id"])
+            .allow_attributes("img", &["srcset"])
+            .allow_url_schemes(&["https"])
+            .allow_classes(&["note", "icon-*"])
+            .allow_styles(&["color", "background"])
+            .drop_tags(&["aside"]);
+        let (html, report) = sanitize(
+            r#"<p id="a" class="note big icon-x" style="color: red; position: fixed; background: url(x.png)">t<aside>gone</aside><span>s</span><!-- c --><script>kept()</script><img srcset="https://x.org/a.png 1x, http://x.org/b.png 2x"></p>"#,
+            &policy,
+        );
+        assert_eq!(
+            html,
+            nodes(
+                r#"<p id="a" class="note icon-x" style="color: red">t<span>s</span><script>kept()</script><img></p>"#
+            )
+        );
+        assert_eq!(
+            report.removed,
+            vec![
+                Removal::Class {
+                    tag: "p".to_owned(),
+                    class: "big".to_owned()
+                },
+                Removal::Style {
+                    tag: "p".to_owned(),
+                    declaration: "position: fixed".to_owned()
+                },
+                Removal::Style {
+                    tag: "p".to_owned(),
+                    declaration: "background: url(x.png)".to_owned()
+                },
+                Removal::Tag {
+                    tag: "aside".to_owned(),
+                    action: TagAction::Drop
+                },
+                Removal::Comment(" c ".to_owned()),
+                Removal::Attribute {
+                    tag: "img".to_owned(),
+                    name: "srcset".to_owned(),
+                    value: "https://x.org/a.png 1x, http://x.org/b.png 2x".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sanitize_attribute_quotes_test() {
+        let policy = SanitizePolicy::default();
+        let doc = r#"<p><a href="/ok" title=x"/onmouseover=alert(1)//>hi</a></p>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let (clean, report) = doc.sanitize(&policy);
+        let html = clean.nodes[0].to_html_string();
+        assert!(
+            html.contains(r#"title="x&quot;/onmouseover=alert(1)//""#),
+            "{}",
+            html
+        );
+        assert!(!html.contains(r#"x"/"#), "{}", html);
+        assert!(report.is_clean());
+
+        let (html, _) = sanitize(
+            r#"<p class=a"b style=color:red"/onclick=x() title="a &amp; b <c>">t</p>"#,
+            &SanitizePolicy::new()
+                .allow_tags(&["p"])
+                .allow_attributes("p", &["class", "style", "title"]),
+        );
+        match &html[0] {
+            HtmlNode::Tag(p) => {
+                assert_eq!(p.classes, vec!["a&quot;b"]);
+                assert_eq!(p.attributes["title"], "a &amp; b &lt;c&gt;");
+                assert_eq!(p.attributes["style"], "color:red&quot;/onclick=x()");
+            }
+            n => panic!("expected a tag, got {:?}", n),
+        }
+    }
+
+    #[test]
+    fn allows_url_test() {
+        let policy = SanitizePolicy::default();
+        for url in [
+            "https://x.org",
+            "/a:b",
+            "page?q=a:b",
+            "#x",
+            "MAILTO:a@b.org",
+            "",
+        ] {
+            assert!(policy.allows_url(url), "{}", url);
+        }
+        for url in [
+            "javascript:x",
+            "java\nscript:x",
+            "&#106;avascript:x",
+            "data:x",
+            "a b:x",
+        ] {
+            assert!(!policy.allows_url(url), "{}", url);
+        }
+        let policy = SanitizePolicy {
+            relative_urls: false,
+            ..policy
+        };
+        assert!(!policy.allows_url("/page"));
+    }
+}
@@ -0,0 +1,492 @@
+//! Matching many CSS selectors against a HTML document in a single traversal.
+//!
+//! The rules of every selector are put into buckets by the id, class or tag of their
+//! rightmost selector item, so only the rules which could possibly match a tag are checked.
+//! A bloom filter of the ids, classes and tags of the current ancestors is kept during the
+//! traversal so that rules which need an ancestor that is not there are skipped without
+//! walking up the tree.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::HtmlDocument;
+//! use hb_html::selector_set::CompiledSelectorSet;
+//! let doc = r#"<html><body><div class=post><a href="/a">A</a></div><a href="/b">B</a></body></html>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let set = CompiledSelectorSet::from_strs(&["div.post a", "a[href]", "p"]).unwrap();
+//! let matches = doc.match_selector_set(&set);
+//! assert_eq!(matches.len(), 2);
+//! assert_eq!(matches[0].selectors, vec![0, 1]);
+//! assert_eq!(matches[1].selectors, vec![1]);
+//! ```
+use crate::error::ParseHtmlError;
+use crate::objects::{
+    CssSelector, CssSelectorItem, CssSelectorRelationship, CssSelectorRule, HtmlDocument, HtmlNode,
+    HtmlTag,
+};
+use crate::querying::HtmlQueryResult;
+use hb_selector::selector::split_css_type;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+const BLOOM_BITS: usize = 12;
+const BLOOM_SIZE: usize = 1 << BLOOM_BITS;
+const BLOOM_MASK: u64 = (BLOOM_SIZE as u64) - 1;
+
+/// The different parts of a tag that are stored in the ancestor bloom filter.
+#[derive(Hash)]
+enum BloomKey<'s> {
+    Tag(&'s str),
+    Id(&'s str),
+    Class(&'s str),
+}
+
+impl<'s> BloomKey<'s> {
+    fn hash_value(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// A counting bloom filter holding the tags, ids and classes of the ancestors of the node
+/// currently being visited. Uses two hash functions taken from the halves of one 64 bit hash.
+struct AncestorBloomFilter {
+    counters: Vec<u8>,
+}
+
+impl AncestorBloomFilter {
+    fn new() -> AncestorBloomFilter {
+        AncestorBloomFilter {
+            counters: vec![0; BLOOM_SIZE],
+        }
+    }
+
+    fn indexes(hash: u64) -> (usize, usize) {
+        (
+            (hash & BLOOM_MASK) as usize,
+            ((hash >> 32) & BLOOM_MASK) as usize,
+        )
+    }
+
+    fn insert(&mut self, hash: u64) {
+        let (a, b) = Self::indexes(hash);
+        self.counters[a] = self.counters[a].saturating_add(1);
+        self.counters[b] = self.counters[b].saturating_add(1);
+    }
+
+    fn remove(&mut self, hash: u64) {
+        let (a, b) = Self::indexes(hash);
+        // a saturated counter can no longer be decremented safely
+        if self.counters[a] != u8::MAX {
+            self.counters[a] -= 1;
+        }
+        if self.counters[b] != u8::MAX {
+            self.counters[b] -= 1;
+        }
+    }
+
+    fn might_contain(&self, hash: u64) -> bool {
+        let (a, b) = Self::indexes(hash);
+        self.counters[a] != 0 && self.counters[b] != 0
+    }
+}
+
+fn tag_hashes(tag: &HtmlTag) -> Vec<u64> {
+    let mut hashes = Vec::with_capacity(1 + tag.ids.len() + tag.classes.len());
+    hashes.push(BloomKey::Tag(tag.local_name()).hash_value());
+    for id in &tag.ids {
+        hashes.push(BloomKey::Id(id).hash_value());
+    }
+    for class in &tag.classes {
+        hashes.push(BloomKey::Class(class).hash_value());
+    }
+    hashes
+}
+
+fn item_hashes(item: &CssSelectorItem, hashes: &mut Vec<u64>) {
+    if let Some(tag) = &item.tag {
+        let name = split_css_type(tag).1;
+        if name != "*" {
+            hashes.push(BloomKey::Tag(name).hash_value());
+        }
+    }
+    if let Some(ids) = &item.ids {
+        for id in ids {
+            hashes.push(BloomKey::Id(id).hash_value());
+        }
+    }
+    if let Some(classes) = &item.classes {
+        for class in classes {
+            hashes.push(BloomKey::Class(class).hash_value());
+        }
+    }
+}
+
+/// A single rule from one of the selectors in the set.
+struct CompiledRule {
+    /// The index of the selector the rule came from.
+    selector: usize,
+    rule: CssSelectorRule,
+    /// The hashes of everything the ancestors of a matching node must have.
+    ancestor_hashes: Vec<u64>,
+}
+
+/// The selectors in a [`CompiledSelectorSet`] that matched a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectorSetMatch<'a> {
+    /// The node that matched.
+    pub node: HtmlQueryResult<'a>,
+    /// The indexes of the selectors that matched, in ascending order.
+    pub selectors: Vec<usize>,
+}
+
+/// A set of CSS selectors prepared so that they can all be matched against a document in
+/// a single traversal. The selectors are referred to by the index they were added at.
+pub struct CompiledSelectorSet {
+    selectors: Vec<CssSelector>,
+    rules: Vec<CompiledRule>,
+    by_id: HashMap<String, Vec<usize>>,
+    by_class: HashMap<String, Vec<usize>>,
+    by_tag: HashMap<String, Vec<usize>>,
+    universal: Vec<usize>,
+    any_selectors: Vec<usize>,
+}
+
+impl Default for CompiledSelectorSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompiledSelectorSet {
+    /// Creates an empty [`CompiledSelectorSet`].
+    pub fn new() -> CompiledSelectorSet {
+        CompiledSelectorSet {
+            selectors: vec![],
+            rules: vec![],
+            by_id: HashMap::new(),
+            by_class: HashMap::new(),
+            by_tag: HashMap::new(),
+            universal: vec![],
+            any_selectors: vec![],
+        }
+    }
+
+    /// Creates a [`CompiledSelectorSet`] by parsing each of the selector strings.
+    pub fn from_strs(selectors: &[&str]) -> Result<CompiledSelectorSet, ParseHtmlError> {
+        let mut set = CompiledSelectorSet::new();
+        for s in selectors {
+            set.add(CssSelector::from_str(s)?);
+        }
+        Ok(set)
+    }
+
+    /// Adds a selector to the set and returns the index used to refer to it in matches.
+    pub fn add(&mut self, selector: CssSelector) -> usize {
+        let index = self.selectors.len();
+        match &selector {
+            CssSelector::Any => self.any_selectors.push(index),
+            CssSelector::Specific(rules) => {
+                for rule in rules {
+                    self.add_rule(index, rule.clone());
+                }
+            }
+        }
+        self.selectors.push(selector);
+        index
+    }
+
+    fn add_rule(&mut self, selector: usize, rule: CssSelectorRule) {
+        let rule_index = self.rules.len();
+        let mut ancestor_hashes = vec![];
+        for relationship in &rule.rules {
+            match relationship {
+                CssSelectorRelationship::Parent(item) | CssSelectorRelationship::Ancestor(item) => {
+                    item_hashes(item, &mut ancestor_hashes)
+                }
+                _ => (),
+            }
+        }
+        // bucket by the most selective part of the rightmost item
+        let bucket = match rule.rules.last().map(|r| r.item()) {
+            None => None,
+            Some(item) => {
+                if let Some(id) = item.ids.as_ref().and_then(|ids| ids.first()) {
+                    Some(self.by_id.entry(id.clone()).or_default())
+                } else if let Some(class) = item.classes.as_ref().and_then(|c| c.first()) {
+                    Some(self.by_class.entry(class.clone()).or_default())
+                } else {
+                    match item.tag.as_deref().map(|t| split_css_type(t).1) {
+                        Some(name) if name != "*" => {
+                            Some(self.by_tag.entry(name.to_owned()).or_default())
+                        }
+                        _ => Some(&mut self.universal),
+                    }
+                }
+            }
+        };
+        // a rule without any items can never match
+        if let Some(bucket) = bucket {
+            bucket.push(rule_index);
+            self.rules.push(CompiledRule {
+                selector,
+                rule,
+                ancestor_hashes,
+            });
+        }
+    }
+
+    /// The number of selectors in the set.
+    pub fn len(&self) -> usize {
+        self.selectors.len()
+    }
+
+    /// Checks if there are no selectors in the set.
+    pub fn is_empty(&self) -> bool {
+        self.selectors.is_empty()
+    }
+
+    /// Gets the selector at the index provided.
+    pub fn get(&self, index: usize) -> Option<&CssSelector> {
+        self.selectors.get(index)
+    }
+
+    /// Gets the indexes of the rules which could match the tag based on the buckets.
+    fn candidate_rules(&self, tag: &HtmlTag, candidates: &mut Vec<usize>) {
+        candidates.clear();
+        for id in &tag.ids {
+            if let Some(rules) = self.by_id.get(id) {
+                candidates.extend_from_slice(rules);
+            }
+        }
+        for class in &tag.classes {
+            if let Some(rules) = self.by_class.get(class) {
+                candidates.extend_from_slice(rules);
+            }
+        }
+        if let Some(rules) = self.by_tag.get(tag.local_name()) {
+            candidates.extend_from_slice(rules);
+        }
+        candidates.extend_from_slice(&self.universal);
+        // duplicate ids or classes on the tag can add a rule twice
+        candidates.sort_unstable();
+        candidates.dedup();
+    }
+
+    /// Gets the indexes of every selector in the set which matches the node.
+    pub fn matches(&self, node: &HtmlQueryResult) -> Vec<usize> {
+        let tag = match node.get_node() {
+            Some(HtmlNode::Tag(t)) => t,
+            _ => return vec![],
+        };
+        let mut candidates = vec![];
+        self.candidate_rules(tag, &mut candidates);
+        self.confirm(node, &candidates, None)
+    }
+
+    fn confirm(
+        &self,
+        node: &HtmlQueryResult,
+        candidates: &[usize],
+        bloom: Option<&AncestorBloomFilter>,
+    ) -> Vec<usize> {
+        let mut selectors = self.any_selectors.clone();
+        for &rule_index in candidates {
+            let compiled = &self.rules[rule_index];
+            if selectors.contains(&compiled.selector) {
+                continue;
+            }
+            if let Some(bloom) = bloom {
+                if !compiled
+                    .ancestor_hashes
+                    .iter()
+                    .all(|h| bloom.might_contain(*h))
+                {
+                    continue;
+                }
+            }
+            if node.matches_selector_rule(&compiled.rule) {
+                selectors.push(compiled.selector);
+            }
+        }
+        selectors.sort_unstable();
+        selectors
+    }
+
+    /// Walks every node once and returns each tag that matched at least one selector
+    /// along with the indexes of the selectors it matched, in document order.
+    pub fn match_nodes<'a>(&self, nodes: &'a Vec<HtmlNode>) -> Vec<SelectorSetMatch<'a>> {
+        let mut matches = vec![];
+        if nodes.is_empty() {
+            return matches;
+        }
+        let mut res = HtmlQueryResult {
+            path: vec![(nodes, 0)],
+        };
+        let mut bloom = AncestorBloomFilter::new();
+        let mut candidates = vec![];
+        self.match_level(&mut res, &mut bloom, &mut candidates, &mut matches);
+        matches
+    }
+
+    /// Matches each node in the vector at the bottom of the path and then its children.
+    fn match_level<'a>(
+        &self,
+        res: &mut HtmlQueryResult<'a>,
+        bloom: &mut AncestorBloomFilter,
+        candidates: &mut Vec<usize>,
+        matches: &mut Vec<SelectorSetMatch<'a>>,
+    ) {
+        let level = res.path.len() - 1;
+        let nodes = res.path[level].0;
+        for (i, node) in nodes.iter().enumerate() {
+            let tag = match node {
+                HtmlNode::Tag(t) => t,
+                _ => continue,
+            };
+            res.path[level].1 = i;
+            self.candidate_rules(tag, candidates);
+            if !candidates.is_empty() || !self.any_selectors.is_empty() {
+                let selectors = self.confirm(res, candidates, Some(bloom));
+                if !selectors.is_empty() {
+                    matches.push(SelectorSetMatch {
+                        node: res.clone(),
+                        selectors,
+                    });
+                }
+            }
+            if !tag.contents.is_empty() {
+                let hashes = tag_hashes(tag);
+                for h in &hashes {
+                    bloom.insert(*h);
+                }
+                res.path.push((&tag.contents, 0));
+                self.match_level(res, bloom, candidates, matches);
+                res.path.pop();
+                for h in &hashes {
+                    bloom.remove(*h);
+                }
+            }
+        }
+    }
+}
+
+impl HtmlDocument {
+    /// Matches every selector in the set against the document in a single traversal.
+    /// See [`CompiledSelectorSet::match_nodes`].
+    pub fn match_selector_set(&self, set: &CompiledSelectorSet) -> Vec<SelectorSetMatch<'_>> {
+        set.match_nodes(&self.nodes)
+    }
+}
+
+#[cfg(test)]
+mod selector_set_tests {
+    use super::*;
+
+    const DOC: &str = r#"<html><head><title>Test</title></head>
+<body id=top class="page home">
+    <div id=main class="content wide">
+        <h1 class=title>Heading</h1>
+        <p class="intro lead">Intro <a href="/one" class=ext>one</a></p>
+        <ul class=list>
+            <li class=item>A</li>
+            <li class="item odd">B</li>
+            <li class=item><div class=content><a href="/two">two</a></div></li>
+        </ul>
+        <p>Last <span><a href="/three">three</a></span></p>
+    </div>
+    <footer><a href="/four" class=ext>four</a></footer>
+</body></html>"#;
+
+    #[test]
+    fn selector_set_matches_find_test() {
+        let doc = DOC.parse::<HtmlDocument>().unwrap();
+        let selectors = vec![
+            "a",
+            "div a",
+            "#main > p a",
+            "body.home div.content a.ext",
+            "li.item + li.odd",
+            "h1 ~ ul li",
+            "li:first-child",
+            "li:not(.odd)",
+            "*",
+            "ul.list > li > div.content > a",
+            "footer a[href^=/f]",
+            "p span a, h1.title",
+            "section a",
+            ".content .content a",
+        ];
+        let set = CompiledSelectorSet::from_strs(&selectors).unwrap();
+        let matches = doc.match_selector_set(&set);
+        for (i, s) in selectors.iter().enumerate() {
+            // the set only reports tags while find("*") also returns text nodes
+            let expected = doc
+                .find(s)
+                .results
+                .into_iter()
+                .filter(|r| matches!(r.get_node(), Some(HtmlNode::Tag(_))))
+                .collect::<Vec<HtmlQueryResult>>();
+            let found = matches
+                .iter()
+                .filter(|m| m.selectors.contains(&i))
+                .map(|m| m.node.clone())
+                .collect::<Vec<HtmlQueryResult>>();
+            assert_eq!(found, expected, "selector {}", s);
+        }
+    }
+
+    #[test]
+    fn selector_set_backtracking_matches_find_test() {
+        // the nearest p before the span is not preceded by the h1, so the sibling
+        // combinator has to try the p before it as well
+        let doc = "<div><h1>A</h1><p>B</p><p>C</p><span>D</span></div>"
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let selectors = ["h1 + p ~ span", "div > h1 ~ p + p"];
+        let set = CompiledSelectorSet::from_strs(&selectors).unwrap();
+        let matches = doc.match_selector_set(&set);
+        for (i, s) in selectors.iter().enumerate() {
+            let found = matches
+                .iter()
+                .filter(|m| m.selectors.contains(&i))
+                .map(|m| m.node.clone())
+                .collect::<Vec<HtmlQueryResult>>();
+            assert_eq!(found.len(), 1, "selector {}", s);
+            assert_eq!(found, doc.find(s).results, "selector {}", s);
+            assert!(found[0].matches(&s.parse::<CssSelector>().unwrap()));
+        }
+    }
+
+    #[test]
+    fn selector_set_single_node_test() {
+        let doc = DOC.parse::<HtmlDocument>().unwrap();
+        let set = CompiledSelectorSet::from_strs(&["li", "li.odd", "ul li.item", "p li"]).unwrap();
+        let odd = doc.find("li.odd").results.remove(0);
+        assert_eq!(set.matches(&odd), vec![0, 1, 2]);
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.get(1), Some(&"li.odd".parse::<CssSelector>().unwrap()));
+    }
+
+    #[test]
+    fn ancestor_bloom_filter_test() {
+        let mut bloom = AncestorBloomFilter::new();
+        let div = BloomKey::Tag("div").hash_value();
+        let main = BloomKey::Id("main").hash_value();
+        assert!(!bloom.might_contain(div));
+        bloom.insert(div);
+        bloom.insert(div);
+        bloom.insert(main);
+        assert!(bloom.might_contain(div));
+        assert!(bloom.might_contain(main));
+        bloom.remove(div);
+        assert!(bloom.might_contain(div));
+        bloom.remove(div);
+        assert!(!bloom.might_contain(div));
+        assert!(bloom.might_contain(main));
+    }
+}
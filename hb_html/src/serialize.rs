@@ -0,0 +1,341 @@
+//! Serde support for the HTML object model and CSS selectors, enabled with the `serde`
+//! feature.
+//!
+//! The JSON shape is stable, and it follows the DOM-JSON style of `{tag, attrs, children}`:
+//!
+//! * A [`HtmlDocument`] is `{"doctype": "html", "children": [...]}`.
+//! * A [`HtmlTag`], or a tag node, is `{"tag": "a", "attrs": {...}, "children": [...]}`.
+//!   The ids and classes are in the attrs as the `id` and `class` attributes, joined with
+//!   spaces, and the attrs are written in order of their names. Tags parsed as XML also
+//!   have a `"namespace"` when they are in one.
+//! * A text node is a string, `"text"`.
+//! * A comment node is `{"comment": "text"}`.
+//!
+//! When reading, `doctype`, `attrs` and `children` can be left out and other keys are
+//! ignored. Text and attribute values are kept as they are in the tree, so character
+//! references are not decoded.
+//!
+//! A [`CssSelector`](crate::objects::CssSelector) is written as the selector text, as are [`CssSelectorRule`](crate::objects::CssSelectorRule),
+//! [`CssSelectorItem`](crate::objects::CssSelectorItem), [`CssRefiner`](crate::objects::CssRefiner) (`":nth-child(2n+1)"`), [`CssRefinerNumberType`](crate::objects::CssRefinerNumberType)
+//! (`"2n+1"`), [`CssAttributeCompareType`](crate::objects::CssAttributeCompareType) (`"[href^=http]"`) and [`CssRegex`](crate::objects::CssRegex) (the
+//! pattern). They are parsed again when read, so an invalid selector is an error.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::{CssSelector, HtmlDocument};
+//! let doc = r#"<p id=intro class="big note">Hi <!-- c --><br></p>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let json = serde_json::to_string(&doc).unwrap();
+//! assert_eq!(
+//!     json,
+//!     concat!(
+//!         r#"{"doctype":"","children":[{"tag":"p","attrs":{"class":"big note","id":"intro"},"#,
+//!         r#""children":["Hi ",{"comment":" c "},{"tag":"br","attrs":{},"children":[]}]}]}"#
+//!     )
+//! );
+//! assert_eq!(serde_json::from_str::<HtmlDocument>(&json).unwrap(), doc);
+//!
+//! let selector = "ul > li:nth-child(odd) a[href]".parse::<CssSelector>().unwrap();
+//! let json = serde_json::to_string(&selector).unwrap();
+//! assert_eq!(json, r#""ul > li:nth-child(odd) a[href]""#);
+//! assert_eq!(serde_json::from_str::<CssSelector>(&json).unwrap(), selector);
+//! ```
+use crate::objects::{HtmlDocument, HtmlNode, HtmlTag};
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeStruct, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+
+impl Serialize for HtmlDocument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut document = serializer.serialize_struct("HtmlDocument", 2)?;
+        document.serialize_field("doctype", &self.doctype)?;
+        document.serialize_field("children", &self.nodes)?;
+        document.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HtmlDocument {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(DocumentVisitor)
+    }
+}
+
+struct DocumentVisitor;
+
+impl<'de> Visitor<'de> for DocumentVisitor {
+    type Value = HtmlDocument;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object with a doctype and children")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<HtmlDocument, A::Error> {
+        let mut document = HtmlDocument::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "doctype" => document.doctype = map.next_value()?,
+                "children" => document.nodes = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(document)
+    }
+}
+
+impl Serialize for HtmlTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = if self.namespace.is_some() { 4 } else { 3 };
+        let mut tag = serializer.serialize_struct("HtmlTag", fields)?;
+        tag.serialize_field("tag", &self.tag)?;
+        if let Some(namespace) = &self.namespace {
+            tag.serialize_field("namespace", namespace)?;
+        }
+        tag.serialize_field("attrs", &TagAttributes(self))?;
+        tag.serialize_field("children", &self.contents)?;
+        tag.end()
+    }
+}
+
+/// Writes the ids, classes and attributes of a tag as one map, in order of their names.
+struct TagAttributes<'a>(&'a HtmlTag);
+
+impl Serialize for TagAttributes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tag = self.0;
+        let mut attributes = vec![];
+        if !tag.ids.is_empty() {
+            attributes.push(("id", tag.ids.join(" ")));
+        }
+        if !tag.classes.is_empty() {
+            attributes.push(("class", tag.classes.join(" ")));
+        }
+        for (name, value) in tag.attributes.iter() {
+            attributes.push((name.as_str(), value.clone()));
+        }
+        // the id and class from the lists win over any in the attributes
+        attributes.sort_by(|a, b| a.0.cmp(b.0));
+        attributes.dedup_by(|a, b| a.0 == b.0);
+        let mut map = serializer.serialize_map(Some(attributes.len()))?;
+        for (name, value) in attributes.iter() {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HtmlTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match deserializer.deserialize_map(NodeVisitor)? {
+            HtmlNode::Tag(tag) => Ok(tag),
+            _ => Err(de::Error::custom("expected an object with a tag")),
+        }
+    }
+}
+
+impl Serialize for HtmlNode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            HtmlNode::Tag(tag) => tag.serialize(serializer),
+            HtmlNode::Text(text) => serializer.serialize_str(text),
+            HtmlNode::Comment(comment) => {
+                let mut node = serializer.serialize_struct("HtmlComment", 1)?;
+                node.serialize_field("comment", comment)?;
+                node.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HtmlNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = HtmlNode;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string of text or an object with a tag or a comment")
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<HtmlNode, E> {
+        Ok(HtmlNode::Text(text.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, text: String) -> Result<HtmlNode, E> {
+        Ok(HtmlNode::Text(text))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<HtmlNode, A::Error> {
+        let mut name: Option<String> = None;
+        let mut comment: Option<String> = None;
+        let mut namespace: Option<String> = None;
+        let mut attributes: HashMap<String, String> = HashMap::new();
+        let mut contents: Vec<HtmlNode> = vec![];
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "tag" => name = Some(map.next_value()?),
+                "comment" => comment = Some(map.next_value()?),
+                "namespace" => namespace = map.next_value()?,
+                "attrs" => attributes = map.next_value()?,
+                "children" => contents = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        match (name, comment) {
+            (Some(name), None) => {
+                let mut tag = HtmlTag::new(&name);
+                if let Some(ids) = attributes.remove("id") {
+                    tag.ids = ids.split_whitespace().map(|id| id.to_owned()).collect();
+                }
+                if let Some(classes) = attributes.remove("class") {
+                    tag.classes = classes.split_whitespace().map(|c| c.to_owned()).collect();
+                }
+                tag.attributes = attributes;
+                tag.contents = contents;
+                tag.namespace = namespace;
+                Ok(HtmlNode::Tag(tag))
+            }
+            (None, Some(comment)) => Ok(HtmlNode::Comment(comment)),
+            (Some(_), Some(_)) => Err(de::Error::custom(
+                "a node can't have both a tag and a comment",
+            )),
+            (None, None) => Err(de::Error::custom("a node needs a tag or a comment")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod serialize_tests {
+    use super::*;
+    use crate::objects::{
+        CssAttributeCompareType, CssRefiner, CssRefinerNumberType, CssRegex, CssSelector,
+        CssSelectorItem, CssSelectorRule,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn html_json_test() {
+        let doc = r#"<!DOCTYPE html><html><body><a href="/x" id="a b" class=link>Go<!-- c --></a></body></html>"#
+            .parse::<HtmlDocument>()
+            .unwrap();
+        let value = serde_json::to_value(&doc).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "doctype": "html",
+                "children": [{"tag": "html", "attrs": {}, "children": [
+                    {"tag": "body", "attrs": {}, "children": [
+                        {
+                            "tag": "a",
+                            "attrs": {"class": "link", "href": "/x", "id": "a b"},
+                            "children": ["Go", {"comment": " c "}]
+                        }
+                    ]}
+                ]}]
+            })
+        );
+        assert_eq!(serde_json::from_value::<HtmlDocument>(value).unwrap(), doc);
+        // the attrs are in order so the same tree always gives the same JSON
+        let json = serde_json::to_string(&doc).unwrap();
+        assert!(json.contains(r#"{"class":"link","href":"/x","id":"a b"}"#));
+    }
+
+    #[test]
+    fn dom_json_test() {
+        let node = serde_json::from_value::<HtmlNode>(json!({
+            "tag": "div",
+            "nodeType": 1,
+            "attrs": {"class": " a  b ", "title": "t"},
+            "children": [{"tag": "br"}, "text", {"comment": "c"}]
+        }))
+        .unwrap();
+        let expected = HtmlTag::new("div")
+            .classes(vec!["a".to_owned(), "b".to_owned()])
+            .attributes(vec![("title", "t")])
+            .contents(vec![
+                HtmlNode::Tag(HtmlTag::new("br")),
+                HtmlNode::Text("text".to_owned()),
+                HtmlNode::Comment("c".to_owned()),
+            ]);
+        assert_eq!(node, HtmlNode::Tag(expected.clone()));
+        let tag: HtmlTag =
+            serde_json::from_value(serde_json::to_value(&expected).unwrap()).unwrap();
+        assert_eq!(tag, expected);
+        let doc: HtmlDocument = serde_json::from_str(r#"{"children": ["x"]}"#).unwrap();
+        assert_eq!(doc.doctype, "");
+        assert_eq!(doc.nodes, vec![HtmlNode::Text("x".to_owned())]);
+
+        let svg = HtmlDocument::parse_xml(r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#).unwrap();
+        let json = serde_json::to_value(&svg.nodes[0]).unwrap();
+        assert_eq!(json["namespace"], "http://www.w3.org/2000/svg");
+        assert_eq!(
+            serde_json::from_value::<HtmlNode>(json).unwrap(),
+            svg.nodes[0]
+        );
+
+        assert!(serde_json::from_str::<HtmlNode>(r#"{"attrs": {}}"#).is_err());
+        assert!(serde_json::from_str::<HtmlNode>(r#"{"tag": "a", "comment": "c"}"#).is_err());
+        assert!(serde_json::from_str::<HtmlTag>(r#""text""#).is_err());
+        assert!(serde_json::from_str::<HtmlNode>("3").is_err());
+    }
+
+    #[test]
+    fn css_json_test() {
+        for text in [
+            "*",
+            "div.item > a[href^=http]",
+            "ul li:nth-child(2n+1), p:not(.x):first-child",
+            r#"a:contains("Next \"page\"") ~ b + i#id"#,
+            "p:matches(/\\d+/)",
+        ] {
+            let selector = text.parse::<CssSelector>().unwrap();
+            let json = serde_json::to_string(&selector).unwrap();
+            assert_eq!(
+                serde_json::from_str::<CssSelector>(&json).unwrap(),
+                selector
+            );
+        }
+        let refiner: CssRefiner = serde_json::from_str(r#"":nth-last-of-type(odd)""#).unwrap();
+        assert_eq!(
+            refiner,
+            CssRefiner::NthLastOfType(CssRefinerNumberType::Odd)
+        );
+        assert_eq!(
+            serde_json::to_string(&CssRefinerNumberType::Functional((3, 1))).unwrap(),
+            r#""3n+1""#
+        );
+        assert_eq!(
+            serde_json::from_str::<CssRefinerNumberType>(r#""4""#).unwrap(),
+            CssRefinerNumberType::Specific(4)
+        );
+        let attribute = CssAttributeCompareType::EndsWith(("src".to_owned(), ".png".to_owned()));
+        let json = serde_json::to_string(&attribute).unwrap();
+        assert_eq!(json, r#""[src$=.png]""#);
+        assert_eq!(
+            serde_json::from_str::<CssAttributeCompareType>(&json).unwrap(),
+            attribute
+        );
+        let rule: CssSelectorRule = serde_json::from_str(r#""div > p""#).unwrap();
+        assert_eq!(rule.rules.len(), 2);
+        let regex: CssRegex = serde_json::from_str(r#""^a+$""#).unwrap();
+        assert!(regex.is_match("aaa"));
+
+        assert!(serde_json::from_str::<CssSelector>(r#""p:frist-child""#).is_err());
+        assert!(serde_json::from_str::<CssSelectorRule>(r#""a, b""#).is_err());
+        assert!(serde_json::from_str::<CssSelectorItem>(r#""a b""#).is_err());
+        assert!(serde_json::from_str::<CssRefiner>(r#""p:empty""#).is_err());
+        assert!(serde_json::from_str::<CssRefinerNumberType>(r#""x""#).is_err());
+        assert!(serde_json::from_str::<CssRegex>(r#""(""#).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Reads `sitemap.xml` files, both the `<urlset>` of pages and the `<sitemapindex>` of
+//! other sitemaps, as well as plain text sitemaps with one URL on each line.
+//!
+//! Sitemaps are parsed with [`HtmlDocument::parse_xml_lenient`] and the elements are
+//! matched by their local names, so sitemaps with a wrong or missing namespace can still
+//! be read. Entries without a `<loc>` are skipped, and values which are not valid are
+//! left out. The `robots.txt` of a site, which lists its sitemaps, can be read with
+//! [`hb_parse::robots::RobotsTxt`].
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::sitemap::{ChangeFrequency, Sitemap};
+//! let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+//! <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+//!   <url>
+//!     <loc>https://example.com/recipes/soup?size=large&amp;hot=1</loc>
+//!     <lastmod>2024-03-01</lastmod>
+//!     <changefreq>weekly</changefreq>
+//!     <priority>0.8</priority>
+//!   </url>
+//! </urlset>"#;
+//! let sitemap = Sitemap::parse(xml, None).unwrap();
+//! assert!(!sitemap.is_index());
+//! let url = &sitemap.urls[0];
+//! assert_eq!(url.loc.to_string(), "https://example.com/recipes/soup?size=large&hot=1");
+//! assert_eq!(url.lastmod.unwrap().to_string(), "2024-03-01T00:00:00Z");
+//! assert_eq!(url.changefreq, Some(ChangeFrequency::Weekly));
+//! assert_eq!(url.priority, Some(0.8));
+//! ```
+use crate::entities::decode_entities;
+use crate::error::ParseHtmlError;
+use crate::feed::FeedDate;
+use crate::objects::{HtmlDocument, HtmlNode, HtmlTag};
+use crate::reading::{children, resolve};
+use crate::url::Url;
+use std::str::FromStr;
+
+/// A sitemap, which has either pages in its urls or other sitemaps in its sitemaps.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Sitemap {
+    /// The pages of a `<urlset>` or a text sitemap.
+    pub urls: Vec<SitemapUrl>,
+    /// The sitemaps of a `<sitemapindex>`.
+    pub sitemaps: Vec<SitemapIndexEntry>,
+}
+
+/// A page from a sitemap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapUrl {
+    pub loc: Url,
+    /// When the page was last changed. W3C dates with only a year or month are the start
+    /// of that year or month.
+    pub lastmod: Option<FeedDate>,
+    pub changefreq: Option<ChangeFrequency>,
+    /// The priority of the page compared to the other pages of the site, from 0 to 1.
+    pub priority: Option<f32>,
+}
+
+/// A sitemap listed in a sitemap index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapIndexEntry {
+    pub loc: Url,
+    pub lastmod: Option<FeedDate>,
+}
+
+/// How often a page is likely to change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeFrequency {
+    Always,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    Never,
+}
+
+impl FromStr for ChangeFrequency {
+    type Err = ParseHtmlError;
+    fn from_str(frequency: &str) -> Result<Self, <Self as std::str::FromStr>::Err> {
+        match frequency.trim().to_ascii_lowercase().as_str() {
+            "always" => Ok(ChangeFrequency::Always),
+            "hourly" => Ok(ChangeFrequency::Hourly),
+            "daily" => Ok(ChangeFrequency::Daily),
+            "weekly" => Ok(ChangeFrequency::Weekly),
+            "monthly" => Ok(ChangeFrequency::Monthly),
+            "yearly" => Ok(ChangeFrequency::Yearly),
+            "never" => Ok(ChangeFrequency::Never),
+            _ => Err(ParseHtmlError::with_msg(format!(
+                "{} is not a sitemap change frequency",
+                frequency
+            ))),
+        }
+    }
+}
+
+impl Sitemap {
+    /// Parses a XML or plain text sitemap, resolving its URLs against the URL of the
+    /// sitemap.
+    pub fn parse(sitemap: &str, sitemap_url: Option<&Url>) -> Result<Sitemap, ParseHtmlError> {
+        let text = sitemap.trim_start_matches('\u{feff}').trim_start();
+        if !text.starts_with('<') {
+            return Ok(text_sitemap(text, sitemap_url));
+        }
+        HtmlDocument::parse_xml_lenient(text)?
+            .sitemap(sitemap_url)
+            .ok_or_else(|| ParseHtmlError::with_msg("the document is not a sitemap"))
+    }
+
+    /// True if this is a sitemap index, which lists other sitemaps.
+    pub fn is_index(&self) -> bool {
+        !self.sitemaps.is_empty() && self.urls.is_empty()
+    }
+}
+
+impl HtmlDocument {
+    /// Reads the document as a [`Sitemap`], resolving its URLs against the URL of the
+    /// sitemap. It is None when the root element is not a `<urlset>` or `<sitemapindex>`.
+    pub fn sitemap(&self, sitemap_url: Option<&Url>) -> Option<Sitemap> {
+        let root = self.nodes.iter().find_map(|n| match n {
+            HtmlNode::Tag(t) => Some(t),
+            _ => None,
+        })?;
+        let mut sitemap = Sitemap::default();
+        match root.local_name() {
+            "urlset" => {
+                for url in children(root).filter(|t| t.local_name() == "url") {
+                    let mut loc = None;
+                    let mut lastmod = None;
+                    let mut changefreq = None;
+                    let mut priority = None;
+                    for tag in children(url) {
+                        let value = text(tag);
+                        match tag.local_name() {
+                            "loc" => loc = resolve(sitemap_url, &value),
+                            "lastmod" => lastmod = parse_w3c_date(&value),
+                            "changefreq" => changefreq = value.parse::<ChangeFrequency>().ok(),
+                            "priority" => {
+                                priority = value
+                                    .parse::<f32>()
+                                    .ok()
+                                    .filter(|p| (0.0..=1.0).contains(p))
+                            }
+                            _ => (),
+                        }
+                    }
+                    if let Some(loc) = loc {
+                        sitemap.urls.push(SitemapUrl {
+                            loc,
+                            lastmod,
+                            changefreq,
+                            priority,
+                        });
+                    }
+                }
+            }
+            "sitemapindex" => {
+                for entry in children(root).filter(|t| t.local_name() == "sitemap") {
+                    let mut loc = None;
+                    let mut lastmod = None;
+                    for tag in children(entry) {
+                        match tag.local_name() {
+                            "loc" => loc = resolve(sitemap_url, &text(tag)),
+                            "lastmod" => lastmod = parse_w3c_date(&text(tag)),
+                            _ => (),
+                        }
+                    }
+                    if let Some(loc) = loc {
+                        sitemap.sitemaps.push(SitemapIndexEntry { loc, lastmod });
+                    }
+                }
+            }
+            _ => return None,
+        }
+        Some(sitemap)
+    }
+}
+
+/// Reads a text sitemap, which has one URL on each line.
+fn text_sitemap(text: &str, sitemap_url: Option<&Url>) -> Sitemap {
+    let urls = text
+        .lines()
+        .filter_map(|line| resolve(sitemap_url, line))
+        .map(|loc| SitemapUrl {
+            loc,
+            lastmod: None,
+            changefreq: None,
+            priority: None,
+        })
+        .collect();
+    Sitemap {
+        urls,
+        sitemaps: vec![],
+    }
+}
+
+/// Parses a W3C date, which is a RFC 3339 date or only the year, month or day.
+fn parse_w3c_date(date: &str) -> Option<FeedDate> {
+    match date.len() {
+        4 => FeedDate::parse(&format!("{}-01-01", date)),
+        7 => FeedDate::parse(&format!("{}-01", date)),
+        _ => FeedDate::parse(date),
+    }
+}
+
+fn text(tag: &HtmlTag) -> String {
+    decode_entities(tag.text().trim())
+}
+
+#[cfg(test)]
+mod sitemap_tests {
+    use super::*;
+
+    #[test]
+    fn sitemap_urlset_test() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+  xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+  <url><loc>https://example.com/</loc><priority>1.0</priority></url>
+  <url>
+    <loc> /recipes/soup </loc>
+    <lastmod>2024-03-01T10:15:00+01:00</lastmod>
+    <changefreq>Daily</changefreq>
+    <priority>2</priority>
+    <image:image><image:loc>https://example.com/soup.jpg</image:loc></image:image>
+  </url>
+  <url><lastmod>2024</lastmod></url>
+  <url><loc>/old</loc><lastmod>2019-07</lastmod><changefreq>sometimes</changefreq></url>
+</urlset>"#;
+        let base = Url::parse("https://example.com/sitemap.xml").unwrap();
+        let sitemap = Sitemap::parse(xml, Some(&base)).unwrap();
+        assert!(!sitemap.is_index());
+        assert_eq!(sitemap.urls.len(), 3);
+        assert_eq!(sitemap.urls[0].priority, Some(1.0));
+        let soup = &sitemap.urls[1];
+        assert_eq!(soup.loc.to_string(), "https://example.com/recipes/soup");
+        assert_eq!(
+            soup.lastmod.unwrap().to_string(),
+            "2024-03-01T10:15:00+01:00"
+        );
+        assert_eq!(soup.changefreq, Some(ChangeFrequency::Daily));
+        assert_eq!(soup.priority, None);
+        let old = &sitemap.urls[2];
+        assert_eq!(old.lastmod.unwrap().to_string(), "2019-07-01T00:00:00Z");
+        assert_eq!(old.changefreq, None);
+    }
+
+    #[test]
+    fn sitemap_index_test() {
+        let xml = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-1.xml.gz</loc><lastmod>2024-01-02</lastmod></sitemap>
+  <sitemap><loc>https://example.com/sitemap-2.xml?a=1&b=2</loc></sitemap>
+</sitemapindex>"#;
+        let sitemap = Sitemap::parse(xml, None).unwrap();
+        assert!(sitemap.is_index());
+        assert_eq!(
+            sitemap.sitemaps,
+            vec![
+                SitemapIndexEntry {
+                    loc: Url::parse("https://example.com/sitemap-1.xml.gz").unwrap(),
+                    lastmod: FeedDate::parse("2024-01-02"),
+                },
+                SitemapIndexEntry {
+                    loc: Url::parse("https://example.com/sitemap-2.xml?a=1&b=2").unwrap(),
+                    lastmod: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sitemap_text_test() {
+        let base = Url::parse("https://example.com/sitemap.txt").unwrap();
+        let sitemap =
+            Sitemap::parse("\u{feff}https://example.com/a\r\n\r\n/b\n", Some(&base)).unwrap();
+        let urls: Vec<String> = sitemap.urls.iter().map(|u| u.loc.to_string()).collect();
+        assert_eq!(urls, vec!["https://example.com/a", "https://example.com/b"]);
+
+        assert!(Sitemap::parse("<rss><channel/></rss>", None).is_err());
+        assert_eq!(Sitemap::parse("", None).unwrap(), Sitemap::default());
+    }
+}
@@ -0,0 +1,839 @@
+//! Extracts the JSON-LD, Microdata and RDFa in a document into a common tree of items.
+//!
+//! Each item has its types, an optional id and a list of properties in document order.
+//! Property names are kept as they were written, so the schema.org `name` property is
+//! "name" whichever format it came from. Types are expanded to full IRIs where the format
+//! gives a vocabulary, so "Recipe" in JSON-LD with a schema.org context becomes
+//! "https://schema.org/Recipe". Use [`StructuredItem::has_type`] to match either form.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::HtmlDocument;
+//! let doc = r#"<div itemscope itemtype="https://schema.org/Recipe">
+//! <h1 itemprop="name">Pancakes</h1>
+//! <div itemprop="author" itemscope itemtype="https://schema.org/Person">
+//!   <span itemprop="name">Ann</span>
+//! </div>
+//! </div>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let items = doc.structured_data(None);
+//! assert!(items[0].has_type("Recipe"));
+//! assert_eq!(items[0].text("name"), Some("Pancakes"));
+//! assert_eq!(items[0].item("author").and_then(|a| a.text("name")), Some("Ann"));
+//! ```
+use crate::entities::decode_entities;
+use crate::json::JsonValue;
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use crate::url::Url;
+use std::collections::{HashMap, HashSet};
+
+/// The format an item was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StructuredSource {
+    JsonLd,
+    Microdata,
+    Rdfa,
+}
+
+/// The value of a property of a [`StructuredItem`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructuredValue {
+    /// Text, including numbers and booleans from JSON-LD.
+    Text(String),
+    /// A URL from an attribute such as href, or a JSON-LD `{"@id": ...}` reference.
+    /// It is resolved against the base URL of the document when there is one, and
+    /// JSON-LD blank nodes such as "_:b0" are left as written.
+    Url(String),
+    /// A nested item.
+    Item(StructuredItem),
+}
+
+impl StructuredValue {
+    /// Gets the text of a text or URL value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            StructuredValue::Text(t) | StructuredValue::Url(t) => Some(t),
+            StructuredValue::Item(_) => None,
+        }
+    }
+
+    /// Gets the item of a nested item value.
+    pub fn as_item(&self) -> Option<&StructuredItem> {
+        match self {
+            StructuredValue::Item(i) => Some(i),
+            _ => None,
+        }
+    }
+}
+
+/// An item of structured data such as a schema.org Recipe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuredItem {
+    pub source: StructuredSource,
+    pub types: Vec<String>,
+    /// The itemid, `@id`, or RDFa resource of the item.
+    pub id: Option<String>,
+    /// The properties of the item. A property can have more than one value.
+    pub properties: Vec<(String, StructuredValue)>,
+}
+
+impl StructuredItem {
+    fn new(source: StructuredSource) -> StructuredItem {
+        StructuredItem {
+            source,
+            types: vec![],
+            id: None,
+            properties: vec![],
+        }
+    }
+
+    /// True if the item has the type, either as the full IRI or as the last part of it,
+    /// so "Recipe" matches "https://schema.org/Recipe".
+    pub fn has_type(&self, name: &str) -> bool {
+        self.types.iter().any(|t| {
+            t == name
+                || t.rsplit(['/', '#', ':'])
+                    .next()
+                    .map(|short| short == name)
+                    .unwrap_or(false)
+        })
+    }
+
+    /// Gets every value of the property.
+    pub fn values<'i>(&'i self, name: &'i str) -> impl Iterator<Item = &'i StructuredValue> {
+        self.properties
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Gets the first value of the property.
+    pub fn value(&self, name: &str) -> Option<&StructuredValue> {
+        self.properties
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Gets the first text or URL value of the property.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .filter(|(n, _)| n == name)
+            .find_map(|(_, v)| v.as_str())
+    }
+
+    /// Gets the first nested item of the property.
+    pub fn item(&self, name: &str) -> Option<&StructuredItem> {
+        self.properties
+            .iter()
+            .filter(|(n, _)| n == name)
+            .find_map(|(_, v)| v.as_item())
+    }
+
+    /// Finds this item and every nested item with the type, depth first.
+    pub fn find_type<'i>(&'i self, name: &str, found: &mut Vec<&'i StructuredItem>) {
+        if self.has_type(name) {
+            found.push(self);
+        }
+        for (_, value) in &self.properties {
+            if let StructuredValue::Item(item) = value {
+                item.find_type(name, found);
+            }
+        }
+    }
+}
+
+impl HtmlDocument {
+    /// Gets the JSON-LD, Microdata and RDFa items in the document, in that order.
+    /// URLs are resolved against [`HtmlDocument::base_url`].
+    pub fn structured_data(&self, page_url: Option<&Url>) -> Vec<StructuredItem> {
+        let mut items = self.json_ld(page_url);
+        items.extend(self.microdata(page_url));
+        items.extend(self.rdfa(page_url));
+        items
+    }
+
+    /// Gets the items with the type from all of the structured data, including nested
+    /// items. See [`StructuredItem::has_type`].
+    pub fn structured_items_of_type(
+        &self,
+        name: &str,
+        page_url: Option<&Url>,
+    ) -> Vec<StructuredItem> {
+        let items = self.structured_data(page_url);
+        let mut found = vec![];
+        for item in &items {
+            item.find_type(name, &mut found);
+        }
+        found.into_iter().cloned().collect()
+    }
+
+    /// Gets the items in the `<script type="application/ld+json">` tags. Scripts which are
+    /// not valid JSON are skipped, and `@graph` lists are flattened into separate items.
+    /// `@id` values are resolved against [`HtmlDocument::base_url`].
+    pub fn json_ld(&self, page_url: Option<&Url>) -> Vec<StructuredItem> {
+        let base = self.base_url(page_url);
+        let selector = "script[type]".parse::<CssSelector>().unwrap();
+        let mut items = vec![];
+        for node in self.select(&selector) {
+            let tag = match node.get_node() {
+                Some(HtmlNode::Tag(t)) => t,
+                _ => continue,
+            };
+            let mime = tag.attributes["type"].split(';').next().unwrap_or_default();
+            if !mime.trim().eq_ignore_ascii_case("application/ld+json") {
+                continue;
+            }
+            // some sites wrap the json in a comment or CDATA section for old browsers
+            let text = tag
+                .contents
+                .iter()
+                .map(|n| match n {
+                    HtmlNode::Comment(c) => c.clone(),
+                    n => n.text(),
+                })
+                .collect::<String>();
+            let text = text
+                .trim()
+                .trim_start_matches("<![CDATA[")
+                .trim_end_matches("]]>");
+            if let Ok(json) = text.parse::<JsonValue>() {
+                json_ld_items(&json, None, base.as_ref(), &mut items);
+            }
+        }
+        items
+    }
+
+    /// Gets the top level Microdata items, which are the tags with an itemscope attribute
+    /// that are not the value of an itemprop.
+    pub fn microdata(&self, page_url: Option<&Url>) -> Vec<StructuredItem> {
+        let base = self.base_url(page_url);
+        let mut ids = HashMap::new();
+        let mut positions = HashMap::new();
+        collect_ids(&self.nodes, &mut ids, &mut positions);
+        let mut roots = vec![];
+        find_microdata_roots(&self.nodes, &mut roots);
+        let reader = MicrodataReader {
+            ids,
+            positions,
+            base: base.as_ref(),
+        };
+        roots
+            .into_iter()
+            .map(|root| reader.item(root, &mut vec![]))
+            .collect()
+    }
+
+    /// Gets the RDFa items, which are the tags with a typeof attribute. Properties which
+    /// are not inside an item, such as Open Graph meta tags, are put in an item for the
+    /// document which has no types and comes first.
+    pub fn rdfa(&self, page_url: Option<&Url>) -> Vec<StructuredItem> {
+        let base = self.base_url(page_url);
+        let mut reader = RdfaReader {
+            base: base.as_ref(),
+            items: vec![],
+        };
+        let mut document = StructuredItem::new(StructuredSource::Rdfa);
+        let context = RdfaContext {
+            vocab: None,
+            prefixes: default_rdfa_prefixes(),
+        };
+        reader.walk(&self.nodes, &context, &mut document);
+        if !document.properties.is_empty() {
+            document.id = base.as_ref().map(|b| b.to_string());
+            reader.items.insert(0, document);
+        }
+        reader.items
+    }
+}
+
+/// Gets the vocabulary from a JSON-LD @context, which is either a URL or an object with
+/// a @vocab.
+fn json_ld_vocab(context: &JsonValue) -> Option<String> {
+    match context {
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Object(_) => context
+            .get("@vocab")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_owned()),
+        JsonValue::Array(values) => values.iter().find_map(json_ld_vocab),
+        _ => None,
+    }
+}
+
+/// Expands a term with a vocabulary unless it is already an IRI or compact IRI.
+fn expand_term(term: &str, vocab: Option<&str>) -> String {
+    match vocab {
+        Some(vocab) if !term.contains(':') => match vocab.ends_with('/') || vocab.ends_with('#') {
+            true => format!("{}{}", vocab, term),
+            false => format!("{}/{}", vocab, term),
+        },
+        _ => term.to_owned(),
+    }
+}
+
+/// Adds the items in a JSON-LD value, which can be an object, an array of objects or an
+/// object with a @graph.
+fn json_ld_items(
+    json: &JsonValue,
+    vocab: Option<&str>,
+    base: Option<&Url>,
+    items: &mut Vec<StructuredItem>,
+) {
+    match json {
+        JsonValue::Array(values) => {
+            for value in values {
+                json_ld_items(value, vocab, base, items);
+            }
+        }
+        JsonValue::Object(_) => {
+            let vocab = json
+                .get("@context")
+                .and_then(json_ld_vocab)
+                .or(vocab.map(|v| v.to_owned()));
+            match json.get("@graph") {
+                Some(graph) => json_ld_items(graph, vocab.as_deref(), base, items),
+                None => items.push(json_ld_item(json, vocab.as_deref(), base)),
+            }
+        }
+        _ => (),
+    }
+}
+
+fn json_ld_item(json: &JsonValue, vocab: Option<&str>, base: Option<&Url>) -> StructuredItem {
+    let vocab = json
+        .get("@context")
+        .and_then(json_ld_vocab)
+        .or(vocab.map(|v| v.to_owned()));
+    let vocab = vocab.as_deref();
+    let mut item = StructuredItem::new(StructuredSource::JsonLd);
+    for (key, value) in json.as_object().unwrap_or_default() {
+        match key.as_str() {
+            "@type" => {
+                let types = match value {
+                    JsonValue::Array(a) => a.iter().filter_map(|t| t.as_str()).collect(),
+                    _ => value.as_str().into_iter().collect::<Vec<&str>>(),
+                };
+                item.types = types.into_iter().map(|t| expand_term(t, vocab)).collect();
+            }
+            "@id" => item.id = value.as_str().map(|i| resolve_json_ld_id(base, i)),
+            k if k.starts_with('@') => (),
+            _ => json_ld_values(key, value, vocab, base, &mut item.properties),
+        }
+    }
+    item
+}
+
+fn json_ld_values(
+    key: &str,
+    value: &JsonValue,
+    vocab: Option<&str>,
+    base: Option<&Url>,
+    properties: &mut Vec<(String, StructuredValue)>,
+) {
+    let value = match value {
+        JsonValue::Null => return,
+        JsonValue::Array(values) => {
+            for value in values {
+                json_ld_values(key, value, vocab, base, properties);
+            }
+            return;
+        }
+        JsonValue::Object(entries) => match (value.get("@value"), value.get("@id")) {
+            (Some(v), _) => match v {
+                JsonValue::String(s) => StructuredValue::Text(s.clone()),
+                v => StructuredValue::Text(v.to_string()),
+            },
+            (None, Some(JsonValue::String(id))) if entries.len() == 1 => {
+                StructuredValue::Url(resolve_json_ld_id(base, id))
+            }
+            _ => StructuredValue::Item(json_ld_item(value, vocab, base)),
+        },
+        JsonValue::String(s) => StructuredValue::Text(s.clone()),
+        v => StructuredValue::Text(v.to_string()),
+    };
+    properties.push((key.to_owned(), value));
+}
+
+/// Resolves a JSON-LD `@id` against the base URL, unless it is a blank node such as "_:b0"
+/// which is only an id inside of the document.
+fn resolve_json_ld_id(base: Option<&Url>, id: &str) -> String {
+    match id.starts_with("_:") {
+        true => id.to_owned(),
+        false => resolve_url(base, id),
+    }
+}
+
+/// Maps every id in the document to the first tag with it, and every tag to its position
+/// in document order.
+fn collect_ids<'d>(
+    nodes: &'d [HtmlNode],
+    ids: &mut HashMap<&'d str, &'d HtmlTag>,
+    positions: &mut HashMap<*const HtmlTag, usize>,
+) {
+    for node in nodes {
+        if let HtmlNode::Tag(t) = node {
+            for id in &t.ids {
+                ids.entry(id.as_str()).or_insert(t);
+            }
+            positions.insert(t as *const HtmlTag, positions.len());
+            collect_ids(&t.contents, ids, positions);
+        }
+    }
+}
+
+fn find_microdata_roots<'d>(nodes: &'d [HtmlNode], roots: &mut Vec<&'d HtmlTag>) {
+    for node in nodes {
+        if let HtmlNode::Tag(t) = node {
+            if t.attributes.contains_key("itemscope") && !t.attributes.contains_key("itemprop") {
+                roots.push(t);
+            }
+            find_microdata_roots(&t.contents, roots);
+        }
+    }
+}
+
+/// Reads Microdata items following the algorithm in the HTML spec.
+struct MicrodataReader<'d> {
+    ids: HashMap<&'d str, &'d HtmlTag>,
+    positions: HashMap<*const HtmlTag, usize>,
+    base: Option<&'d Url>,
+}
+
+impl<'d> MicrodataReader<'d> {
+    /// Reads an item. The stack holds the items being read, so an item which contains
+    /// itself through itemref is not read forever.
+    fn item(&self, root: &'d HtmlTag, stack: &mut Vec<*const HtmlTag>) -> StructuredItem {
+        stack.push(root as *const HtmlTag);
+        let mut item = StructuredItem::new(StructuredSource::Microdata);
+        if let Some(types) = root.attributes.get("itemtype") {
+            item.types = types.split_whitespace().map(|t| t.to_owned()).collect();
+        }
+        // itemid is only used when the item has a type
+        if !item.types.is_empty() {
+            item.id = root
+                .attributes
+                .get("itemid")
+                .map(|i| self.resolve(&decode_entities(i)));
+        }
+        for element in self.property_elements(root) {
+            let names = element.attributes["itemprop"]
+                .split_whitespace()
+                .map(|n| n.to_owned())
+                .collect::<Vec<String>>();
+            let value = if element.attributes.contains_key("itemscope") {
+                if stack.contains(&(element as *const HtmlTag)) {
+                    continue;
+                }
+                StructuredValue::Item(self.item(element, stack))
+            } else {
+                self.value(element)
+            };
+            for name in names {
+                item.properties.push((name, value.clone()));
+            }
+        }
+        stack.pop();
+        item
+    }
+
+    /// Finds the elements with an itemprop that belong to the item, in document order.
+    fn property_elements(&self, root: &'d HtmlTag) -> Vec<&'d HtmlTag> {
+        let mut pending: Vec<&HtmlTag> = vec![];
+        if let Some(refs) = root.attributes.get("itemref") {
+            for id in refs.split_whitespace() {
+                if let Some(t) = self.ids.get(id) {
+                    pending.push(t);
+                }
+            }
+        }
+        let mut visited: HashSet<*const HtmlTag> = HashSet::new();
+        visited.insert(root);
+        let mut results = vec![];
+        let mut search = |tag: &'d HtmlTag, include_self: bool, results: &mut Vec<&'d HtmlTag>| {
+            let mut stack: Vec<(&HtmlTag, bool)> = vec![(tag, include_self)];
+            while let Some((t, check)) = stack.pop() {
+                if check {
+                    if !visited.insert(t as *const HtmlTag) {
+                        continue;
+                    }
+                    if t.attributes.contains_key("itemprop") {
+                        results.push(t);
+                    }
+                    // the properties of a nested item belong to that item
+                    if t.attributes.contains_key("itemscope") {
+                        continue;
+                    }
+                }
+                for child in t.contents.iter().rev() {
+                    if let HtmlNode::Tag(c) = child {
+                        stack.push((c, true));
+                    }
+                }
+            }
+        };
+        search(root, false, &mut results);
+        for t in pending {
+            search(t, true, &mut results);
+        }
+        // the elements of an itemref can be anywhere in the document
+        results.sort_by_key(|t| self.positions.get(&(*t as *const HtmlTag)));
+        results
+    }
+
+    fn value(&self, element: &HtmlTag) -> StructuredValue {
+        let attribute = |name: &str| {
+            decode_entities(
+                element
+                    .attributes
+                    .get(name)
+                    .map(|a| a.as_str())
+                    .unwrap_or_default(),
+            )
+        };
+        match element.tag.to_ascii_lowercase().as_str() {
+            "meta" => StructuredValue::Text(attribute("content")),
+            "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => {
+                StructuredValue::Url(self.resolve(&attribute("src")))
+            }
+            "a" | "area" | "link" => StructuredValue::Url(self.resolve(&attribute("href"))),
+            "object" => StructuredValue::Url(self.resolve(&attribute("data"))),
+            "data" | "meter" => StructuredValue::Text(attribute("value")),
+            "time" if element.attributes.contains_key("datetime") => {
+                StructuredValue::Text(attribute("datetime"))
+            }
+            _ => StructuredValue::Text(decode_entities(&element.text()).trim().to_owned()),
+        }
+    }
+
+    fn resolve(&self, url: &str) -> String {
+        resolve_url(self.base, url)
+    }
+}
+
+fn resolve_url(base: Option<&Url>, url: &str) -> String {
+    match base {
+        Some(base) => base.join(url.trim()).to_string(),
+        None => url.trim().to_owned(),
+    }
+}
+
+/// The prefixes which RDFa documents can use without declaring them.
+fn default_rdfa_prefixes() -> HashMap<String, String> {
+    [
+        ("schema", "http://schema.org/"),
+        ("og", "http://ogp.me/ns#"),
+        ("dc", "http://purl.org/dc/terms/"),
+        ("foaf", "http://xmlns.com/foaf/0.1/"),
+        ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ]
+    .iter()
+    .map(|(p, i)| (p.to_string(), i.to_string()))
+    .collect()
+}
+
+#[derive(Clone)]
+struct RdfaContext {
+    vocab: Option<String>,
+    prefixes: HashMap<String, String>,
+}
+
+impl RdfaContext {
+    /// Expands a type with a declared prefix or the vocabulary.
+    fn expand(&self, term: &str) -> String {
+        if let Some((prefix, rest)) = term.split_once(':') {
+            if let Some(iri) = self.prefixes.get(prefix) {
+                return format!("{}{}", iri, rest);
+            }
+            return term.to_owned();
+        }
+        expand_term(term, self.vocab.as_deref())
+    }
+}
+
+/// Reads RDFa Lite: vocab, prefix, typeof, property and resource, along with the href,
+/// src and content attributes for values.
+struct RdfaReader<'d> {
+    base: Option<&'d Url>,
+    items: Vec<StructuredItem>,
+}
+
+impl<'d> RdfaReader<'d> {
+    fn walk(&mut self, nodes: &[HtmlNode], context: &RdfaContext, subject: &mut StructuredItem) {
+        for node in nodes {
+            let tag = match node {
+                HtmlNode::Tag(t) => t,
+                _ => continue,
+            };
+            let mut context = context.clone();
+            if let Some(vocab) = tag.attributes.get("vocab") {
+                context.vocab = Some(vocab.trim().to_owned()).filter(|v| !v.is_empty());
+            }
+            if let Some(prefix) = tag.attributes.get("prefix") {
+                let parts = prefix.split_whitespace().collect::<Vec<&str>>();
+                for pair in parts.chunks(2) {
+                    if let [name, iri] = pair {
+                        if let Some(name) = name.strip_suffix(':') {
+                            context.prefixes.insert(name.to_owned(), iri.to_string());
+                        }
+                    }
+                }
+            }
+            let properties = tag
+                .attributes
+                .get("property")
+                .map(|p| {
+                    p.split_whitespace()
+                        .map(|p| p.to_owned())
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default();
+            match tag.attributes.get("typeof") {
+                Some(types) => {
+                    let mut item = StructuredItem::new(StructuredSource::Rdfa);
+                    item.types = types
+                        .split_whitespace()
+                        .map(|t| context.expand(t))
+                        .collect();
+                    item.id = ["resource", "about"]
+                        .iter()
+                        .find_map(|a| tag.attributes.get(*a))
+                        .map(|r| resolve_url(self.base, &decode_entities(r)));
+                    self.walk(&tag.contents, &context, &mut item);
+                    match properties.is_empty() {
+                        true => self.items.push(item),
+                        false => {
+                            for name in properties {
+                                subject
+                                    .properties
+                                    .push((name, StructuredValue::Item(item.clone())));
+                            }
+                        }
+                    }
+                }
+                None => {
+                    if !properties.is_empty() {
+                        let value = self.value(tag);
+                        for name in properties {
+                            subject.properties.push((name, value.clone()));
+                        }
+                    }
+                    self.walk(&tag.contents, &context, subject);
+                }
+            }
+        }
+    }
+
+    fn value(&self, tag: &HtmlTag) -> StructuredValue {
+        if let Some(content) = tag.attributes.get("content") {
+            return StructuredValue::Text(decode_entities(content));
+        }
+        for attribute in ["resource", "href", "src"] {
+            if let Some(url) = tag.attributes.get(attribute) {
+                return StructuredValue::Url(resolve_url(self.base, &decode_entities(url)));
+            }
+        }
+        match tag.attributes.get("datetime") {
+            Some(datetime) => StructuredValue::Text(decode_entities(datetime)),
+            None => StructuredValue::Text(decode_entities(&tag.text()).trim().to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod structured_tests {
+    use super::*;
+
+    fn doc(html: &str) -> HtmlDocument {
+        html.parse::<HtmlDocument>().unwrap()
+    }
+
+    #[test]
+    fn json_ld_test() {
+        let d = doc(r##"<script type="application/ld+json">
+{"@context": "https://schema.org", "@graph": [
+  {"@type": "Recipe", "@id": "#r", "name": "Soup", "recipeYield": 4,
+   "author": {"@type": ["Person", "http://x.org/Cook"], "name": "Ann"},
+   "recipeIngredient": ["water", "salt",], "image": {"@id": "soup.jpg"},
+   "description": {"@value": "Hot", "@language": "en"}, "video": null},
+  {"@type": "WebPage"}
+]}
+</script>
+<script type="application/ld+json"><!-- {"@context": {"@vocab": "http://v.org/"}, "@type": "Thing"} --></script>
+<script type="application/ld+json">{not json</script>
+<script type="text/javascript">{"@type": "Ignored"}</script>"##);
+        let items = d.json_ld(None);
+        assert_eq!(items.len(), 3);
+        let recipe = &items[0];
+        assert_eq!(recipe.source, StructuredSource::JsonLd);
+        assert_eq!(recipe.types, vec!["https://schema.org/Recipe"]);
+        assert_eq!(recipe.id.as_deref(), Some("#r"));
+        assert_eq!(recipe.text("recipeYield"), Some("4"));
+        assert_eq!(recipe.text("description"), Some("Hot"));
+        assert_eq!(
+            recipe.value("image"),
+            Some(&StructuredValue::Url("soup.jpg".to_owned()))
+        );
+        assert_eq!(
+            recipe
+                .values("recipeIngredient")
+                .filter_map(|v| v.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["water", "salt"]
+        );
+        assert!(recipe.value("video").is_none());
+        let author = recipe.item("author").unwrap();
+        assert_eq!(
+            author.types,
+            vec!["https://schema.org/Person", "http://x.org/Cook"]
+        );
+        assert!(author.has_type("Cook"));
+        assert!(items[1].has_type("WebPage"));
+        assert_eq!(items[2].types, vec!["http://v.org/Thing"]);
+    }
+
+    #[test]
+    fn json_ld_base_test() {
+        let d = doc(r##"<script type="application/ld+json">
+{"@id": "#r", "image": {"@id": "soup.jpg"}, "author": {"@id": "_:b0", "name": "Ann"}}
+</script>"##);
+        let page = Url::parse("http://example.com/r/soup").unwrap();
+        let items = d.json_ld(Some(&page));
+        assert_eq!(items[0].id.as_deref(), Some("http://example.com/r/soup#r"));
+        assert_eq!(
+            items[0].value("image"),
+            Some(&StructuredValue::Url(
+                "http://example.com/r/soup.jpg".to_owned()
+            ))
+        );
+        assert_eq!(items[0].item("author").unwrap().id.as_deref(), Some("_:b0"));
+    }
+
+    #[test]
+    fn microdata_itemref_order_test() {
+        let d = doc(r#"<p id="first"><span itemprop="a">1</span></p>
+<div itemscope itemref="last first"><span itemprop="b">2</span></div>
+<p id="last"><span itemprop="c">3</span></p>"#);
+        let items = d.microdata(None);
+        let names = items[0]
+            .properties
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn structured_entities_test() {
+        let d = doc(r#"<script type="application/ld+json">
+{"@type": "Recipe", "name": "Fish & chips", "url": "/r?a=1&b=2"}
+</script>
+<div itemscope itemtype="https://schema.org/Recipe">
+<h1 itemprop="name">Fish &amp; chips</h1><a itemprop="url" href="/r?a=1&amp;b=2">r</a>
+<meta itemprop="description" content="&quot;Crispy&quot;">
+</div>
+<div vocab="https://schema.org/" typeof="Recipe">
+<h1 property="name">Fish &#38; chips</h1><a property="url" href="/r?a=1&amp;b=2">r</a>
+</div>"#);
+        let json_ld = d.json_ld(None);
+        let microdata = d.microdata(None);
+        let rdfa = d.rdfa(None);
+        for item in [&microdata[0], &rdfa[0]] {
+            assert_eq!(item.text("name"), json_ld[0].text("name"));
+            assert_eq!(
+                item.value("url"),
+                Some(&StructuredValue::Url("/r?a=1&b=2".to_owned()))
+            );
+        }
+        assert_eq!(microdata[0].text("description"), Some("\"Crispy\""));
+    }
+
+    #[test]
+    fn microdata_test() {
+        let d = doc(r#"<base href="http://example.com/r/">
+<div itemscope itemtype="https://schema.org/Recipe" itemid="soup" itemref="extra loop">
+  <span itemprop="name alternateName">Soup</span>
+  <img itemprop="image" src="soup.jpg">
+  <meta itemprop="cookTime" content="PT1H">
+  <time itemprop="datePublished" datetime="2024-01-02">Jan 2</time>
+  <div itemprop="nutrition" itemscope><span itemprop="calories">100</span></div>
+  <div itemscope itemprop="loop" id="loop" itemref="loop"><span itemprop="x">y</span></div>
+</div>
+<p id="extra"><span itemprop="recipeYield">4</span></p>
+<div itemscope><span itemprop="other">second</span></div>"#);
+        let items = d.microdata(None);
+        assert_eq!(items.len(), 2);
+        let recipe = &items[0];
+        assert_eq!(recipe.id.as_deref(), Some("http://example.com/r/soup"));
+        let names = recipe
+            .properties
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            names,
+            vec![
+                "name",
+                "alternateName",
+                "image",
+                "cookTime",
+                "datePublished",
+                "nutrition",
+                "loop",
+                "recipeYield"
+            ]
+        );
+        assert_eq!(recipe.text("alternateName"), Some("Soup"));
+        assert_eq!(
+            recipe.value("image"),
+            Some(&StructuredValue::Url(
+                "http://example.com/r/soup.jpg".to_owned()
+            ))
+        );
+        assert_eq!(recipe.text("datePublished"), Some("2024-01-02"));
+        assert_eq!(
+            recipe.item("nutrition").unwrap().text("calories"),
+            Some("100")
+        );
+        // the item that refers to itself is only read once
+        assert_eq!(recipe.item("loop").unwrap().text("x"), Some("y"));
+        assert_eq!(items[1].text("other"), Some("second"));
+        assert_eq!(items[1].id, None);
+    }
+
+    #[test]
+    fn rdfa_test() {
+        let d = doc(
+            r##"<head><meta property="og:title" content="Soup page"></head>
+<body vocab="https://schema.org/" prefix="ex: http://ex.org/ns#">
+<div typeof="Recipe ex:Dish" resource="#soup">
+  <h1 property="name">Soup</h1>
+  <a property="url" href="/soup">link</a>
+  <div property="author" typeof="Person"><span property="name">Ann</span></div>
+</div>
+</body>"##,
+        );
+        let page = Url::parse("http://example.com/page").unwrap();
+        let items = d.rdfa(Some(&page));
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id.as_deref(), Some("http://example.com/page"));
+        assert_eq!(items[0].text("og:title"), Some("Soup page"));
+        let recipe = &items[1];
+        assert_eq!(
+            recipe.types,
+            vec!["https://schema.org/Recipe", "http://ex.org/ns#Dish"]
+        );
+        assert_eq!(recipe.id.as_deref(), Some("http://example.com/page#soup"));
+        assert_eq!(recipe.text("name"), Some("Soup"));
+        assert_eq!(recipe.text("url"), Some("http://example.com/soup"));
+        let author = recipe.item("author").unwrap();
+        assert_eq!(author.types, vec!["https://schema.org/Person"]);
+        assert_eq!(author.text("name"), Some("Ann"));
+        // every format ends up in the same tree
+        let all = d.structured_items_of_type("Person", Some(&page));
+        assert_eq!(all.len(), 1);
+    }
+}
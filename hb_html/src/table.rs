@@ -0,0 +1,411 @@
+//! Turns HTML tables into rectangular grids of cells.
+//!
+//! Cells spanning several rows or columns are copied into every position they cover, and
+//! rows that are short are padded with empty cells. Rows inside `<thead>`, or the leading
+//! rows made up only of `<th>` cells, are treated as header rows.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::HtmlDocument;
+//! let doc = r#"<table>
+//! <tr><th>Name</th><th>Team</th></tr>
+//! <tr><td>Ann</td><td rowspan=2>Red</td></tr>
+//! <tr><td>Bob</td></tr>
+//! </table>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let table = doc.tables().remove(0);
+//! assert_eq!(table.to_csv(), "Name,Team\nAnn,Red\nBob,Red\n");
+//! let records = table.to_records();
+//! assert_eq!(records[1]["Name"], "Bob");
+//! assert_eq!(records[1]["Team"], "Red");
+//! ```
+use crate::entities::decode_text;
+use crate::objects::{CssSelector, HtmlDocument, HtmlNode, HtmlTag};
+use crate::querying::HtmlQueryResult;
+use std::collections::HashMap;
+
+/// The largest colspan allowed by the HTML spec.
+const MAX_COLSPAN: usize = 1000;
+/// The largest rowspan allowed by the HTML spec.
+const MAX_ROWSPAN: usize = 65534;
+
+/// A single position in the grid of a [`HtmlTable`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableCell {
+    /// The decoded text of the cell with the whitespace collapsed.
+    pub text: String,
+    /// True if the cell is a `<th>` or is in a header row.
+    pub is_header: bool,
+    /// The row and column of the cell in the source that this position is covered by.
+    /// It is different to the position of the cell when it is covered by a rowspan or colspan,
+    /// and None when the position was added to pad out a short row.
+    pub origin: Option<(usize, usize)>,
+}
+
+impl TableCell {
+    fn padding() -> TableCell {
+        TableCell {
+            text: String::new(),
+            is_header: false,
+            origin: None,
+        }
+    }
+}
+
+/// A HTML table as a rectangular grid of cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlTable {
+    /// The text of the `<caption>`, if there is one.
+    pub caption: Option<String>,
+    /// The rows of the table, each with the same number of cells.
+    pub rows: Vec<Vec<TableCell>>,
+    /// The number of rows at the top of the table which are header rows.
+    pub header_rows: usize,
+}
+
+impl HtmlTable {
+    /// Creates a table from a `<table>` tag. Returns None if the tag is not a table.
+    pub fn from_tag(tag: &HtmlTag) -> Option<HtmlTable> {
+        if !tag.tag.eq_ignore_ascii_case("table") {
+            return None;
+        }
+        let mut caption = None;
+        let mut head = vec![];
+        let mut bodies = vec![];
+        let mut foot = vec![];
+        // rows directly inside the table form their own groups between the sections
+        let mut loose_rows = vec![];
+        for child in child_tags(tag) {
+            match child.tag.to_ascii_lowercase().as_str() {
+                "caption" if caption.is_none() => caption = Some(decode_text(&child.text())),
+                "tr" => loose_rows.push(child),
+                section @ ("thead" | "tbody" | "tfoot") => {
+                    if !loose_rows.is_empty() {
+                        bodies.push(std::mem::take(&mut loose_rows));
+                    }
+                    let rows = child_tags(child)
+                        .filter(|t| t.tag.eq_ignore_ascii_case("tr"))
+                        .collect::<Vec<&HtmlTag>>();
+                    match section {
+                        "thead" if head.is_empty() => head = rows,
+                        "tfoot" if foot.is_empty() => foot = rows,
+                        _ => bodies.push(rows),
+                    }
+                }
+                _ => (),
+            }
+        }
+        if !loose_rows.is_empty() {
+            bodies.push(loose_rows);
+        }
+        let mut rows = group_grid(&head, true, 0);
+        for body in &bodies {
+            let start = rows.len();
+            rows.extend(group_grid(body, false, start));
+        }
+        let start = rows.len();
+        rows.extend(group_grid(&foot, false, start));
+        let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        for row in &mut rows {
+            row.resize(width, TableCell::padding());
+        }
+        let header_rows = match head.is_empty() {
+            false => head.len().min(rows.len()),
+            // without a thead, the leading rows made of only th cells are the headers
+            true => rows
+                .iter()
+                .take_while(|r| !r.is_empty() && r.iter().all(|c| c.is_header))
+                .count(),
+        };
+        Some(HtmlTable {
+            caption,
+            rows,
+            header_rows,
+        })
+    }
+
+    /// The number of columns in the table.
+    pub fn width(&self) -> usize {
+        self.rows.first().map(|r| r.len()).unwrap_or(0)
+    }
+
+    /// The number of rows in the table, including the header rows.
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Gets the cell at the row and column, if it is inside the table.
+    pub fn cell(&self, row: usize, col: usize) -> Option<&TableCell> {
+        self.rows.get(row).and_then(|r| r.get(col))
+    }
+
+    /// Overrides the number of rows that are treated as header rows.
+    pub fn with_header_rows(mut self, header_rows: usize) -> HtmlTable {
+        self.header_rows = header_rows.min(self.rows.len());
+        self
+    }
+
+    /// The rows after the header rows.
+    pub fn body_rows(&self) -> &[Vec<TableCell>] {
+        &self.rows[self.header_rows..]
+    }
+
+    /// Gets a name for each column from the header rows. When there are multiple header rows
+    /// the different texts in the column are joined with a space. Columns without a header
+    /// are named "column N" counting from 1, and repeated names get " N" added to them.
+    pub fn headers(&self) -> Vec<String> {
+        let mut headers = vec![];
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for col in 0..self.width() {
+            let mut parts: Vec<&str> = vec![];
+            for row in &self.rows[..self.header_rows] {
+                let text = row[col].text.as_str();
+                if !text.is_empty() && parts.last() != Some(&text) {
+                    parts.push(text);
+                }
+            }
+            let mut name = match parts.is_empty() {
+                true => format!("column {}", col + 1),
+                false => parts.join(" "),
+            };
+            let count = counts.entry(name.clone()).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                name = format!("{} {}", name, count);
+            }
+            headers.push(name);
+        }
+        headers
+    }
+
+    /// Writes every row of the table, including the header rows, as CSV.
+    /// Fields containing commas, quotes or new lines are quoted.
+    pub fn to_csv(&self) -> String {
+        let mut output = String::new();
+        for row in &self.rows {
+            let fields = row
+                .iter()
+                .map(|c| csv_field(&c.text))
+                .collect::<Vec<String>>();
+            output.push_str(&fields.join(","));
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Creates a map from the header of each column to the value in the column for each
+    /// of the body rows. See [`HtmlTable::headers`] for how the headers are named.
+    pub fn to_records(&self) -> Vec<HashMap<String, String>> {
+        let headers = self.headers();
+        self.body_rows()
+            .iter()
+            .map(|row| {
+                headers
+                    .iter()
+                    .cloned()
+                    .zip(row.iter().map(|c| c.text.clone()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Iterates over the tags directly inside a tag.
+fn child_tags(tag: &HtmlTag) -> impl Iterator<Item = &HtmlTag> {
+    tag.contents.iter().filter_map(|n| match n {
+        HtmlNode::Tag(t) => Some(t),
+        _ => None,
+    })
+}
+
+/// Reads a rowspan or colspan attribute, using 1 if it is missing or invalid.
+fn span(tag: &HtmlTag, name: &str, min: usize, max: usize) -> usize {
+    match tag.attributes.get(name).map(|v| v.trim().parse::<usize>()) {
+        Some(Ok(n)) => n.clamp(min, max),
+        _ => 1,
+    }
+}
+
+/// Builds the grid for a group of rows, such as a tbody. Rowspans can't reach outside of
+/// the group, and a rowspan of 0 covers the rest of the group. The origin of each cell is
+/// offset by the row the group starts on.
+fn group_grid(rows: &[&HtmlTag], header: bool, start: usize) -> Vec<Vec<TableCell>> {
+    let mut grid: Vec<Vec<Option<TableCell>>> = vec![vec![]; rows.len()];
+    for (y, row) in rows.iter().enumerate() {
+        let mut x = 0;
+        for cell in child_tags(row) {
+            let is_th = cell.tag.eq_ignore_ascii_case("th");
+            if !is_th && !cell.tag.eq_ignore_ascii_case("td") {
+                continue;
+            }
+            while grid[y].get(x).map(|c| c.is_some()).unwrap_or(false) {
+                x += 1;
+            }
+            let colspan = span(cell, "colspan", 1, MAX_COLSPAN);
+            let rowspan = match span(cell, "rowspan", 0, MAX_ROWSPAN) {
+                0 => rows.len() - y,
+                n => n.min(rows.len() - y),
+            };
+            let value = TableCell {
+                text: decode_text(&cell.text()),
+                is_header: header || is_th,
+                origin: Some((start + y, x)),
+            };
+            for covered in grid.iter_mut().skip(y).take(rowspan) {
+                if covered.len() < x + colspan {
+                    covered.resize(x + colspan, None);
+                }
+                for position in covered.iter_mut().skip(x).take(colspan) {
+                    // cells from earlier rows keep the positions they already cover
+                    if position.is_none() {
+                        *position = Some(value.clone());
+                    }
+                }
+            }
+            x += colspan;
+        }
+    }
+    grid.into_iter()
+        .map(|r| {
+            r.into_iter()
+                .map(|c| c.unwrap_or_else(TableCell::padding))
+                .collect()
+        })
+        .collect()
+}
+
+/// Quotes a CSV field if it needs to be.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_owned(),
+    }
+}
+
+impl HtmlTag {
+    /// Turns the tag into a [`HtmlTable`] if it is a `<table>` tag.
+    pub fn table(&self) -> Option<HtmlTable> {
+        HtmlTable::from_tag(self)
+    }
+}
+
+impl<'a> HtmlQueryResult<'a> {
+    /// Turns the node into a [`HtmlTable`] if it is a `<table>` tag.
+    pub fn table(&self) -> Option<HtmlTable> {
+        match self.get_node() {
+            Some(HtmlNode::Tag(t)) => t.table(),
+            _ => None,
+        }
+    }
+}
+
+impl HtmlDocument {
+    /// Gets every table in the document, including tables inside other tables, in
+    /// document order.
+    pub fn tables(&self) -> Vec<HtmlTable> {
+        let selector = "table".parse::<CssSelector>().unwrap();
+        self.select(&selector).filter_map(|n| n.table()).collect()
+    }
+}
+
+#[cfg(test)]
+mod table_tests {
+    use super::*;
+
+    fn table(html: &str) -> HtmlTable {
+        html.parse::<HtmlDocument>().unwrap().tables().remove(0)
+    }
+
+    fn texts(table: &HtmlTable) -> Vec<Vec<&str>> {
+        table
+            .rows
+            .iter()
+            .map(|r| r.iter().map(|c| c.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn table_spans_test() {
+        let t = table(
+            r#"<table>
+<tr><td rowspan=2>A</td><td colspan=2>B</td></tr>
+<tr><td>C</td><td rowspan=3>D</td></tr>
+<tr><td colspan=2>E</td></tr>
+<tr><td>F</td></tr>
+</table>"#,
+        );
+        assert_eq!(
+            texts(&t),
+            vec![
+                vec!["A", "B", "B"],
+                vec!["A", "C", "D"],
+                vec!["E", "E", "D"],
+                vec!["F", "", "D"],
+            ]
+        );
+        assert_eq!(t.cell(2, 2).unwrap().origin, Some((1, 2)));
+        assert_eq!(t.cell(3, 1).unwrap().origin, None);
+        assert_eq!(t.header_rows, 0);
+        assert_eq!((t.width(), t.height()), (3, 4));
+    }
+
+    #[test]
+    fn table_sections_test() {
+        let t = table(
+            r#"<table><caption> Scores  2024 </caption>
+<tfoot><tr><td>Total</td><td>9</td></tr></tfoot>
+<thead><tr><th rowspan=2>Name</th><th colspan=2>Score</th></tr><tr><th>Math</th><th>Art</th></tr></thead>
+<tbody><tr><td>Ann</td><td>5</td><td rowspan=0>3</td></tr><tr><td>Bob</td><td>4</td></tr></tbody>
+<tbody><tr><td>Cy</td><td><table><tr><td>nested</td></tr></table></td><td>1</td></tr></tbody>
+</table>"#,
+        );
+        assert_eq!(t.caption, Some("Scores 2024".to_owned()));
+        assert_eq!(t.header_rows, 2);
+        assert_eq!(t.headers(), vec!["Name", "Score Math", "Score Art"]);
+        // the rowspan=0 stops at the end of the tbody and the tfoot goes last
+        assert_eq!(
+            texts(&t)[2..],
+            vec![
+                vec!["Ann", "5", "3"],
+                vec!["Bob", "4", "3"],
+                vec!["Cy", "nested", "1"],
+                vec!["Total", "9", ""],
+            ]
+        );
+        let records = t.to_records();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1]["Score Art"], "3");
+        assert_eq!(records[3]["Name"], "Total");
+    }
+
+    #[test]
+    fn table_headers_test() {
+        let t = table(
+            "<table><tr><th>A</th><th></th><th>A</th></tr><tr><th>x</th><td>1</td><td>2</td></tr></table>",
+        );
+        // only the first row is all th cells
+        assert_eq!(t.header_rows, 1);
+        assert_eq!(t.headers(), vec!["A", "column 2", "A 2"]);
+        let t = t.with_header_rows(0);
+        assert_eq!(t.headers(), vec!["column 1", "column 2", "column 3"]);
+        assert_eq!(t.to_records().len(), 2);
+    }
+
+    #[test]
+    fn table_csv_test() {
+        let t = table(
+            r#"<table><tr><td>a, b</td><td>say "hi"</td><td>plain
+text</td></tr></table>"#,
+        );
+        assert_eq!(t.to_csv(), "\"a, b\",\"say \"\"hi\"\"\",plain text\n");
+        let t = table(
+            "<table><tr><th>Fish &amp; chips</th><th>A&#44;B</th></tr><tr><td>&pound;5&nbsp;</td><td>&lt;1&gt;</td></tr></table>",
+        );
+        assert_eq!(t.to_csv(), "Fish & chips,\"A,B\"\n£5,<1>\n");
+        assert_eq!(t.to_records()[0]["Fish & chips"], "£5");
+        let doc = "<div>not a table</div>".parse::<HtmlDocument>().unwrap();
+        assert!(doc.find("div").results[0].table().is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! URI references and their resolution following RFC 3986.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::url::Url;
+//! let base = Url::parse("http://a/b/c/d;p?q").unwrap();
+//! assert_eq!(base.join("../g?x#s").to_string(), "http://a/b/g?x#s");
+//! assert_eq!(base.join("//other/path").to_string(), "http://other/path");
+//! ```
+use crate::error::UrlError;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A URI reference split into the five components of RFC 3986. A missing component is
+/// None, which is different to an empty one, and the path is always present but can be
+/// empty.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Url {
+    pub scheme: Option<String>,
+    pub authority: Option<String>,
+    pub path: String,
+    pub query: Option<String>,
+    pub fragment: Option<String>,
+}
+
+impl Url {
+    /// Parses an absolute URL, which must have a scheme.
+    pub fn parse(url: &str) -> Result<Url, UrlError> {
+        let parsed = Url::parse_reference(url);
+        match parsed.scheme {
+            Some(_) => Ok(parsed),
+            None => Err(UrlError::with_url(url, "the url does not have a scheme")),
+        }
+    }
+
+    /// Parses a URI reference, which can be relative. Every string is a valid reference
+    /// when split with the regular expression from appendix B of RFC 3986.
+    pub fn parse_reference(reference: &str) -> Url {
+        let (rest, fragment) = match reference.find('#') {
+            Some(i) => (&reference[..i], Some(reference[i + 1..].to_owned())),
+            None => (reference, None),
+        };
+        let (rest, query) = match rest.find('?') {
+            Some(i) => (&rest[..i], Some(rest[i + 1..].to_owned())),
+            None => (rest, None),
+        };
+        let (scheme, rest) = match rest.find([':', '/']) {
+            Some(i) if rest[i..].starts_with(':') && is_scheme(&rest[..i]) => {
+                (Some(rest[..i].to_ascii_lowercase()), &rest[i + 1..])
+            }
+            _ => (None, rest),
+        };
+        let (authority, path) = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let end = rest.find('/').unwrap_or(rest.len());
+                (Some(rest[..end].to_owned()), rest[end..].to_owned())
+            }
+            None => (None, rest.to_owned()),
+        };
+        Url {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+
+    /// True if the URL has a scheme.
+    pub fn is_absolute(&self) -> bool {
+        self.scheme.is_some()
+    }
+
+    /// Gets the host from the authority, without the user info or port.
+    pub fn host(&self) -> Option<&str> {
+        let authority = self.authority.as_deref()?;
+        let host = match authority.rfind('@') {
+            Some(i) => &authority[i + 1..],
+            None => authority,
+        };
+        // the port comes after the last colon, unless it is inside an IPv6 literal
+        match host.rfind(':') {
+            Some(i) if !host[i..].contains(']') => Some(&host[..i]),
+            _ => Some(host),
+        }
+    }
+
+    /// Resolves a reference against this URL as the base with the algorithm in section
+    /// 5.2.2 of RFC 3986. The base should be absolute.
+    pub fn join(&self, reference: &str) -> Url {
+        self.resolve(&Url::parse_reference(reference))
+    }
+
+    /// Resolves a parsed reference against this URL as the base. See [`Url::join`].
+    pub fn resolve(&self, reference: &Url) -> Url {
+        let (scheme, authority, path, query) = if reference.scheme.is_some() {
+            (
+                reference.scheme.clone(),
+                reference.authority.clone(),
+                remove_dot_segments(&reference.path),
+                reference.query.clone(),
+            )
+        } else if reference.authority.is_some() {
+            (
+                self.scheme.clone(),
+                reference.authority.clone(),
+                remove_dot_segments(&reference.path),
+                reference.query.clone(),
+            )
+        } else if reference.path.is_empty() {
+            (
+                self.scheme.clone(),
+                self.authority.clone(),
+                self.path.clone(),
+                reference.query.clone().or_else(|| self.query.clone()),
+            )
+        } else if reference.path.starts_with('/') {
+            (
+                self.scheme.clone(),
+                self.authority.clone(),
+                remove_dot_segments(&reference.path),
+                reference.query.clone(),
+            )
+        } else {
+            (
+                self.scheme.clone(),
+                self.authority.clone(),
+                remove_dot_segments(&self.merge(&reference.path)),
+                reference.query.clone(),
+            )
+        };
+        Url {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment: reference.fragment.clone(),
+        }
+    }
+
+    /// Merges a relative path with the path of this URL as in section 5.2.3.
+    fn merge(&self, path: &str) -> String {
+        if self.authority.is_some() && self.path.is_empty() {
+            return format!("/{}", path);
+        }
+        match self.path.rfind('/') {
+            Some(i) => format!("{}{}", &self.path[..=i], path),
+            None => path.to_owned(),
+        }
+    }
+}
+
+/// Checks the scheme is a letter followed by letters, digits, "+", "-" or ".".
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+/// Removes the "." and ".." segments from a path as in section 5.2.4 of RFC 3986.
+pub fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output = String::new();
+    while !input.is_empty() {
+        if let Some(rest) = input.strip_prefix("../") {
+            input = rest;
+        } else if let Some(rest) = input.strip_prefix("./") {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") || input == "/.." {
+            input = match input.len() {
+                3 => "/",
+                _ => &input[3..],
+            };
+            let end = output.rfind('/').unwrap_or(0);
+            output.truncate(end);
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            // move the first segment, including its leading "/", to the output
+            let start = usize::from(input.starts_with('/'));
+            let end = input[start..]
+                .find('/')
+                .map(|i| i + start)
+                .unwrap_or(input.len());
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output
+}
+
+impl Display for Url {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{}:", scheme)?;
+        }
+        if let Some(authority) = &self.authority {
+            write!(f, "//{}", authority)?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = &self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Url {
+    type Err = UrlError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Url::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod url_tests {
+    use super::*;
+
+    #[test]
+    fn url_parse_test() {
+        let url = Url::parse("HTTP://user@Example.com:8080/a/b?x=1#frag").unwrap();
+        assert_eq!(url.scheme.as_deref(), Some("http"));
+        assert_eq!(url.authority.as_deref(), Some("user@Example.com:8080"));
+        assert_eq!(url.host(), Some("Example.com"));
+        assert_eq!(url.path, "/a/b");
+        assert_eq!(url.query.as_deref(), Some("x=1"));
+        assert_eq!(url.fragment.as_deref(), Some("frag"));
+        assert_eq!(url.to_string(), "http://user@Example.com:8080/a/b?x=1#frag");
+        assert_eq!(
+            Url::parse("http://[::1]:80/").unwrap().host(),
+            Some("[::1]")
+        );
+        assert!(Url::parse("/relative").is_err());
+        let reference = Url::parse_reference("a:b/c:d");
+        assert_eq!(reference.scheme.as_deref(), Some("a"));
+        let reference = Url::parse_reference("./a:b");
+        assert_eq!(reference.scheme, None);
+        assert_eq!(reference.path, "./a:b");
+        assert_eq!(Url::parse_reference("?#").to_string(), "?#");
+    }
+
+    #[test]
+    fn url_resolve_test() {
+        // the examples from section 5.4 of RFC 3986
+        let base = Url::parse("http://a/b/c/d;p?q").unwrap();
+        let tests = [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("g?y#s", "http://a/b/c/g?y#s"),
+            (";x", "http://a/b/c/;x"),
+            ("g;x", "http://a/b/c/g;x"),
+            ("g;x?y#s", "http://a/b/c/g;x?y#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("../../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            (".g", "http://a/b/c/.g"),
+            ("g..", "http://a/b/c/g.."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+            ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("g?y/./x", "http://a/b/c/g?y/./x"),
+            ("g?y/../x", "http://a/b/c/g?y/../x"),
+            ("g#s/./x", "http://a/b/c/g#s/./x"),
+            ("g#s/../x", "http://a/b/c/g#s/../x"),
+            ("http:g", "http:g"),
+        ];
+        for (reference, expected) in tests {
+            assert_eq!(base.join(reference).to_string(), expected, "{}", reference);
+        }
+        let base = Url::parse("http://a").unwrap();
+        assert_eq!(base.join("g").to_string(), "http://a/g");
+    }
+
+    #[test]
+    fn remove_dot_segments_test() {
+        assert_eq!(remove_dot_segments("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(remove_dot_segments("mid/content=5/../6"), "mid/6");
+        assert_eq!(remove_dot_segments(""), "");
+    }
+}
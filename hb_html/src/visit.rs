@@ -0,0 +1,382 @@
+//! Traits for walking over and transforming trees of [`HtmlNode`], in the style of
+//! `syn::visit`, `syn::visit_mut` and `syn::fold`.
+//!
+//! * [`Visit`] walks over a tree by reference.
+//! * [`VisitMut`] walks over a tree and can change the nodes in place.
+//! * [`Fold`] takes a tree by value and builds a new one, where each node can be replaced
+//!   by any number of nodes.
+//!
+//! Each trait has a method for every kind of node with a default that carries on into the
+//! children, using the free function of the same name. Tags also get `enter_tag` and
+//! `leave_tag` hooks around their contents, and returning [`Walk::SkipChildren`] from
+//! `enter_tag` skips the contents of the tag.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_html::objects::{HtmlDocument, HtmlNode, HtmlTag};
+//! use hb_html::url::Url;
+//! use hb_html::visit::{Fold, VisitMut, Walk};
+//!
+//! struct StripComments;
+//! impl Fold for StripComments {
+//!     fn fold_comment(&mut self, _: String) -> Vec<HtmlNode> {
+//!         vec![]
+//!     }
+//! }
+//!
+//! struct AbsoluteUrls(Url);
+//! impl VisitMut for AbsoluteUrls {
+//!     fn enter_tag(&mut self, tag: &mut HtmlTag) -> Walk {
+//!         for name in ["href", "src"] {
+//!             if let Some(url) = tag.attributes.get_mut(name) {
+//!                 *url = self.0.join(url).to_string();
+//!             }
+//!         }
+//!         Walk::Continue
+//!     }
+//! }
+//!
+//! let doc = r#"<p><!-- note --><a href="../b">b</a><img src="c.png"></p>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! let mut doc = StripComments.fold_document(doc);
+//! AbsoluteUrls(Url::parse("https://x.org/a/").unwrap()).visit_document_mut(&mut doc);
+//! let expected = r#"<p><a href="https://x.org/b">b</a><img src="https://x.org/a/c.png"></p>"#
+//!     .parse::<HtmlDocument>()
+//!     .unwrap();
+//! assert_eq!(doc, expected);
+//! ```
+use crate::objects::{HtmlDocument, HtmlNode, HtmlTag};
+
+/// Whether to carry on into the contents of a tag, returned when entering the tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    /// Visit the contents of the tag.
+    Continue,
+    /// Don't visit the contents of the tag. The tag is still left.
+    SkipChildren,
+}
+
+/// Walks over a tree of nodes by reference.
+pub trait Visit<'a> {
+    fn visit_document(&mut self, document: &'a HtmlDocument) {
+        visit_document(self, document)
+    }
+
+    fn visit_node(&mut self, node: &'a HtmlNode) {
+        visit_node(self, node)
+    }
+
+    fn visit_tag(&mut self, tag: &'a HtmlTag) {
+        visit_tag(self, tag)
+    }
+
+    /// Called before the contents of the tag are visited.
+    fn enter_tag(&mut self, _tag: &'a HtmlTag) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called after the contents of the tag are visited, or skipped.
+    fn leave_tag(&mut self, _tag: &'a HtmlTag) {}
+
+    fn visit_text(&mut self, _text: &'a str) {}
+
+    fn visit_comment(&mut self, _comment: &'a str) {}
+}
+
+/// Visits each of the top level nodes of the document.
+pub fn visit_document<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, document: &'a HtmlDocument) {
+    for node in document.nodes.iter() {
+        visitor.visit_node(node);
+    }
+}
+
+/// Calls the method of the visitor for the kind of node.
+pub fn visit_node<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, node: &'a HtmlNode) {
+    match node {
+        HtmlNode::Tag(tag) => visitor.visit_tag(tag),
+        HtmlNode::Text(text) => visitor.visit_text(text),
+        HtmlNode::Comment(comment) => visitor.visit_comment(comment),
+    }
+}
+
+/// Enters the tag, visits its contents unless they are skipped, then leaves the tag.
+pub fn visit_tag<'a, V: Visit<'a> + ?Sized>(visitor: &mut V, tag: &'a HtmlTag) {
+    if visitor.enter_tag(tag) == Walk::Continue {
+        for node in tag.contents.iter() {
+            visitor.visit_node(node);
+        }
+    }
+    visitor.leave_tag(tag);
+}
+
+/// Walks over a tree of nodes and can change them in place.
+pub trait VisitMut {
+    fn visit_document_mut(&mut self, document: &mut HtmlDocument) {
+        visit_document_mut(self, document)
+    }
+
+    fn visit_node_mut(&mut self, node: &mut HtmlNode) {
+        visit_node_mut(self, node)
+    }
+
+    fn visit_tag_mut(&mut self, tag: &mut HtmlTag) {
+        visit_tag_mut(self, tag)
+    }
+
+    /// Called before the contents of the tag are visited.
+    fn enter_tag(&mut self, _tag: &mut HtmlTag) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called after the contents of the tag are visited, or skipped.
+    fn leave_tag(&mut self, _tag: &mut HtmlTag) {}
+
+    fn visit_text_mut(&mut self, _text: &mut String) {}
+
+    fn visit_comment_mut(&mut self, _comment: &mut String) {}
+}
+
+/// Visits each of the top level nodes of the document.
+pub fn visit_document_mut<V: VisitMut + ?Sized>(visitor: &mut V, document: &mut HtmlDocument) {
+    for node in document.nodes.iter_mut() {
+        visitor.visit_node_mut(node);
+    }
+}
+
+/// Calls the method of the visitor for the kind of node.
+pub fn visit_node_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut HtmlNode) {
+    match node {
+        HtmlNode::Tag(tag) => visitor.visit_tag_mut(tag),
+        HtmlNode::Text(text) => visitor.visit_text_mut(text),
+        HtmlNode::Comment(comment) => visitor.visit_comment_mut(comment),
+    }
+}
+
+/// Enters the tag, visits its contents unless they are skipped, then leaves the tag.
+pub fn visit_tag_mut<V: VisitMut + ?Sized>(visitor: &mut V, tag: &mut HtmlTag) {
+    if visitor.enter_tag(tag) == Walk::Continue {
+        for node in tag.contents.iter_mut() {
+            visitor.visit_node_mut(node);
+        }
+    }
+    visitor.leave_tag(tag);
+}
+
+/// Builds a new tree from a tree of nodes. Every node is folded into a list of nodes which
+/// replaces it, so nodes can be removed, replaced or split into several.
+pub trait Fold {
+    fn fold_document(&mut self, document: HtmlDocument) -> HtmlDocument {
+        fold_document(self, document)
+    }
+
+    fn fold_nodes(&mut self, nodes: Vec<HtmlNode>) -> Vec<HtmlNode> {
+        fold_nodes(self, nodes)
+    }
+
+    fn fold_node(&mut self, node: HtmlNode) -> Vec<HtmlNode> {
+        fold_node(self, node)
+    }
+
+    fn fold_tag(&mut self, tag: HtmlTag) -> Vec<HtmlNode> {
+        fold_tag(self, tag)
+    }
+
+    /// Called before the contents of the tag are folded.
+    fn enter_tag(&mut self, _tag: &mut HtmlTag) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called after the contents of the tag are folded, or skipped, and gives the nodes
+    /// which replace the tag.
+    fn leave_tag(&mut self, tag: HtmlTag) -> Vec<HtmlNode> {
+        vec![HtmlNode::Tag(tag)]
+    }
+
+    fn fold_text(&mut self, text: String) -> Vec<HtmlNode> {
+        vec![HtmlNode::Text(text)]
+    }
+
+    fn fold_comment(&mut self, comment: String) -> Vec<HtmlNode> {
+        vec![HtmlNode::Comment(comment)]
+    }
+}
+
+/// Folds the top level nodes of the document.
+pub fn fold_document<F: Fold + ?Sized>(folder: &mut F, document: HtmlDocument) -> HtmlDocument {
+    HtmlDocument {
+        doctype: document.doctype,
+        nodes: folder.fold_nodes(document.nodes),
+    }
+}
+
+/// Folds each of the nodes and joins the nodes that replace them.
+pub fn fold_nodes<F: Fold + ?Sized>(folder: &mut F, nodes: Vec<HtmlNode>) -> Vec<HtmlNode> {
+    let mut folded = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        folded.extend(folder.fold_node(node));
+    }
+    folded
+}
+
+/// Calls the method of the folder for the kind of node.
+pub fn fold_node<F: Fold + ?Sized>(folder: &mut F, node: HtmlNode) -> Vec<HtmlNode> {
+    match node {
+        HtmlNode::Tag(tag) => folder.fold_tag(tag),
+        HtmlNode::Text(text) => folder.fold_text(text),
+        HtmlNode::Comment(comment) => folder.fold_comment(comment),
+    }
+}
+
+/// Enters the tag, folds its contents unless they are skipped, then leaves the tag.
+pub fn fold_tag<F: Fold + ?Sized>(folder: &mut F, mut tag: HtmlTag) -> Vec<HtmlNode> {
+    if folder.enter_tag(&mut tag) == Walk::Continue {
+        let contents = std::mem::take(&mut tag.contents);
+        tag.contents = folder.fold_nodes(contents);
+    }
+    folder.leave_tag(tag)
+}
+
+#[cfg(test)]
+mod visit_tests {
+    use super::*;
+
+    fn doc(html: &str) -> HtmlDocument {
+        html.parse::<HtmlDocument>().unwrap()
+    }
+
+    /// Records the order things are visited in, skipping the contents of tables.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl<'a> Visit<'a> for Recorder {
+        fn enter_tag(&mut self, tag: &'a HtmlTag) -> Walk {
+            self.0.push(format!("<{}>", tag.tag));
+            match tag.tag.as_str() {
+                "table" => Walk::SkipChildren,
+                _ => Walk::Continue,
+            }
+        }
+
+        fn leave_tag(&mut self, tag: &'a HtmlTag) {
+            self.0.push(format!("</{}>", tag.tag));
+        }
+
+        fn visit_text(&mut self, text: &'a str) {
+            self.0.push(text.to_owned());
+        }
+
+        fn visit_comment(&mut self, comment: &'a str) {
+            self.0.push(format!("<!--{}-->", comment));
+        }
+    }
+
+    #[test]
+    fn visit_test() {
+        let d = doc("<div><p>a<b>b</b></p><!-- c --><table><tr><td>x</td></tr></table></div>");
+        let mut recorder = Recorder::default();
+        recorder.visit_document(&d);
+        assert_eq!(
+            recorder.0,
+            vec![
+                "<div>",
+                "<p>",
+                "a",
+                "<b>",
+                "b",
+                "</b>",
+                "</p>",
+                "<!-- c -->",
+                "<table>",
+                "</table>",
+                "</div>"
+            ]
+        );
+        let p = doc("<p>a<b>b</b></p>");
+        let mut recorder = Recorder::default();
+        recorder.visit_node(&p.nodes[0]);
+        assert_eq!(recorder.0, vec!["<p>", "a", "<b>", "b", "</b>", "</p>"]);
+    }
+
+    struct Shout;
+
+    impl VisitMut for Shout {
+        fn enter_tag(&mut self, tag: &mut HtmlTag) -> Walk {
+            match tag.tag.as_str() {
+                "code" => Walk::SkipChildren,
+                _ => Walk::Continue,
+            }
+        }
+
+        fn leave_tag(&mut self, tag: &mut HtmlTag) {
+            tag.attributes.insert("data-seen".to_owned(), String::new());
+        }
+
+        fn visit_text_mut(&mut self, text: &mut String) {
+            *text = text.to_uppercase();
+        }
+    }
+
+    #[test]
+    fn visit_mut_test() {
+        let mut d = doc("<p>quiet <code>code</code></p>");
+        Shout.visit_document_mut(&mut d);
+        assert_eq!(d, doc("<p data-seen>QUIET <code data-seen>code</code></p>"));
+    }
+
+    /// Unwraps spans, removes comments, splits text on '|' and skips the contents of pre.
+    struct Rewrite;
+
+    impl Fold for Rewrite {
+        fn enter_tag(&mut self, tag: &mut HtmlTag) -> Walk {
+            match tag.tag.as_str() {
+                "pre" => Walk::SkipChildren,
+                _ => Walk::Continue,
+            }
+        }
+
+        fn leave_tag(&mut self, tag: HtmlTag) -> Vec<HtmlNode> {
+            match tag.tag.as_str() {
+                "span" => tag.contents,
+                _ => vec![HtmlNode::Tag(tag)],
+            }
+        }
+
+        fn fold_text(&mut self, text: String) -> Vec<HtmlNode> {
+            let mut nodes = vec![];
+            for (i, part) in text.split('|').enumerate() {
+                if i > 0 {
+                    nodes.push(HtmlNode::Tag(HtmlTag::new("br")));
+                }
+                nodes.push(HtmlNode::Text(part.to_owned()));
+            }
+            nodes
+        }
+
+        fn fold_comment(&mut self, _: String) -> Vec<HtmlNode> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn fold_test() {
+        let d = doc("<div><!-- x --><span>a|<span>b</span></span><pre>c|d</pre></div>");
+        let folded = Rewrite.fold_document(d);
+        let mut expected = doc("<div>a<br>b<pre>c|d</pre></div>");
+        // the text from the unwrapped spans stays as separate nodes
+        if let HtmlNode::Tag(div) = &mut expected.nodes[0] {
+            div.contents.insert(2, HtmlNode::Text(String::new()));
+        }
+        assert_eq!(folded, expected);
+        let tag = HtmlTag::new("span").contents(vec![HtmlNode::Text("x|y".to_owned())]);
+        assert_eq!(
+            Rewrite.fold_tag(tag),
+            vec![
+                HtmlNode::Text("x".to_owned()),
+                HtmlNode::Tag(HtmlTag::new("br")),
+                HtmlNode::Text("y".to_owned())
+            ]
+        );
+    }
+}
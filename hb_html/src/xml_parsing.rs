@@ -0,0 +1,1042 @@
+use crate::entities::{collapse_whitespace, encode_entities};
+use crate::objects::{HtmlDocument, HtmlNode, HtmlTag};
+use hb_error::{context, ErrorContext};
+use hb_parse::error::{ParseError, ParseResult};
+use hb_parse::source::Source;
+use std::collections::HashMap;
+
+/// The namespace that the `xml` prefix is always bound to.
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+/// How many characters entity references can expand to in one document, so entities
+/// which refer to each other many times can't use up all of the memory.
+const MAX_ENTITY_EXPANSION: usize = 1 << 20;
+/// How deep entities can refer to other entities before the parser gives up.
+const MAX_ENTITY_DEPTH: usize = 16;
+
+/// Parsing functions for XML which can be used on any [`Source`].
+pub trait XmlParserFunctions {
+    /// Parses a whole XML document into the same tree that is used for HTML. Text and
+    /// attribute values keep their character references as they were written, CDATA
+    /// sections become encoded text and the entities declared in the doctype are expanded
+    /// as text. Processing instructions are skipped.
+    fn parse_xml(&mut self) -> ParseResult<HtmlDocument>;
+
+    /// Parses XML like [`XmlParserFunctions::parse_xml`], but recovers from the mistakes
+    /// that are common in feeds found on the web instead of failing: text around the root
+    /// element, a bare `&` or `<` in text, undeclared entities such as `&nbsp;`, unclosed
+    /// or misnested tags, attributes without quotes or values and undeclared namespace
+    /// prefixes. Tags with an undeclared prefix have no namespace.
+    fn parse_xml_lenient(&mut self) -> ParseResult<HtmlDocument>;
+}
+
+impl<S: Source> XmlParserFunctions for S {
+    #[context("could not parse xml")]
+    fn parse_xml(&mut self) -> ParseResult<HtmlDocument> {
+        XmlReader::new(self, false).document()
+    }
+
+    #[context("could not parse xml")]
+    fn parse_xml_lenient(&mut self) -> ParseResult<HtmlDocument> {
+        XmlReader::new(self, true).document()
+    }
+}
+
+/// Reads XML from a source, keeping the state that is needed across the document.
+struct XmlReader<'s, S: Source> {
+    source: &'s mut S,
+    line: usize,
+    column: usize,
+    /// The entities declared in the doctype. External entities have no value.
+    entities: HashMap<String, Option<String>>,
+    /// Whether the doctype refers to declarations that are not in the document, in which
+    /// case unknown entities are kept as they are written.
+    external_declarations: bool,
+    /// Whether the XML declaration says the document doesn't use outside declarations.
+    standalone: bool,
+    /// The namespace prefixes in scope, innermost last. The empty prefix is the default
+    /// namespace.
+    namespaces: Vec<(String, String)>,
+    /// How many characters entities have expanded to so far.
+    expanded: usize,
+    /// Whether to recover from the mistakes that [`XmlParserFunctions::parse_xml_lenient`]
+    /// allows.
+    lenient: bool,
+}
+
+impl<'s, S: Source> XmlReader<'s, S> {
+    fn new(source: &'s mut S, lenient: bool) -> XmlReader<'s, S> {
+        XmlReader {
+            source,
+            line: 1,
+            column: 1,
+            entities: HashMap::new(),
+            external_declarations: false,
+            standalone: false,
+            namespaces: vec![],
+            expanded: 0,
+            lenient,
+        }
+    }
+
+    fn error<M: Into<String>>(&self, msg: M) -> ParseError {
+        ParseError::new().msg(format!(
+            "{} at line {} column {}",
+            msg.into(),
+            self.line,
+            self.column
+        ))
+    }
+
+    /// Gets the next character, turning "\r\n" and "\r" into "\n".
+    fn next(&mut self) -> ParseResult<Option<char>> {
+        let c = match self.source.next()? {
+            Some((_, '\r')) => {
+                if let Some((_, '\n')) = self.source.peek()? {
+                    self.source.next()?;
+                }
+                '\n'
+            }
+            Some((_, c)) => c,
+            None => return Ok(None),
+        };
+        match c {
+            '\n' => {
+                self.line += 1;
+                self.column = 1;
+            }
+            _ => self.column += 1,
+        }
+        Ok(Some(c))
+    }
+
+    fn peek(&mut self) -> ParseResult<Option<char>> {
+        Ok(self.source.peek()?.map(|(_, c)| c))
+    }
+
+    /// Reads the next character, which must not be the end of the document.
+    fn next_in<M: Into<String>>(&mut self, context: M) -> ParseResult<char> {
+        match self.next()? {
+            Some(c) => Ok(c),
+            None => Err(self.error(format!("the document ended inside {}", context.into()))),
+        }
+    }
+
+    /// Reads the text, which must come next.
+    fn expect(&mut self, text: &str) -> ParseResult<()> {
+        for expected in text.chars() {
+            match self.next()? {
+                Some(c) if c == expected => (),
+                _ => return Err(self.error(format!("expected '{}'", text))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips any white space, returning whether there was some.
+    fn skip_whitespace(&mut self) -> ParseResult<bool> {
+        let mut skipped = false;
+        while let Some(c) = self.peek()? {
+            if !matches!(c, ' ' | '\t' | '\n' | '\r') {
+                break;
+            }
+            self.next()?;
+            skipped = true;
+        }
+        Ok(skipped)
+    }
+
+    /// Reads characters up to and including the end text, returning the characters
+    /// before it.
+    fn until(&mut self, end: &str, context: &str) -> ParseResult<String> {
+        let mut text = String::new();
+        while !text.ends_with(end) {
+            text.push(self.next_in(context)?);
+        }
+        text.truncate(text.len() - end.len());
+        Ok(text)
+    }
+
+    fn name(&mut self) -> ParseResult<String> {
+        let mut name = String::new();
+        match self.peek()? {
+            Some(c) if is_name_start(c) => (),
+            Some(c) => return Err(self.error(format!("expected a name but found '{}'", c))),
+            None => return Err(self.error("expected a name but found the end")),
+        }
+        while let Some(c) = self.peek()? {
+            if !is_name_char(c) {
+                break;
+            }
+            name.push(c);
+            self.next()?;
+        }
+        Ok(name)
+    }
+
+    fn document(&mut self) -> ParseResult<HtmlDocument> {
+        let mut document = HtmlDocument::new();
+        if self.peek()? == Some('\u{feff}') {
+            self.source.next()?;
+        }
+        let mut first = true;
+        let mut root = false;
+        loop {
+            if self.skip_whitespace()? {
+                first = false;
+            }
+            match self.next()? {
+                None => break,
+                Some('<') => (),
+                Some(_) if self.lenient => continue,
+                Some(_) => {
+                    return Err(self.error("text is not allowed outside of the root element"))
+                }
+            }
+            match self.peek()? {
+                Some('?') => {
+                    self.next()?;
+                    let target = self.name()?;
+                    if first && target == "xml" {
+                        self.declaration()?;
+                    } else {
+                        self.processing_instruction(&target)?;
+                    }
+                }
+                Some('!') => {
+                    self.next()?;
+                    match self.peek()? {
+                        Some('-') => {
+                            let comment = self.comment()?;
+                            document.nodes.push(HtmlNode::Comment(comment));
+                        }
+                        Some('D') if !root && document.doctype.is_empty() => {
+                            self.expect("DOCTYPE")?;
+                            document.doctype = self.doctype()?;
+                        }
+                        _ if self.lenient => self.skip_declaration()?,
+                        _ => {
+                            return Err(self.error(
+                                "expected a comment or the doctype outside of the root element",
+                            ))
+                        }
+                    }
+                }
+                _ if root && !self.lenient => {
+                    return Err(self.error("there can only be one root element"))
+                }
+                _ => {
+                    let tag = self.element()?;
+                    document.nodes.push(HtmlNode::Tag(tag));
+                    root = true;
+                }
+            }
+            first = false;
+        }
+        match root {
+            true => Ok(document),
+            false => Err(self.error("the document has no root element")),
+        }
+    }
+
+    /// Reads the XML declaration after "<?xml".
+    fn declaration(&mut self) -> ParseResult<()> {
+        let mut names = vec![];
+        loop {
+            let space = self.skip_whitespace()?;
+            if self.peek()? == Some('?') {
+                self.expect("?>")?;
+                break;
+            }
+            if !space {
+                return Err(self.error("expected white space in the xml declaration"));
+            }
+            let name = self.name()?;
+            self.skip_whitespace()?;
+            self.expect("=")?;
+            self.skip_whitespace()?;
+            let value = self.quoted("the xml declaration")?;
+            match name.as_str() {
+                "version" if names.is_empty() && value.starts_with("1.") => (),
+                "encoding" if names == ["version"] => (),
+                "standalone" if names.contains(&"version".to_owned()) => match value.as_str() {
+                    "yes" => self.standalone = true,
+                    "no" => (),
+                    _ => return Err(self.error("standalone must be 'yes' or 'no'")),
+                },
+                _ => {
+                    return Err(self.error(format!(
+                        "unexpected {}=\"{}\" in the xml declaration",
+                        name, value
+                    )))
+                }
+            }
+            names.push(name);
+        }
+        match names.is_empty() {
+            true => Err(self.error("the xml declaration needs a version")),
+            false => Ok(()),
+        }
+    }
+
+    /// Skips a processing instruction after its target.
+    fn processing_instruction(&mut self, target: &str) -> ParseResult<()> {
+        if target.eq_ignore_ascii_case("xml") && !self.lenient {
+            return Err(self.error("the xml declaration must be at the start of the document"));
+        }
+        self.until("?>", "a processing instruction")?;
+        Ok(())
+    }
+
+    /// Reads a comment after "<!".
+    fn comment(&mut self) -> ParseResult<String> {
+        self.expect("--")?;
+        if self.lenient {
+            return self.until("-->", "a comment");
+        }
+        let comment = self.until("--", "a comment")?;
+        match self.next()? {
+            Some('>') => Ok(comment),
+            _ => Err(self.error("'--' is not allowed inside a comment")),
+        }
+    }
+
+    /// Reads a quoted value, such as an attribute value in a declaration.
+    fn quoted(&mut self, context: &str) -> ParseResult<String> {
+        let quote = match self.next()? {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => return Err(self.error(format!("expected a quoted value in {}", context))),
+        };
+        let mut value = String::new();
+        loop {
+            match self.next_in(context)? {
+                c if c == quote => return Ok(value),
+                c => value.push(c),
+            }
+        }
+    }
+
+    /// Reads the doctype after "<!DOCTYPE", returning it without the internal subset.
+    fn doctype(&mut self) -> ParseResult<String> {
+        if !self.skip_whitespace()? {
+            return Err(self.error("expected white space after DOCTYPE"));
+        }
+        let mut doctype = self.name()?;
+        loop {
+            match self.peek()? {
+                Some('"') | Some('\'') => {
+                    let quote = self.peek()?.unwrap_or('"');
+                    let value = self.quoted("the doctype")?;
+                    doctype.push(quote);
+                    doctype.push_str(&value);
+                    doctype.push(quote);
+                }
+                Some('[') => {
+                    self.next()?;
+                    self.internal_subset()?;
+                }
+                Some('>') => {
+                    self.next()?;
+                    break;
+                }
+                Some(c) => {
+                    doctype.push(c);
+                    self.next()?;
+                }
+                None => return Err(self.error("the document ended inside the doctype")),
+            }
+        }
+        let doctype = collapse_whitespace(&doctype);
+        let mut words = doctype.split(' ');
+        if matches!(words.nth(1), Some("SYSTEM") | Some("PUBLIC")) {
+            self.external_declarations = true;
+        }
+        Ok(doctype)
+    }
+
+    /// Reads the declarations in the doctype after the "[", keeping the entities.
+    fn internal_subset(&mut self) -> ParseResult<()> {
+        loop {
+            self.skip_whitespace()?;
+            match self.next_in("the doctype")? {
+                ']' => return Ok(()),
+                '%' => {
+                    // a parameter entity can bring in declarations from outside
+                    self.name()?;
+                    self.expect(";")?;
+                    self.external_declarations = true;
+                }
+                '<' => match self.next_in("the doctype")? {
+                    '?' => {
+                        let target = self.name()?;
+                        self.processing_instruction(&target)?;
+                    }
+                    '!' if self.peek()? == Some('-') => {
+                        self.comment()?;
+                    }
+                    '!' => match self.name()?.as_str() {
+                        "ENTITY" => self.entity_declaration()?,
+                        "ELEMENT" | "ATTLIST" | "NOTATION" => self.skip_declaration()?,
+                        name => return Err(self.error(format!("unknown declaration {}", name))),
+                    },
+                    c => return Err(self.error(format!("unexpected '{}' in the doctype", c))),
+                },
+                c => return Err(self.error(format!("unexpected '{}' in the doctype", c))),
+            }
+        }
+    }
+
+    /// Skips to the end of a declaration in the doctype.
+    fn skip_declaration(&mut self) -> ParseResult<()> {
+        loop {
+            match self.peek()? {
+                Some('"') | Some('\'') => {
+                    self.quoted("a declaration")?;
+                }
+                Some('>') => {
+                    self.next()?;
+                    return Ok(());
+                }
+                _ => {
+                    self.next_in("a declaration")?;
+                }
+            }
+        }
+    }
+
+    /// Reads an entity declaration after "<!ENTITY".
+    fn entity_declaration(&mut self) -> ParseResult<()> {
+        self.skip_whitespace()?;
+        if self.peek()? == Some('%') {
+            // parameter entities are only used inside the doctype
+            return self.skip_declaration();
+        }
+        let name = self.name()?;
+        self.skip_whitespace()?;
+        let value = match self.peek()? {
+            Some('"') | Some('\'') => {
+                let value = self.quoted("an entity declaration")?;
+                self.skip_whitespace()?;
+                self.expect(">")?;
+                Some(value)
+            }
+            _ => {
+                self.skip_declaration()?;
+                None
+            }
+        };
+        // the first declaration of an entity is the one that is used
+        self.entities.entry(name).or_insert(value);
+        Ok(())
+    }
+
+    /// Reads an element after its "<", including all of its contents.
+    fn element(&mut self) -> ParseResult<HtmlTag> {
+        let mut open: Vec<(HtmlTag, usize)> = vec![];
+        let mut text = String::new();
+        let scope = self.namespaces.len();
+        let (tag, closed) = self.start_tag()?;
+        if closed {
+            self.namespaces.truncate(scope);
+            return Ok(tag);
+        }
+        open.push((tag, scope));
+        loop {
+            let c = match self.next()? {
+                Some(c) => c,
+                None if self.lenient => {
+                    if let Some((parent, _)) = open.last_mut() {
+                        push_text(&mut parent.contents, &mut text);
+                    }
+                    loop {
+                        match self.close_tag(&mut open) {
+                            Some(root) => return Ok(root),
+                            None if open.is_empty() => return Err(self.error("no open tags")),
+                            None => (),
+                        }
+                    }
+                }
+                None => {
+                    let name = open.last().map(|(t, _)| t.tag.clone()).unwrap_or_default();
+                    return Err(self.error(format!("the tag <{}> was not closed", name)));
+                }
+            };
+            let parent = match open.last_mut() {
+                Some((tag, _)) => tag,
+                None => return Err(self.error("found content after the root element")),
+            };
+            match c {
+                '<' => {
+                    push_text(&mut parent.contents, &mut text);
+                    match self.peek()? {
+                        Some('/') => {
+                            self.next()?;
+                            let name = self.name()?;
+                            self.skip_whitespace()?;
+                            self.expect(">")?;
+                            let depth = match open.iter().rposition(|(t, _)| t.tag == name) {
+                                Some(depth) if self.lenient || depth + 1 == open.len() => depth,
+                                // a closing tag which was never opened is dropped
+                                _ if self.lenient => continue,
+                                _ => {
+                                    let expected = open.last().map(|(t, _)| t.tag.as_str());
+                                    return Err(self.error(format!(
+                                        "expected </{}> but found </{}>",
+                                        expected.unwrap_or_default(),
+                                        name
+                                    )));
+                                }
+                            };
+                            while open.len() > depth {
+                                if let Some(root) = self.close_tag(&mut open) {
+                                    return Ok(root);
+                                }
+                            }
+                        }
+                        Some('!') => {
+                            self.next()?;
+                            match self.peek()? {
+                                Some('-') => {
+                                    let comment = self.comment()?;
+                                    parent.contents.push(HtmlNode::Comment(comment));
+                                }
+                                Some('[') => {
+                                    self.expect("[CDATA[")?;
+                                    let data = self.until("]]>", "a CDATA section")?;
+                                    text.push_str(&encode_entities(&data));
+                                }
+                                _ if self.lenient => self.skip_declaration()?,
+                                _ => return Err(self.error("expected a comment or CDATA")),
+                            }
+                        }
+                        Some('?') => {
+                            self.next()?;
+                            let target = self.name()?;
+                            self.processing_instruction(&target)?;
+                        }
+                        Some(c) if self.lenient && !is_name_start(c) => text.push_str("&lt;"),
+                        _ => {
+                            let scope = self.namespaces.len();
+                            let (tag, closed) = self.start_tag()?;
+                            match closed {
+                                true => {
+                                    self.namespaces.truncate(scope);
+                                    parent.contents.push(HtmlNode::Tag(tag));
+                                }
+                                false => open.push((tag, scope)),
+                            }
+                        }
+                    }
+                }
+                '&' => self.reference(&mut text)?,
+                '>' if text.ends_with("]]") && !self.lenient => {
+                    return Err(self.error("']]>' is not allowed in text"))
+                }
+                c => text.push(c),
+            }
+        }
+    }
+
+    /// Reads a start tag after its "<", returning the tag and whether it was self-closing.
+    /// The namespaces declared by the tag are added to the ones in scope.
+    fn start_tag(&mut self) -> ParseResult<(HtmlTag, bool)> {
+        let name = self.name()?;
+        let mut attributes: Vec<(String, String)> = vec![];
+        let closed = loop {
+            let space = self.skip_whitespace()?;
+            match self.peek()? {
+                Some('>') => {
+                    self.next()?;
+                    break false;
+                }
+                Some('/') => {
+                    self.next()?;
+                    self.expect(">")?;
+                    break true;
+                }
+                None => return Err(self.error(format!("the tag <{}> was not finished", name))),
+                _ if !space && !self.lenient => {
+                    return Err(self.error("expected white space between attributes"))
+                }
+                _ => (),
+            }
+            let attribute = self.name()?;
+            self.skip_whitespace()?;
+            let value = match self.peek()? {
+                Some(c) if c != '=' && self.lenient => String::new(),
+                _ => {
+                    self.expect("=")?;
+                    self.skip_whitespace()?;
+                    self.attribute_value()?
+                }
+            };
+            if attributes.iter().any(|(a, _)| *a == attribute) {
+                if self.lenient {
+                    continue;
+                }
+                return Err(self.error(format!("the attribute {} is repeated", attribute)));
+            }
+            attributes.push((attribute, value));
+        };
+        for (attribute, value) in attributes.iter() {
+            if attribute == "xmlns" {
+                self.namespaces.push((String::new(), value.clone()));
+            } else if let Some(prefix) = attribute.strip_prefix("xmlns:") {
+                if value.is_empty()
+                    || prefix == "xmlns"
+                    || (prefix == "xml") != (value == XML_NAMESPACE)
+                {
+                    if self.lenient {
+                        continue;
+                    }
+                    return Err(
+                        self.error(format!("can't bind the prefix {} to \"{}\"", prefix, value))
+                    );
+                }
+                self.namespaces.push((prefix.to_owned(), value.clone()));
+            }
+        }
+        let mut tag = HtmlTag::new(name.as_str());
+        tag.namespace = match self.qualified_name(&name) {
+            Ok(Some(prefix)) => match self.namespace_uri(prefix) {
+                Ok(uri) => Some(uri),
+                Err(_) if self.lenient => None,
+                Err(e) => return Err(e),
+            },
+            Ok(None) => self.namespace_uri("").ok().filter(|uri| !uri.is_empty()),
+            Err(_) if self.lenient => None,
+            Err(e) => return Err(e),
+        };
+        for (attribute, value) in attributes {
+            if !self.lenient {
+                if let Some(prefix) = self.qualified_name(&attribute)? {
+                    if prefix != "xmlns" {
+                        self.namespace_uri(prefix)?;
+                    }
+                }
+            }
+            match attribute.as_str() {
+                "id" => tag.ids = value.split_whitespace().map(|i| i.to_owned()).collect(),
+                "class" => tag.classes = value.split_whitespace().map(|c| c.to_owned()).collect(),
+                _ => {
+                    tag.attributes.insert(attribute, value);
+                }
+            }
+        }
+        Ok((tag, closed))
+    }
+
+    /// Closes the innermost open tag, adding it to its parent, and returns it if it was the
+    /// root element.
+    fn close_tag(&mut self, open: &mut Vec<(HtmlTag, usize)>) -> Option<HtmlTag> {
+        let (tag, scope) = open.pop()?;
+        self.namespaces.truncate(scope);
+        match open.last_mut() {
+            Some((parent, _)) => {
+                parent.contents.push(HtmlNode::Tag(tag));
+                None
+            }
+            None => Some(tag),
+        }
+    }
+
+    /// Checks the name has at most one colon, returning the prefix before it.
+    fn qualified_name<'n>(&self, name: &'n str) -> ParseResult<Option<&'n str>> {
+        match name.split_once(':') {
+            None => Ok(None),
+            Some((prefix, local))
+                if !prefix.is_empty() && !local.is_empty() && !local.contains(':') =>
+            {
+                Ok(Some(prefix))
+            }
+            Some(_) => Err(self.error(format!("{} is not a valid qualified name", name))),
+        }
+    }
+
+    /// Gets the namespace URI the prefix is bound to.
+    fn namespace_uri(&self, prefix: &str) -> ParseResult<String> {
+        if prefix == "xml" {
+            return Ok(XML_NAMESPACE.to_owned());
+        }
+        match self.namespaces.iter().rev().find(|(p, _)| p == prefix) {
+            Some((_, uri)) => Ok(uri.clone()),
+            None => Err(self.error(format!("the namespace prefix {} is not declared", prefix))),
+        }
+    }
+
+    /// Reads a quoted attribute value, turning white space characters into spaces.
+    fn attribute_value(&mut self) -> ParseResult<String> {
+        let quote = match self.peek()? {
+            Some(c) if c == '"' || c == '\'' => c,
+            Some(_) if self.lenient => return self.unquoted_value(),
+            _ => return Err(self.error("expected a quoted attribute value")),
+        };
+        self.next()?;
+        let mut value = String::new();
+        loop {
+            match self.next_in("an attribute value")? {
+                c if c == quote => return Ok(value),
+                '<' if self.lenient => value.push_str("&lt;"),
+                '<' => return Err(self.error("'<' is not allowed in attribute values")),
+                '&' => self.reference(&mut value)?,
+                '\t' | '\n' => value.push(' '),
+                c => value.push(c),
+            }
+        }
+    }
+
+    /// Reads an attribute value without quotes, which ends at white space or the end of
+    /// the tag.
+    fn unquoted_value(&mut self) -> ParseResult<String> {
+        let mut value = String::new();
+        while let Some(c) = self.peek()? {
+            match c {
+                ' ' | '\t' | '\n' | '\r' | '>' => break,
+                '&' => {
+                    self.next()?;
+                    self.reference(&mut value)?;
+                }
+                '<' => {
+                    self.next()?;
+                    value.push_str("&lt;");
+                }
+                c => {
+                    self.next()?;
+                    value.push(c);
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Reads a reference after the "&". Character references and the predefined entities
+    /// are kept as they were written, and declared entities are expanded.
+    fn reference(&mut self, output: &mut String) -> ParseResult<()> {
+        if self.peek()? == Some('#') {
+            self.next()?;
+            let mut number = String::new();
+            while let Some(c) = self.peek()? {
+                if !c.is_ascii_alphanumeric() || number.len() >= 10 {
+                    break;
+                }
+                number.push(c);
+                self.next()?;
+            }
+            let closed = self.peek()? == Some(';');
+            if closed {
+                self.next()?;
+            }
+            let code = match number.strip_prefix('x') {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse::<u32>().ok(),
+            };
+            return match code.and_then(char::from_u32) {
+                Some(c) if c != '\0' && closed => {
+                    output.push_str(&format!("&#{};", number));
+                    Ok(())
+                }
+                _ if self.lenient => {
+                    output.push_str("&amp;#");
+                    output.push_str(&number);
+                    if closed {
+                        output.push(';');
+                    }
+                    Ok(())
+                }
+                _ => Err(self.error(format!("&#{}; is not a valid character reference", number))),
+            };
+        }
+        match self.peek()? {
+            Some(c) if self.lenient && !is_name_start(c) => {
+                output.push_str("&amp;");
+                return Ok(());
+            }
+            None if self.lenient => {
+                output.push_str("&amp;");
+                return Ok(());
+            }
+            _ => (),
+        }
+        let name = self.name()?;
+        if self.lenient && self.peek()? != Some(';') {
+            output.push_str("&amp;");
+            output.push_str(&name);
+            return Ok(());
+        }
+        self.expect(";")?;
+        self.entity(&name, output, 0).map_err(|msg| self.error(msg))
+    }
+
+    /// Adds the text of the entity to the output.
+    fn entity(&mut self, name: &str, output: &mut String, depth: usize) -> Result<(), String> {
+        if matches!(name, "lt" | "gt" | "amp" | "apos" | "quot") {
+            output.push_str(&format!("&{};", name));
+            return Ok(());
+        }
+        let value = match self.entities.get(name) {
+            Some(Some(value)) => value.clone(),
+            Some(None) => {
+                output.push_str(&format!("&{};", name));
+                return Ok(());
+            }
+            None if self.lenient || (self.external_declarations && !self.standalone) => {
+                output.push_str(&format!("&{};", name));
+                return Ok(());
+            }
+            None => return Err(format!("the entity &{}; is not declared", name)),
+        };
+        if depth >= MAX_ENTITY_DEPTH {
+            return Err(format!("the entity &{}; refers to itself", name));
+        }
+        self.expanded += value.len();
+        if self.expanded > MAX_ENTITY_EXPANSION {
+            return Err("the entities expand to too much text".to_owned());
+        }
+        let mut rest = value.as_str();
+        while let Some(start) = rest.find(['&', '<', '>']) {
+            output.push_str(&rest[..start]);
+            let c = rest[start..].chars().next().unwrap_or('&');
+            rest = &rest[start + 1..];
+            match c {
+                '<' => output.push_str("&lt;"),
+                '>' => output.push_str("&gt;"),
+                _ => {
+                    let end = match rest.find(';') {
+                        Some(end) => end,
+                        None => return Err(format!("invalid reference in the entity &{};", name)),
+                    };
+                    match rest[..end].starts_with('#') {
+                        true => output.push_str(&format!("&{};", &rest[..end])),
+                        false => self.entity(&rest[..end], output, depth + 1)?,
+                    }
+                    rest = &rest[end + 1..];
+                }
+            }
+        }
+        output.push_str(rest);
+        Ok(())
+    }
+}
+
+/// Adds the text to the end of the nodes, joining it to any text already there.
+fn push_text(nodes: &mut Vec<HtmlNode>, text: &mut String) {
+    if text.is_empty() {
+        return;
+    }
+    match nodes.last_mut() {
+        Some(HtmlNode::Text(last)) => last.push_str(text),
+        _ => nodes.push(HtmlNode::Text(text.clone())),
+    }
+    text.clear();
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == ':' || (!c.is_ascii() && !c.is_whitespace())
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || matches!(c, '-' | '.' | '\u{b7}')
+}
+
+#[cfg(test)]
+mod xml_parsing_tests {
+    use super::*;
+    use hb_parse::StrParser;
+
+    fn parse(xml: &str) -> ParseResult<HtmlDocument> {
+        StrParser::new(xml).parse_xml()
+    }
+
+    #[test]
+    fn parse_xml_test() {
+        let doc = parse(
+            "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\" standalone='yes'?>\r\n\
+            <!-- top -->\n\
+            <?xml-stylesheet href=\"style.xsl\"?>\n\
+            <root xmlns=\"urn:a\" xmlns:b=\"urn:b\" id=\"r\" class=\"x y\">\n\
+              <b:item b:kind=\"one\" note='a &amp; b&#x21;'>Fish &lt; chips<br/></b:item>\n\
+              <Item>Case</Item><![CDATA[<raw> & ]]]]><![CDATA[>]]>\n\
+              <inner xmlns=\"\"><deep xml:lang=\"en\"/></inner>\n\
+            </root>\n",
+        )
+        .unwrap();
+        assert_eq!(doc.doctype, "");
+        assert_eq!(doc.nodes.len(), 2);
+        assert_eq!(doc.nodes[0], HtmlNode::Comment(" top ".to_owned()));
+        let root = match &doc.nodes[1] {
+            HtmlNode::Tag(t) => t,
+            _ => panic!("expected the root"),
+        };
+        assert_eq!(root.tag, "root");
+        assert_eq!(root.namespace.as_deref(), Some("urn:a"));
+        assert_eq!(root.ids, vec!["r"]);
+        assert_eq!(root.classes, vec!["x", "y"]);
+        assert_eq!(root.attributes["xmlns:b"], "urn:b");
+        let tags: Vec<&HtmlTag> = root
+            .contents
+            .iter()
+            .filter_map(|n| match n {
+                HtmlNode::Tag(t) => Some(t),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tags[0].tag, "b:item");
+        assert_eq!(tags[0].local_name(), "item");
+        assert_eq!(tags[0].namespace.as_deref(), Some("urn:b"));
+        assert_eq!(tags[0].attributes["note"], "a &amp; b&#x21;");
+        assert_eq!(tags[0].attributes["b:kind"], "one");
+        assert_eq!(
+            tags[0].contents,
+            vec![
+                HtmlNode::Text("Fish &lt; chips".to_owned()),
+                HtmlNode::Tag(HtmlTag::new("br").namespace("urn:a"))
+            ]
+        );
+        assert_eq!(tags[1].tag, "Item");
+        assert_eq!(tags[1].namespace.as_deref(), Some("urn:a"));
+        assert_eq!(
+            root.contents[4],
+            HtmlNode::Text("&lt;raw&gt; &amp; ]]&gt;\n".to_owned())
+        );
+        assert_eq!(tags[2].namespace, None);
+        let deep = match &tags[2].contents[0] {
+            HtmlNode::Tag(t) => t,
+            _ => panic!("expected deep"),
+        };
+        assert_eq!(deep.namespace, None);
+        assert_eq!(deep.attributes["xml:lang"], "en");
+    }
+
+    #[test]
+    fn parse_xml_doctype_test() {
+        let doc = parse(
+            r#"<!DOCTYPE note [
+                <!ELEMENT note (#PCDATA)>
+                <!ATTLIST note kind CDATA "x>y">
+                <!-- a comment -->
+                <!ENTITY who "World">
+                <!ENTITY greeting "Hello &who;&#33; <b>">
+                <!ENTITY who "Ignored">
+                <!ENTITY logo SYSTEM "logo.png">
+                <!ENTITY % shared "x">
+            ]>
+            <note title="&greeting;">&greeting; &logo;</note>"#,
+        )
+        .unwrap();
+        assert_eq!(doc.doctype, "note");
+        let note = match &doc.nodes[0] {
+            HtmlNode::Tag(t) => t,
+            _ => panic!("expected note"),
+        };
+        assert_eq!(note.attributes["title"], "Hello World&#33; &lt;b&gt;");
+        assert_eq!(
+            note.contents,
+            vec![HtmlNode::Text(
+                "Hello World&#33; &lt;b&gt; &logo;".to_owned()
+            )]
+        );
+
+        let doc = parse(
+            r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN"
+                "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">
+            <html xmlns="http://www.w3.org/1999/xhtml"><p>&nbsp;&copy;</p></html>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            doc.doctype,
+            r#"html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd""#
+        );
+        assert_eq!(doc.nodes[0].text(), "&nbsp;&copy;");
+    }
+
+    #[test]
+    fn parse_xml_errors_test() {
+        for bad in [
+            "",
+            "text",
+            "<a>",
+            "<a></b>",
+            "<a><b></a></b>",
+            "<a/><b/>",
+            "<a>x</a>tail",
+            "<br>",
+            "<a x=1/>",
+            "<a x='1' x='2'/>",
+            "<a x='<'/>",
+            "<a x='1'y='2'/>",
+            "<a>&nbsp;</a>",
+            "<a>&#0;</a>",
+            "<a>&#xZZ;</a>",
+            "<a>& b</a>",
+            "<a>]]></a>",
+            "<a><!-- a -- b --></a>",
+            "<p:a/>",
+            "<a xmlns:p=''/>",
+            "<a p:x='1'/>",
+            "<a:b:c xmlns:a='urn:a'/>",
+            "<a/><?xml version='1.0'?>",
+            " <?xml version='1.0'?><a/>",
+            "<?xml encoding='UTF-8'?><a/>",
+            "<?xml version='1.0' standalone='maybe'?><a/>",
+            "<a><!DOCTYPE a></a>",
+            "<!DOCTYPE a [<!ENTITY e '&e;'>]><a>&e;</a>",
+            "<?xml version='1.0' standalone='yes'?><!DOCTYPE a SYSTEM 'a.dtd'><a>&x;</a>",
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+        let laughs = r#"<!DOCTYPE a [
+            <!ENTITY a "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa">
+            <!ENTITY b "&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;">
+            <!ENTITY c "&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;&b;">
+            <!ENTITY d "&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;&c;">
+            <!ENTITY e "&d;&d;&d;&d;&d;&d;&d;&d;&d;&d;&d;&d;&d;&d;&d;&d;">
+        ]><a>&e;</a>"#;
+        assert!(parse(laughs).is_err());
+        let error = format!("{}", parse("<a>\n  <b></c>\n</a>").unwrap_err());
+        assert!(
+            error.contains("expected </b> but found </c> at line 2"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn parse_xml_lenient_test() {
+        let xml = "junk <?xml version=\"1.0\"?>\n<rss><channel>\
+            <title>Fish & Chips &nbsp;&#xZZ; 1 < 2 &copy</title>\
+            <link href=/feed.xml rel=self async>https://example.com</link>\
+            <media:thumbnail url='a.jpg' url='b.jpg'/>\
+            <item><b>bold <i>both</b> after</i></item>\
+            <!-- a --->\
+            <description>unclosed";
+        let doc = StrParser::new(xml).parse_xml_lenient().unwrap();
+        assert!(parse(xml).is_err());
+        assert_eq!(doc.nodes.len(), 1);
+        let channel = match &doc.nodes[0] {
+            HtmlNode::Tag(rss) => match &rss.contents[0] {
+                HtmlNode::Tag(channel) => channel,
+                _ => panic!("expected channel"),
+            },
+            _ => panic!("expected rss"),
+        };
+        assert_eq!(
+            channel.contents[0].text(),
+            "Fish &amp; Chips &nbsp;&amp;#xZZ; 1 &lt; 2 &amp;copy"
+        );
+        let link = match &channel.contents[1] {
+            HtmlNode::Tag(link) => link,
+            _ => panic!("expected link"),
+        };
+        assert_eq!(link.attributes["href"], "/feed.xml");
+        assert_eq!(link.attributes["async"], "");
+        assert_eq!(link.text(), "https://example.com");
+        let thumbnail = match &channel.contents[2] {
+            HtmlNode::Tag(thumbnail) => thumbnail,
+            _ => panic!("expected thumbnail"),
+        };
+        assert_eq!(thumbnail.namespace, None);
+        assert_eq!(thumbnail.attributes["url"], "a.jpg");
+        assert_eq!(channel.contents[3].text(), "bold both after");
+        assert_eq!(channel.contents[4], HtmlNode::Comment(" a -".to_owned()));
+        assert_eq!(channel.contents[5].text(), "unclosed");
+    }
+}
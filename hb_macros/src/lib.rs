@@ -1,11 +1,20 @@
+use hb_selector::elements::VOID_ELEMENTS;
+use hb_selector::error::ParseHtmlError;
+use hb_selector::selector::{
+    CssAttributeCompareType, CssRefiner, CssRefinerNumberType, CssSelector, CssSelectorItem,
+    CssSelectorRelationship, CssSelectorRule,
+};
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::{quote, TokenStreamExt};
+use syn::ext::IdentExt;
 use syn::fold::{self, Fold};
+use syn::parse::{Parse, ParseStream};
 use syn::token::Comma;
 use syn::{
-    parse_macro_input, parse_quote, Attribute, Expr, ExprMatch, FieldValue, Fields, Ident, Item,
-    ItemFn, ItemStruct, LitStr, ReturnType, Type, Variant,
+    parse_macro_input, parse_quote, Attribute, Data, DataStruct, DeriveInput, Expr, ExprMatch,
+    FieldValue, Fields, GenericArgument, Ident, Item, ItemFn, ItemStruct, Lit, LitStr, Meta,
+    NestedMeta, PathArguments, ReturnType, Token, Type, Variant,
 };
 
 /// Struct to handle the folding of the ItemFn.
@@ -859,3 +868,837 @@ pub fn hberror(args: TokenStream, input: TokenStream) -> TokenStream {
     };
     final_output.into()
 }
+
+/// How a field of a struct deriving HtmlExtract is filled.
+struct HtmlExtractField {
+    ident: Ident,
+    selector: LitStr,
+    attr: Option<LitStr>,
+    nested: bool,
+}
+
+impl HtmlExtractField {
+    /// Reads the #[html(...)] attribute on a field.
+    fn parse(field: &syn::Field) -> syn::Result<HtmlExtractField> {
+        let ident = match &field.ident {
+            Some(i) => i.clone(),
+            None => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "HtmlExtract can only be derived for structs with named fields",
+                ))
+            }
+        };
+        let attr = match field.attrs.iter().find(|a| a.path.is_ident("html")) {
+            Some(a) => a,
+            None => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "HtmlExtract needs an #[html(select = \"...\")] attribute on every field",
+                ))
+            }
+        };
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            m => {
+                return Err(syn::Error::new_spanned(
+                    m,
+                    "expected #[html(select = \"...\", ...)]",
+                ))
+            }
+        };
+        let mut selector = None;
+        let mut attr_name = None;
+        let mut text = false;
+        let mut nested = false;
+        for item in list.nested {
+            match item {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("select") => {
+                    match nv.lit {
+                        Lit::Str(s) => selector = Some(s),
+                        l => {
+                            return Err(syn::Error::new_spanned(l, "the selector must be a string"))
+                        }
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("attr") => match nv.lit {
+                    Lit::Str(s) => attr_name = Some(s),
+                    l => {
+                        return Err(syn::Error::new_spanned(
+                            l,
+                            "the attribute name must be a string",
+                        ))
+                    }
+                },
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("text") => text = true,
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("nested") => nested = true,
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "unknown option, expected one of select, attr, text or nested",
+                    ))
+                }
+            }
+        }
+        let selector = match selector {
+            Some(s) => s,
+            None => {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "missing the selector, expected #[html(select = \"...\")]",
+                ))
+            }
+        };
+        // a mistake in the selector is a compile error, like in css!
+        if selector.value() != ":scope" {
+            if let Err(e) = selector.value().parse::<CssSelector>() {
+                return Err(css_selector_error(&selector, e));
+            }
+        }
+        if [attr_name.is_some(), text, nested]
+            .iter()
+            .filter(|x| **x)
+            .count()
+            > 1
+        {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "only one of attr, text or nested can be used",
+            ));
+        }
+        Ok(HtmlExtractField {
+            ident,
+            selector,
+            attr: attr_name,
+            nested,
+        })
+    }
+}
+
+/// Gets the type inside of a wrapper type such as Option<T> or Vec<T>.
+fn wrapped_type<'t>(ty: &'t Type, wrapper: &str) -> Option<&'t Type> {
+    let segment = match ty {
+        Type::Path(p) if p.qself.is_none() => p.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(t) => Some(t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Derives hb_html's HtmlExtract trait so the struct can be filled from a HTML document.
+/// Each field needs a #[html(...)] attribute with the selector used to find its value and
+/// optionally how the value is read from the tag found.
+///
+/// * `select = "..."` - the CSS selector, ":scope" refers to the tag a nested struct is
+///   extracted from. It is parsed when the crate is compiled, so a mistake is a compile error.
+/// * `text` - the trimmed text of the tag (the default).
+/// * `attr = "..."` - the value of an attribute of the tag.
+/// * `nested` - the field implements HtmlExtract and is filled from the tag found.
+///
+/// Fields of type `Option<T>` are None when nothing is found and `Vec<T>` fields get a value
+/// from every tag found. Other values are converted using FromStr.
+/// ```ignore
+/// #[derive(HtmlExtract)]
+/// struct Product {
+///     #[html(select = "h1")]
+///     name: String,
+///     #[html(select = ".price")]
+///     price: f64,
+///     #[html(select = "a.more", attr = "href")]
+///     more: Option<String>,
+///     #[html(select = ".review", nested)]
+///     reviews: Vec<Review>,
+/// }
+/// ```
+/// This becomes the code below, where the selectors are parsed the first time a Product
+/// is extracted.
+/// ```ignore
+/// impl ::hb_html::extract::HtmlExtract for Product {
+///     fn extract(
+///         scope: ::hb_html::extract::ExtractScope,
+///     ) -> Result<Self, ::hb_html::extract::HtmlExtractError> {
+///         static SELECTORS: OnceLock<Vec<ExtractSelector>> = OnceLock::new();
+///         let selectors = SELECTORS.get_or_init(|| {
+///             vec![
+///                 ExtractSelector::new("h1"),
+///                 ExtractSelector::new(".price"),
+///                 ExtractSelector::new("a.more"),
+///                 ExtractSelector::new(".review"),
+///             ]
+///         });
+///         Ok(Product {
+///             name: scope.extract_one::<String>("name", &selectors[0], ExtractMode::Text)?,
+///             price: scope.extract_one::<f64>("price", &selectors[1], ExtractMode::Text)?,
+///             more: scope.extract_optional::<String>("more", &selectors[2], ExtractMode::Attr("href"))?,
+///             reviews: scope.extract_nested_all::<Review>("reviews", &selectors[3])?,
+///         })
+///     }
+/// }
+/// ```
+#[proc_macro_derive(HtmlExtract, attributes(html))]
+pub fn html_extract(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let named = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(named),
+            ..
+        }) => named,
+        _ => {
+            return syn::Error::new_spanned(
+                &input,
+                "HtmlExtract can only be derived for structs with named fields",
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+    let mut field_values = syn::punctuated::Punctuated::<FieldValue, Comma>::new();
+    let mut selectors = syn::punctuated::Punctuated::<Expr, Comma>::new();
+    for (index, field) in named.named.iter().enumerate() {
+        let parsed = match HtmlExtractField::parse(field) {
+            Ok(p) => p,
+            Err(e) => return e.to_compile_error().into(),
+        };
+        let f_ident = &parsed.ident;
+        let name = f_ident.to_string();
+        let source = &parsed.selector;
+        selectors.push(parse_quote!(::hb_html::extract::ExtractSelector::new(#source)));
+        let selector: Expr = parse_quote!(&selectors[#index]);
+        let mode: Expr = match &parsed.attr {
+            Some(a) => parse_quote!(::hb_html::extract::ExtractMode::Attr(#a)),
+            None => parse_quote!(::hb_html::extract::ExtractMode::Text),
+        };
+        let value: Expr = match (
+            parsed.nested,
+            wrapped_type(&field.ty, "Option"),
+            wrapped_type(&field.ty, "Vec"),
+        ) {
+            (true, Some(inner), _) => {
+                parse_quote!(scope.extract_nested_optional::<#inner>(#name, #selector)?)
+            }
+            (true, None, Some(inner)) => {
+                parse_quote!(scope.extract_nested_all::<#inner>(#name, #selector)?)
+            }
+            (true, None, None) => {
+                let ty = &field.ty;
+                parse_quote!(scope.extract_nested::<#ty>(#name, #selector)?)
+            }
+            (false, Some(inner), _) => {
+                parse_quote!(scope.extract_optional::<#inner>(#name, #selector, #mode)?)
+            }
+            (false, None, Some(inner)) => {
+                parse_quote!(scope.extract_all::<#inner>(#name, #selector, #mode)?)
+            }
+            (false, None, None) => {
+                let ty = &field.ty;
+                parse_quote!(scope.extract_one::<#ty>(#name, #selector, #mode)?)
+            }
+        };
+        field_values.push(parse_quote!(#f_ident: #value));
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote!(
+        impl #impl_generics ::hb_html::extract::HtmlExtract for #ident #ty_generics #where_clause {
+            fn extract(
+                scope: ::hb_html::extract::ExtractScope,
+            ) -> ::std::result::Result<Self, ::hb_html::extract::HtmlExtractError> {
+                static SELECTORS: ::std::sync::OnceLock<
+                    ::std::vec::Vec<::hb_html::extract::ExtractSelector>,
+                > = ::std::sync::OnceLock::new();
+                let selectors = SELECTORS.get_or_init(|| ::std::vec![#selectors]);
+                ::std::result::Result::Ok(#ident {
+                    #field_values
+                })
+            }
+        }
+    )
+    .into()
+}
+
+/// A node in the markup given to the html! macro.
+enum HtmlMarkup {
+    Element(HtmlElement),
+    Fragment(Vec<HtmlMarkup>),
+    Text(LitStr),
+    Expr(Box<Expr>),
+    Iter(Box<Expr>),
+}
+
+/// An element in the markup given to the html! macro.
+struct HtmlElement {
+    name: String,
+    span: Span,
+    attributes: Vec<HtmlAttribute>,
+    children: Vec<HtmlMarkup>,
+}
+
+/// An attribute of an element in the markup given to the html! macro.
+struct HtmlAttribute {
+    name: String,
+    span: Span,
+    value: HtmlAttributeValue,
+}
+
+enum HtmlAttributeValue {
+    Lit(String),
+    Expr(Box<Expr>),
+    Empty,
+}
+
+/// Parses a tag or attribute name, which can contain '-' and ':' like `data-id` or
+/// `xlink:href`, and can be a Rust keyword like `for` or `type`.
+fn parse_markup_name(input: ParseStream) -> syn::Result<(String, Span)> {
+    let first = Ident::parse_any(input)?;
+    let span = first.span();
+    let mut name = first.to_string();
+    while input.peek(Token![-]) || input.peek(Token![:]) {
+        if input.peek(Token![-]) {
+            input.parse::<Token![-]>()?;
+            name.push('-');
+        } else {
+            input.parse::<Token![:]>()?;
+            name.push(':');
+        }
+        name.push_str(&Ident::parse_any(input)?.to_string());
+    }
+    Ok((name.to_ascii_lowercase(), span))
+}
+
+/// True if the input is at a closing tag such as `</div>` or `</>`.
+fn at_closing_tag(input: ParseStream) -> bool {
+    input.peek(Token![<]) && input.peek2(Token![/])
+}
+
+impl Parse for HtmlMarkup {
+    fn parse(input: ParseStream) -> syn::Result<HtmlMarkup> {
+        if input.peek(LitStr) {
+            return Ok(HtmlMarkup::Text(input.parse()?));
+        }
+        if input.peek(syn::token::Brace) {
+            let content;
+            syn::braced!(content in input);
+            let markup = match content.peek(Token![for]) {
+                true => {
+                    content.parse::<Token![for]>()?;
+                    HtmlMarkup::Iter(content.parse()?)
+                }
+                false => HtmlMarkup::Expr(content.parse()?),
+            };
+            if !content.is_empty() {
+                return Err(content.error("expected a single expression"));
+            }
+            return Ok(markup);
+        }
+        if !input.peek(Token![<]) {
+            return Err(input.error("expected a tag, a string literal or a {...} expression"));
+        }
+        if at_closing_tag(input) {
+            let start = input.parse::<Token![<]>()?;
+            return Err(syn::Error::new(
+                start.span,
+                "unexpected closing tag without an opening tag",
+            ));
+        }
+        let open = input.parse::<Token![<]>()?;
+        if input.peek(Token![>]) {
+            input.parse::<Token![>]>()?;
+            let mut children = vec![];
+            while !at_closing_tag(input) {
+                if input.is_empty() {
+                    return Err(syn::Error::new(
+                        open.span,
+                        "the fragment `<>` is not closed",
+                    ));
+                }
+                children.push(input.parse()?);
+            }
+            input.parse::<Token![<]>()?;
+            input.parse::<Token![/]>()?;
+            if !input.peek(Token![>]) {
+                let (name, span) = parse_markup_name(input)?;
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "expected `</>` to close the fragment but found `</{}>`",
+                        name
+                    ),
+                ));
+            }
+            input.parse::<Token![>]>()?;
+            return Ok(HtmlMarkup::Fragment(children));
+        }
+        let (name, span) = parse_markup_name(input)?;
+        let void = VOID_ELEMENTS.contains(&name.as_str());
+        let mut attributes: Vec<HtmlAttribute> = vec![];
+        while !(input.peek(Token![>]) || input.peek(Token![/])) {
+            if input.is_empty() {
+                return Err(syn::Error::new(
+                    span,
+                    format!("`<{}` is missing its `>`", name),
+                ));
+            }
+            let (attr_name, attr_span) = parse_markup_name(input)?;
+            if attributes.iter().any(|a| a.name == attr_name) {
+                return Err(syn::Error::new(
+                    attr_span,
+                    format!("the attribute `{}` is set more than once", attr_name),
+                ));
+            }
+            let value = match input.peek(Token![=]) {
+                false => HtmlAttributeValue::Empty,
+                true => {
+                    input.parse::<Token![=]>()?;
+                    if input.peek(syn::token::Brace) {
+                        let content;
+                        syn::braced!(content in input);
+                        HtmlAttributeValue::Expr(content.parse()?)
+                    } else {
+                        match input.parse::<Lit>()? {
+                            Lit::Str(s) => HtmlAttributeValue::Lit(s.value()),
+                            Lit::Int(i) => HtmlAttributeValue::Lit(i.base10_digits().to_owned()),
+                            Lit::Float(f) => HtmlAttributeValue::Lit(f.base10_digits().to_owned()),
+                            Lit::Bool(b) => HtmlAttributeValue::Lit(b.value.to_string()),
+                            Lit::Char(c) => HtmlAttributeValue::Lit(c.value().to_string()),
+                            l => {
+                                return Err(syn::Error::new_spanned(
+                                    l,
+                                    "expected a string, number or {...} expression",
+                                ))
+                            }
+                        }
+                    }
+                }
+            };
+            attributes.push(HtmlAttribute {
+                name: attr_name,
+                span: attr_span,
+                value,
+            });
+        }
+        let element = |children| {
+            Ok(HtmlMarkup::Element(HtmlElement {
+                name: name.clone(),
+                span,
+                attributes,
+                children,
+            }))
+        };
+        if input.peek(Token![/]) {
+            let slash = input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>()?;
+            if !void {
+                return Err(syn::Error::new(
+                    slash.span,
+                    format!(
+                        "`<{0}>` is not a void element so it can't be self-closing, use `<{0}></{0}>`",
+                        name
+                    ),
+                ));
+            }
+            return element(vec![]);
+        }
+        input.parse::<Token![>]>()?;
+        if void {
+            return element(vec![]);
+        }
+        let mut children = vec![];
+        loop {
+            if input.is_empty() {
+                return Err(syn::Error::new(span, format!("`<{}>` is not closed", name)));
+            }
+            if !at_closing_tag(input) {
+                children.push(input.parse()?);
+                continue;
+            }
+            let fork = input.fork();
+            fork.parse::<Token![<]>()?;
+            fork.parse::<Token![/]>()?;
+            if fork.peek(Token![>]) {
+                return Err(fork.error(format!("expected `</{}>` but found `</>`", name)));
+            }
+            let (close, close_span) = parse_markup_name(&fork)?;
+            if close == name {
+                input.parse::<Token![<]>()?;
+                input.parse::<Token![/]>()?;
+                parse_markup_name(input)?;
+                input.parse::<Token![>]>()?;
+                return element(children);
+            }
+            if VOID_ELEMENTS.contains(&close.as_str()) {
+                return Err(syn::Error::new(
+                    close_span,
+                    format!(
+                        "`<{}>` is a void element so it can't have a closing tag",
+                        close
+                    ),
+                ));
+            }
+            return Err(syn::Error::new(
+                close_span,
+                format!("expected `</{}>` but found `</{}>`", name, close),
+            ));
+        }
+    }
+}
+
+impl HtmlMarkup {
+    /// Creates the code which adds this markup to the end of a Vec<HtmlNode>.
+    fn append_tokens(&self, nodes: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self {
+            HtmlMarkup::Element(element) => {
+                let tag = element.tag_tokens();
+                quote!(#nodes.push(::hb_html::objects::HtmlNode::Tag(#tag));)
+            }
+            HtmlMarkup::Fragment(children) => {
+                let children = children.iter().map(|c| c.append_tokens(nodes));
+                quote!(#(#children)*)
+            }
+            HtmlMarkup::Text(text) => {
+                quote!(::hb_html::build::IntoHtmlNodes::append_to(#text, &mut #nodes);)
+            }
+            HtmlMarkup::Expr(expr) => {
+                quote!(::hb_html::build::IntoHtmlNodes::append_to(#expr, &mut #nodes);)
+            }
+            HtmlMarkup::Iter(expr) => {
+                let item = Ident::new("__hb_item", Span::mixed_site());
+                quote!(
+                    for #item in #expr {
+                        ::hb_html::build::IntoHtmlNodes::append_to(#item, &mut #nodes);
+                    }
+                )
+            }
+        }
+    }
+
+    /// Creates the expression for the markup at the top of the macro.
+    fn root_tokens(&self) -> proc_macro2::TokenStream {
+        let nodes = Ident::new("__hb_nodes", Span::mixed_site());
+        match self {
+            HtmlMarkup::Element(element) => {
+                let tag = element.tag_tokens();
+                quote!(::hb_html::objects::HtmlNode::Tag(#tag))
+            }
+            HtmlMarkup::Text(text) => {
+                quote!(::hb_html::objects::HtmlNode::Text(
+                    ::hb_html::entities::encode_entities(#text)
+                ))
+            }
+            markup => {
+                let append = markup.append_tokens(&quote!(#nodes));
+                quote!({
+                    let mut #nodes: ::std::vec::Vec<::hb_html::objects::HtmlNode> =
+                        ::std::vec::Vec::new();
+                    #append
+                    #nodes
+                })
+            }
+        }
+    }
+}
+
+impl HtmlElement {
+    /// Creates the expression which builds the HtmlTag.
+    fn tag_tokens(&self) -> proc_macro2::TokenStream {
+        let tag = Ident::new("__hb_tag", Span::mixed_site());
+        let name = &self.name;
+        let mut statements = vec![];
+        for attribute in &self.attributes {
+            let value = match &attribute.value {
+                HtmlAttributeValue::Lit(v) => quote!(#v),
+                HtmlAttributeValue::Expr(e) => quote!(&::std::string::ToString::to_string(&(#e))),
+                HtmlAttributeValue::Empty => quote!(""),
+            };
+            let list = match attribute.name.as_str() {
+                "id" => Some(quote!(ids)),
+                "class" => Some(quote!(classes)),
+                _ => None,
+            };
+            statements.push(match list {
+                Some(list) => quote!(
+                    #tag.#list.extend(
+                        ::std::primitive::str::split_whitespace(#value)
+                            .map(::hb_html::entities::encode_entities),
+                    );
+                ),
+                None => {
+                    let attr_name = LitStr::new(&attribute.name, attribute.span);
+                    quote!(
+                        #tag.attributes.insert(
+                            ::std::string::String::from(#attr_name),
+                            ::hb_html::entities::encode_entities(#value),
+                        );
+                    )
+                }
+            });
+        }
+        let contents = quote!(#tag.contents);
+        for child in &self.children {
+            statements.push(child.append_tokens(&contents));
+        }
+        let name = LitStr::new(name, self.span);
+        quote!({
+            #[allow(unused_mut)]
+            let mut #tag = ::hb_html::objects::HtmlTag::new(#name);
+            #(#statements)*
+            #tag
+        })
+    }
+}
+
+/// Builds a hb_html `HtmlNode` from HTML written in the code, checking at compile time that
+/// the closing tags match and that void elements such as `<br>` have no contents. Strings
+/// are used for text, and Rust values can be used in braces as attribute values, `{expr}`
+/// children or `{for iter}` children. A fragment, `<>...</>`, makes a `Vec<HtmlNode>`.
+/// See hb_html's build module for more details.
+/// ```ignore
+/// let name = "Sam";
+/// html! { <p class="greeting" data-name={name}>"Hello " {name}<br></p> }
+/// ```
+/// This becomes
+/// ```ignore
+/// ::hb_html::objects::HtmlNode::Tag({
+///     let mut tag = ::hb_html::objects::HtmlTag::new("p");
+///     tag.classes.extend(str::split_whitespace("greeting").map(encode_entities));
+///     tag.attributes.insert(String::from("data-name"), encode_entities(&name.to_string()));
+///     IntoHtmlNodes::append_to("Hello ", &mut tag.contents);
+///     IntoHtmlNodes::append_to(name, &mut tag.contents);
+///     tag.contents.push(HtmlNode::Tag({ HtmlTag::new("br") }));
+///     tag
+/// })
+/// ```
+#[proc_macro]
+pub fn html(input: TokenStream) -> TokenStream {
+    let parse = |input: ParseStream| -> syn::Result<HtmlMarkup> {
+        if input.is_empty() {
+            return Err(input.error("expected a tag, a string literal or a fragment"));
+        }
+        let markup = input.parse::<HtmlMarkup>()?;
+        match markup {
+            HtmlMarkup::Element(_) | HtmlMarkup::Fragment(_) | HtmlMarkup::Text(_) => (),
+            _ => {
+                return Err(input
+                    .error("expected a tag, a string literal or a fragment at the top of html!"))
+            }
+        }
+        if !input.is_empty() {
+            return Err(input
+                .error("html! makes a single node, use a fragment `<>...</>` for more than one"));
+        }
+        Ok(markup)
+    };
+    match syn::parse::Parser::parse(parse, input) {
+        Ok(markup) => markup.root_tokens().into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Creates the expression which builds the selector, so the value of css! is made without
+/// any parsing at runtime.
+fn css_selector_tokens(selector: &CssSelector) -> proc_macro2::TokenStream {
+    match selector {
+        CssSelector::Any => quote!(::hb_html::objects::CssSelector::Any),
+        CssSelector::Specific(rules) => {
+            let rules = rules.iter().map(css_rule_tokens);
+            quote!(::hb_html::objects::CssSelector::Specific(vec![#(#rules),*]))
+        }
+    }
+}
+
+fn css_rule_tokens(rule: &CssSelectorRule) -> proc_macro2::TokenStream {
+    let rules = rule.rules.iter().map(|relationship| {
+        let variant = match relationship {
+            CssSelectorRelationship::Parent(_) => quote!(Parent),
+            CssSelectorRelationship::Ancestor(_) => quote!(Ancestor),
+            CssSelectorRelationship::PreviousSibling(_) => quote!(PreviousSibling),
+            CssSelectorRelationship::PreviousSiblingOnce(_) => quote!(PreviousSiblingOnce),
+            CssSelectorRelationship::Current(_) => quote!(Current),
+        };
+        let item = css_item_tokens(relationship.item());
+        quote!(::hb_html::objects::CssSelectorRelationship::#variant(#item))
+    });
+    quote!(::hb_html::objects::CssSelectorRule {
+        rules: vec![#(#rules),*],
+    })
+}
+
+fn css_item_tokens(item: &CssSelectorItem) -> proc_macro2::TokenStream {
+    fn option<T>(
+        values: &Option<T>,
+        tokens: impl Fn(&T) -> proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        match values {
+            Some(v) => {
+                let v = tokens(v);
+                quote!(::std::option::Option::Some(#v))
+            }
+            None => quote!(::std::option::Option::None),
+        }
+    }
+    let strings = |values: &Vec<String>| quote!(vec![#(::std::string::String::from(#values)),*]);
+    let tag = option(&item.tag, |t| quote!(::std::string::String::from(#t)));
+    let classes = option(&item.classes, strings);
+    let ids = option(&item.ids, strings);
+    let refiners = option(&item.refiners, |r| {
+        let r = r.iter().map(css_refiner_tokens);
+        quote!(vec![#(#r),*])
+    });
+    let attributes = option(&item.attributes, |a| {
+        let a = a.iter().map(css_attribute_tokens);
+        quote!(vec![#(#a),*])
+    });
+    quote!(::hb_html::objects::CssSelectorItem {
+        tag: #tag,
+        classes: #classes,
+        ids: #ids,
+        refiners: #refiners,
+        attributes: #attributes,
+    })
+}
+
+fn css_refiner_tokens(refiner: &CssRefiner) -> proc_macro2::TokenStream {
+    let string = |s: &String| quote!(::std::string::String::from(#s));
+    let (variant, value) = match refiner {
+        CssRefiner::Checked => (quote!(Checked), None),
+        CssRefiner::Default => (quote!(Default), None),
+        CssRefiner::Disabled => (quote!(Disabled), None),
+        CssRefiner::Enabled => (quote!(Enabled), None),
+        CssRefiner::Optional => (quote!(Optional), None),
+        CssRefiner::Required => (quote!(Required), None),
+        CssRefiner::ReadOnly => (quote!(ReadOnly), None),
+        CssRefiner::ReadWrite => (quote!(ReadWrite), None),
+        CssRefiner::Empty => (quote!(Empty), None),
+        CssRefiner::FirstChild => (quote!(FirstChild), None),
+        CssRefiner::LastChild => (quote!(LastChild), None),
+        CssRefiner::NthChild(n) => (quote!(NthChild), Some(css_number_tokens(n))),
+        CssRefiner::NthLastChild(n) => (quote!(NthLastChild), Some(css_number_tokens(n))),
+        CssRefiner::OnlyChild => (quote!(OnlyChild), None),
+        CssRefiner::FirstOfType => (quote!(FirstOfType), None),
+        CssRefiner::LastOfType => (quote!(LastOfType), None),
+        CssRefiner::NthOfType(n) => (quote!(NthOfType), Some(css_number_tokens(n))),
+        CssRefiner::NthLastOfType(n) => (quote!(NthLastOfType), Some(css_number_tokens(n))),
+        CssRefiner::OnlyOfType => (quote!(OnlyOfType), None),
+        CssRefiner::Not(selector) => (quote!(Not), Some(css_selector_tokens(selector))),
+        CssRefiner::Root => (quote!(Root), None),
+        CssRefiner::Contains(text) => (quote!(Contains), Some(string(text))),
+        CssRefiner::IContains(text) => (quote!(IContains), Some(string(text))),
+        CssRefiner::Matches(regex) => {
+            let pattern = regex.as_str();
+            let value = quote!(::hb_html::objects::CssRegex::new(#pattern)
+                .expect("the regular expression was checked by css!"));
+            (quote!(Matches), Some(value))
+        }
+        CssRefiner::TextEquals(text) => (quote!(TextEquals), Some(string(text))),
+    };
+    match value {
+        Some(value) => quote!(::hb_html::objects::CssRefiner::#variant(#value)),
+        None => quote!(::hb_html::objects::CssRefiner::#variant),
+    }
+}
+
+fn css_number_tokens(number: &CssRefinerNumberType) -> proc_macro2::TokenStream {
+    match number {
+        CssRefinerNumberType::Odd => quote!(::hb_html::objects::CssRefinerNumberType::Odd),
+        CssRefinerNumberType::Even => quote!(::hb_html::objects::CssRefinerNumberType::Even),
+        CssRefinerNumberType::Specific(n) => {
+            quote!(::hb_html::objects::CssRefinerNumberType::Specific(#n))
+        }
+        CssRefinerNumberType::Functional((a, b)) => {
+            quote!(::hb_html::objects::CssRefinerNumberType::Functional((#a, #b)))
+        }
+    }
+}
+
+fn css_attribute_tokens(attribute: &CssAttributeCompareType) -> proc_macro2::TokenStream {
+    let (variant, name, value) = match attribute {
+        CssAttributeCompareType::Present(name) => {
+            return quote!(::hb_html::objects::CssAttributeCompareType::Present(
+                ::std::string::String::from(#name)
+            ))
+        }
+        CssAttributeCompareType::Equals((n, v)) => (quote!(Equals), n, v),
+        CssAttributeCompareType::EqualsOrBeingsWith((n, v)) => (quote!(EqualsOrBeingsWith), n, v),
+        CssAttributeCompareType::BeginsWith((n, v)) => (quote!(BeginsWith), n, v),
+        CssAttributeCompareType::EndsWith((n, v)) => (quote!(EndsWith), n, v),
+        CssAttributeCompareType::Contains((n, v)) => (quote!(Contains), n, v),
+        CssAttributeCompareType::ContainsWord((n, v)) => (quote!(ContainsWord), n, v),
+    };
+    quote!(::hb_html::objects::CssAttributeCompareType::#variant((
+        ::std::string::String::from(#name),
+        ::std::string::String::from(#value),
+    )))
+}
+
+/// Creates a hb_html `CssSelector` from a selector string. The selector is parsed at compile
+/// time with the same parser as `str::parse::<CssSelector>()`, from the hb_selector crate,
+/// so a mistake in the selector is a compile error pointing at the column at fault, instead
+/// of a `ParseHtmlError` at runtime. The value is the same as parsing the string, without
+/// any parsing at runtime.
+/// ```ignore
+/// let selector = css!("div.item > a[href]");
+/// ```
+/// This becomes
+/// ```ignore
+/// CssSelector::Specific(vec![CssSelectorRule {
+///     rules: vec![
+///         CssSelectorRelationship::Parent(CssSelectorItem {
+///             tag: Some(String::from("div")),
+///             classes: Some(vec![String::from("item")]),
+///             ids: None,
+///             refiners: None,
+///             attributes: None,
+///         }),
+///         CssSelectorRelationship::Current(CssSelectorItem {
+///             tag: Some(String::from("a")),
+///             classes: None,
+///             ids: None,
+///             refiners: None,
+///             attributes: Some(vec![CssAttributeCompareType::Present(String::from("href"))]),
+///         }),
+///     ],
+/// }])
+/// ```
+#[proc_macro]
+pub fn css(input: TokenStream) -> TokenStream {
+    let selector = parse_macro_input!(input as LitStr);
+    match selector.value().parse::<CssSelector>() {
+        Ok(parsed) => css_selector_tokens(&parsed).into(),
+        Err(e) => css_selector_error(&selector, e).to_compile_error().into(),
+    }
+}
+
+/// Creates the compile error for a selector which could not be parsed, pointing at the
+/// column at fault.
+fn css_selector_error(selector: &LitStr, e: ParseHtmlError) -> syn::Error {
+    let value = selector.value();
+    let offset = match e.offset {
+        Some(offset) => offset.min(value.len()),
+        None => return syn::Error::new(selector.span(), e),
+    };
+    // point at the character itself where the compiler allows it, which needs the text in
+    // the source to be the same as the value, so without any escapes
+    let source = selector.token().to_string();
+    let prefix = source.find('"').map(|i| i + 1).unwrap_or(0);
+    let len = value[offset..]
+        .chars()
+        .next()
+        .map(char::len_utf8)
+        .unwrap_or(1);
+    let span = match source[prefix..].starts_with(&value) {
+        true => selector
+            .token()
+            .subspan(prefix + offset..prefix + offset + len)
+            .unwrap_or_else(|| selector.span()),
+        false => selector.span(),
+    };
+    let column = value[..offset].chars().count() + 1;
+    syn::Error::new(span, format!("{} at column {}", e.msg, column))
+}
@@ -75,6 +75,7 @@
 //! ```
 pub mod error;
 pub mod parser_funcs;
+pub mod robots;
 pub mod source;
 pub use self::parser_funcs::CommonParserFunctions;
 pub use error::{ParseError, ParseResult, SourceEmpty, SourceError, SourceResult};
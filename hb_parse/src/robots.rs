@@ -0,0 +1,348 @@
+//! The structures of a `robots.txt` file, which is read with [`RobotsTxt::from_text`] or
+//! [`FromStr`] and follows RFC 9309.
+//!
+//! # Example
+//!
+//! ```
+//! use hb_parse::robots::RobotsTxt;
+//! let robots = "User-agent: *\nDisallow: /private/\nAllow: /private/public*.html$\n\
+//!     Crawl-delay: 2\nSitemap: https://example.com/sitemap.xml"
+//!     .parse::<RobotsTxt>()
+//!     .unwrap();
+//! assert!(robots.is_allowed("hbbot", "/recipes/soup"));
+//! assert!(!robots.is_allowed("hbbot", "/private/notes"));
+//! assert!(robots.is_allowed("hbbot", "/private/public-page.html"));
+//! assert_eq!(robots.crawl_delay("hbbot"), Some(2.0));
+//! assert_eq!(robots.sitemaps, vec!["https://example.com/sitemap.xml"]);
+//! ```
+use std::convert::Infallible;
+use std::str::FromStr;
+
+/// A parsed `robots.txt` file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RobotsTxt {
+    pub groups: Vec<RobotsGroup>,
+    /// The URLs from the sitemap lines, which apply to every user agent.
+    pub sitemaps: Vec<String>,
+}
+
+/// The rules for a group of user agents.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RobotsGroup {
+    /// The product tokens of the user agents in lower case, where "*" is any user agent.
+    pub user_agents: Vec<String>,
+    pub rules: Vec<RobotsRule>,
+    /// How many seconds to wait between requests.
+    pub crawl_delay: Option<f64>,
+}
+
+/// An allow or disallow line. The path can use `*` to match any characters and can end
+/// with `$` to match the end of the URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobotsRule {
+    pub allow: bool,
+    pub path: String,
+}
+
+impl RobotsTxt {
+    /// Checks whether the user agent may crawl the path, which is the path of the URL with
+    /// its query, such as "/search?q=soup".
+    ///
+    /// The groups for the product token of the user agent, compared without case, are
+    /// used, or else the "*" groups. The rule with the longest path that matches wins, and
+    /// allow wins a tie. Everything is allowed when no rule matches, and "/robots.txt" is
+    /// always allowed.
+    pub fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        let path = normalize_path(if path.is_empty() { "/" } else { path });
+        if path == "/robots.txt" {
+            return true;
+        }
+        let mut best: Option<(usize, bool)> = None;
+        for rule in self.groups_for(user_agent).flat_map(|g| g.rules.iter()) {
+            let pattern = normalize_path(&rule.path);
+            if !matches_pattern(&pattern, &path) {
+                continue;
+            }
+            let length = pattern.len();
+            best = match best {
+                Some((best_length, allow))
+                    if best_length > length || (best_length == length && allow) =>
+                {
+                    Some((best_length, allow))
+                }
+                _ => Some((length, rule.allow)),
+            };
+        }
+        best.map(|(_, allow)| allow).unwrap_or(true)
+    }
+
+    /// Gets the crawl delay in seconds for the user agent.
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<f64> {
+        self.groups_for(user_agent).find_map(|g| g.crawl_delay)
+    }
+
+    /// Gets the groups which apply to the user agent, which are merged together.
+    pub fn groups_for<'r>(&'r self, user_agent: &str) -> impl Iterator<Item = &'r RobotsGroup> {
+        let token = product_token(user_agent);
+        let named = self.groups.iter().any(|g| g.user_agents.contains(&token));
+        let agent = if named { token } else { "*".to_owned() };
+        self.groups
+            .iter()
+            .filter(move |g| g.user_agents.contains(&agent))
+    }
+
+    /// Reads the lines of a `robots.txt` file. Lines which can't be understood are skipped.
+    pub fn from_text(text: &str) -> RobotsTxt {
+        let mut robots = RobotsTxt::default();
+        // a user-agent line after a rule starts a new group
+        let mut in_rules = true;
+        for line in text.trim_start_matches('\u{feff}').lines() {
+            let line = match line.find('#') {
+                Some(i) => &line[..i],
+                None => line,
+            };
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            match key.as_str() {
+                "user-agent" | "useragent" | "user agent" => {
+                    if in_rules {
+                        robots.groups.push(RobotsGroup::default());
+                        in_rules = false;
+                    }
+                    if let Some(group) = robots.groups.last_mut() {
+                        group.user_agents.push(product_token(value));
+                    }
+                }
+                "allow" | "disallow" | "dissallow" | "disalow" => {
+                    in_rules = true;
+                    // an empty disallow allows everything, which is the same as no rule
+                    if let (false, Some(group)) = (value.is_empty(), robots.groups.last_mut()) {
+                        group.rules.push(RobotsRule {
+                            allow: key == "allow",
+                            path: value.to_owned(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    let delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|d| d.is_finite() && *d >= 0.0);
+                    if let (Some(delay), Some(group)) = (delay, robots.groups.last_mut()) {
+                        group.crawl_delay = group.crawl_delay.or(Some(delay));
+                    }
+                }
+                "sitemap" | "site-map" if !value.is_empty() => {
+                    robots.sitemaps.push(value.to_owned());
+                }
+                _ => (),
+            }
+        }
+        robots
+    }
+}
+
+impl FromStr for RobotsTxt {
+    type Err = Infallible;
+    /// Reads the file with [`RobotsTxt::from_text`], which never fails.
+    fn from_str(text: &str) -> Result<Self, <Self as std::str::FromStr>::Err> {
+        Ok(RobotsTxt::from_text(text))
+    }
+}
+
+/// Gets the product token from a user agent, such as "googlebot" from "Googlebot/2.1",
+/// in lower case.
+fn product_token(user_agent: &str) -> String {
+    let user_agent = user_agent.trim();
+    if user_agent.starts_with('*') {
+        return "*".to_owned();
+    }
+    user_agent
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Percent encodes the characters outside of ASCII and puts the hex digits of escapes in
+/// upper case, so paths and patterns written in different ways can be compared.
+fn normalize_path(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => {
+                normalized.push('%');
+                for _ in 0..2 {
+                    match chars.peek() {
+                        Some(h) if h.is_ascii_hexdigit() => {
+                            normalized.push(h.to_ascii_uppercase());
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+            }
+            c if c.is_ascii() => normalized.push(c),
+            c => {
+                let mut buffer = [0; 4];
+                for byte in c.encode_utf8(&mut buffer).bytes() {
+                    normalized.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+    }
+    normalized
+}
+
+/// Checks whether the pattern matches the start of the path, where `*` matches any
+/// characters and a `$` at the end matches the end of the path.
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let path = path.as_bytes();
+    // the positions in the path that the pattern so far can end at
+    let mut positions = vec![0];
+    for p in pattern.bytes() {
+        positions = match p {
+            b'*' => match positions.first() {
+                Some(start) => (*start..=path.len()).collect(),
+                None => return false,
+            },
+            _ => positions
+                .iter()
+                .filter(|i| path.get(**i) == Some(&p))
+                .map(|i| i + 1)
+                .collect(),
+        };
+        if positions.is_empty() {
+            return false;
+        }
+    }
+    match anchored {
+        true => positions.contains(&path.len()),
+        false => !positions.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\u{feff}# robots for example.com
+User-agent: Googlebot
+User-agent: bingbot/2.0
+Disallow: /search
+Allow: /search/about
+Crawl-delay: 1.5
+
+User-agent: *
+Disallow: /private
+Disallow: /*.pdf$
+Disallow: /tmp/*/cache
+Allow: /private/shared
+Disallow:
+Sitemap: https://example.com/sitemap.xml # main
+
+user-agent: googlebot
+disallow: /drafts/
+unknown: line
+this line has no colon
+Sitemap: https://example.com/news.xml
+";
+
+    #[test]
+    fn robots_parse_test() {
+        let robots = ROBOTS.parse::<RobotsTxt>().unwrap();
+        assert_eq!(robots.groups.len(), 3);
+        assert_eq!(robots.groups[0].user_agents, vec!["googlebot", "bingbot"]);
+        assert_eq!(
+            robots.groups[0].rules,
+            vec![
+                RobotsRule {
+                    allow: false,
+                    path: "/search".to_owned()
+                },
+                RobotsRule {
+                    allow: true,
+                    path: "/search/about".to_owned()
+                },
+            ]
+        );
+        assert_eq!(robots.groups[0].crawl_delay, Some(1.5));
+        assert_eq!(robots.groups[1].rules.len(), 4);
+        assert_eq!(
+            robots.sitemaps,
+            vec![
+                "https://example.com/sitemap.xml",
+                "https://example.com/news.xml"
+            ]
+        );
+        assert_eq!(robots.crawl_delay("Googlebot"), Some(1.5));
+        assert_eq!(robots.crawl_delay("other"), None);
+        assert_eq!(robots.groups_for("GOOGLEBOT/2.1").count(), 2);
+        assert_eq!("".parse::<RobotsTxt>().unwrap(), RobotsTxt::default());
+    }
+
+    #[test]
+    fn robots_is_allowed_test() {
+        let robots = ROBOTS.parse::<RobotsTxt>().unwrap();
+        let tests = vec![
+            ("googlebot", "/", true),
+            ("googlebot", "/search?q=soup", false),
+            ("googlebot", "/search/about", true),
+            ("googlebot", "/drafts/one", false),
+            // the named group replaces the * group
+            ("googlebot", "/private", true),
+            ("Bingbot", "/searching", false),
+            ("hbbot", "/search", true),
+            ("hbbot", "/private", false),
+            ("hbbot", "/private/shared/a", true),
+            ("hbbot", "/privateer", false),
+            ("hbbot", "/files/menu.pdf", false),
+            ("hbbot", "/files/menu.pdf?download=1", true),
+            ("hbbot", "/tmp/a/b/cache/x", false),
+            ("hbbot", "/tmp/cache", true),
+            ("hbbot", "/robots.txt", true),
+            ("hbbot", "", true),
+        ];
+        for (agent, path, allowed) in tests {
+            assert_eq!(
+                robots.is_allowed(agent, path),
+                allowed,
+                "{} {}",
+                agent,
+                path
+            );
+        }
+
+        let robots = "User-agent: *\nDisallow: /\nAllow: /$\nDisallow: /caf%c3%a9\nAllow: /page\nDisallow: /page"
+            .parse::<RobotsTxt>()
+            .unwrap();
+        assert!(robots.is_allowed("a", "/"));
+        assert!(!robots.is_allowed("a", "/index.html"));
+        assert!(!robots.is_allowed("a", "/café"));
+        assert!(robots.is_allowed("a", "/robots.txt"));
+        // allow wins when the rules are the same length
+        assert!(robots.is_allowed("a", "/page"));
+        assert!(RobotsTxt::default().is_allowed("a", "/anything"));
+    }
+
+    #[test]
+    fn robots_pattern_test() {
+        assert!(matches_pattern("/", "/a"));
+        assert!(matches_pattern("/a*", "/a"));
+        assert!(matches_pattern("/*a*b$", "/xaxxb"));
+        assert!(!matches_pattern("/*a*b$", "/xaxxbc"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("/a**b", "/ab"));
+        assert!(!matches_pattern("/a$", "/ab"));
+        assert!(!matches_pattern("/abc", "/ab"));
+        assert_eq!(normalize_path("/caf%c3%a9/é"), "/caf%C3%A9/%C3%A9");
+    }
+}
@@ -0,0 +1,7 @@
+//! Facts about HTML elements which the parser and the `html!` macro both rely on.
+
+/// The elements which can't have contents or a closing tag.
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "command", "embed", "hr", "img", "input", "keygen", "link",
+    "meta", "param", "source", "track", "wbr",
+];
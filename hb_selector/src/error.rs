@@ -0,0 +1,53 @@
+use std::error::Error;
+
+#[derive(PartialEq)]
+pub struct ParseHtmlError {
+    pub msg: String,
+    /// The byte offset of the mistake in the text that was parsed, when it is known.
+    pub offset: Option<usize>,
+}
+
+impl ParseHtmlError {
+    pub fn new(msg: String) -> ParseHtmlError {
+        ParseHtmlError { msg, offset: None }
+    }
+
+    pub fn with_msg<S: Into<String>>(msg: S) -> ParseHtmlError {
+        return ParseHtmlError::new(msg.into());
+    }
+
+    pub fn add_context<S: Into<String>>(mut self, msg: S) -> ParseHtmlError {
+        self.msg = format!("{} because {}", msg.into(), self.msg);
+        self
+    }
+
+    /// Moves the offset of the mistake along by the offset given, for when the text that
+    /// was parsed is part of a longer string. An error without an offset is placed at the
+    /// offset given.
+    pub fn add_offset(mut self, offset: usize) -> ParseHtmlError {
+        self.offset = Some(offset + self.offset.unwrap_or(0));
+        self
+    }
+}
+
+impl std::fmt::Display for ParseHtmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Html Doc Error: '{}'", self.msg)?;
+        Ok(())
+    }
+}
+impl std::fmt::Debug for ParseHtmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Html Doc Error: '{}'", self.msg)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseHtmlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
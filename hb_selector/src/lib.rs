@@ -0,0 +1,12 @@
+//! CSS selectors and their parser.
+//!
+//! The selectors are shared by `hb_html`, which matches them against documents, and the
+//! `css!` macro in `hb_macros`, which checks them when the crate is compiled. The list of
+//! void elements in [`elements`] is shared by the parser and the `html!` macro in the same way.
+pub mod elements;
+pub mod error;
+pub mod selector;
+
+mod parsing;
+#[cfg(feature = "serde")]
+mod serialize;
@@ -0,0 +1,1363 @@
+use crate::error::ParseHtmlError;
+use crate::selector::{
+    CssAttributeCompareType, CssRefiner, CssRefinerNumberType, CssRegex, CssSelector,
+    CssSelectorItem, CssSelectorParseOptions, CssSelectorRelationship, CssSelectorRule,
+};
+
+pub fn parse_until_end_or_one_of_peekable(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+    end_chars: Vec<char>,
+) -> Option<String> {
+    let mut buffer = String::new();
+    while let Some(ch) = chs.peek() {
+        for end_char in &end_chars {
+            if ch == end_char {
+                return Some(buffer);
+            }
+        }
+        buffer.push(chs.next().unwrap());
+    }
+    if buffer.len() > 0 {
+        return Some(buffer);
+    }
+    None
+}
+
+pub fn parse_until_one_of_peekable(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+    end_chars: Vec<char>,
+) -> Result<String, ParseHtmlError> {
+    let mut buffer = String::new();
+    while let Some(ch) = chs.peek() {
+        for end_char in &end_chars {
+            if ch == end_char {
+                return Ok(buffer);
+            }
+        }
+        buffer.push(chs.next().unwrap());
+    }
+    return Err(ParseHtmlError::new(format!(
+        "end of string '{}' encountered before any end char '{:?}' was found",
+        buffer, end_chars
+    )));
+}
+
+pub fn parse_until_and_including_char(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+    ending: char,
+) -> Result<String, ParseHtmlError> {
+    let mut buffer = String::new();
+    while let Some(ch) = chs.peek() {
+        if ch == &ending {
+            buffer.push(chs.next().unwrap());
+            return Ok(buffer);
+        }
+        buffer.push(chs.next().unwrap());
+    }
+    return Err(ParseHtmlError::new(format!(
+        "end of string '{}' encountered before ending '{}' was found",
+        buffer, ending
+    )));
+}
+
+pub fn parse_until_char_peekable(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+    ending: char,
+) -> Result<String, ParseHtmlError> {
+    let mut buffer = String::new();
+    while let Some(ch) = chs.peek() {
+        if ch == &ending {
+            return Ok(buffer);
+        }
+        buffer.push(chs.next().unwrap());
+    }
+    return Err(ParseHtmlError::new(format!(
+        "end of string '{}' encountered before ending '{}' was found",
+        buffer, ending
+    )));
+}
+
+#[derive(PartialEq, Debug)]
+enum CssSelectorRelationshipType {
+    Current,
+    Parent,
+    Ancestor,
+    PreviousSibling,
+    PreviousSiblingOnce,
+}
+
+/// Keeps track of whether the characters of a CSS selector are inside of a quoted string,
+/// or a /regex/ straight after a '(', so that separators inside of them can be ignored.
+#[derive(Default)]
+struct CssQuoteTracker {
+    quote: Option<char>,
+    escaped: bool,
+    previous: Option<char>,
+}
+
+impl CssQuoteTracker {
+    /// Updates the state with the next character and returns true if the character
+    /// is part of a quoted section, including the quotes themselves.
+    fn quoted(&mut self, ch: char) -> bool {
+        let previous = match ch.is_whitespace() {
+            true => self.previous,
+            false => self.previous.replace(ch),
+        };
+        match self.quote {
+            Some(q) => {
+                if self.escaped {
+                    self.escaped = false;
+                } else if ch == '\\' {
+                    self.escaped = true;
+                } else if ch == q {
+                    self.quote = None;
+                }
+                true
+            }
+            None => {
+                if ch == '"' || ch == '\'' || (ch == '/' && previous == Some('(')) {
+                    self.quote = Some(ch);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// Splits a list of CSS selectors on the commas between them, ignoring any commas inside
+/// of brackets or quotes.
+pub fn split_css_selector_list(selector: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut tracker = CssQuoteTracker::default();
+    let mut level = 0;
+    let mut start = 0;
+    for (i, ch) in selector.char_indices() {
+        if tracker.quoted(ch) {
+            continue;
+        }
+        match ch {
+            '(' | '[' => level += 1,
+            ')' | ']' => level -= 1,
+            ',' if level == 0 => {
+                parts.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&selector[start..]);
+    parts
+}
+
+/// Gets the number of bytes left in the characters, so the offset of the next character
+/// is the length of the text less this.
+fn remaining_len(chs: &std::iter::Peekable<std::str::Chars>) -> usize {
+    chs.clone().map(char::len_utf8).sum()
+}
+
+pub fn parse_css_selector_rule(
+    selector_rule: &str,
+    options: &CssSelectorParseOptions,
+) -> Result<CssSelectorRule, ParseHtmlError> {
+    let mut css_rule = CssSelectorRule::new();
+    let mut selector_chs = selector_rule.chars().peekable();
+    loop {
+        let at = selector_rule.len() - remaining_len(&selector_chs);
+        match selector_chs.peek() {
+            Some(c) => match c {
+                ',' => {
+                    return Err(ParseHtmlError::with_msg(format!(
+                        "unexpected ',' in css selector rule {}",
+                        selector_rule
+                    ))
+                    .add_offset(at))
+                }
+                _ => (),
+            },
+            None => break,
+        }
+        let item =
+            parse_css_selector_item(&mut selector_chs, options).map_err(|e| e.add_offset(at))?;
+        let at = selector_rule.len() - remaining_len(&selector_chs);
+        match item {
+            //check following characters to work out what the relationship should be
+            Some(item) => match parse_css_selector_relationship(&mut selector_chs)
+                .map_err(|e| e.add_offset(at))?
+            {
+                CssSelectorRelationshipType::Current => {
+                    css_rule.rules.push(CssSelectorRelationship::Current(item))
+                }
+                CssSelectorRelationshipType::Ancestor => {
+                    css_rule.rules.push(CssSelectorRelationship::Ancestor(item))
+                }
+                CssSelectorRelationshipType::Parent => {
+                    css_rule.rules.push(CssSelectorRelationship::Parent(item))
+                }
+                CssSelectorRelationshipType::PreviousSibling => css_rule
+                    .rules
+                    .push(CssSelectorRelationship::PreviousSibling(item)),
+                CssSelectorRelationshipType::PreviousSiblingOnce => css_rule
+                    .rules
+                    .push(CssSelectorRelationship::PreviousSiblingOnce(item)),
+            },
+            None => break,
+        }
+    }
+    Ok(css_rule)
+}
+
+fn parse_css_selector_relationship(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+) -> Result<CssSelectorRelationshipType, ParseHtmlError> {
+    let mut rel = CssSelectorRelationshipType::Current;
+    // the separators are all one byte long
+    let mut at = 0;
+    while let Some(c) = chs.peek() {
+        match *c {
+            ' ' => {
+                if rel == CssSelectorRelationshipType::Current {
+                    rel = CssSelectorRelationshipType::Ancestor;
+                }
+                chs.next();
+                at += 1;
+            }
+            '>' => {
+                //if we have already read a > or a ~
+                if rel != CssSelectorRelationshipType::Ancestor
+                    && rel != CssSelectorRelationshipType::Current
+                {
+                    return Err(ParseHtmlError::with_msg(format!(
+                        "found multiple relationship seperators in selector first {:?} and now {:?}",
+                        rel,
+                        CssSelectorRelationshipType::Parent
+                    ))
+                    .add_offset(at));
+                }
+                chs.next();
+                at += 1;
+                rel = CssSelectorRelationshipType::Parent;
+            }
+            '~' => {
+                //if we have already read a > or a ~
+                if rel != CssSelectorRelationshipType::Ancestor
+                    && rel != CssSelectorRelationshipType::Current
+                {
+                    return Err(ParseHtmlError::with_msg(format!(
+                        "found multiple relationship seperators in selector first {:?} and now {:?}",
+                        rel,
+                        CssSelectorRelationshipType::PreviousSibling
+                    ))
+                    .add_offset(at));
+                }
+                chs.next();
+                at += 1;
+                rel = CssSelectorRelationshipType::PreviousSibling;
+            }
+            '+' => {
+                //if we have already read a > or a ~
+                if rel != CssSelectorRelationshipType::Ancestor
+                    && rel != CssSelectorRelationshipType::Current
+                {
+                    return Err(ParseHtmlError::with_msg(format!(
+                        "found multiple relationship seperators in selector first {:?} and now {:?}",
+                        rel,
+                        CssSelectorRelationshipType::PreviousSiblingOnce
+                    ))
+                    .add_offset(at));
+                }
+                chs.next();
+                at += 1;
+                rel = CssSelectorRelationshipType::PreviousSiblingOnce;
+            }
+            _ => break,
+        }
+    }
+    Ok(rel)
+}
+
+pub fn parse_css_selector_item(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+    options: &CssSelectorParseOptions,
+) -> Result<Option<CssSelectorItem>, ParseHtmlError> {
+    //consume whitespace, which is all one byte long
+    let mut start = 0;
+    loop {
+        match chs.peek() {
+            None => {
+                return Err(ParseHtmlError::with_msg(
+                    "Could not parse a CSS selector item - only whitespace in string.",
+                ))
+            }
+            Some(ch) => {
+                if ch.is_ascii_whitespace() {
+                    chs.next();
+                    start += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
+    // read until one of " " + > ~
+    let mut item_str = String::new();
+    // the closing brackets expected for the brackets opened so far, such as the ')' and ']'
+    // of :not([alt])
+    let mut open_brackets = vec![];
+    let mut tracker = CssQuoteTracker::default();
+    loop {
+        match chs.peek() {
+            None => {
+                break;
+            }
+            Some(ch) => {
+                if tracker.quoted(*ch) {
+                    item_str.push(chs.next().unwrap());
+                    continue;
+                }
+                if open_brackets.last() == Some(&'}') {
+                    // a namespace written as {uri} can contain any character
+                    if *ch == '}' {
+                        open_brackets.pop();
+                    }
+                } else if *ch == '(' {
+                    open_brackets.push(')');
+                } else if *ch == '[' {
+                    open_brackets.push(']');
+                } else if *ch == '{' && open_brackets.is_empty() {
+                    // braces are only a namespace at the start of the item, inside of
+                    // brackets they are part of a value such as a[href$=}]
+                    open_brackets.push('}');
+                } else if *ch == ')' || *ch == ']' || (*ch == '}' && open_brackets.is_empty()) {
+                    if open_brackets.last() != Some(ch) {
+                        let opening = match ch {
+                            ')' => '(',
+                            ']' => '[',
+                            _ => '{',
+                        };
+                        return Err(ParseHtmlError::with_msg(format!(
+                            "Found '{}' without a opening '{}'",
+                            ch, opening
+                        ))
+                        .add_offset(start + item_str.len()));
+                    }
+                    open_brackets.pop();
+                } else if (*ch == ' ' || *ch == '+' || *ch == '>' || *ch == '~')
+                    && open_brackets.is_empty()
+                {
+                    break;
+                }
+                item_str.push(chs.next().unwrap());
+            }
+        }
+    }
+
+    if item_str.len() == 0 {
+        return Ok(None);
+    }
+
+    //parse the selector item
+    let mut item_chars = item_str.chars().peekable();
+    // check prefix:
+    let mut item = CssSelectorItem::new();
+    loop {
+        let at = start + item_str.len() - remaining_len(&item_chars);
+        match item_chars.peek() {
+            None => break,
+            Some(c) => match c {
+                '.' => {
+                    item_chars.next(); //consume the .
+                    match parse_until_end_or_one_of_peekable(
+                        &mut item_chars,
+                        vec!['.', '#', ':', '['],
+                    ) {
+                        Some(class) => match &mut item.classes {
+                            Some(classes) => classes.push(class),
+                            None => item.classes = Some(vec![class]),
+                        },
+                        None => (),
+                    }
+                }
+                '#' => {
+                    item_chars.next(); //consume the #
+                    match parse_until_end_or_one_of_peekable(
+                        &mut item_chars,
+                        vec!['.', '#', ':', '['],
+                    ) {
+                        Some(id) => match &mut item.ids {
+                            Some(ids) => ids.push(id),
+                            None => item.ids = Some(vec![id]),
+                        },
+                        None => (),
+                    }
+                }
+                ':' => {
+                    item_chars.next(); //consume the :
+                    let refiner = parse_css_refiner(&mut item_chars, options)
+                        .map_err(|e| e.add_offset(at))?;
+                    match &mut item.refiners {
+                        Some(refiners) => refiners.push(refiner),
+                        None => item.refiners = Some(vec![refiner]),
+                    }
+                }
+                '[' => {
+                    item_chars.next(); //consume the [
+                    match &mut item.attributes {
+                        None => {
+                            let mut attributes = vec![];
+                            attributes.push(
+                                parse_css_attribute_rule(&mut item_chars)
+                                    .map_err(|e| e.add_offset(at))?,
+                            );
+                            item.attributes = Some(attributes);
+                        }
+                        Some(attributes) => attributes.push(
+                            parse_css_attribute_rule(&mut item_chars)
+                                .map_err(|e| e.add_offset(at))?,
+                        ),
+                    }
+                }
+                _ => {
+                    // a namespace written as {uri} can contain any of the separators
+                    let mut namespace = String::new();
+                    if *c == '{' {
+                        for ch in item_chars.by_ref() {
+                            namespace.push(ch);
+                            if ch == '}' {
+                                break;
+                            }
+                        }
+                    }
+                    match parse_until_end_or_one_of_peekable(
+                        &mut item_chars,
+                        vec!['.', '#', ':', '['],
+                    ) {
+                        Some(tag) => {
+                            item.tag = Some(
+                                resolve_css_type_namespace(namespace + &tag, options)
+                                    .map_err(|e| e.add_offset(at))?,
+                            )
+                        }
+                        None => {
+                            return Err(ParseHtmlError::with_msg(format!(
+                                "expected a tag name after the namespace {}",
+                                namespace
+                            ))
+                            .add_offset(at))
+                        }
+                    }
+                } //tag
+            },
+        }
+    }
+    Ok(Some(item))
+}
+
+/// Resolves the namespace prefix of a type selector, such as `atom|link`, using the
+/// namespaces from the options. The tag is written as `{uri}name` when it has a
+/// namespace, `*|name` gives a name in any namespace and `|name` a name in no namespace.
+fn resolve_css_type_namespace(
+    tag: String,
+    options: &CssSelectorParseOptions,
+) -> Result<String, ParseHtmlError> {
+    if tag.starts_with('{') {
+        return match tag.find('}') {
+            Some(end) if end + 1 < tag.len() => Ok(tag),
+            _ => Err(ParseHtmlError::with_msg(format!(
+                "expected {{uri}}name for the namespaced tag {}",
+                tag
+            ))),
+        };
+    }
+    let (prefix, name) = match tag.split_once('|') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, tag.as_str()),
+    };
+    if name.is_empty() || name.contains('|') {
+        return Err(ParseHtmlError::with_msg(format!(
+            "invalid namespaced tag {}",
+            tag
+        )));
+    }
+    match prefix {
+        Some("*") => Ok(name.to_owned()),
+        Some("") => Ok(format!("{{}}{}", name)),
+        Some(prefix) => match options.namespaces.get(prefix) {
+            Some(uri) => Ok(format!("{{{}}}{}", uri, name)),
+            None => Err(ParseHtmlError::with_msg(format!(
+                "unknown namespace prefix '{}' in {}",
+                prefix, tag
+            ))),
+        },
+        None => match options.namespaces.get("") {
+            Some(uri) => Ok(format!("{{{}}}{}", uri, name)),
+            None => Ok(tag),
+        },
+    }
+}
+
+/// Parses a peekable chars iterator for a CSS selector attribute rule.
+/// CSS selector attribute rules refers to the modifiers in a CSS selector that are
+/// contained in square brackets "[]", for example [attr=value].
+///
+/// # Example
+///
+/// ```ignore
+/// use hb_selector::parsing::parse_css_attribute_rule;
+/// use hb_selector::selector::CssAttributeCompareType;
+/// let css_attr_rule = parse_css_attribute_rule("[attr=val]".chars().peekable()).expect();
+/// assert_eq!(css_attr_rule, CssAttributeCompareType::Equals(("attr".to_owned(), "val".to_owned())));
+/// ```
+fn parse_css_attribute_rule(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+) -> Result<CssAttributeCompareType, ParseHtmlError> {
+    let attr = parse_until_one_of_peekable(chs, vec![']', '=', '|', '^', '$', '*', '~'])
+        .map_err(|e| e.add_context("could not parse css selector attribute rule"))?;
+    let mut sep = String::new();
+    match chs.peek() {
+        None => {
+            return Err(ParseHtmlError::with_msg(
+                "No attribute rule found in between [].",
+            ));
+        }
+        Some(c) => match c {
+            ']' => {
+                chs.next(); //consume ]
+                return Ok(CssAttributeCompareType::Present(attr));
+            }
+            _ => sep.push(chs.next().unwrap()),
+        },
+    }
+    if sep != "=" {
+        match chs.peek() {
+            None => {
+                return Err(ParseHtmlError::with_msg(format!(
+                    "Attribute rule not finished {}{}.",
+                    attr, sep
+                )));
+            }
+            Some(c) => {
+                if *c != '=' {
+                    return Err(ParseHtmlError::with_msg(format!(
+                        "unknown attribute rule qualifier {}{}.",
+                        sep, c
+                    )));
+                }
+                sep.push(chs.next().unwrap());
+            }
+        }
+    }
+    let value = parse_until_char_peekable(chs, ']')?;
+    chs.next(); // consume ]
+    match sep.as_str() {
+        "=" => Ok(CssAttributeCompareType::Equals((attr, value))),
+        "|=" => Ok(CssAttributeCompareType::EqualsOrBeingsWith((attr, value))),
+        "^=" => Ok(CssAttributeCompareType::BeginsWith((attr, value))),
+        "$=" => Ok(CssAttributeCompareType::EndsWith((attr, value))),
+        "*=" => Ok(CssAttributeCompareType::Contains((attr, value))),
+        "~=" => Ok(CssAttributeCompareType::ContainsWord((attr, value))),
+        _ => {
+            return Err(ParseHtmlError::with_msg(format!(
+                "unknown attribute rule qualifier {}.",
+                sep
+            )));
+        }
+    }
+}
+
+/// Parses a peekable chars iterator for a CSS selector refiner.
+/// CSS selector refiners are refering to the modifiers in a CSS selector that follow a ":",
+/// this includes things such a ":first-of-type".
+///
+/// # Example
+///
+/// ```ignore
+/// use hb_selector::parsing::parse_css_refiner;
+/// use hb_selector::selector::CssRefiner;
+/// let css_ref = parse_css_refiner(":first-of-type".chars().peekable()).expect();
+/// assert_eq!(css_ref, CssRefiner::FirstOfType);
+/// ```
+fn parse_css_refiner(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+    options: &CssSelectorParseOptions,
+) -> Result<CssRefiner, ParseHtmlError> {
+    let refiner = match parse_until_end_or_one_of_peekable(chs, vec!['.', '#', ':', '[', '(']) {
+        None => return Err(ParseHtmlError::with_msg("No refiner found after :")),
+        Some(r) => r,
+    };
+    if refiner == "checked" {
+        return Ok(CssRefiner::Checked);
+    } else if refiner == "default" {
+        return Ok(CssRefiner::Default);
+    } else if refiner == "disabled" {
+        return Ok(CssRefiner::Disabled);
+    } else if refiner == "enabled" {
+        return Ok(CssRefiner::Enabled);
+    } else if refiner == "optional" {
+        return Ok(CssRefiner::Optional);
+    } else if refiner == "required" {
+        return Ok(CssRefiner::Required);
+    } else if refiner == "read-only" {
+        return Ok(CssRefiner::ReadOnly);
+    } else if refiner == "read-write" {
+        return Ok(CssRefiner::ReadWrite);
+    } else if refiner == "empty" {
+        return Ok(CssRefiner::Empty);
+    } else if refiner == "first-child" {
+        return Ok(CssRefiner::FirstChild);
+    } else if refiner == "last-child" {
+        return Ok(CssRefiner::LastChild);
+    } else if refiner.starts_with("nth-child") {
+        return Ok(CssRefiner::NthChild(parse_css_refiner_number(
+            &parse_until_and_including_char(chs, ')').map_err(|e| {
+                e.add_context(format!(
+                    "error while trying to read CSS refiner number after {}",
+                    refiner
+                ))
+            })?,
+        )?));
+    } else if refiner.starts_with("nth-last-child") {
+        return Ok(CssRefiner::NthLastChild(parse_css_refiner_number(
+            &parse_until_and_including_char(chs, ')').map_err(|e| {
+                e.add_context(format!(
+                    "error while trying to read CSS refiner number after {}",
+                    refiner
+                ))
+            })?,
+        )?));
+    } else if refiner == "only-child" {
+        return Ok(CssRefiner::OnlyChild);
+    } else if refiner == "first-of-type" {
+        return Ok(CssRefiner::FirstOfType);
+    } else if refiner == "last-of-type" {
+        return Ok(CssRefiner::LastOfType);
+    } else if refiner.starts_with("nth-of-type") {
+        return Ok(CssRefiner::NthOfType(parse_css_refiner_number(
+            &parse_until_and_including_char(chs, ')').map_err(|e| {
+                e.add_context(format!(
+                    "error while trying to read CSS refiner number after {}",
+                    refiner
+                ))
+            })?,
+        )?));
+    } else if refiner.starts_with("nth-last-of-type") {
+        return Ok(CssRefiner::NthLastOfType(
+            parse_css_refiner_number(&parse_until_and_including_char(chs, ')').map_err(|e| {
+                e.add_context(format!(
+                    "error while trying to read CSS refiner number after {}",
+                    refiner
+                ))
+            })?)
+            .map_err(|e| {
+                e.add_context(format!(
+                    "error while trying to read CSS refiner number after {}",
+                    refiner
+                ))
+            })?,
+        ));
+    } else if refiner == "only-of-type" {
+        return Ok(CssRefiner::OnlyOfType);
+    } else if refiner == "not" {
+        return Ok(CssRefiner::Not(
+            CssSelector::parse_with_options(
+                parse_css_refiner_argument(chs)
+                    .map_err(|e| e.add_context("could not find closing brace for :not( refiner"))?
+                    .as_str(),
+                options,
+            )
+            .map_err(|e| {
+                // the argument starts after the ':' and the '(' around the refiner name
+                e.add_context("could not parse css selector inside the :not(..) refiner")
+                    .add_offset(refiner.len() + 2)
+            })?,
+        ));
+    } else if refiner == "root" {
+        return Ok(CssRefiner::Root);
+    } else if ["contains", "icontains", "matches", "text-equals"].contains(&refiner.as_str()) {
+        if !options.text_refiners {
+            return Err(ParseHtmlError::with_msg(format!(
+                "the non-standard refiner :{}() is not allowed when text refiners are turned off",
+                refiner
+            )));
+        }
+        let argument = parse_css_refiner_argument(chs)
+            .map_err(|e| e.add_context(format!("could not read the argument of :{}(", refiner)))?;
+        return match refiner.as_str() {
+            "matches" => Ok(CssRefiner::Matches(parse_css_regex_argument(&argument)?)),
+            "contains" => Ok(CssRefiner::Contains(parse_css_text_argument(&argument))),
+            "icontains" => Ok(CssRefiner::IContains(parse_css_text_argument(&argument))),
+            _ => Ok(CssRefiner::TextEquals(parse_css_text_argument(&argument))),
+        };
+    }
+    return Err(ParseHtmlError::with_msg(format!(
+        "unknown refiner type {}.",
+        refiner
+    )));
+}
+
+/// Reads the contents of the brackets following a refiner such as :not(..) or :contains(..).
+/// Brackets inside quotes or a /regex/ are ignored, and nested brackets are kept.
+fn parse_css_refiner_argument(
+    chs: &mut std::iter::Peekable<std::str::Chars>,
+) -> Result<String, ParseHtmlError> {
+    match chs.next() {
+        Some('(') => (),
+        _ => return Err(ParseHtmlError::with_msg("no opening brace was found")),
+    }
+    let mut buffer = String::new();
+    let mut tracker = CssQuoteTracker::default();
+    tracker.quoted('(');
+    let mut level = 1;
+    for ch in chs.by_ref() {
+        if !tracker.quoted(ch) {
+            match ch {
+                '(' => level += 1,
+                ')' => {
+                    level -= 1;
+                    if level == 0 {
+                        return Ok(buffer);
+                    }
+                }
+                _ => (),
+            }
+        }
+        buffer.push(ch);
+    }
+    Err(ParseHtmlError::with_msg(format!(
+        "end of string '{}' encountered before closing brace ')' was found",
+        buffer
+    )))
+}
+
+/// Gets the text from the argument of a text refiner, which is either a quoted string
+/// with backslash escapes or the trimmed argument itself.
+fn parse_css_text_argument(argument: &str) -> String {
+    let argument = argument.trim();
+    let quote = match argument.chars().next() {
+        Some(q) if (q == '"' || q == '\'') && argument.len() > 1 && argument.ends_with(q) => q,
+        _ => return argument.to_owned(),
+    };
+    let mut text = String::new();
+    let mut chs = argument[1..argument.len() - quote.len_utf8()].chars();
+    while let Some(ch) = chs.next() {
+        match ch {
+            '\\' => {
+                if let Some(escaped) = chs.next() {
+                    text.push(escaped);
+                }
+            }
+            _ => text.push(ch),
+        }
+    }
+    text
+}
+
+/// Gets the regular expression from the argument of :matches(..). It can be either
+/// /pattern/flags or a quoted string.
+fn parse_css_regex_argument(argument: &str) -> Result<CssRegex, ParseHtmlError> {
+    let argument = argument.trim();
+    if !argument.starts_with('/') {
+        return CssRegex::new(&parse_css_text_argument(argument));
+    }
+    let end = match argument.rfind('/') {
+        Some(end) if end > 0 => end,
+        _ => {
+            return Err(ParseHtmlError::with_msg(format!(
+                "no closing / found for the regular expression {}",
+                argument
+            )))
+        }
+    };
+    let pattern = argument[1..end].replace("\\/", "/");
+    let flags = &argument[end + 1..];
+    match flags.is_empty() {
+        true => CssRegex::new(&pattern),
+        false => CssRegex::new(&format!("(?{}){}", flags, pattern)),
+    }
+}
+
+/// Parses a peekable chars iterator for a number or function used in a CSS selector refiner.
+/// CSS selector refiners are refering to the modifiers in a CSS selector that follow a ":".
+/// The number or function that this parses is used in specific refiners such as ":nth-of-type".
+///
+/// # Example
+///
+/// ```ignore
+/// use hb_selector::parsing::parse_css_refiner_number;
+/// use hb_selector::selector::CssRefinerNumberType;
+/// let css_ref_num = parse_css_refiner_number("(2n+1)".chars().peekable()).expect();
+/// assert_eq!(css_ref_num, CssRefinerNumberType::Functional((2,1)));
+/// ```
+fn parse_css_refiner_number(raw_str: &str) -> Result<CssRefinerNumberType, ParseHtmlError> {
+    let mut str_iter = raw_str.chars();
+    match str_iter.next() {
+        None => {
+            return Err(ParseHtmlError::with_msg(format!(
+                "No number found for refiner, expected a ( at the start of {}.",
+                raw_str
+            )))
+        }
+        Some(c) => {
+            if c != '(' {
+                return Err(ParseHtmlError::with_msg(format!(
+                    "Expected ( after refiner which needs a number/even/odd/function but got {}",
+                    c
+                )));
+            }
+        }
+    }
+    match str_iter.last() {
+        None => {
+            return Err(ParseHtmlError::with_msg(format!(
+                "No ) found after ( in refiner {}.",
+                raw_str
+            )))
+        }
+        Some(c) => {
+            if c != ')' {
+                return Err(ParseHtmlError::with_msg(format!(
+                    "Expected ) at end of refiner number/even/odd/function but got {}",
+                    c
+                )));
+            }
+        }
+    }
+
+    let num_str = &raw_str[1..raw_str.len() - 1];
+    let parts: Vec<&str> = num_str.split('+').map(|x| x.trim()).collect();
+    if parts.len() > 2 {
+        return Err(ParseHtmlError::with_msg(format!(
+            "too many +'s present in refiner number {}",
+            raw_str
+        )));
+    }
+
+    if parts.len() == 1 {
+        match parts[0] {
+            "odd" => return Ok(CssRefinerNumberType::Odd),
+            "even" => return Ok(CssRefinerNumberType::Even),
+            p => match p.parse::<usize>() {
+                Err(_) => {
+                    return Err(ParseHtmlError::with_msg(format!(
+                        "could not parse number in refiner {}",
+                        raw_str
+                    )))
+                }
+                Ok(i) => return Ok(CssRefinerNumberType::Specific(i)),
+            },
+        }
+    }
+
+    if parts[0].chars().last().unwrap() != 'n' {
+        return Err(ParseHtmlError::with_msg(format!(
+            "error parsing functional refiner, expected a 'n' at the end of {}",
+            parts[0]
+        )));
+    }
+
+    let multi = match parts[0][0..parts[0].len() - 1].parse::<i32>() {
+        Err(_) => {
+            return Err(ParseHtmlError::with_msg(format!(
+                "could not parse int before the n in {}",
+                parts[0]
+            )))
+        }
+        Ok(i) => i,
+    };
+    let b = match parts[1].parse::<i32>() {
+        Err(_) => {
+            return Err(ParseHtmlError::with_msg(format!(
+                "could not parse int in {}",
+                parts[1]
+            )))
+        }
+        Ok(i) => i,
+    };
+
+    Ok(CssRefinerNumberType::Functional((multi, b)))
+}
+
+#[cfg(test)]
+mod parse_css_selector_tests {
+    use super::*;
+
+    #[test]
+    fn parse_css_refiner_test() {
+        let tests = vec![
+            ("checked", CssRefiner::Checked),
+            ("default", CssRefiner::Default),
+            ("disabled", CssRefiner::Disabled),
+            ("enabled", CssRefiner::Enabled),
+            ("optional", CssRefiner::Optional),
+            ("required", CssRefiner::Required),
+            ("read-only", CssRefiner::ReadOnly),
+            ("read-write", CssRefiner::ReadWrite),
+            ("empty", CssRefiner::Empty),
+            ("first-child", CssRefiner::FirstChild),
+            ("last-child", CssRefiner::LastChild),
+            (
+                "nth-child(1)",
+                CssRefiner::NthChild(CssRefinerNumberType::Specific(1)),
+            ),
+            (
+                "nth-child(2)",
+                CssRefiner::NthChild(CssRefinerNumberType::Specific(2)),
+            ),
+            (
+                "nth-child(2n+1)",
+                CssRefiner::NthChild(CssRefinerNumberType::Functional((2, 1))),
+            ),
+            (
+                "nth-child(odd)",
+                CssRefiner::NthChild(CssRefinerNumberType::Odd),
+            ),
+            (
+                "nth-child(even)",
+                CssRefiner::NthChild(CssRefinerNumberType::Even),
+            ),
+            (
+                "nth-last-child(1)",
+                CssRefiner::NthLastChild(CssRefinerNumberType::Specific(1)),
+            ),
+            ("only-child", CssRefiner::OnlyChild),
+            ("first-of-type", CssRefiner::FirstOfType),
+            ("last-of-type", CssRefiner::LastOfType),
+            (
+                "nth-of-type(1)",
+                CssRefiner::NthOfType(CssRefinerNumberType::Specific(1)),
+            ),
+            (
+                "nth-last-of-type(1)",
+                CssRefiner::NthLastOfType(CssRefinerNumberType::Specific(1)),
+            ),
+            ("only-of-type", CssRefiner::OnlyOfType),
+            ("root", CssRefiner::Root),
+            ("empty:checked", CssRefiner::Empty),
+            ("empty[attr]", CssRefiner::Empty),
+            ("empty#id", CssRefiner::Empty),
+            (
+                "not(p#id)",
+                CssRefiner::Not(CssSelector::Specific(vec![CssSelectorRule {
+                    rules: vec![CssSelectorRelationship::Current(CssSelectorItem {
+                        tag: Some("p".to_owned()),
+                        classes: None,
+                        ids: Some(vec!["id".to_owned()]),
+                        refiners: None,
+                        attributes: None,
+                    })],
+                }])),
+            ),
+        ];
+
+        for t in tests {
+            assert_eq!(
+                parse_css_refiner(
+                    &mut t.0.chars().peekable(),
+                    &CssSelectorParseOptions::default()
+                )
+                .unwrap(),
+                t.1
+            );
+        }
+    }
+
+    #[test]
+    fn parse_css_text_refiner_test() {
+        let options = CssSelectorParseOptions::default();
+        let tests = vec![
+            (
+                r#"contains("Next page")"#,
+                CssRefiner::Contains("Next page".to_owned()),
+            ),
+            (
+                "contains('it\\'s')",
+                CssRefiner::Contains("it's".to_owned()),
+            ),
+            (
+                "contains( plain text )",
+                CssRefiner::Contains("plain text".to_owned()),
+            ),
+            (
+                r#"contains("a) > b, c")"#,
+                CssRefiner::Contains("a) > b, c".to_owned()),
+            ),
+            (
+                r#"icontains("PRICE")"#,
+                CssRefiner::IContains("PRICE".to_owned()),
+            ),
+            (r#"text-equals("")"#, CssRefiner::TextEquals("".to_owned())),
+            (
+                r#"matches(/^\d+(\.\d+)?$/)"#,
+                CssRefiner::Matches(CssRegex::new(r#"^\d+(\.\d+)?$"#).unwrap()),
+            ),
+            (
+                r#"matches(/a\/b/i)"#,
+                CssRefiner::Matches(CssRegex::new("(?i)a/b").unwrap()),
+            ),
+            (
+                r#"matches("[0-9]")"#,
+                CssRefiner::Matches(CssRegex::new("[0-9]").unwrap()),
+            ),
+        ];
+        for t in tests {
+            assert_eq!(
+                parse_css_refiner(&mut t.0.chars().peekable(), &options).unwrap(),
+                t.1,
+                "{}",
+                t.0
+            );
+        }
+        let strict = CssSelectorParseOptions::strict();
+        assert_eq!(
+            parse_css_refiner(&mut r#"contains("x")"#.chars().peekable(), &strict).unwrap_err(),
+            ParseHtmlError::with_msg(
+                "the non-standard refiner :contains() is not allowed when text refiners are turned off"
+            )
+        );
+        assert!(parse_css_refiner(&mut "matches(/(/)".chars().peekable(), &options).is_err());
+        assert!(parse_css_refiner(&mut r#"contains("x""#.chars().peekable(), &options).is_err());
+        // the :not() refiner passes the options on
+        assert!(
+            parse_css_refiner(&mut r#"not(:contains("x"))"#.chars().peekable(), &strict).is_err()
+        );
+    }
+
+    #[test]
+    fn split_css_selector_list_test() {
+        assert_eq!(split_css_selector_list("a, b"), vec!["a", " b"]);
+        assert_eq!(
+            split_css_selector_list(r#"a:contains("x, y"), p:not(.a, .b), [title="c,d"]"#),
+            vec![
+                r#"a:contains("x, y")"#,
+                " p:not(.a, .b)",
+                r#" [title="c,d"]"#
+            ]
+        );
+        assert_eq!(
+            split_css_selector_list("td:matches(/a,(b/), th"),
+            vec!["td:matches(/a,(b/)", " th"]
+        );
+    }
+
+    #[test]
+    fn parse_css_refiner_errors_test() {
+        let tests = vec![(
+            "nth-last-of-type(1a)",
+            ParseHtmlError::with_msg(
+                "error while trying to read CSS refiner number after nth-last-of-type because could not parse number in refiner (1a)",
+            )),
+            ("nth-last-of-type(1",
+            ParseHtmlError::with_msg(
+                "error while trying to read CSS refiner number after nth-last-of-type because end of string '(1' encountered before ending ')' was found",
+            )),
+            ("something-not-a-refiner",
+             ParseHtmlError::with_msg("unknown refiner type something-not-a-refiner.")
+            ),
+        ];
+
+        for t in tests {
+            assert_eq!(
+                parse_css_refiner(
+                    &mut t.0.chars().peekable(),
+                    &CssSelectorParseOptions::default()
+                )
+                .unwrap_err(),
+                t.1
+            );
+        }
+    }
+
+    #[test]
+    fn parse_css_selector_relationship_test() {
+        let tests = vec![
+            (" > ", Ok(CssSelectorRelationshipType::Parent)),
+            (">", Ok(CssSelectorRelationshipType::Parent)),
+            ("   ", Ok(CssSelectorRelationshipType::Ancestor)),
+            ("  ", Ok(CssSelectorRelationshipType::Ancestor)),
+            (" ", Ok(CssSelectorRelationshipType::Ancestor)),
+            (" ~ ", Ok(CssSelectorRelationshipType::PreviousSibling)),
+            ("~", Ok(CssSelectorRelationshipType::PreviousSibling)),
+            (" + ", Ok(CssSelectorRelationshipType::PreviousSiblingOnce)),
+            ("+", Ok(CssSelectorRelationshipType::PreviousSiblingOnce)),
+            (" > ~ ", Err(ParseHtmlError::with_msg("found multiple relationship seperators in selector first Parent and now PreviousSibling").add_offset(3))),
+            ("~>", Err(ParseHtmlError::with_msg("found multiple relationship seperators in selector first PreviousSibling and now Parent").add_offset(1))),
+        ];
+
+        for t in tests {
+            assert_eq!(
+                parse_css_selector_relationship(&mut t.0.chars().peekable()),
+                t.1
+            );
+        }
+    }
+
+    #[test]
+    fn parse_css_selector_item_test() {
+        let tests = vec![
+            (
+                "div",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: None,
+                    ids: None,
+                    refiners: None,
+                    attributes: None,
+                })),
+            ),
+            (
+                "div.c1",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: Some(vec!["c1".to_owned()]),
+                    ids: None,
+                    refiners: None,
+                    attributes: None,
+                })),
+            ),
+            (
+                "div.c1.c2",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: Some(vec!["c1".to_owned(), "c2".to_owned()]),
+                    ids: None,
+                    refiners: None,
+                    attributes: None,
+                })),
+            ),
+            (
+                "div#first",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: None,
+                    ids: Some(vec!["first".to_owned()]),
+                    refiners: None,
+                    attributes: None,
+                })),
+            ),
+            (
+                "div#first#second",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: None,
+                    ids: Some(vec!["first".to_owned(), "second".to_owned()]),
+                    refiners: None,
+                    attributes: None,
+                })),
+            ),
+            (
+                "div[attr]",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: None,
+                    ids: None,
+                    refiners: None,
+                    attributes: Some(vec![CssAttributeCompareType::Present("attr".to_owned())]),
+                })),
+            ),
+            (
+                "div:first-child[attr]",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: None,
+                    ids: None,
+                    refiners: Some(vec![CssRefiner::FirstChild]),
+                    attributes: Some(vec![CssAttributeCompareType::Present("attr".to_owned())]),
+                })),
+            ),
+            (
+                "div.c1:first-child[attr][attr2=1].c2#first:nth-of-type(2n+1)#second",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("div".to_owned()),
+                    classes: Some(vec!["c1".to_owned(), "c2".to_owned()]),
+                    ids: Some(vec!["first".to_owned(), "second".to_owned()]),
+                    refiners: Some(vec![
+                        CssRefiner::FirstChild,
+                        CssRefiner::NthOfType(CssRefinerNumberType::Functional((2, 1))),
+                    ]),
+                    attributes: Some(vec![
+                        CssAttributeCompareType::Present("attr".to_owned()),
+                        CssAttributeCompareType::Equals(("attr2".to_owned(), "1".to_owned())),
+                    ]),
+                })),
+            ),
+            (
+                "img:not([alt])",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("img".to_owned()),
+                    classes: None,
+                    ids: None,
+                    refiners: Some(vec![CssRefiner::Not(
+                        "[alt]".parse::<CssSelector>().unwrap(),
+                    )]),
+                    attributes: None,
+                })),
+            ),
+            (
+                "div:not([attr)]",
+                Err(ParseHtmlError::with_msg("Found ')' without a opening '('").add_offset(13)),
+            ),
+            (
+                "a[href$=}]",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("a".to_owned()),
+                    classes: None,
+                    ids: None,
+                    refiners: None,
+                    attributes: Some(vec![CssAttributeCompareType::EndsWith((
+                        "href".to_owned(),
+                        "}".to_owned(),
+                    ))]),
+                })),
+            ),
+            (
+                "{urn:x(1]}a.c",
+                Ok(Some(CssSelectorItem {
+                    tag: Some("{urn:x(1]}a".to_owned()),
+                    classes: Some(vec!["c".to_owned()]),
+                    ids: None,
+                    refiners: None,
+                    attributes: None,
+                })),
+            ),
+            (
+                "a}",
+                Err(ParseHtmlError::with_msg("Found '}' without a opening '{'").add_offset(1)),
+            ),
+        ];
+
+        for t in tests {
+            assert_eq!(
+                parse_css_selector_item(
+                    &mut t.0.chars().peekable(),
+                    &CssSelectorParseOptions::default()
+                ),
+                t.1
+            );
+        }
+    }
+
+    #[test]
+    fn parse_css_selector_rule_test() {
+        let tests = vec![
+            (
+                "div",
+                CssSelectorRule {
+                    rules: vec![CssSelectorRelationship::Current(CssSelectorItem {
+                        tag: Some("div".to_owned()),
+                        classes: None,
+                        ids: None,
+                        refiners: None,
+                        attributes: None,
+                    })],
+                },
+            ),
+            (
+                "div p",
+                CssSelectorRule {
+                    rules: vec![
+                        CssSelectorRelationship::Ancestor(CssSelectorItem {
+                            tag: Some("div".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                        CssSelectorRelationship::Current(CssSelectorItem {
+                            tag: Some("p".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                    ],
+                },
+            ),
+            (
+                "div > p",
+                CssSelectorRule {
+                    rules: vec![
+                        CssSelectorRelationship::Parent(CssSelectorItem {
+                            tag: Some("div".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                        CssSelectorRelationship::Current(CssSelectorItem {
+                            tag: Some("p".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                    ],
+                },
+            ),
+            (
+                "div a > p",
+                CssSelectorRule {
+                    rules: vec![
+                        CssSelectorRelationship::Ancestor(CssSelectorItem {
+                            tag: Some("div".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                        CssSelectorRelationship::Parent(CssSelectorItem {
+                            tag: Some("a".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                        CssSelectorRelationship::Current(CssSelectorItem {
+                            tag: Some("p".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                    ],
+                },
+            ),
+            (
+                "div a ~ p",
+                CssSelectorRule {
+                    rules: vec![
+                        CssSelectorRelationship::Ancestor(CssSelectorItem {
+                            tag: Some("div".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                        CssSelectorRelationship::PreviousSibling(CssSelectorItem {
+                            tag: Some("a".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                        CssSelectorRelationship::Current(CssSelectorItem {
+                            tag: Some("p".to_owned()),
+                            classes: None,
+                            ids: None,
+                            refiners: None,
+                            attributes: None,
+                        }),
+                    ],
+                },
+            ),
+        ];
+
+        for t in tests {
+            assert_eq!(
+                parse_css_selector_rule(t.0, &CssSelectorParseOptions::default()).unwrap(),
+                t.1
+            );
+        }
+    }
+}
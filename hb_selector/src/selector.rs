@@ -0,0 +1,572 @@
+//! The CSS selectors used to find tags in a HTML document, such as `div.item > a[href]`.
+//!
+//! Selectors are parsed with [`FromStr`] or [`CssSelector::parse_with_options`], and they
+//! are written back as CSS with [`fmt::Display`].
+use crate::error::ParseHtmlError;
+use crate::parsing::{parse_css_selector_rule, split_css_selector_list};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents the relationship that is next to be matched in the list of selector items.
+pub enum CssSelectorRelationship {
+    Parent(CssSelectorItem),
+    Ancestor(CssSelectorItem),
+    PreviousSibling(CssSelectorItem),
+    PreviousSiblingOnce(CssSelectorItem),
+    Current(CssSelectorItem),
+}
+
+impl CssSelectorRelationship {
+    /// Gets the selector item that has to match for this relationship.
+    pub fn item(&self) -> &CssSelectorItem {
+        match self {
+            CssSelectorRelationship::Parent(item) => item,
+            CssSelectorRelationship::Ancestor(item) => item,
+            CssSelectorRelationship::PreviousSibling(item) => item,
+            CssSelectorRelationship::PreviousSiblingOnce(item) => item,
+            CssSelectorRelationship::Current(item) => item,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// represents all of the CSS selectors which follow a :, for example :last-child
+pub enum CssRefiner {
+    Checked,
+    Default,
+    Disabled,
+    Enabled,
+    Optional,
+    Required,
+    ReadOnly,
+    ReadWrite,
+    Empty,
+    FirstChild,
+    LastChild,
+    NthChild(CssRefinerNumberType),
+    NthLastChild(CssRefinerNumberType),
+    OnlyChild,
+    FirstOfType,
+    LastOfType,
+    NthOfType(CssRefinerNumberType),
+    NthLastOfType(CssRefinerNumberType),
+    OnlyOfType,
+    Not(CssSelector),
+    Root,
+    /// Non-standard :contains("text"), the text of the tag contains the string.
+    Contains(String),
+    /// Non-standard :icontains("text"), the same as :contains but ignoring case.
+    IContains(String),
+    /// Non-standard :matches(/regex/), the text of the tag matches the regular expression.
+    Matches(CssRegex),
+    /// Non-standard :text-equals("text"), the trimmed text of the tag is equal to the string.
+    TextEquals(String),
+}
+
+/// A regular expression used by the :matches(/regex/) refiner.
+/// Two are equal if they were created from the same pattern.
+#[derive(Debug, Clone)]
+pub struct CssRegex(regex::Regex);
+
+impl CssRegex {
+    /// Creates a CssRegex by compiling the pattern provided.
+    pub fn new(pattern: &str) -> Result<CssRegex, ParseHtmlError> {
+        match regex::Regex::new(pattern) {
+            Ok(r) => Ok(CssRegex(r)),
+            Err(e) => Err(ParseHtmlError::with_msg(format!(
+                "could not compile the regular expression {}: {}",
+                pattern, e
+            ))),
+        }
+    }
+
+    /// The pattern the regular expression was created from.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Checks if the text matches the regular expression anywhere.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+impl PartialEq for CssRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Used for CSS Selectors such as :nth-child(x) where x can be odd, even or a specific number
+pub enum CssRefinerNumberType {
+    Odd,
+    Even,
+    Specific(usize),
+    Functional((i32, i32)),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Used to represents the different types of attributes selections for example [attribute=value]
+pub enum CssAttributeCompareType {
+    /// [attribute]
+    Present(String),
+    /// [attribute=value]
+    Equals((String, String)),
+    /// [attribute|=value]
+    EqualsOrBeingsWith((String, String)),
+    /// [attribute^=value]
+    BeginsWith((String, String)),
+    /// [attribute$=value]
+    EndsWith((String, String)),
+    /// [attribute*=value]
+    Contains((String, String)),
+    /// [attribute~=value]
+    ContainsWord((String, String)),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents a CSS selector for a particular node
+pub struct CssSelectorItem {
+    pub tag: Option<String>,
+    pub classes: Option<Vec<String>>,
+    pub ids: Option<Vec<String>>,
+    pub refiners: Option<Vec<CssRefiner>>, // anything like :... eg :only-child
+    pub attributes: Option<Vec<CssAttributeCompareType>>,
+}
+
+impl CssSelectorItem {
+    pub fn new() -> CssSelectorItem {
+        CssSelectorItem {
+            tag: None,
+            classes: None,
+            ids: None,
+            refiners: None,
+            attributes: None,
+        }
+    }
+
+    /// Calculates the specificity of this selector item on its own.
+    /// The :not(..) refiner takes the specificity of the most specific selector inside it,
+    /// all other refiners count as a pseudo-class.
+    pub fn specificity(&self) -> CssSpecificity {
+        let mut spec = CssSpecificity::default();
+        if let Some(ids) = &self.ids {
+            spec.0 += ids.len() as u32;
+        }
+        if let Some(classes) = &self.classes {
+            spec.1 += classes.len() as u32;
+        }
+        if let Some(attributes) = &self.attributes {
+            spec.1 += attributes.len() as u32;
+        }
+        if let Some(refiners) = &self.refiners {
+            for refiner in refiners {
+                match refiner {
+                    CssRefiner::Not(selector) => spec = spec + selector.specificity(),
+                    _ => spec.1 += 1,
+                }
+            }
+        }
+        if let Some(tag) = &self.tag {
+            if split_css_type(tag).1 != "*" {
+                spec.2 += 1;
+            }
+        }
+        spec
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+/// Represents the specificity of a CSS selector as the (ids, classes, tags) triple.
+/// The first value counts ids, the second counts classes, attribute selectors and refiners,
+/// and the third counts tag names. Specificities are compared from left to right so deriving
+/// the ordering gives the same result as the CSS cascade.
+pub struct CssSpecificity(pub u32, pub u32, pub u32);
+
+impl std::ops::Add for CssSpecificity {
+    type Output = CssSpecificity;
+    fn add(self, other: CssSpecificity) -> CssSpecificity {
+        CssSpecificity(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+    }
+}
+
+impl fmt::Display for CssSpecificity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({},{},{})", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents a rule that must match for a CSS selector
+pub struct CssSelectorRule {
+    pub rules: Vec<CssSelectorRelationship>,
+}
+
+impl CssSelectorRule {
+    pub fn new() -> CssSelectorRule {
+        CssSelectorRule { rules: vec![] }
+    }
+
+    /// Calculates the specificity of the rule by adding up the specificity of every
+    /// selector item in the rule.
+    pub fn specificity(&self) -> CssSpecificity {
+        self.rules
+            .iter()
+            .fold(CssSpecificity::default(), |spec, rule| {
+                spec + rule.item().specificity()
+            })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Represents a CSS selector which could be anything (*) or based on a some selection rules.
+/// CSS selectors all multiple different match rules seperated by a comma. This is handle by
+/// having each matching rule in a vector.
+pub enum CssSelector {
+    Any,
+    Specific(Vec<CssSelectorRule>),
+}
+
+impl CssSelector {
+    /// Calculates the specificity of the selector, which is the specificity of the most
+    /// specific rule. The universal selector (*) has a specificity of (0,0,0).
+    pub fn specificity(&self) -> CssSpecificity {
+        match self {
+            CssSelector::Any => CssSpecificity::default(),
+            CssSelector::Specific(rules) => rules
+                .iter()
+                .map(|r| r.specificity())
+                .max()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for CssRefinerNumberType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CssRefinerNumberType::Odd => write!(f, "odd"),
+            CssRefinerNumberType::Even => write!(f, "even"),
+            CssRefinerNumberType::Specific(i) => write!(f, "{}", i),
+            CssRefinerNumberType::Functional((a, b)) => write!(f, "{}n+{}", a, b),
+        }
+    }
+}
+
+impl fmt::Display for CssRefiner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CssRefiner::Checked => write!(f, ":checked"),
+            CssRefiner::Default => write!(f, ":default"),
+            CssRefiner::Disabled => write!(f, ":disabled"),
+            CssRefiner::Enabled => write!(f, ":enabled"),
+            CssRefiner::Optional => write!(f, ":optional"),
+            CssRefiner::Required => write!(f, ":required"),
+            CssRefiner::ReadOnly => write!(f, ":read-only"),
+            CssRefiner::ReadWrite => write!(f, ":read-write"),
+            CssRefiner::Empty => write!(f, ":empty"),
+            CssRefiner::FirstChild => write!(f, ":first-child"),
+            CssRefiner::LastChild => write!(f, ":last-child"),
+            CssRefiner::NthChild(n) => write!(f, ":nth-child({})", n),
+            CssRefiner::NthLastChild(n) => write!(f, ":nth-last-child({})", n),
+            CssRefiner::OnlyChild => write!(f, ":only-child"),
+            CssRefiner::FirstOfType => write!(f, ":first-of-type"),
+            CssRefiner::LastOfType => write!(f, ":last-of-type"),
+            CssRefiner::NthOfType(n) => write!(f, ":nth-of-type({})", n),
+            CssRefiner::NthLastOfType(n) => write!(f, ":nth-last-of-type({})", n),
+            CssRefiner::OnlyOfType => write!(f, ":only-of-type"),
+            CssRefiner::Not(s) => write!(f, ":not({})", s),
+            CssRefiner::Root => write!(f, ":root"),
+            CssRefiner::Contains(t) => write!(f, ":contains({})", quote_css_string(t)),
+            CssRefiner::IContains(t) => write!(f, ":icontains({})", quote_css_string(t)),
+            CssRefiner::Matches(r) => write!(f, ":matches(/{}/)", r.as_str().replace('/', "\\/")),
+            CssRefiner::TextEquals(t) => write!(f, ":text-equals({})", quote_css_string(t)),
+        }
+    }
+}
+
+/// Puts the text in double quotes, escaping any quotes or backslashes in it.
+fn quote_css_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl fmt::Display for CssAttributeCompareType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CssAttributeCompareType::Present(a) => write!(f, "[{}]", a),
+            CssAttributeCompareType::Equals((a, v)) => write!(f, "[{}={}]", a, v),
+            CssAttributeCompareType::EqualsOrBeingsWith((a, v)) => write!(f, "[{}|={}]", a, v),
+            CssAttributeCompareType::BeginsWith((a, v)) => write!(f, "[{}^={}]", a, v),
+            CssAttributeCompareType::EndsWith((a, v)) => write!(f, "[{}$={}]", a, v),
+            CssAttributeCompareType::Contains((a, v)) => write!(f, "[{}*={}]", a, v),
+            CssAttributeCompareType::ContainsWord((a, v)) => write!(f, "[{}~={}]", a, v),
+        }
+    }
+}
+
+impl fmt::Display for CssSelectorItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(tag) = &self.tag {
+            write!(f, "{}", tag)?;
+        }
+        if let Some(ids) = &self.ids {
+            for id in ids {
+                write!(f, "#{}", id)?;
+            }
+        }
+        if let Some(classes) = &self.classes {
+            for class in classes {
+                write!(f, ".{}", class)?;
+            }
+        }
+        if let Some(attributes) = &self.attributes {
+            for attribute in attributes {
+                write!(f, "{}", attribute)?;
+            }
+        }
+        if let Some(refiners) = &self.refiners {
+            for refiner in refiners {
+                write!(f, "{}", refiner)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CssSelectorRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rule in &self.rules {
+            match rule {
+                CssSelectorRelationship::Current(item) => write!(f, "{}", item)?,
+                CssSelectorRelationship::Parent(item) => write!(f, "{} > ", item)?,
+                CssSelectorRelationship::Ancestor(item) => write!(f, "{} ", item)?,
+                CssSelectorRelationship::PreviousSibling(item) => write!(f, "{} ~ ", item)?,
+                CssSelectorRelationship::PreviousSiblingOnce(item) => write!(f, "{} + ", item)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats the selector as CSS, which can be parsed back into the same selector.
+impl fmt::Display for CssSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CssSelector::Any => write!(f, "*"),
+            CssSelector::Specific(rules) => {
+                for (i, rule) in rules.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", rule)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Options that change how CSS selectors are parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CssSelectorParseOptions {
+    /// Allows the non-standard text refiners :contains(), :icontains(), :matches() and
+    /// :text-equals(). Defaults to true.
+    pub text_refiners: bool,
+    /// The namespace URIs for the prefixes used in type selectors such as `atom|link`,
+    /// like the `@namespace` rule in CSS. The empty prefix sets the default namespace for
+    /// type selectors without a prefix. Defaults to no namespaces.
+    pub namespaces: HashMap<String, String>,
+}
+
+impl Default for CssSelectorParseOptions {
+    fn default() -> Self {
+        CssSelectorParseOptions {
+            text_refiners: true,
+            namespaces: HashMap::new(),
+        }
+    }
+}
+
+impl CssSelectorParseOptions {
+    /// Options that only allow standard CSS selectors.
+    pub fn strict() -> CssSelectorParseOptions {
+        CssSelectorParseOptions {
+            text_refiners: false,
+            namespaces: HashMap::new(),
+        }
+    }
+
+    /// Adds a namespace prefix which can be used in type selectors. An empty prefix sets
+    /// the default namespace.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hb_selector::selector::{CssSelector, CssSelectorParseOptions};
+    /// let options = CssSelectorParseOptions::default()
+    ///     .namespace("atom", "http://www.w3.org/2005/Atom");
+    /// assert!(CssSelector::parse_with_options("atom|entry > atom|title", &options).is_ok());
+    /// // the prefix has to be declared before it can be used
+    /// assert!("atom|entry".parse::<CssSelector>().is_err());
+    /// ```
+    pub fn namespace<P: Into<String>, U: Into<String>>(
+        mut self,
+        prefix: P,
+        uri: U,
+    ) -> CssSelectorParseOptions {
+        self.namespaces.insert(prefix.into(), uri.into());
+        self
+    }
+}
+
+impl CssSelector {
+    /// Parses a CSS selector using the options provided.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hb_selector::selector::{CssSelector, CssSelectorParseOptions};
+    /// let selector = r#"a:contains("Next page")"#;
+    /// assert!(CssSelector::parse_with_options(selector, &CssSelectorParseOptions::default()).is_ok());
+    /// assert!(CssSelector::parse_with_options(selector, &CssSelectorParseOptions::strict()).is_err());
+    /// ```
+    pub fn parse_with_options(
+        selector: &str,
+        options: &CssSelectorParseOptions,
+    ) -> Result<CssSelector, ParseHtmlError> {
+        if selector == "*" {
+            return Ok(CssSelector::Any);
+        }
+        let mut rules: Vec<CssSelectorRule> = vec![];
+        for s in split_css_selector_list(selector) {
+            // the parts are slices of the selector, so errors can be placed in the whole of it
+            let start =
+                s.as_ptr() as usize - selector.as_ptr() as usize + s.len() - s.trim_start().len();
+            //parse rule and add to rules;
+            rules
+                .push(parse_css_selector_rule(s.trim(), options).map_err(|e| e.add_offset(start))?);
+        }
+        if rules.len() > 0 {
+            return Ok(CssSelector::Specific(rules));
+        }
+        Err(ParseHtmlError::with_msg(format!(
+            "No valid CSS selector found in {}",
+            selector
+        )))
+    }
+}
+
+impl FromStr for CssSelector {
+    type Err = ParseHtmlError;
+    fn from_str(selector: &str) -> Result<Self, <Self as std::str::FromStr>::Err> {
+        CssSelector::parse_with_options(selector, &CssSelectorParseOptions::default())
+    }
+}
+
+impl TryFrom<&str> for CssSelector {
+    type Error = ParseHtmlError;
+    fn try_from(selector: &str) -> Result<Self, Self::Error> {
+        CssSelector::parse_with_options(selector, &CssSelectorParseOptions::default())
+    }
+}
+
+/// Splits the type part of a [`CssSelectorItem`] into the namespace, if it is written as
+/// `{uri}name`, and the name.
+pub fn split_css_type(selector_tag: &str) -> (Option<&str>, &str) {
+    if let Some(rest) = selector_tag.strip_prefix('{') {
+        if let Some((namespace, name)) = rest.split_once('}') {
+            return (Some(namespace), name);
+        }
+    }
+    (None, selector_tag)
+}
+
+#[cfg(test)]
+mod css_selector_display_tests {
+    use super::*;
+
+    #[test]
+    fn css_selector_display_test() {
+        let tests = vec![
+            "*",
+            "div",
+            "div#main.big.wide",
+            "ul > li:nth-child(2n+1)",
+            "h1 + p ~ a[href^=http][target]",
+            "body div:not(.hidden) p:first-of-type",
+            "input:checked, option:default",
+            r#"a:contains("Next, page"):not(.x)"#,
+            r#"p:icontains("say \"hi\"")"#,
+            r#"td:text-equals("Price") + td:matches(/^\d+(\.\d+)? \/ kg$/)"#,
+        ];
+        for test in tests {
+            let selector = test.parse::<CssSelector>().unwrap();
+            assert_eq!(format!("{}", selector), test);
+            assert_eq!(
+                format!("{}", selector).parse::<CssSelector>().unwrap(),
+                selector
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod css_specificity_tests {
+    use super::*;
+
+    #[test]
+    fn css_selector_specificity_test() {
+        let tests = vec![
+            ("*", CssSpecificity(0, 0, 0)),
+            ("li", CssSpecificity(0, 0, 1)),
+            ("ul li", CssSpecificity(0, 0, 2)),
+            ("ul ol+li", CssSpecificity(0, 0, 3)),
+            ("h1 + *[rel=up]", CssSpecificity(0, 1, 1)),
+            ("ul ol li.red", CssSpecificity(0, 1, 3)),
+            ("li.red.level", CssSpecificity(0, 2, 1)),
+            ("#x34y", CssSpecificity(1, 0, 0)),
+            ("div#main > p:first-child", CssSpecificity(1, 1, 2)),
+            ("p:not(#foo)", CssSpecificity(1, 0, 1)),
+            ("p, div#main", CssSpecificity(1, 0, 1)),
+        ];
+        for (selector, spec) in tests {
+            assert_eq!(
+                selector.parse::<CssSelector>().unwrap().specificity(),
+                spec,
+                "specificity of {}",
+                selector
+            );
+        }
+        assert!(CssSpecificity(1, 0, 0) > CssSpecificity(0, 12, 12));
+        assert!(CssSpecificity(0, 1, 0) > CssSpecificity(0, 0, 12));
+    }
+}
+
+#[cfg(test)]
+mod css_selector_error_tests {
+    use super::*;
+
+    #[test]
+    fn css_selector_error_offset_test() {
+        let tests = vec![
+            ("li a:frist-child", 4),
+            ("a, b >> c", 6),
+            ("div:not(p:bad)", 9),
+            ("a[href", 1),
+            ("ul > li)", 7),
+            ("  a, x|b", 5),
+        ];
+        for (selector, offset) in tests {
+            assert_eq!(
+                selector.parse::<CssSelector>().unwrap_err().offset,
+                Some(offset),
+                "offset of the error in {}",
+                selector
+            );
+        }
+    }
+}
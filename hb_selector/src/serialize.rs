@@ -0,0 +1,116 @@
+//! Serde support for the CSS selectors, enabled with the `serde` feature.
+//!
+//! A [`CssSelector`] is written as the selector text, as are [`CssSelectorRule`],
+//! [`CssSelectorItem`], [`CssRefiner`] (`":nth-child(2n+1)"`), [`CssRefinerNumberType`]
+//! (`"2n+1"`), [`CssAttributeCompareType`] (`"[href^=http]"`) and [`CssRegex`] (the
+//! pattern). They are parsed again when read, so an invalid selector is an error.
+use crate::parsing::{parse_css_selector_rule, split_css_selector_list};
+use crate::selector::{
+    CssAttributeCompareType, CssRefiner, CssRefinerNumberType, CssRegex, CssSelector,
+    CssSelectorItem, CssSelectorParseOptions, CssSelectorRelationship, CssSelectorRule,
+};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// Parses a single selector rule, without a comma separated list.
+fn parse_rule(text: &str) -> Result<CssSelectorRule, String> {
+    let parts = split_css_selector_list(text);
+    if parts.len() != 1 {
+        return Err(format!("expected a single selector rule, got '{}'", text));
+    }
+    parse_css_selector_rule(parts[0].trim(), &CssSelectorParseOptions::default()).map_err(|e| e.msg)
+}
+
+/// Parses a selector item, without any relationships to other items.
+fn parse_item(text: &str) -> Result<CssSelectorItem, String> {
+    let mut rule = parse_rule(text)?;
+    match (rule.rules.pop(), rule.rules.is_empty()) {
+        (Some(CssSelectorRelationship::Current(item)), true) => Ok(item),
+        _ => Err(format!("expected a single selector item, got '{}'", text)),
+    }
+}
+
+/// Parses a selector item which only has one refiner.
+fn parse_refiner(text: &str) -> Result<CssRefiner, String> {
+    let item = parse_item(text)?;
+    match item {
+        CssSelectorItem {
+            tag: None,
+            classes: None,
+            ids: None,
+            attributes: None,
+            refiners: Some(mut refiners),
+        } if refiners.len() == 1 => Ok(refiners.remove(0)),
+        _ => Err(format!("expected a single refiner, got '{}'", text)),
+    }
+}
+
+fn parse_refiner_number(text: &str) -> Result<CssRefinerNumberType, String> {
+    match parse_refiner(&format!(":nth-child({})", text)) {
+        Ok(CssRefiner::NthChild(number)) => Ok(number),
+        _ => Err(format!(
+            "expected odd, even, a number or an+b, got '{}'",
+            text
+        )),
+    }
+}
+
+/// Parses a selector item which only has one attribute selector.
+fn parse_attribute(text: &str) -> Result<CssAttributeCompareType, String> {
+    let item = parse_item(text)?;
+    match item {
+        CssSelectorItem {
+            tag: None,
+            classes: None,
+            ids: None,
+            refiners: None,
+            attributes: Some(mut attributes),
+        } if attributes.len() == 1 => Ok(attributes.remove(0)),
+        _ => Err(format!(
+            "expected a single attribute selector, got '{}'",
+            text
+        )),
+    }
+}
+
+/// Serializes the types as their CSS text, and parses the text back when deserializing.
+macro_rules! impl_css_text_serde {
+    ($($t:ty => $parse:expr),* $(,)?) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $t {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let text = String::deserialize(deserializer)?;
+                    $parse(text.as_str()).map_err(de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+impl_css_text_serde!(
+    CssSelector => |text: &str| text.parse::<CssSelector>().map_err(|e| e.msg),
+    CssSelectorRule => parse_rule,
+    CssSelectorItem => parse_item,
+    CssRefiner => parse_refiner,
+    CssRefinerNumberType => parse_refiner_number,
+    CssAttributeCompareType => parse_attribute,
+);
+
+impl Serialize for CssRegex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CssRegex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        CssRegex::new(&pattern).map_err(|e| de::Error::custom(e.msg))
+    }
+}